@@ -2,6 +2,20 @@
 
 pub mod prelude;
 
+pub mod autocomplete_mru;
+pub mod digest_message;
+pub mod distance_address;
+pub mod distance_result;
+pub mod filtered_word;
+pub mod item_alias;
+pub mod item_reminder;
 pub mod list;
 pub mod list_item;
 pub mod payment;
+pub mod pinned_item;
+pub mod private_thread;
+pub mod recurring_item;
+pub mod seed_entry;
+pub mod sheet_connection;
+pub mod shop_template;
+pub mod shop_visibility;
@@ -24,6 +24,18 @@ pub struct Model {
     pub personal: bool,
     pub store: Option<String>,
     pub notes: Option<String>,
+    pub guild_id: Option<i64>,
+    pub channel_id: Option<i64>,
+    pub recipe_group: Option<String>,
+    pub modified_by: Option<i64>,
+    pub modified_at: Option<DateTime>,
+    pub bought_at: Option<DateTime>,
+    pub cleared_at: Option<DateTime>,
+    pub order_index: Option<i32>,
+    pub message_expired_at: Option<DateTime>,
+    pub bought_for: Option<String>,
+    pub product_url: Option<String>,
+    pub bought_by: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -39,6 +51,18 @@ pub enum Column {
     Personal,
     Store,
     Notes,
+    GuildId,
+    ChannelId,
+    RecipeGroup,
+    ModifiedBy,
+    ModifiedAt,
+    BoughtAt,
+    ClearedAt,
+    OrderIndex,
+    MessageExpiredAt,
+    BoughtFor,
+    ProductUrl,
+    BoughtBy,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -73,6 +97,18 @@ impl ColumnTrait for Column {
             Self::Personal => ColumnType::Boolean.def(),
             Self::Store => ColumnType::String(None).def().null(),
             Self::Notes => ColumnType::String(None).def().null(),
+            Self::GuildId => ColumnType::BigInteger.def().null(),
+            Self::ChannelId => ColumnType::BigInteger.def().null(),
+            Self::RecipeGroup => ColumnType::String(None).def().null(),
+            Self::ModifiedBy => ColumnType::BigInteger.def().null(),
+            Self::ModifiedAt => ColumnType::DateTime.def().null(),
+            Self::BoughtAt => ColumnType::DateTime.def().null(),
+            Self::ClearedAt => ColumnType::DateTime.def().null(),
+            Self::OrderIndex => ColumnType::Integer.def().null(),
+            Self::MessageExpiredAt => ColumnType::DateTime.def().null(),
+            Self::BoughtFor => ColumnType::String(None).def().null(),
+            Self::ProductUrl => ColumnType::String(None).def().null(),
+            Self::BoughtBy => ColumnType::BigInteger.def().null(),
         }
     }
 }
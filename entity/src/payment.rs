@@ -2,6 +2,7 @@
 
 use sea_orm::entity::prelude::*;
 
+/// only created on postgres, as sqlite has no type for the array columns
 #[derive(Copy, Clone, Default, Debug, DeriveEntity)]
 pub struct Entity;
 
@@ -1,5 +1,19 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.6
 
+pub use super::autocomplete_mru::Entity as AutocompleteMru;
+pub use super::digest_message::Entity as DigestMessage;
+pub use super::distance_address::Entity as DistanceAddress;
+pub use super::distance_result::Entity as DistanceResult;
+pub use super::filtered_word::Entity as FilteredWord;
+pub use super::item_alias::Entity as ItemAlias;
+pub use super::item_reminder::Entity as ItemReminder;
 pub use super::list::Entity as List;
 pub use super::list_item::Entity as ListItem;
 pub use super::payment::Entity as Payment;
+pub use super::pinned_item::Entity as PinnedItem;
+pub use super::private_thread::Entity as PrivateThread;
+pub use super::recurring_item::Entity as RecurringItem;
+pub use super::seed_entry::Entity as SeedEntry;
+pub use super::sheet_connection::Entity as SheetConnection;
+pub use super::shop_template::Entity as ShopTemplate;
+pub use super::shop_visibility::Entity as ShopVisibility;
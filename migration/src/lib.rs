@@ -1,12 +1,65 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20220101_000001_create_table_sqlite;
+mod m20230601_000001_add_list_item_guild;
+mod m20230601_000002_add_list_item_channel;
+mod m20230601_000003_add_recipe_group;
+mod m20230601_000004_create_distance_result;
+mod m20230601_000005_create_recurring_item;
+mod m20230601_000006_create_pinned_item;
+mod m20230601_000007_add_list_item_modified;
+mod m20230601_000008_create_filtered_word;
+mod m20230601_000009_add_recurring_item_active;
+mod m20230601_000010_create_digest_message;
+mod m20230601_000011_create_item_alias;
+mod m20230601_000012_create_shop_template;
+mod m20230601_000013_add_list_item_bought_at;
+mod m20230601_000014_add_list_item_order_index;
+mod m20230601_000015_create_item_reminder;
+mod m20230601_000016_add_list_item_message_expired_at;
+mod m20230601_000017_add_list_item_bought_for;
+mod m20230601_000018_create_seed_entry;
+mod m20230601_000019_create_autocomplete_mru;
+mod m20230601_000020_add_list_item_product_url;
+mod m20230601_000021_create_private_thread;
+mod m20230601_000022_create_shop_visibility;
+mod m20230601_000023_add_list_item_bought_by;
+mod m20230601_000024_create_sheet_connection;
+mod m20230601_000025_create_distance_address;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table_sqlite::Migration),
+            Box::new(m20230601_000001_add_list_item_guild::Migration),
+            Box::new(m20230601_000002_add_list_item_channel::Migration),
+            Box::new(m20230601_000003_add_recipe_group::Migration),
+            Box::new(m20230601_000004_create_distance_result::Migration),
+            Box::new(m20230601_000005_create_recurring_item::Migration),
+            Box::new(m20230601_000006_create_pinned_item::Migration),
+            Box::new(m20230601_000007_add_list_item_modified::Migration),
+            Box::new(m20230601_000008_create_filtered_word::Migration),
+            Box::new(m20230601_000009_add_recurring_item_active::Migration),
+            Box::new(m20230601_000010_create_digest_message::Migration),
+            Box::new(m20230601_000011_create_item_alias::Migration),
+            Box::new(m20230601_000012_create_shop_template::Migration),
+            Box::new(m20230601_000013_add_list_item_bought_at::Migration),
+            Box::new(m20230601_000014_add_list_item_order_index::Migration),
+            Box::new(m20230601_000015_create_item_reminder::Migration),
+            Box::new(m20230601_000016_add_list_item_message_expired_at::Migration),
+            Box::new(m20230601_000017_add_list_item_bought_for::Migration),
+            Box::new(m20230601_000018_create_seed_entry::Migration),
+            Box::new(m20230601_000019_create_autocomplete_mru::Migration),
+            Box::new(m20230601_000020_add_list_item_product_url::Migration),
+            Box::new(m20230601_000021_create_private_thread::Migration),
+            Box::new(m20230601_000022_create_shop_visibility::Migration),
+            Box::new(m20230601_000023_add_list_item_bought_by::Migration),
+            Box::new(m20230601_000024_create_sheet_connection::Migration),
+            Box::new(m20230601_000025_create_distance_address::Migration),
+        ]
     }
 }
@@ -0,0 +1,133 @@
+//! The first migration, as it is run on each backend. [super::m20220101_000001_create_table] was
+//! written for postgres alone, and sqlite has no type for the array columns of its payment table.
+//! It is left exactly as it was released, and this runs in its place under the same name so that
+//! databases it has already migrated see no difference.
+
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_table;
+
+#[derive(Iden)]
+enum List {
+    Table,
+    Id,
+    Name,
+    CreatedBy,
+    CreatedAt,
+
+    CreationMessageId,
+    CreationMessageChannelId,
+    CreationMessageGuildId,
+}
+
+#[derive(Iden)]
+enum ListItem {
+    Table,
+    Id,
+    ListId,
+    MessageId,
+    UserId,
+
+    CreatedAt,
+    Bought,
+
+    Item,
+    Quantity,
+    Personal,
+    Store,
+    Notes,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        m20220101_000001_create_table::Migration.name()
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != sea_orm::DbBackend::Sqlite {
+            return m20220101_000001_create_table::Migration.up(manager).await;
+        }
+
+        // the payment table is left out, nothing uses it and its entity needs postgres arrays
+        manager
+            .create_table(
+                Table::create()
+                    .table(List::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(List::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(List::Name).string().not_null())
+                    .col(ColumnDef::new(List::CreatedBy).big_integer().not_null())
+                    .col(ColumnDef::new(List::CreatedAt).date_time().not_null())
+                    .col(
+                        ColumnDef::new(List::CreationMessageId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(List::CreationMessageChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(List::CreationMessageGuildId).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        // sqlite can't add a foreign key to an existing table, so it is created with the table
+        manager
+            .create_table(
+                Table::create()
+                    .table(ListItem::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ListItem::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ListItem::ListId).integer().not_null())
+                    .col(ColumnDef::new(ListItem::MessageId).big_integer().not_null())
+                    .col(ColumnDef::new(ListItem::UserId).big_integer().not_null())
+                    .col(ColumnDef::new(ListItem::CreatedAt).date_time().not_null())
+                    .col(ColumnDef::new(ListItem::Bought).boolean().not_null())
+                    .col(ColumnDef::new(ListItem::Item).string().not_null())
+                    .col(ColumnDef::new(ListItem::Quantity).big_integer().not_null())
+                    .col(ColumnDef::new(ListItem::Personal).boolean().not_null())
+                    .col(ColumnDef::new(ListItem::Store).string())
+                    .col(ColumnDef::new(ListItem::Notes).string())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ListItem::Table, ListItem::ListId)
+                            .to(List::Table, List::Id),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != sea_orm::DbBackend::Sqlite {
+            return m20220101_000001_create_table::Migration.down(manager).await;
+        }
+
+        manager
+            .drop_table(Table::drop().table(ListItem::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(List::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum ListItem {
+    Table,
+    GuildId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .add_column(ColumnDef::new(ListItem::GuildId).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        // existing items inherit the guild of the list they were added to
+        manager
+            .get_connection()
+            .execute(sea_orm::Statement::from_string(
+                manager.get_database_backend(),
+                "UPDATE list_item SET guild_id = \
+                 (SELECT creation_message_guild_id FROM list WHERE list.id = list_item.list_id)"
+                    .to_owned(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .drop_column(ListItem::GuildId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum ListItem {
+    Table,
+    ChannelId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .add_column(ColumnDef::new(ListItem::ChannelId).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        // existing items were posted in the channel their list was created in
+        manager
+            .get_connection()
+            .execute(sea_orm::Statement::from_string(
+                manager.get_database_backend(),
+                "UPDATE list_item SET channel_id = \
+                 (SELECT creation_message_channel_id FROM list WHERE list.id = list_item.list_id)"
+                    .to_owned(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .drop_column(ListItem::ChannelId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum DistanceResult {
+    Table,
+    Id,
+    Origin,
+    Destination,
+    Distance,
+    Duration,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DistanceResult::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DistanceResult::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(DistanceResult::Origin).string().not_null())
+                    .col(
+                        ColumnDef::new(DistanceResult::Destination)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DistanceResult::Distance)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DistanceResult::Duration)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DistanceResult::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DistanceResult::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum RecurringItem {
+    Table,
+    Id,
+    GuildId,
+    ChannelId,
+    UserId,
+    Item,
+    Quantity,
+    Personal,
+    Store,
+    Notes,
+    ScheduleSpec,
+    NextRun,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecurringItem::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RecurringItem::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringItem::GuildId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringItem::ChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringItem::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecurringItem::Item).string().not_null())
+                    .col(
+                        ColumnDef::new(RecurringItem::Quantity)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecurringItem::Personal).boolean().not_null())
+                    .col(ColumnDef::new(RecurringItem::Store).string().null())
+                    .col(ColumnDef::new(RecurringItem::Notes).string().null())
+                    .col(
+                        ColumnDef::new(RecurringItem::ScheduleSpec)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringItem::NextRun)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RecurringItem::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecurringItem::Table).to_owned())
+            .await
+    }
+}
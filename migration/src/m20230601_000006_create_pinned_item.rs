@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum PinnedItem {
+    Table,
+    Id,
+    GuildId,
+    ChannelId,
+    MessageId,
+    ItemId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PinnedItem::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PinnedItem::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PinnedItem::GuildId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PinnedItem::ChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PinnedItem::MessageId).big_integer().null())
+                    .col(ColumnDef::new(PinnedItem::ItemId).integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PinnedItem::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum ListItem {
+    Table,
+    ModifiedBy,
+    ModifiedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // sqlite can only add a single column per statement
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .add_column(ColumnDef::new(ListItem::ModifiedBy).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .add_column(ColumnDef::new(ListItem::ModifiedAt).timestamp())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .drop_column(ListItem::ModifiedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ListItem::Table)
+                    .drop_column(ListItem::ModifiedBy)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum FilteredWord {
+    Table,
+    Id,
+    GuildId,
+    Word,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FilteredWord::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FilteredWord::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FilteredWord::GuildId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(FilteredWord::Word).string().not_null())
+                    .col(
+                        ColumnDef::new(FilteredWord::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FilteredWord::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum DigestMessage {
+    Table,
+    Id,
+    GuildId,
+    ChannelId,
+    MessageId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DigestMessage::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DigestMessage::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(DigestMessage::GuildId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(DigestMessage::ChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DigestMessage::MessageId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DigestMessage::Table).to_owned())
+            .await
+    }
+}
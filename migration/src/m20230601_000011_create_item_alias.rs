@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum ItemAlias {
+    Table,
+    Id,
+    GuildId,
+    Alias,
+    Item,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ItemAlias::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ItemAlias::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ItemAlias::GuildId).big_integer().not_null())
+                    .col(ColumnDef::new(ItemAlias::Alias).string().not_null())
+                    .col(ColumnDef::new(ItemAlias::Item).string().not_null())
+                    .col(ColumnDef::new(ItemAlias::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ItemAlias::Table).to_owned())
+            .await
+    }
+}
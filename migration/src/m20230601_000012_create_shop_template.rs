@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum ShopTemplate {
+    Table,
+    Id,
+    GuildId,
+    Name,
+    Items,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShopTemplate::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ShopTemplate::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ShopTemplate::GuildId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ShopTemplate::Name).string().not_null())
+                    .col(ColumnDef::new(ShopTemplate::Items).text().not_null())
+                    .col(
+                        ColumnDef::new(ShopTemplate::CreatedBy)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ShopTemplate::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ShopTemplate::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum ItemReminder {
+    Table,
+    Id,
+    GuildId,
+    ItemId,
+    UserId,
+    RemindAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ItemReminder::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ItemReminder::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ItemReminder::GuildId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ItemReminder::ItemId).integer().not_null())
+                    .col(
+                        ColumnDef::new(ItemReminder::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ItemReminder::RemindAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ItemReminder::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum SeedEntry {
+    Table,
+    Id,
+    GuildId,
+    Kind,
+    Value,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SeedEntry::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SeedEntry::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SeedEntry::GuildId).big_integer().not_null())
+                    .col(ColumnDef::new(SeedEntry::Kind).string().not_null())
+                    .col(ColumnDef::new(SeedEntry::Value).string().not_null())
+                    .col(ColumnDef::new(SeedEntry::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SeedEntry::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum AutocompleteMru {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    Value,
+    UsedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AutocompleteMru::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AutocompleteMru::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AutocompleteMru::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AutocompleteMru::Kind).string().not_null())
+                    .col(ColumnDef::new(AutocompleteMru::Value).string().not_null())
+                    .col(
+                        ColumnDef::new(AutocompleteMru::UsedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AutocompleteMru::Table).to_owned())
+            .await
+    }
+}
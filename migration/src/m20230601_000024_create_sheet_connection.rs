@@ -0,0 +1,78 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum SheetConnection {
+    Table,
+    Id,
+    GuildId,
+    ChannelId,
+    SheetId,
+    RefreshToken,
+    SyncedUntil,
+    SyncedId,
+    FailedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SheetConnection::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SheetConnection::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SheetConnection::GuildId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SheetConnection::ChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SheetConnection::SheetId).string().not_null())
+                    .col(
+                        ColumnDef::new(SheetConnection::RefreshToken)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SheetConnection::SyncedUntil)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SheetConnection::SyncedId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SheetConnection::FailedAt).timestamp())
+                    .col(
+                        ColumnDef::new(SheetConnection::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SheetConnection::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum DistanceAddress {
+    Table,
+    Id,
+    UserId,
+    Address,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DistanceAddress::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DistanceAddress::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(DistanceAddress::UserId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(DistanceAddress::Address).string().not_null())
+                    .col(
+                        ColumnDef::new(DistanceAddress::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DistanceAddress::Table).to_owned())
+            .await
+    }
+}
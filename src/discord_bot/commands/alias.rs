@@ -0,0 +1,320 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{CreateCommand, CreateCommandOption},
+    prelude::Context,
+};
+
+use crate::{discord_bot::database::item_alias::SerenityItemAliasDatabase, state::AppState};
+
+use super::{command::Command, util::CommandResponse};
+
+/// the guild a command was used in, aliases are always tied to a guild
+fn guild_id(interaction: &CommandInteraction) -> Result<u64, CommandResponse> {
+    match interaction.guild_id {
+        Some(g) => Ok(g.0.into()),
+        None => Err(CommandResponse::BasicFailure(
+            "this command can only be used in a server".to_string(),
+        )),
+    }
+}
+
+/// normalise an alias before it is stored or looked up, so that `TP` and `tp ` are the same alias
+fn normalise_alias(alias: &str) -> String {
+    alias.trim().to_lowercase()
+}
+
+/// the item an item name typed by a user expands to, if it is an alias set in the guild
+pub(super) async fn expand_alias(
+    app_state: &AppState,
+    guild_id: Option<u64>,
+    item: &str,
+) -> Result<Option<String>, CommandResponse> {
+    let guild_id = match guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(None),
+    };
+
+    match app_state
+        .get_item_alias(guild_id, &normalise_alias(item))
+        .await
+    {
+        Ok(expanded) => Ok(expanded),
+        Err(e) => Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        ))),
+    }
+}
+
+/// Make a shorthand expand to an item name whenever it is added to the shopping list
+pub struct AliasAddCommand<'a> {
+    alias: &'a str,
+    item: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for AliasAddCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let mut alias: Option<&str> = None;
+        let mut item: Option<&str> = None;
+
+        for option in interaction.data.options().into_iter() {
+            match (option.name, option.value) {
+                ("alias", ResolvedValue::String(val)) => alias = Some(val),
+                ("item", ResolvedValue::String(val)) => item = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            alias: alias.ok_or_else(|| String::from("alias is required"))?,
+            item: item.ok_or_else(|| String::from("item is required"))?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for AliasAddCommand<'a> {
+    fn name() -> &'static str {
+        "alias-add"
+    }
+
+    fn description() -> &'static str {
+        "make a shorthand expand to an item name when it is added to the shopping list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(CommandOptionType::String, "alias", "The shorthand, e.g. tp")
+                .required(true)
+                .max_length(100)
+                .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "item",
+                "The item the shorthand expands to, e.g. toilet paper",
+            )
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let alias = normalise_alias(self.alias);
+        let item = self.item.trim();
+
+        if alias.is_empty() || item.is_empty() {
+            return Err(CommandResponse::BasicFailure(
+                "both the alias and the item need to be given".to_string(),
+            ));
+        }
+
+        match app_state.add_item_alias(guild_id, &alias, item).await {
+            Ok(true) => Ok(CommandResponse::BasicSuccess(format!(
+                "`{}` will now be added as `{}`",
+                alias, item
+            ))),
+            Ok(false) => Err(CommandResponse::BasicFailure(format!(
+                "`{}` is already an alias, remove it with /alias-remove first",
+                alias
+            ))),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ))),
+        }
+    }
+}
+
+/// Stop a shorthand from expanding to an item name
+pub struct AliasRemoveCommand<'a> {
+    alias: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for AliasRemoveCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let mut alias: Option<&str> = None;
+
+        for option in interaction.data.options().into_iter() {
+            match (option.name, option.value) {
+                ("alias", ResolvedValue::String(val)) => alias = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            alias: alias.ok_or_else(|| String::from("alias is required"))?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for AliasRemoveCommand<'a> {
+    fn name() -> &'static str {
+        "alias-remove"
+    }
+
+    fn description() -> &'static str {
+        "stop a shorthand from expanding to an item name"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "alias",
+                "The shorthand to remove",
+            )
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let alias = normalise_alias(self.alias);
+
+        match app_state.remove_item_alias(guild_id, &alias).await {
+            Ok(true) => Ok(CommandResponse::BasicSuccess(format!(
+                "`{}` is no longer an alias",
+                alias
+            ))),
+            Ok(false) => Err(CommandResponse::BasicFailure(format!(
+                "`{}` isn't an alias",
+                alias
+            ))),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ))),
+        }
+    }
+}
+
+/// List the aliases set in this server
+pub struct AliasListCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for AliasListCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for AliasListCommand {
+    fn name() -> &'static str {
+        "alias-list"
+    }
+
+    fn description() -> &'static str {
+        "list the shorthands which expand to item names"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+
+        let aliases = match app_state.get_item_aliases(guild_id).await {
+            Ok(aliases) => aliases,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if aliases.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(
+                "No aliases are set in this server".to_string(),
+            ));
+        }
+
+        Ok(CommandResponse::BasicSuccess(format!(
+            "Aliases in this server:\n{}",
+            aliases
+                .iter()
+                .map(|a| format!("- `{}` → {}", a.alias, a.item))
+                .collect::<Vec<String>>()
+                .join("\n")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::ConnectionTrait;
+
+    use super::*;
+    use crate::state::test_database_urls;
+
+    #[tokio::test]
+    async fn aliases_expand_only_in_their_own_guild() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            let added = state
+                .add_item_alias(1, &normalise_alias(" OJ "), "orange juice")
+                .await
+                .unwrap();
+            assert!(added, "{:?}", backend);
+
+            // an alias expands however it is typed
+            for typed in ["oj", " Oj "] {
+                let expanded = expand_alias(&state, Some(1), typed).await.unwrap();
+                assert_eq!(expanded.as_deref(), Some("orange juice"), "{:?}", backend);
+            }
+
+            // anything else is added as it was typed
+            let expanded = expand_alias(&state, Some(1), "milk").await.unwrap();
+            assert_eq!(expanded, None, "{:?}", backend);
+            let expanded = expand_alias(&state, Some(2), "oj").await.unwrap();
+            assert_eq!(expanded, None, "{:?}", backend);
+            let expanded = expand_alias(&state, None, "oj").await.unwrap();
+            assert_eq!(expanded, None, "{:?}", backend);
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use std::collections::HashSet;
+
 use serenity::{
-    all::{AutocompleteOption, CommandInteraction, ComponentInteraction},
+    all::{AutocompleteOption, CommandInteraction, ComponentInteraction, ModalInteraction},
     async_trait,
     builder::{CreateAutocompleteResponse, CreateCommand},
     model::{application::CommandType, Permissions},
@@ -8,16 +10,63 @@ use serenity::{
 
 use crate::{
     discord_bot::commands::{
+        config_show::ConfigShowCommand,
         hide::HideCommand,
+        maintenance::MaintenanceCommand,
         pay::{PayAllCommand, PayCommand},
         ping::PingCommand,
         say::SayCommand,
-        shop::Shop,
     },
     state::AppState,
 };
 
-use super::util::CommandResponse;
+#[cfg(feature = "maps")]
+use crate::discord_bot::commands::distance::{
+    DistanceAgainCommand, DistanceCommand, DistanceHistoryCommand, DistanceHouseholdCommand,
+    DistanceRankCommand,
+};
+
+#[cfg(all(feature = "maps", feature = "shopping"))]
+use crate::discord_bot::commands::shop_plan::ShopPlanCommand;
+
+#[cfg(feature = "shopping")]
+use crate::discord_bot::commands::{
+    alias::{AliasAddCommand, AliasListCommand, AliasRemoveCommand},
+    recurring::{
+        RecurringAddCommand, RecurringListCommand, RecurringPauseCommand, RecurringRemoveCommand,
+        RecurringResumeCommand,
+    },
+    scheduler::{
+        SchedulerPauseCommand, SchedulerResumeCommand, SchedulerRunCommand, SchedulerStatusCommand,
+    },
+    seed::{SeedAddCommand, SeedListCommand, SeedRemoveCommand},
+    shop::{Shop, ShoppingComplete},
+    shop_annotate::ShopAnnotateCommand,
+    shop_audit::ShopAuditCommand,
+    shop_by::ShopByCommand,
+    shop_import_photo::ShopImportPhotoCommand,
+    shop_insights::ShopInsightsCommand,
+    shop_leaderboard::ShopLeaderboardCommand,
+    shop_oldest::ShopOldestCommand,
+    shop_pin::ShopPinTopCommand,
+    shop_progress::ShopProgressCommand,
+    shop_recipe::ShopClearRecipeCommand,
+    shop_share::ShopShareCommand,
+    shop_sheet::ShopSheetConnectCommand,
+    shop_store::{
+        ShopBoughtAllCommand, ShopDoneCommand, ShopRestoreStoreCommand, ShopUnstoreCommand,
+    },
+    shop_suggest::ShopSuggestCommand,
+    shop_template::{
+        ShopTemplateApplyCommand, ShopTemplateCreateCommand, ShopTemplateDeleteCommand,
+        ShopTemplateEditCommand, ShopTemplateListCommand,
+    },
+    shop_visibility::ShopVisibilityCommand,
+    shopping_list::ShoppingListCommand,
+    word_filter::{FilterAddCommand, FilterListCommand, FilterRemoveCommand},
+};
+
+use super::util::{maintenance_notice, CommandResponse};
 
 const DEFAULT_PERMISSIONS: Permissions = Permissions::ADMINISTRATOR;
 
@@ -33,6 +82,12 @@ pub trait Command<'a>: TryFrom<&'a CommandInteraction> {
     /// Get the discord defined usage of this command, to be sent to discord
     fn get_application_command_options(command: CreateCommand) -> CreateCommand;
 
+    /// whether the command can be used while the bot is in maintenance, which should only be true
+    /// of commands which don't change anything
+    fn allowed_in_maintenance() -> bool {
+        false
+    }
+
     /// handle the execution of this application command
     async fn handle_application_command<'b>(
         self,
@@ -72,6 +127,25 @@ pub trait InteractionCommand<'a>: Command<'a> {
     ) -> Result<CommandResponse, CommandResponse>;
 }
 
+/// A command which opens a modal, and must handle the modal once it is submitted
+#[cfg_attr(not(feature = "shopping"), allow(dead_code))]
+#[async_trait]
+pub trait ModalCommand<'a>: Command<'a> {
+    /// validate if this modal submission is related to a given command
+    async fn modal_answerable<'b>(
+        interaction: &'b ModalInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> bool;
+
+    /// handle the submitted modal for this command
+    async fn modal_submit<'b>(
+        interaction: &'b ModalInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse>;
+}
+
 // #[async_trait]
 // pub trait PaginatedResponse<'a>: Command<'a> {
 //     /// Get the number of pages this response has
@@ -102,7 +176,13 @@ macro_rules! application_command {
     };
 }
 
-/// match against a list of provided command types, and produce a response which can be sent to the user
+/// whether a command is refused because the bot is in maintenance, see [Command::allowed_in_maintenance]
+fn refused_in_maintenance<'a, T: Command<'a>>(in_maintenance: bool) -> bool {
+    in_maintenance && !T::allowed_in_maintenance()
+}
+
+/// match against a list of provided command types, and produce a response which can be sent to the user.
+/// returns from the calling function if one matches, so the caller decides what to do otherwise
 macro_rules! command {
     ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
         {
@@ -111,17 +191,20 @@ macro_rules! command {
             $(
                 assert_command::<$x>();
                 if ($cmd).data.name == <$x>::name() {
+                    if refused_in_maintenance::<$x>(($state).in_maintenance()) {
+                        return Err(maintenance_notice());
+                    }
                     if let Ok(value) = <$x>::try_from($cmd) {
                         return value.handle_application_command($cmd, $state, $context).await
                     }
                 }
             )*
-            Err(CommandResponse::InternalFailure(String::from("Unsupported Command")))
         }
     };
 }
 
-/// match against a list of provided autocomplete command types, and produce a response which can be sent to the user
+/// match against a list of provided autocomplete command types, and produce a response which can be sent to the user.
+/// returns from the calling function if one matches, so the caller decides what to do otherwise
 macro_rules! autocomplete {
     ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
         {
@@ -136,12 +219,12 @@ macro_rules! autocomplete {
                     }
                 }
             )*
-            Err(CommandResponse::InternalFailure(String::from("Unsupported Autocomplete Command")))
         }
     };
 }
 
-/// match against a list of provided interaction command types, and produce a response which can be sent to the user
+/// match against a list of provided interaction command types, and produce a response which can be sent to the user.
+/// returns from the calling function if one matches, so the caller decides what to do otherwise
 macro_rules! interaction {
     ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
         {
@@ -153,7 +236,24 @@ macro_rules! interaction {
                     return <$x>::interaction($cmd, $state, $context).await
                 }
             )*
-            Err(CommandResponse::InternalFailure(String::from("Unsupported Interaction Command")))
+        }
+    };
+}
+
+/// match against a list of provided modal command types, and produce a response which can be sent to the user.
+/// returns from the calling function if one matches, so the caller decides what to do otherwise
+#[cfg_attr(not(feature = "shopping"), allow(unused_macros))]
+macro_rules! modal {
+    ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
+        {
+            /// ensures that the provided type has relevant traits
+            fn assert_modal<'a, T: ModalCommand<'a, Error=String>>() {}
+            $(
+                assert_modal::<$x>();
+                if <$x>::modal_answerable($cmd, $state, $context).await {
+                    return <$x>::modal_submit($cmd, $state, $context).await
+                }
+            )*
         }
     };
 }
@@ -165,15 +265,116 @@ pub fn application_command() -> Vec<CreateCommand> {
         HideCommand,
         PingCommand,
         SayCommand,
-        // DistanceCommand,
         PayCommand,
         PayAllCommand,
+        ConfigShowCommand,
+        MaintenanceCommand,
+    );
+    #[cfg(feature = "maps")]
+    application_command!(
+        &mut base,
+        DistanceCommand,
+        DistanceAgainCommand,
+        DistanceHistoryCommand,
+        DistanceRankCommand,
+        DistanceHouseholdCommand
+    );
+    #[cfg(all(feature = "maps", feature = "shopping"))]
+    application_command!(&mut base, ShopPlanCommand);
+    #[cfg(feature = "shopping")]
+    application_command!(
+        &mut base,
         Shop,
-        // ShoppingComplete,
+        ShopByCommand,
+        ShopClearRecipeCommand,
+        ShopUnstoreCommand,
+        ShopRestoreStoreCommand,
+        ShopBoughtAllCommand,
+        ShopDoneCommand,
+        ShopPinTopCommand,
+        ShopSuggestCommand,
+        ShopOldestCommand,
+        ShopShareCommand,
+        ShopAuditCommand,
+        ShopTemplateCreateCommand,
+        ShopTemplateEditCommand,
+        ShopTemplateDeleteCommand,
+        ShopTemplateListCommand,
+        ShopTemplateApplyCommand,
+        ShopInsightsCommand,
+        ShopImportPhotoCommand,
+        ShopAnnotateCommand,
+        ShopProgressCommand,
+        ShopLeaderboardCommand,
+        ShopSheetConnectCommand,
+        ShopVisibilityCommand,
+        ShoppingListCommand,
+        RecurringAddCommand,
+        RecurringListCommand,
+        RecurringRemoveCommand,
+        RecurringPauseCommand,
+        RecurringResumeCommand,
+        FilterAddCommand,
+        FilterRemoveCommand,
+        FilterListCommand,
+        SeedAddCommand,
+        SeedRemoveCommand,
+        SeedListCommand,
+        SchedulerStatusCommand,
+        SchedulerRunCommand,
+        SchedulerPauseCommand,
+        SchedulerResumeCommand,
+        AliasAddCommand,
+        AliasRemoveCommand,
+        AliasListCommand,
+        ShoppingComplete,
     );
     base
 }
 
+/// the names of every command in [application_command], in the order they are registered
+pub fn application_command_names() -> Vec<String> {
+    application_command()
+        .iter()
+        .filter_map(|command| serde_json::to_value(command).ok())
+        .filter_map(|value| value["name"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// check the generated command definitions against the limits discord enforces, so that a bad
+/// definition is caught at startup rather than when registering commands with each guild
+pub fn validate_application_commands() -> Result<usize, String> {
+    let commands = application_command();
+    if commands.len() > 100 {
+        return Err(format!(
+            "{} commands defined, at most 100 are allowed",
+            commands.len()
+        ));
+    }
+
+    let mut names = HashSet::new();
+    for command in commands.iter() {
+        let value = serde_json::to_value(command).map_err(|e| e.to_string())?;
+        let name = value["name"].as_str().unwrap_or_default();
+        let description = value["description"].as_str().unwrap_or_default();
+
+        if name.is_empty()
+            || name.chars().count() > 32
+            || name.chars().any(|c| c.is_uppercase() || c.is_whitespace())
+        {
+            return Err(format!("invalid command name `{}`", name));
+        }
+        if description.is_empty() || description.chars().count() > 100 {
+            return Err(format!("invalid description for command `{}`", name));
+        }
+        if !names.insert(name.to_string()) {
+            return Err(format!("command `{}` is defined more than once", name));
+        }
+    }
+
+    Ok(commands.len())
+}
+
 pub async fn command<'a>(
     command: &'a CommandInteraction,
     app_state: &'a AppState,
@@ -186,12 +387,77 @@ pub async fn command<'a>(
         HideCommand,
         PingCommand,
         SayCommand,
-        // DistanceCommand,
         PayCommand,
         PayAllCommand,
+        ConfigShowCommand,
+        MaintenanceCommand,
+    );
+    #[cfg(feature = "maps")]
+    command!(
+        command,
+        app_state,
+        context,
+        DistanceCommand,
+        DistanceAgainCommand,
+        DistanceHistoryCommand,
+        DistanceRankCommand,
+        DistanceHouseholdCommand
+    );
+    #[cfg(all(feature = "maps", feature = "shopping"))]
+    command!(command, app_state, context, ShopPlanCommand);
+    #[cfg(feature = "shopping")]
+    command!(
+        command,
+        app_state,
+        context,
         Shop,
-        // ShoppingComplete,
-    )
+        ShopByCommand,
+        ShopClearRecipeCommand,
+        ShopUnstoreCommand,
+        ShopRestoreStoreCommand,
+        ShopBoughtAllCommand,
+        ShopDoneCommand,
+        ShopPinTopCommand,
+        ShopSuggestCommand,
+        ShopOldestCommand,
+        ShopShareCommand,
+        ShopAuditCommand,
+        ShopTemplateCreateCommand,
+        ShopTemplateEditCommand,
+        ShopTemplateDeleteCommand,
+        ShopTemplateListCommand,
+        ShopTemplateApplyCommand,
+        ShopInsightsCommand,
+        ShopImportPhotoCommand,
+        ShopAnnotateCommand,
+        ShopProgressCommand,
+        ShopLeaderboardCommand,
+        ShopSheetConnectCommand,
+        ShopVisibilityCommand,
+        ShoppingListCommand,
+        RecurringAddCommand,
+        RecurringListCommand,
+        RecurringRemoveCommand,
+        RecurringPauseCommand,
+        RecurringResumeCommand,
+        FilterAddCommand,
+        FilterRemoveCommand,
+        FilterListCommand,
+        SeedAddCommand,
+        SeedRemoveCommand,
+        SeedListCommand,
+        SchedulerStatusCommand,
+        SchedulerRunCommand,
+        SchedulerPauseCommand,
+        SchedulerResumeCommand,
+        AliasAddCommand,
+        AliasRemoveCommand,
+        AliasListCommand,
+        ShoppingComplete,
+    );
+    Err(CommandResponse::InternalFailure(String::from(
+        "Unsupported Command",
+    )))
 }
 
 #[allow(dead_code, unused_variables)]
@@ -200,7 +466,12 @@ pub async fn autocomplete<'a>(
     app_state: &'a AppState,
     context: &'a Context,
 ) -> Result<CreateAutocompleteResponse, CommandResponse> {
-    autocomplete!(command, app_state, context, PayCommand, PayAllCommand, Shop)
+    autocomplete!(command, app_state, context, PayCommand, PayAllCommand);
+    #[cfg(feature = "shopping")]
+    autocomplete!(command, app_state, context, Shop);
+    Err(CommandResponse::InternalFailure(String::from(
+        "Unsupported Autocomplete Command",
+    )))
 }
 
 pub async fn interaction<'a>(
@@ -208,9 +479,90 @@ pub async fn interaction<'a>(
     app_state: &'a AppState,
     context: &'a Context,
 ) -> Result<CommandResponse, CommandResponse> {
+    // buttons and menus are mostly used to change items, so none are answered during maintenance
+    if app_state.in_maintenance() {
+        return Err(maintenance_notice());
+    }
+
+    // PayCommand also handles PayAllCommand
+    interaction!(command, app_state, context, PayCommand);
+    #[cfg(feature = "shopping")]
     interaction!(
-        command, app_state, context, PayCommand, // also handles PayAllCommand
+        command,
+        app_state,
+        context,
+        ShopSuggestCommand,
+        // ShopDoneCommand also confirms ShopBoughtAllCommand
+        ShopDoneCommand,
+        ShopOldestCommand,
+        ShopAuditCommand,
+        ShopImportPhotoCommand,
+        ShopAnnotateCommand,
         Shop,
         // ShoppingList
-    )
+    );
+    Err(CommandResponse::InternalFailure(String::from(
+        "Unsupported Interaction Command",
+    )))
+}
+
+// every modal belongs to a shopping command
+#[cfg_attr(not(feature = "shopping"), allow(unused_variables))]
+pub async fn modal<'a>(
+    command: &'a ModalInteraction,
+    app_state: &'a AppState,
+    context: &'a Context,
+) -> Result<CommandResponse, CommandResponse> {
+    if app_state.in_maintenance() {
+        return Err(maintenance_notice());
+    }
+
+    #[cfg(feature = "shopping")]
+    modal!(command, app_state, context, Shop);
+    Err(CommandResponse::InternalFailure(String::from(
+        "Unsupported Modal Command",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_which_change_nothing_are_allowed_in_maintenance() {
+        assert!(!refused_in_maintenance::<PingCommand>(true));
+        assert!(!refused_in_maintenance::<MaintenanceCommand>(true));
+        assert!(!refused_in_maintenance::<ConfigShowCommand>(true));
+    }
+
+    #[cfg(feature = "shopping")]
+    #[test]
+    fn commands_which_change_the_list_are_refused_in_maintenance() {
+        assert!(refused_in_maintenance::<Shop>(true));
+        assert!(refused_in_maintenance::<ShopUnstoreCommand>(true));
+        assert!(refused_in_maintenance::<RecurringAddCommand>(true));
+        assert!(refused_in_maintenance::<SchedulerRunCommand>(true));
+        assert!(!refused_in_maintenance::<ShoppingListCommand>(true));
+    }
+
+    #[cfg(feature = "maps")]
+    #[test]
+    fn distance_lookups_are_refused_in_maintenance_as_they_are_recorded() {
+        assert!(refused_in_maintenance::<DistanceCommand>(true));
+        assert!(refused_in_maintenance::<DistanceAgainCommand>(true));
+        assert!(!refused_in_maintenance::<DistanceHistoryCommand>(true));
+    }
+
+    #[cfg(all(feature = "maps", feature = "shopping"))]
+    #[test]
+    fn shop_plan_is_allowed_in_maintenance() {
+        assert!(!refused_in_maintenance::<ShopPlanCommand>(true));
+    }
+
+    #[test]
+    fn nothing_is_refused_outside_of_maintenance() {
+        assert!(!refused_in_maintenance::<PayCommand>(false));
+        #[cfg(feature = "shopping")]
+        assert!(!refused_in_maintenance::<Shop>(false));
+    }
 }
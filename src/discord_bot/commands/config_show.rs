@@ -0,0 +1,90 @@
+use serenity::{
+    all::CommandInteraction,
+    async_trait,
+    builder::{
+        CreateCommand, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{common::embed::EmbedColor, utils::AutocompleteDebouncer},
+    state::{AppState, CONFIG},
+};
+
+use super::{
+    command::Command,
+    util::{owner_guild_id, CommandResponse},
+};
+
+/// the most of the config shown, leaving room in the embed description for the code block
+const MAX_CONFIG_LENGTH: usize = 4000;
+
+/// Show the configuration the bot is running with, for troubleshooting
+pub struct ConfigShowCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ConfigShowCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ConfigShowCommand {
+    fn name() -> &'static str {
+        "config-show"
+    }
+
+    fn description() -> &'static str {
+        "show the configuration the bot is running with in this server, secrets are masked"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        _: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = owner_guild_id(interaction, ctx).await?;
+
+        // other guilds' settings and the destinations' addresses are never shown
+        let mut config = CONFIG.shown_to(guild_id);
+        if config.chars().count() > MAX_CONFIG_LENGTH {
+            config = config.chars().take(MAX_CONFIG_LENGTH).collect();
+            config.push_str("\n…");
+        }
+
+        let mut embed = CreateEmbed::new()
+            .title("Effective config")
+            .description(format!("```toml\n{}```", config))
+            .color(EmbedColor::Red as u32);
+
+        // how many autocomplete queries the debounce has saved since the bot started
+        if let Some(debouncer) = ctx.data.read().await.get::<AutocompleteDebouncer>() {
+            let (answered, skipped) = debouncer.counts();
+            embed = embed.field(
+                "Autocomplete",
+                format!("{} answered, {} skipped", answered, skipped),
+                false,
+            );
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
@@ -0,0 +1,888 @@
+use std::collections::BTreeMap;
+
+use chrono::{Local, TimeZone, Timelike};
+use chrono_tz::Tz;
+use log::error;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::distance::{format_duration, load_maps_data_to_embed, timed_out},
+        database::distance::{DistanceResultModel, NewDistanceResult, SerenityDistanceDatabase},
+    },
+    google_api::maps::{GoogleMapApiResponse, GoogleMapsElement},
+    state::{AppState, Destination, CONFIG},
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// tidy up an address before it is sent to the maps api, rejecting input which is empty, junk or
+/// too vague to be worth spending a request on
+pub(super) fn normalise_address(address: &str) -> Result<String, String> {
+    let address = address.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    if address.is_empty() {
+        return Err("the address can't be empty".to_string());
+    }
+    if !address.chars().any(|c| c.is_alphabetic()) || address.contains("://") {
+        return Err(format!("`{}` doesn't look like an address", address));
+    }
+    // a single word with no street number (e.g. `home` or `Auckland`) matches too broad an area
+    if !address.contains(' ') && !address.chars().any(|c| c.is_ascii_digit()) {
+        return Err(format!(
+            "`{}` is too vague, include a street or place name e.g. `5 Te Apunga Place, Mount Wellington`",
+            address
+        ));
+    }
+
+    Ok(address)
+}
+
+/// Look up the travel time from an address to each of the configured destinations
+pub struct DistanceCommand<'a> {
+    address: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for DistanceCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut address: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("address", ResolvedValue::String(val)) => address = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        match address {
+            Some(address) => Ok(Self { address }),
+            None => Err(String::from("address is required")),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for DistanceCommand<'a> {
+    fn name() -> &'static str {
+        "distance"
+    }
+
+    fn description() -> &'static str {
+        "Get the travel time from an address to all the usual destinations"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "address",
+                "The address to travel from",
+            )
+            .required(true)
+            .max_length(200)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let address = match normalise_address(self.address) {
+            Ok(address) => address,
+            Err(e) => return Err(CommandResponse::BasicFailure(e)),
+        };
+
+        distance_response(interaction, app_state, ctx, &address).await
+    }
+}
+
+/// look up and show the travel times from an address, as the deferred response to an interaction.
+/// A successful lookup is remembered as the user's last address, for /distance-again
+async fn distance_response(
+    interaction: &CommandInteraction,
+    app_state: &AppState,
+    ctx: &Context,
+    address: &str,
+) -> Result<CommandResponse, CommandResponse> {
+    // the maps api can be slow, so defer the response while waiting for it
+    if let Err(e) = interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    let response = match load_maps_data_to_embed(address.to_string(), app_state).await {
+        Ok(embed) => {
+            // the travel times are shown either way, so failing to remember the address is only
+            // logged
+            if let Err(e) = app_state
+                .set_last_address(interaction.user.id.into(), address)
+                .await
+            {
+                error!(
+                    "error saving last address of {}: {}",
+                    interaction.user.id, e
+                );
+            }
+            EditInteractionResponse::new().embed(embed)
+        }
+        Err(e) => {
+            error!("failed to load distance for {}: {}", address, e);
+            EditInteractionResponse::new().content(match timed_out(e.as_ref()) {
+                true => "Google Maps didn't respond in time, try again in a moment".to_string(),
+                false => format!("Unable to find travel times from `{}`", address),
+            })
+        }
+    };
+
+    if let Err(e) = interaction.edit_response(ctx, response).await {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    Ok(CommandResponse::NoResponse)
+}
+
+/// Look up the travel times again from the address the user last gave /distance
+pub struct DistanceAgainCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for DistanceAgainCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for DistanceAgainCommand {
+    fn name() -> &'static str {
+        "distance-again"
+    }
+
+    fn description() -> &'static str {
+        "Get the travel times again from the last address you gave /distance"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let address = match app_state.get_last_address(interaction.user.id.into()).await {
+            Ok(Some(address)) => address,
+            Ok(None) => {
+                return Err(CommandResponse::BasicFailure(
+                    "You haven't looked up an address yet, use /distance first and it will be \
+                     remembered for next time"
+                        .to_string(),
+                ))
+            }
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )))
+            }
+        };
+
+        distance_response(interaction, app_state, ctx, &address).await
+    }
+}
+
+/// Summarise how the travel time between an address and a destination has varied over time
+pub struct DistanceHistoryCommand<'a> {
+    destination: &'a str,
+    address: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for DistanceHistoryCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut destination: Option<&str> = None;
+        let mut address: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("destination", ResolvedValue::String(val)) => destination = Some(val),
+                ("address", ResolvedValue::String(val)) => address = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        if destination.is_none() || address.is_none() {
+            return Err(String::from("destination and address are required"));
+        }
+
+        Ok(Self {
+            destination: destination.unwrap(),
+            address: address.unwrap(),
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for DistanceHistoryCommand<'a> {
+    fn name() -> &'static str {
+        "distance-history"
+    }
+
+    fn description() -> &'static str {
+        "Show how travel times from an address have varied over time"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        let mut destination = CreateCommandOption::new(
+            CommandOptionType::String,
+            "destination",
+            "The destination to show travel times to",
+        )
+        .required(true);
+        for d in CONFIG.destinations.iter() {
+            destination = destination.add_string_choice(&d.label, &d.label);
+        }
+
+        cmd.add_option(destination).add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "address",
+                "The address previously looked up with /distance",
+            )
+            .required(true)
+            .max_length(200)
+            .to_owned(),
+        )
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let history = match app_state
+            .get_distance_history(self.address, self.destination)
+            .await
+        {
+            Ok(history) => history,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if history.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "No travel times have been recorded from `{}` to {} yet, try /distance first",
+                self.address, self.destination
+            )));
+        }
+
+        let durations: Vec<i64> = history.iter().map(|h| h.duration).collect();
+        let min = durations.iter().min().copied().unwrap_or_default();
+        let max = durations.iter().max().copied().unwrap_or_default();
+        let avg = durations.iter().sum::<i64>() / durations.len() as i64;
+
+        let timezone = CONFIG
+            .guild(interaction.guild_id.map(|g| g.0.into()))
+            .timezone;
+        let by_hour = durations_by_hour(&history, timezone)
+            .into_iter()
+            .map(|(hour, durations)| {
+                format!(
+                    "`{:02}:00` {} ({} lookup{})",
+                    hour,
+                    format_duration(durations.iter().sum::<i64>() / durations.len() as i64),
+                    durations.len(),
+                    if durations.len() == 1 { "" } else { "s" }
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().embed(
+                    CreateEmbed::new()
+                        .title(format!("{} to {}", self.address, self.destination))
+                        .description(format!("**Average by time of day**\n{}", by_hour))
+                        .field("Fastest", format_duration(min), true)
+                        .field("Average", format_duration(avg), true)
+                        .field("Slowest", format_duration(max), true)
+                        .field("Lookups", history.len().to_string(), true)
+                        .color(0x4285F4),
+                ),
+            ),
+        ))
+    }
+}
+
+/// the durations of lookups grouped by the hour of the day they were made in the guild's timezone,
+/// to show how traffic changes the trip. Lookups are stored in the server's local time
+fn durations_by_hour(history: &[DistanceResultModel], timezone: Tz) -> BTreeMap<u32, Vec<i64>> {
+    let mut by_hour: BTreeMap<u32, Vec<i64>> = BTreeMap::new();
+    for result in history.iter() {
+        let hour = match Local.from_local_datetime(&result.created_at).earliest() {
+            Some(created_at) => created_at.with_timezone(&timezone).hour(),
+            None => result.created_at.hour(),
+        };
+        by_hour.entry(hour).or_default().push(result.duration);
+    }
+    by_hour
+}
+
+/// the most addresses which can be ranked at once, each costs a request to the maps api
+const MAX_RANK_ADDRESSES: usize = 10;
+
+/// Rank several addresses by the travel time from each to a single destination
+pub struct DistanceRankCommand<'a> {
+    destination: &'a str,
+    addresses: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for DistanceRankCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut destination: Option<&str> = None;
+        let mut addresses: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("destination", ResolvedValue::String(val)) => destination = Some(val),
+                ("addresses", ResolvedValue::String(val)) => addresses = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        if destination.is_none() || addresses.is_none() {
+            return Err(String::from("destination and addresses are required"));
+        }
+
+        Ok(Self {
+            destination: destination.unwrap(),
+            addresses: addresses.unwrap(),
+        })
+    }
+}
+
+/// wait for a queued lookup of the route to a single destination
+async fn await_route(
+    rx: tokio::sync::oneshot::Receiver<GoogleMapApiResponse>,
+) -> Result<GoogleMapsElement, String> {
+    let data = match tokio::time::timeout(CONFIG.maps.max_wait(), rx).await {
+        Ok(Ok(Ok(data))) => data,
+        Ok(Ok(Err(e))) => return Err(e.to_string()),
+        Ok(Err(_)) | Err(_) => return Err("timed out waiting for google maps".to_string()),
+    };
+
+    match data.rows.first().and_then(|row| row.elements.first()) {
+        Some(element) if element.is_ok() => Ok(element.clone()),
+        _ => Err("no route found".to_string()),
+    }
+}
+
+/// look up the route from an address to a single destination, recording it like /distance does
+async fn lookup_route(
+    address: &str,
+    destination: &Destination,
+    rx: tokio::sync::oneshot::Receiver<GoogleMapApiResponse>,
+    app_state: &AppState,
+) -> Result<GoogleMapsElement, String> {
+    let element = await_route(rx).await?;
+
+    let result = NewDistanceResult {
+        destination: &destination.label,
+        distance: element.distance.value,
+        duration: element.travel_time().value,
+    };
+    if let Err(e) = app_state.add_distance_results(address, vec![result]).await {
+        error!("failed to record distance results for {}: {}", address, e);
+    }
+
+    Ok(element)
+}
+
+#[async_trait]
+impl<'a> Command<'a> for DistanceRankCommand<'a> {
+    fn name() -> &'static str {
+        "distance-rank"
+    }
+
+    fn description() -> &'static str {
+        "Rank several addresses by their travel time to a destination"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        let mut destination = CreateCommandOption::new(
+            CommandOptionType::String,
+            "destination",
+            "The destination to rank the addresses by",
+        )
+        .required(true);
+        for d in CONFIG.destinations.iter() {
+            destination = destination.add_string_choice(&d.label, &d.label);
+        }
+
+        cmd.add_option(destination).add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "addresses",
+                "The addresses to compare, separated by semicolons",
+            )
+            .required(true)
+            .max_length(1000)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let destination = match CONFIG
+            .destinations
+            .iter()
+            .find(|d| d.label == self.destination)
+        {
+            Some(destination) => destination,
+            None => {
+                return Err(CommandResponse::BasicFailure(format!(
+                    "`{}` isn't one of the configured destinations",
+                    self.destination
+                )))
+            }
+        };
+
+        let addresses: Vec<&str> = self
+            .addresses
+            .split(';')
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .collect();
+        if addresses.len() < 2 {
+            return Err(CommandResponse::BasicFailure(
+                "give at least two addresses separated by semicolons, e.g. `5 Te Apunga Place, Mount Wellington; 1 Queen Street, Auckland`".to_string(),
+            ));
+        }
+        if addresses.len() > MAX_RANK_ADDRESSES {
+            return Err(CommandResponse::BasicFailure(format!(
+                "at most {} addresses can be ranked at once",
+                MAX_RANK_ADDRESSES
+            )));
+        }
+
+        // the maps api can be slow, so defer the response while waiting for it
+        if let Err(e) = interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        // queue every lookup before waiting on any, so that they are made back to back
+        let mut pending = Vec::with_capacity(addresses.len());
+        let mut failed: Vec<(String, String)> = Vec::new();
+        for address in addresses {
+            match normalise_address(address) {
+                Ok(address) => {
+                    let (tx, rx) = tokio::sync::oneshot::channel();
+                    app_state
+                        .maps_api()
+                        .add_to_queue(address.clone(), std::slice::from_ref(destination), tx)
+                        .await;
+                    pending.push((address, rx));
+                }
+                Err(e) => failed.push((address.to_string(), e)),
+            }
+        }
+
+        let mut ranked: Vec<(String, GoogleMapsElement)> = Vec::new();
+        for (address, rx) in pending {
+            match lookup_route(&address, destination, rx, app_state).await {
+                Ok(element) => ranked.push((address, element)),
+                Err(e) => failed.push((address, e)),
+            }
+        }
+        ranked.sort_by_key(|(_, element)| element.travel_time().value);
+
+        let mut description = ranked
+            .iter()
+            .enumerate()
+            .map(|(i, (address, element))| {
+                format!(
+                    "{}. **{}** {} ({})",
+                    i + 1,
+                    address,
+                    format_duration(element.travel_time().value as i64),
+                    element.distance.text
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        if !failed.is_empty() {
+            description.push_str("\n\n**Couldn't be ranked**\n");
+            description.push_str(
+                &failed
+                    .iter()
+                    .map(|(address, e)| format!("- `{}`: {}", address, e))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            );
+        }
+
+        let response = EditInteractionResponse::new().embed(
+            CreateEmbed::new()
+                .title(format!("Travel time to {}", destination.label))
+                .description(description)
+                .color(0x4285F4),
+        );
+
+        if let Err(e) = interaction.edit_response(ctx, response).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+/// the travel time from a flatmate's home to a destination, or why there isn't one
+enum HouseholdRoute<'a> {
+    Found(&'a str, GoogleMapsElement),
+    Failed(&'a str, String),
+    NoAddress(&'a str),
+}
+
+/// lay the travel time of each flatmate out as a table, fastest first, followed by anyone who
+/// couldn't be included
+fn household_table(mut routes: Vec<HouseholdRoute>) -> String {
+    routes.sort_by_key(|route| match route {
+        HouseholdRoute::Found(_, element) => (0, element.travel_time().value),
+        HouseholdRoute::Failed(..) => (1, 0),
+        HouseholdRoute::NoAddress(_) => (2, 0),
+    });
+
+    let width = routes
+        .iter()
+        .map(|route| match route {
+            HouseholdRoute::Found(name, _)
+            | HouseholdRoute::Failed(name, _)
+            | HouseholdRoute::NoAddress(name) => name.chars().count(),
+        })
+        .max()
+        .unwrap_or_default()
+        .max("Flatmate".len());
+
+    let mut table = format!("{:<width$}  {:<14}  {}\n", "Flatmate", "Time", "Distance");
+    for route in routes.iter() {
+        let line = match route {
+            HouseholdRoute::Found(name, element) => format!(
+                "{:<width$}  {:<14}  {}",
+                name,
+                format_duration(element.travel_time().value as i64),
+                element.distance.text
+            ),
+            HouseholdRoute::Failed(name, e) => format!("{:<width$}  {}", name, e),
+            HouseholdRoute::NoAddress(name) => {
+                format!("{:<width$}  no address shared", name)
+            }
+        };
+        table.push_str(&line);
+        table.push('\n');
+    }
+
+    format!("```\n{}```", table)
+}
+
+/// Compare the travel time from every flatmate's home to a single destination
+pub struct DistanceHouseholdCommand<'a> {
+    destination: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for DistanceHouseholdCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut destination: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("destination", ResolvedValue::String(val)) => destination = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        match destination {
+            Some(destination) => Ok(Self { destination }),
+            None => Err(String::from("destination is required")),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for DistanceHouseholdCommand<'a> {
+    fn name() -> &'static str {
+        "distance-household"
+    }
+
+    fn description() -> &'static str {
+        "Compare the travel time from each flatmate's home to a destination"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        let mut destination = CreateCommandOption::new(
+            CommandOptionType::String,
+            "destination",
+            "The destination to compare travel times to",
+        )
+        .required(true);
+        for d in CONFIG.destinations.iter() {
+            destination = destination.add_string_choice(&d.label, &d.label);
+        }
+
+        cmd.add_option(destination)
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let destination = match CONFIG
+            .destinations
+            .iter()
+            .find(|d| d.label == self.destination)
+        {
+            Some(destination) => destination,
+            None => {
+                return Err(CommandResponse::BasicFailure(format!(
+                    "`{}` isn't one of the configured destinations",
+                    self.destination
+                )))
+            }
+        };
+
+        if !CONFIG.flatmates.iter().any(|f| f.home_address.is_some()) {
+            return Err(CommandResponse::BasicFailure(
+                "no flatmates have shared a home address, add `home_address` to them in config.toml"
+                    .to_string(),
+            ));
+        }
+
+        // the maps api can be slow, so defer the response while waiting for it
+        if let Err(e) = interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        // queue every lookup before waiting on any, so that they are made back to back. Home
+        // addresses aren't recorded in the distance history, as that can be searched by address
+        let mut pending = Vec::new();
+        let mut routes = Vec::with_capacity(CONFIG.flatmates.len());
+        for flatmate in CONFIG.flatmates.iter() {
+            let name = flatmate.display_name.as_str();
+            match flatmate.home_address.as_deref().map(normalise_address) {
+                Some(Ok(address)) => {
+                    let (tx, rx) = tokio::sync::oneshot::channel();
+                    app_state
+                        .maps_api()
+                        .add_to_queue(address, std::slice::from_ref(destination), tx)
+                        .await;
+                    pending.push((name, rx));
+                }
+                Some(Err(e)) => {
+                    error!("home address of {} is invalid: {}", flatmate.name, e);
+                    routes.push(HouseholdRoute::Failed(name, "invalid address".to_string()));
+                }
+                None => routes.push(HouseholdRoute::NoAddress(name)),
+            }
+        }
+
+        for (name, rx) in pending {
+            match await_route(rx).await {
+                Ok(element) => routes.push(HouseholdRoute::Found(name, element)),
+                Err(e) => routes.push(HouseholdRoute::Failed(name, e)),
+            }
+        }
+
+        let response = EditInteractionResponse::new().embed(
+            CreateEmbed::new()
+                .title(format!("Travel time from home to {}", destination.label))
+                .description(household_table(routes))
+                .color(0x4285F4),
+        );
+
+        if let Err(e) = interaction.edit_response(ctx, response).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn lookups_are_grouped_by_the_hour_in_the_guilds_timezone() {
+        let lookup = |id: i32, hour: u32, duration: i64| DistanceResultModel {
+            id,
+            origin: "queen street".to_string(),
+            destination: "work".to_string(),
+            distance: 1000,
+            duration,
+            created_at: Utc
+                .with_ymd_and_hms(2023, 6, 1, hour, 30, 0)
+                .unwrap()
+                .with_timezone(&Local)
+                .naive_local(),
+        };
+        let history = [lookup(1, 20, 600), lookup(2, 20, 900), lookup(3, 23, 300)];
+
+        let by_hour = durations_by_hour(&history, Tz::UTC);
+        assert_eq!(
+            by_hour.into_iter().collect::<Vec<_>>(),
+            vec![(20, vec![600, 900]), (23, vec![300])]
+        );
+
+        // auckland is twelve hours ahead in june, so the evening lookups were made in the morning
+        let by_hour = durations_by_hour(&history, Tz::Pacific__Auckland);
+        assert_eq!(
+            by_hour.into_iter().collect::<Vec<_>>(),
+            vec![(8, vec![600, 900]), (11, vec![300])]
+        );
+    }
+
+    #[test]
+    fn addresses_are_tidied() {
+        assert_eq!(
+            normalise_address("  5 Te Apunga  Place,\tMount Wellington "),
+            Ok("5 Te Apunga Place, Mount Wellington".to_string())
+        );
+        assert_eq!(
+            normalise_address("Queen Street"),
+            Ok("Queen Street".to_string())
+        );
+        assert_eq!(normalise_address("5A"), Ok("5A".to_string()));
+    }
+
+    #[test]
+    fn empty_addresses_are_refused() {
+        assert_eq!(
+            normalise_address(""),
+            Err("the address can't be empty".to_string())
+        );
+        assert_eq!(
+            normalise_address("   "),
+            Err("the address can't be empty".to_string())
+        );
+    }
+
+    #[test]
+    fn vague_addresses_are_refused() {
+        for address in ["home", "Auckland"] {
+            let refused = normalise_address(address).unwrap_err();
+            assert!(refused.contains("too vague"), "{}", refused);
+        }
+    }
+
+    #[test]
+    fn junk_is_refused() {
+        for address in ["https://maps.google.com/?q=home", "12 34", "!!!"] {
+            let refused = normalise_address(address).unwrap_err();
+            assert!(
+                refused.contains("doesn't look like an address"),
+                "{}",
+                refused
+            );
+        }
+    }
+}
@@ -32,6 +32,10 @@ impl<'a> Command<'a> for HideCommand {
         i
     }
 
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
     #[allow(clippy::invisible_characters)]
     async fn handle_application_command<'b>(
         self,
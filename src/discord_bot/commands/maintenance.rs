@@ -0,0 +1,111 @@
+use log::info;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{CreateCommand, CreateCommandOption},
+    prelude::Context,
+};
+
+use crate::state::AppState;
+
+use super::{
+    command::Command,
+    util::{require_bot_owner, CommandResponse},
+};
+
+/// Pause every scheduled task and every command which changes anything, for all servers, while the
+/// bot is being worked on
+pub struct MaintenanceCommand {
+    on: bool,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for MaintenanceCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut state: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("state", ResolvedValue::String(val)) => state = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        let on = match state {
+            Some("on") => true,
+            Some("off") => false,
+            Some(val) => return Err(format!("unexpected state `{}`", val)),
+            None => return Err(String::from("state is required")),
+        };
+        Ok(Self { on })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for MaintenanceCommand {
+    fn name() -> &'static str {
+        "maintenance"
+    }
+
+    fn description() -> &'static str {
+        "pause background tasks and changes to the bot's data in every server, for maintenance"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "state",
+                "Whether the bot is in maintenance",
+            )
+            .required(true)
+            .add_string_choice("on", "on")
+            .add_string_choice("off", "off"),
+        )
+    }
+
+    // maintenance has to be turned off while it is on
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        require_bot_owner(interaction)?;
+
+        let changed = app_state.set_maintenance(self.on);
+        if changed {
+            info!(
+                "maintenance turned {} by {}",
+                if self.on { "on" } else { "off" },
+                interaction.user.id
+            );
+        }
+
+        let message = match (self.on, changed) {
+            (true, true) => {
+                "Maintenance is on. Scheduled tasks are paused and nothing can be changed until \
+                 it is turned off, read-only commands still work"
+            }
+            (true, false) => "Maintenance is already on",
+            (false, true) => {
+                "Maintenance is off. Scheduled tasks carry on from the next tick, catching up on \
+                 anything which came due in the meantime"
+            }
+            (false, false) => "Maintenance is already off",
+        };
+        Ok(CommandResponse::BasicSuccess(message.to_string()))
+    }
+}
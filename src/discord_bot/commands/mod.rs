@@ -1,10 +1,70 @@
 mod command;
 mod util;
 
+#[cfg(feature = "shopping")]
+mod alias;
+mod config_show;
+#[cfg(feature = "maps")]
+mod distance;
 mod hide;
+mod maintenance;
 mod pay;
 mod ping;
+#[cfg(feature = "shopping")]
+mod recurring;
 mod say;
+#[cfg(feature = "shopping")]
+mod scheduler;
+#[cfg(feature = "shopping")]
+mod seed;
+#[cfg(feature = "shopping")]
 mod shop;
+#[cfg(feature = "shopping")]
+mod shop_annotate;
+#[cfg(feature = "shopping")]
+mod shop_audit;
+#[cfg(feature = "shopping")]
+mod shop_by;
+#[cfg(feature = "shopping")]
+mod shop_import_photo;
+#[cfg(feature = "shopping")]
+mod shop_insights;
+#[cfg(feature = "shopping")]
+mod shop_leaderboard;
+#[cfg(feature = "shopping")]
+mod shop_oldest;
+#[cfg(feature = "shopping")]
+mod shop_pin;
+#[cfg(all(feature = "maps", feature = "shopping"))]
+mod shop_plan;
+#[cfg(feature = "shopping")]
+mod shop_progress;
+#[cfg(feature = "shopping")]
+mod shop_recipe;
+#[cfg(feature = "shopping")]
+mod shop_share;
+#[cfg(feature = "shopping")]
+mod shop_sheet;
+#[cfg(feature = "shopping")]
+mod shop_store;
+#[cfg(feature = "shopping")]
+mod shop_suggest;
+#[cfg(feature = "shopping")]
+mod shop_template;
+#[cfg(feature = "shopping")]
+mod shop_visibility;
+#[cfg(feature = "shopping")]
+mod shopping_list;
+#[cfg(feature = "shopping")]
+mod word_filter;
 
-pub use command::{application_command, autocomplete, command, interaction};
+#[cfg(feature = "shopping")]
+pub use shop::post_recurring_item;
+
+pub use command::{
+    application_command, application_command_names, autocomplete, command, interaction, modal,
+    validate_application_commands,
+};
+pub use util::{
+    classify_discord_error, is_already_acknowledged, CommandResponse, DiscordErrorKind,
+};
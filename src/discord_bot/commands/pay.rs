@@ -220,6 +220,10 @@ impl<'a> Command<'a> for PayCommand {
         )
     }
 
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
     async fn handle_application_command<'b>(
         self,
         interaction: &'b CommandInteraction,
@@ -515,6 +519,10 @@ impl<'a> Command<'a> for PayAllCommand {
         )
     }
 
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
     async fn handle_application_command<'b>(
         self,
         interaction: &'b CommandInteraction,
@@ -32,6 +32,10 @@ impl<'a> Command<'a> for PingCommand {
         i
     }
 
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
     async fn handle_application_command<'b>(
         self,
         _: &'b CommandInteraction,
@@ -0,0 +1,599 @@
+use chrono::Utc;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            embed::EmbedColor,
+            schedule::{Schedule, SCHEDULE_USAGE},
+        },
+        database::recurring::{NewRecurringItem, RecurringItemModel, SerenityRecurringDatabase},
+    },
+    state::{AppState, CONFIG},
+};
+
+use super::{command::Command, util::CommandResponse, word_filter::filter_text};
+
+/// the guild a command was used in, recurring items are always tied to a guild
+fn guild_id(interaction: &CommandInteraction) -> Result<u64, CommandResponse> {
+    match interaction.guild_id {
+        Some(g) => Ok(g.0.into()),
+        None => Err(CommandResponse::BasicFailure(
+            "this command can only be used in a server".to_string(),
+        )),
+    }
+}
+
+/// the single `item` option shared by the commands which act on an existing recurring item
+fn item_option<'a>(interaction: &'a CommandInteraction) -> Result<&'a str, String> {
+    let mut item: Option<&str> = None;
+
+    for option in interaction.data.options().into_iter() {
+        match (option.name, option.value) {
+            ("item", ResolvedValue::String(val)) => item = Some(val),
+            (opt, val) => {
+                return Err(format!(
+                    "unexpected option name: `{}` and value `{:?}`",
+                    opt, val
+                ))
+            }
+        }
+    }
+
+    item.ok_or_else(|| String::from("item is required"))
+}
+
+/// find a recurring item in the guild by name, ignoring case
+pub(super) async fn find_recurring_item(
+    app_state: &AppState,
+    guild_id: u64,
+    item: &str,
+) -> Result<Option<RecurringItemModel>, CommandResponse> {
+    match app_state.get_recurring_items(guild_id).await {
+        Ok(items) => Ok(items
+            .into_iter()
+            .find(|i| i.item.eq_ignore_ascii_case(item.trim()))),
+        Err(e) => Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        ))),
+    }
+}
+
+/// a line describing a recurring item, used when listing them
+fn describe_recurring_item(item: &RecurringItemModel) -> String {
+    let schedule = match Schedule::parse(&item.schedule_spec) {
+        Ok(schedule) => schedule.to_string(),
+        Err(_) => format!("`{}`", item.schedule_spec),
+    };
+
+    let line = format!(
+        "x{} **{}**{}{} {}",
+        item.quantity,
+        item.item,
+        if item.personal { " (personal)" } else { "" },
+        match &item.store {
+            Some(store) => format!(" from {}", store),
+            None => "".to_string(),
+        },
+        schedule,
+    );
+
+    match item.active {
+        true => format!("{}, next <t:{}:R>", line, item.next_run.timestamp()),
+        false => format!("⏸️ ~~{}~~ (paused)", line),
+    }
+}
+
+/// Add an item to the shopping list on a regular schedule
+pub struct RecurringAddCommand<'a> {
+    item: &'a str,
+    schedule: &'a str,
+    quantity: i64,
+    personal: bool,
+    store: Option<&'a str>,
+    notes: Option<&'a str>,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for RecurringAddCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut item: Option<&str> = None;
+        let mut schedule: Option<&str> = None;
+        let mut quantity: Option<i64> = None;
+        let mut personal: Option<bool> = None;
+        let mut store: Option<&str> = None;
+        let mut notes: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("item", ResolvedValue::String(val)) => item = Some(val),
+                ("schedule", ResolvedValue::String(val)) => schedule = Some(val),
+                ("quantity", ResolvedValue::Integer(val)) => quantity = Some(val),
+                ("personal", ResolvedValue::Boolean(val)) => personal = Some(val),
+                ("store", ResolvedValue::String(val)) => store = Some(val),
+                ("notes", ResolvedValue::String(val)) => notes = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        if item.is_none() || schedule.is_none() {
+            return Err(String::from("item and schedule are required"));
+        }
+
+        Ok(Self {
+            item: item.unwrap(),
+            schedule: schedule.unwrap(),
+            quantity: quantity.unwrap_or(1),
+            personal: personal.unwrap_or(false),
+            store,
+            notes,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for RecurringAddCommand<'a> {
+    fn name() -> &'static str {
+        "recurring-add"
+    }
+
+    fn description() -> &'static str {
+        "add an item to the shopping list on a regular schedule"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "item",
+                "The item to add to the shopping list",
+            )
+            .required(true)
+            .max_length(200)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "schedule",
+                "e.g. `every 2 weeks`, `weekly mon,thu` or `monthly 1`",
+            )
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "quantity",
+                "The quantity of the item to add each time",
+            )
+            .required(false)
+            .min_int_value(1)
+            .max_int_value(25),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "personal",
+                "true if the item is just for you",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "store",
+                "If the item is to be bought or found in a particular store",
+            )
+            .required(false)
+            .max_length(100)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "notes", "Notes about the item")
+                .required(false)
+                .max_length(100)
+                .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let item = filter_text(ctx, app_state, Some(guild_id), "item name", self.item).await?;
+        let notes = match self.notes {
+            Some(notes) => Some(filter_text(ctx, app_state, Some(guild_id), "notes", notes).await?),
+            None => None,
+        };
+
+        let schedule = match Schedule::parse(self.schedule) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                return Err(CommandResponse::BasicFailure(format!(
+                    "{}, {}",
+                    e, SCHEDULE_USAGE
+                )))
+            }
+        };
+
+        if find_recurring_item(app_state, guild_id, &item)
+            .await?
+            .is_some()
+        {
+            return Err(CommandResponse::BasicFailure(format!(
+                "`{}` is already a recurring item, remove it first to change its schedule",
+                item
+            )));
+        }
+
+        let next_run = schedule.next_run(Utc::now(), CONFIG.guild(Some(guild_id)).timezone);
+
+        if let Err(e) = app_state
+            .add_recurring_item(
+                interaction.user.id.into(),
+                interaction.channel_id.into(),
+                guild_id,
+                NewRecurringItem {
+                    item: &item,
+                    store: self.store,
+                    notes: notes.as_deref(),
+                    quantity: self.quantity,
+                    personal: self.personal,
+                    schedule_spec: self.schedule.trim(),
+                    next_run,
+                },
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::BasicSuccess(format!(
+            "x{} {} will be added to the shopping list {}, starting <t:{}:f>",
+            self.quantity,
+            item,
+            schedule,
+            next_run.timestamp()
+        )))
+    }
+}
+
+/// List the recurring items of the guild
+pub struct RecurringListCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for RecurringListCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for RecurringListCommand {
+    fn name() -> &'static str {
+        "recurring-list"
+    }
+
+    fn description() -> &'static str {
+        "list the items which are added to the shopping list on a schedule"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+
+        let items = match app_state.get_recurring_items(guild_id).await {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(
+                "There are no recurring items, add one with /recurring-add".to_string(),
+            ));
+        }
+
+        let description = items
+            .iter()
+            .map(describe_recurring_item)
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().embed(
+                    CreateEmbed::new()
+                        .title("Recurring items")
+                        .description(description)
+                        .color(EmbedColor::Green as u32),
+                ),
+            ),
+        ))
+    }
+}
+
+/// Stop adding a recurring item to the shopping list
+pub struct RecurringRemoveCommand<'a> {
+    item: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for RecurringRemoveCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            item: item_option(interaction)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for RecurringRemoveCommand<'a> {
+    fn name() -> &'static str {
+        "recurring-remove"
+    }
+
+    fn description() -> &'static str {
+        "stop adding a recurring item to the shopping list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "item",
+                "The recurring item to remove",
+            )
+            .required(true)
+            .max_length(200)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+
+        let item = match find_recurring_item(app_state, guild_id, self.item).await? {
+            Some(item) => item,
+            None => {
+                return Err(CommandResponse::BasicFailure(format!(
+                    "`{}` is not a recurring item",
+                    self.item
+                )))
+            }
+        };
+
+        if let Err(e) = app_state.remove_recurring_item(item.id).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::BasicSuccess(format!(
+            "{} will no longer be added to the shopping list",
+            item.item
+        )))
+    }
+}
+
+/// Stop adding a recurring item to the shopping list for now, without forgetting it
+pub struct RecurringPauseCommand<'a> {
+    item: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for RecurringPauseCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            item: item_option(interaction)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for RecurringPauseCommand<'a> {
+    fn name() -> &'static str {
+        "recurring-pause"
+    }
+
+    fn description() -> &'static str {
+        "stop adding a recurring item until it is resumed"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "item",
+                "The recurring item to pause",
+            )
+            .required(true)
+            .max_length(200)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+
+        let item = match find_recurring_item(app_state, guild_id, self.item).await? {
+            Some(item) => item,
+            None => {
+                return Err(CommandResponse::BasicFailure(format!(
+                    "`{}` is not a recurring item",
+                    self.item
+                )))
+            }
+        };
+
+        if !item.active {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "{} is already paused",
+                item.item
+            )));
+        }
+
+        if let Err(e) = app_state.set_recurring_active(item.id, false).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::BasicSuccess(format!(
+            "{} is paused, use /recurring-resume to start adding it again",
+            item.item
+        )))
+    }
+}
+
+/// Start adding a paused recurring item to the shopping list again
+pub struct RecurringResumeCommand<'a> {
+    item: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for RecurringResumeCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            item: item_option(interaction)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for RecurringResumeCommand<'a> {
+    fn name() -> &'static str {
+        "recurring-resume"
+    }
+
+    fn description() -> &'static str {
+        "start adding a paused recurring item again"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "item",
+                "The recurring item to resume",
+            )
+            .required(true)
+            .max_length(200)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+
+        let item = match find_recurring_item(app_state, guild_id, self.item).await? {
+            Some(item) => item,
+            None => {
+                return Err(CommandResponse::BasicFailure(format!(
+                    "`{}` is not a recurring item",
+                    self.item
+                )))
+            }
+        };
+
+        if item.active {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "{} isn't paused",
+                item.item
+            )));
+        }
+
+        let schedule = match Schedule::parse(&item.schedule_spec) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "invalid schedule stored for recurring item {}: {}",
+                    item.id, e
+                )))
+            }
+        };
+
+        // start from the next period, rather than catching up on those missed while paused
+        let next_run = schedule.next_run(Utc::now(), CONFIG.guild(Some(guild_id)).timezone);
+        if let Err(e) = app_state.set_recurring_next_run(item.id, next_run).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+        if let Err(e) = app_state.set_recurring_active(item.id, true).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::BasicSuccess(format!(
+            "{} has been resumed, it will next be added <t:{}:f>",
+            item.item,
+            next_run.timestamp()
+        )))
+    }
+}
@@ -57,6 +57,10 @@ impl<'a> Command<'a> for SayCommand<'a> {
         )
     }
 
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
     async fn handle_application_command<'b>(
         self,
         interaction: &'b CommandInteraction,
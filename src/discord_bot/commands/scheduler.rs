@@ -0,0 +1,315 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        scheduler::{run_task_now, ScheduledTask, SchedulerRegistry},
+    },
+    state::AppState,
+};
+
+use super::{
+    command::Command,
+    util::{owner_guild_id, CommandResponse},
+};
+
+/// the registry the scheduler records into, shared through the context
+async fn registry(ctx: &Context) -> Result<Arc<SchedulerRegistry>, CommandResponse> {
+    match ctx.data.read().await.get::<SchedulerRegistry>() {
+        Some(registry) => Ok(registry.clone()),
+        None => Err(CommandResponse::InternalFailure(
+            "scheduler registry missing from context".to_string(),
+        )),
+    }
+}
+
+/// the single `task` option shared by the commands which act on one task
+fn task_option(interaction: &CommandInteraction) -> Result<ScheduledTask, String> {
+    let mut task: Option<&str> = None;
+
+    for option in interaction.data.options().into_iter() {
+        match (option.name, option.value) {
+            ("task", ResolvedValue::String(val)) => task = Some(val),
+            (opt, val) => {
+                return Err(format!(
+                    "unexpected option name: `{}` and value `{:?}`",
+                    opt, val
+                ))
+            }
+        }
+    }
+
+    let task = task.ok_or_else(|| String::from("task is required"))?;
+    ScheduledTask::from_name(task).ok_or_else(|| format!("unknown task `{}`", task))
+}
+
+/// add the `task` option, offering each task as a choice
+fn add_task_option(cmd: CreateCommand, description: &str) -> CreateCommand {
+    let mut option =
+        CreateCommandOption::new(CommandOptionType::String, "task", description).required(true);
+    for task in ScheduledTask::ALL {
+        option = option.add_string_choice(task.name(), task.name());
+    }
+    cmd.add_option(option)
+}
+
+/// Show each scheduled task, when it will next run and how it last went
+pub struct SchedulerStatusCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for SchedulerStatusCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for SchedulerStatusCommand {
+    fn name() -> &'static str {
+        "scheduler-status"
+    }
+
+    fn description() -> &'static str {
+        "show the background tasks run for this server, and how each last went"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = owner_guild_id(interaction, ctx).await?;
+        let registry = registry(ctx).await?;
+
+        let next_tick = registry.next_tick(guild_id);
+        let mut embed = CreateEmbed::new()
+            .title("Scheduled tasks")
+            .color(EmbedColor::Red as u32);
+        if app_state.in_maintenance() {
+            embed = embed.description("Every task is paused for maintenance, see /maintenance");
+        }
+
+        for task in ScheduledTask::ALL {
+            let next = match (registry.is_paused(guild_id, task), next_tick) {
+                (true, _) => "paused".to_string(),
+                (false, Some(at)) => format!("next run <t:{}:R>", at.timestamp()),
+                (false, None) => "waiting for the first run".to_string(),
+            };
+            let last = match registry.last_run(guild_id, task) {
+                Some(run) => match run.result {
+                    Ok(()) => format!("last ran <t:{}:R>, ok", run.at.timestamp()),
+                    Err(e) => format!("last ran <t:{}:R>, failed: {}", run.at.timestamp(), e),
+                },
+                None => "not run yet".to_string(),
+            };
+
+            embed = embed.field(task.name(), format!("{}\n{}", next, last), false);
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
+
+/// Run a scheduled task straight away rather than waiting for the next tick
+pub struct SchedulerRunCommand {
+    task: ScheduledTask,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for SchedulerRunCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            task: task_option(interaction)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for SchedulerRunCommand {
+    fn name() -> &'static str {
+        "scheduler-run"
+    }
+
+    fn description() -> &'static str {
+        "run a background task for this server now"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        add_task_option(cmd, "The task to run")
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = owner_guild_id(interaction, ctx).await?;
+
+        // a task may post or edit several messages, which can take longer than discord waits
+        if let Err(e) = interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::Defer(
+                    CreateInteractionResponseMessage::new().ephemeral(true),
+                ),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        let content = match run_task_now(self.task, guild_id, ctx, app_state).await {
+            Some(Ok(())) => format!("Ran {}", self.task.name()),
+            Some(Err(e)) => format!("Ran {}, but it failed: {}", self.task.name(), e),
+            None => format!(
+                "{} is already running, try again once it has finished",
+                self.task.name()
+            ),
+        };
+
+        if let Err(e) = interaction
+            .edit_response(ctx, EditInteractionResponse::new().content(content))
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+/// Stop running a scheduled task until it is resumed
+pub struct SchedulerPauseCommand {
+    task: ScheduledTask,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for SchedulerPauseCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            task: task_option(interaction)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for SchedulerPauseCommand {
+    fn name() -> &'static str {
+        "scheduler-pause"
+    }
+
+    fn description() -> &'static str {
+        "stop running a background task for this server until it is resumed"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        add_task_option(cmd, "The task to pause")
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        _: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = owner_guild_id(interaction, ctx).await?;
+        let registry = registry(ctx).await?;
+
+        match registry.set_paused(guild_id, self.task, true) {
+            true => Ok(CommandResponse::BasicSuccess(format!(
+                "{} is paused, use /scheduler-resume to start running it again",
+                self.task.name()
+            ))),
+            false => Ok(CommandResponse::BasicSuccess(format!(
+                "{} is already paused",
+                self.task.name()
+            ))),
+        }
+    }
+}
+
+/// Start running a paused scheduled task again
+pub struct SchedulerResumeCommand {
+    task: ScheduledTask,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for SchedulerResumeCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            task: task_option(interaction)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for SchedulerResumeCommand {
+    fn name() -> &'static str {
+        "scheduler-resume"
+    }
+
+    fn description() -> &'static str {
+        "start running a paused background task for this server again"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        add_task_option(cmd, "The task to resume")
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        _: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = owner_guild_id(interaction, ctx).await?;
+        let registry = registry(ctx).await?;
+
+        match registry.set_paused(guild_id, self.task, false) {
+            true => Ok(CommandResponse::BasicSuccess(format!(
+                "{} will run again from the next tick",
+                self.task.name()
+            ))),
+            false => Ok(CommandResponse::BasicSuccess(format!(
+                "{} isn't paused",
+                self.task.name()
+            ))),
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{CreateCommand, CreateCommandOption},
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::database::{seed::SerenitySeedDatabase, SuggestionKind},
+    state::{AppState, SeedMode, CONFIG},
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// the guild a command was used in, seed lists are always tied to a guild
+fn guild_id(interaction: &CommandInteraction) -> Result<u64, CommandResponse> {
+    match interaction.guild_id {
+        Some(g) => Ok(g.0.into()),
+        None => Err(CommandResponse::BasicFailure(
+            "this command can only be used in a server".to_string(),
+        )),
+    }
+}
+
+/// the `kind` and `value` options shared by the commands which edit the seed lists
+fn seed_options<'a>(
+    interaction: &'a CommandInteraction,
+) -> Result<(SuggestionKind, &'a str), String> {
+    let mut kind: Option<SuggestionKind> = None;
+    let mut value: Option<&str> = None;
+
+    for option in interaction.data.options().into_iter() {
+        match (option.name, option.value) {
+            ("kind", ResolvedValue::String("item")) => kind = Some(SuggestionKind::Item),
+            ("kind", ResolvedValue::String("store")) => kind = Some(SuggestionKind::Store),
+            ("value", ResolvedValue::String(val)) => value = Some(val),
+            (opt, val) => {
+                return Err(format!(
+                    "unexpected option name: `{}` and value `{:?}`",
+                    opt, val
+                ))
+            }
+        }
+    }
+
+    Ok((
+        kind.ok_or_else(|| String::from("kind is required"))?,
+        value.ok_or_else(|| String::from("value is required"))?,
+    ))
+}
+
+/// add the `kind` and `value` options to a command which edits the seed lists
+fn add_seed_options(cmd: CreateCommand, value_description: &str) -> CreateCommand {
+    cmd.add_option(
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "kind",
+            "Whether this is an item or a store",
+        )
+        .required(true)
+        .add_string_choice("item", "item")
+        .add_string_choice("store", "store"),
+    )
+    .add_option(
+        CreateCommandOption::new(CommandOptionType::String, "value", value_description)
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+    )
+}
+
+/// Suggest an item or store in /shop autocomplete, alongside or in place of the built-in ones
+pub struct SeedAddCommand<'a> {
+    kind: SuggestionKind,
+    value: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for SeedAddCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let (kind, value) = seed_options(interaction)?;
+        Ok(Self { kind, value })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for SeedAddCommand<'a> {
+    fn name() -> &'static str {
+        "seed-add"
+    }
+
+    fn description() -> &'static str {
+        "suggest an item or store when adding to the shopping list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        add_seed_options(cmd, "The item or store to suggest, e.g. Trader Joe's")
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let value = self.value.trim();
+
+        if value.is_empty() {
+            return Err(CommandResponse::BasicFailure(format!(
+                "the {} to suggest needs to be given",
+                self.kind.as_str()
+            )));
+        }
+
+        match app_state.add_seed(guild_id, self.kind, value).await {
+            Ok(true) => Ok(CommandResponse::BasicSuccess(format!(
+                "`{}` will now be suggested as a {}",
+                value,
+                self.kind.as_str()
+            ))),
+            Ok(false) => Ok(CommandResponse::BasicSuccess(format!(
+                "`{}` is already suggested as a {}",
+                value,
+                self.kind.as_str()
+            ))),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ))),
+        }
+    }
+}
+
+/// Stop suggesting an item or store added with /seed-add
+pub struct SeedRemoveCommand<'a> {
+    kind: SuggestionKind,
+    value: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for SeedRemoveCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let (kind, value) = seed_options(interaction)?;
+        Ok(Self { kind, value })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for SeedRemoveCommand<'a> {
+    fn name() -> &'static str {
+        "seed-remove"
+    }
+
+    fn description() -> &'static str {
+        "stop suggesting an item or store added with /seed-add"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        add_seed_options(cmd, "The item or store to stop suggesting")
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let value = self.value.trim();
+
+        match app_state.remove_seed(guild_id, self.kind, value).await {
+            Ok(true) => Ok(CommandResponse::BasicSuccess(format!(
+                "`{}` is no longer suggested as a {}",
+                value,
+                self.kind.as_str()
+            ))),
+            Ok(false) => Err(CommandResponse::BasicFailure(format!(
+                "`{}` isn't a {} added with /seed-add, the built-in suggestions can only be turned off with `seed_mode = \"replace\"`",
+                value,
+                self.kind.as_str()
+            ))),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ))),
+        }
+    }
+}
+
+/// List the items and stores added with /seed-add
+pub struct SeedListCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for SeedListCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for SeedListCommand {
+    fn name() -> &'static str {
+        "seed-list"
+    }
+
+    fn description() -> &'static str {
+        "list the items and stores this server has added to the suggestions"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+
+        let mut lists = Vec::new();
+        for kind in [SuggestionKind::Item, SuggestionKind::Store] {
+            let seeds = match app_state.get_seeds(guild_id, kind).await {
+                Ok(seeds) => seeds,
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+            };
+            lists.push(match seeds.is_empty() {
+                true => format!("**{}s:** none", kind.as_str()),
+                false => format!("**{}s:** {}", kind.as_str(), seeds.join(", ")),
+            });
+        }
+
+        let mode = match CONFIG.guild(Some(guild_id)).seed_mode {
+            SeedMode::Merge => "These are suggested alongside the built-in suggestions",
+            SeedMode::Replace => "These are suggested in place of the built-in suggestions",
+        };
+        Ok(CommandResponse::BasicSuccess(format!(
+            "{}\n{}",
+            lists.join("\n"),
+            mode
+        )))
+    }
+}
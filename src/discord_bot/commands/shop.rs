@@ -1,34 +1,129 @@
-use std::{cmp::Ordering, collections::HashSet};
+use std::{borrow::Cow, collections::HashSet, future::Future, time::Duration};
 
-use log::error;
+use log::{error, warn};
 use serenity::{
     all::{
-        AutocompleteOption, ChannelId, CommandInteraction, CommandOptionType, ComponentInteraction,
-        GuildId, Message, ResolvedValue,
+        ActionRowComponent, AutocompleteOption, ChannelId, CommandInteraction, CommandOptionType,
+        ComponentInteraction, ComponentInteractionDataKind, GuildId, InputTextStyle, Message,
+        MessageFlags, MessageId, ModalInteraction, Permissions, ResolvedValue,
     },
     async_trait,
     builder::{
-        AutocompleteChoice, CreateActionRow, CreateAutocompleteResponse, CreateButton,
-        CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
-        CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage,
-        EditMessage,
+        AutocompleteChoice, CreateActionRow, CreateAllowedMentions, CreateAutocompleteResponse,
+        CreateButton, CreateCommand, CreateCommandOption, CreateEmbed, CreateInputText,
+        CreateInteractionResponse, CreateInteractionResponseFollowup,
+        CreateInteractionResponseMessage, CreateMessage, CreateModal, CreateSelectMenu,
+        CreateSelectMenuKind, CreateSelectMenuOption, EditInteractionResponse, EditMessage,
     },
     prelude::Context,
 };
 
 use crate::{
     discord_bot::{
-        common::embed::EmbedColor,
-        database::shopping::{NewShoppingListItem, SerenityShoppingDatabase},
+        common::{
+            bulk::{is_unknown_message, throttled_edit, BulkResult, EDIT_INTERVAL},
+            card::{
+                channel_allows_embeds, embeds_allowed, fit_content, plain_description,
+                render_plain, status_description, ShoppingCard,
+            },
+            custom_id::{decode_custom_id, encode_custom_id},
+            digest::{
+                digest_message, refresh_digest, DIGEST_DONE, DIGEST_MOVE_DOWN, DIGEST_MOVE_UP,
+            },
+            embed::EmbedColor,
+            fuzzy::{closest_match, search_distance, typo_allowance},
+            item_text::clean_item_name,
+            private_thread::{find_or_create_private_thread, routes_to_private_thread},
+            product_link::{clean_product_url, MAX_PRODUCT_URL},
+            quantity::{
+                parse_quantity_words, typical_quantity, QuantityCandidates, QuantitySource,
+                MAX_QUANTITY, TYPICAL_SAMPLE,
+            },
+            quick_edit::{parse_quick_edit, QuickEdit, QUICK_EDIT_USAGE},
+            schedule::{parse_reminder_time, Schedule, SCHEDULE_USAGE},
+            shopping::describe_item,
+            status::{set_status_reaction, set_status_reactions, ItemStatus},
+        },
+        database::{
+            mru::SerenityMruDatabase,
+            private_threads::SerenityPrivateThreadDatabase,
+            recurring::{NewRecurringItem, RecurringItemModel, SerenityRecurringDatabase},
+            reminders::SerenityReminderDatabase,
+            seed::SerenitySeedDatabase,
+            shopping::{NewShoppingListItem, SerenityShoppingDatabase, ShoppingListItemModel},
+            DatabaseResult, SuggestionKind,
+        },
+        utils::{AutocompleteCache, PendingAdd, PendingAddCache, ReaddCooldowns},
     },
-    state::AppState,
+    state::{AppState, ListMode, RemoveMode, SeedMode, CONFIG},
 };
 
 use super::{
-    command::{AutocompleteCommand, Command, InteractionCommand},
-    util::CommandResponse,
+    alias::expand_alias,
+    command::{AutocompleteCommand, Command, InteractionCommand, ModalCommand},
+    recurring::find_recurring_item,
+    shop_visibility::shop_is_ephemeral,
+    shopping_list::{shopping_list_message, GroupBy},
+    util::{discord_failure, CommandResponse},
+    word_filter::filter_text,
 };
 
+/// the custom id prefix of the store select menu, followed by the message id of the item being changed
+const SET_STORE_PREFIX: &str = "set-store:";
+
+/// the custom id prefix of the modal used to duplicate an item, followed by the message id of the
+/// item being copied
+const DUPLICATE_PREFIX: &str = "duplicate:";
+
+/// the custom id prefix of the modal used to set the quantity of an item, followed by the message
+/// id of the item being changed
+const SET_QUANTITY_PREFIX: &str = "set-quantity:";
+
+/// the custom id prefix of the modal used to split part of an item off to another store, followed
+/// by the message id of the item being split
+const SPLIT_PREFIX: &str = "split:";
+
+/// the custom id prefix of the modal used to set a reminder about an item, followed by the message
+/// id of the item
+const REMIND_PREFIX: &str = "remind:";
+
+/// the custom id prefix of the modal used to change an item by typing what to change, followed by
+/// the message id of the item
+const QUICK_EDIT_PREFIX: &str = "quick-edit:";
+
+/// the custom id prefix of the modal used to add a bought item to the list on a schedule, followed
+/// by the message id of the item
+const MAKE_RECURRING_PREFIX: &str = "make-recurring:";
+
+/// the custom id prefix of the modal used to turn part of an item's note into an item of its own,
+/// followed by the message id of the item
+const NOTE_TO_ITEM_PREFIX: &str = "note-to-item:";
+
+/// characters which separate the things written in a note, trimmed from around the part of a note
+/// taken out of it
+const NOTE_SEPARATORS: &[char] = &[',', ';', '/', '&', '+'];
+
+/// the number of times an item posted by a re-add is written to the database before giving up
+const STORE_ATTEMPTS: u32 = 3;
+
+/// the pause between attempts to write a re-added item to the database
+const STORE_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// the permission the bot needs to edit its own messages in a channel, interactions themselves
+/// are answered through their token so need no permissions
+const VIEW_CHANNEL: &str = "View Channel";
+
+/// the custom id prefix of the button which adds to a near-duplicate item instead of posting a new
+/// one, followed by the key of the pending add and the id of the existing item
+const MERGE_PREFIX: &str = "merge:";
+
+/// the custom id prefix of the button which posts an item despite a near-duplicate, followed by
+/// the key of the pending add
+const ADD_SEPARATELY_PREFIX: &str = "add-separately:";
+
+/// the select menu value used to clear the store of an item
+const NO_STORE_VALUE: &str = "__no_store__";
+
 const EXTRA_STORE_NAMES: &[&str] = &[
     "Pack'n'Save",
     "Countdown",
@@ -107,6 +202,303 @@ const EXTRA_ITEMS: &[&str] = &[
     "tomato",
 ];
 
+/// the most pending items checked when marking suggestions which are already on the list
+const MAX_PENDING_LOOKUP: u64 = 200;
+
+/// the marker added to an item suggestion which is already on the list
+const PENDING_MARKER: &str = " • already on list";
+
+/// the longest name discord allows for an autocomplete choice
+const MAX_CHOICE_NAME_LENGTH: usize = 100;
+
+/// the lowercased names of the items pending in a guild, leaving out other users' personal items
+/// as they aren't duplicates of anything the user adds
+async fn pending_item_names(app_state: &AppState, guild_id: u64, user_id: u64) -> HashSet<String> {
+    match app_state
+        .get_recent_pending_items(guild_id, MAX_PENDING_LOOKUP)
+        .await
+    {
+        Ok(items) => items
+            .into_iter()
+            .filter(|item| !item.personal || item.user_id == user_id as i64)
+            .map(|item| item.item.to_lowercase())
+            .collect(),
+        Err(e) => {
+            // the suggestions are still useful without the markers
+            error!("error loading pending items for autocomplete: {}", e);
+            HashSet::new()
+        }
+    }
+}
+
+/// the name of a suggestion which is already on the list, shortening the item so the marker fits.
+/// Only the name shown is changed, the value filled in is still the item
+fn mark_pending(item: &str) -> String {
+    let keep = MAX_CHOICE_NAME_LENGTH - PENDING_MARKER.chars().count();
+    let mut name: String = item.chars().take(keep).collect();
+    name.push_str(PENDING_MARKER);
+    name
+}
+
+/// the default suggestions of a kind for a guild, being the entries it added with `/seed-add`
+/// alongside or in place of the built-in ones. A guild with no entries of its own always gets the
+/// built-in ones, as does one whose entries can't be loaded
+async fn guild_seeds(
+    app_state: &AppState,
+    guild_id: Option<u64>,
+    kind: SuggestionKind,
+) -> Vec<Cow<'static, str>> {
+    let guild_id = match guild_id {
+        Some(guild_id) => guild_id,
+        None => return seed_suggestions(kind, Vec::new(), SeedMode::default()),
+    };
+    let seeds = match app_state.get_seeds(guild_id, kind).await {
+        Ok(seeds) => seeds,
+        Err(e) => {
+            error!("error loading {} seeds: {}", kind.as_str(), e);
+            Vec::new()
+        }
+    };
+
+    seed_suggestions(kind, seeds, CONFIG.guild(Some(guild_id)).seed_mode)
+}
+
+/// combine a guild's own entries with the built-in suggestions as its seed mode says. This runs on
+/// every keystroke, so the built-in suggestions are borrowed rather than copied
+fn seed_suggestions(
+    kind: SuggestionKind,
+    seeds: Vec<String>,
+    mode: SeedMode,
+) -> Vec<Cow<'static, str>> {
+    let built_in = match kind {
+        SuggestionKind::Item => EXTRA_ITEMS,
+        SuggestionKind::Store => EXTRA_STORE_NAMES,
+    };
+    let built_in = built_in.iter().map(|&seed| Cow::Borrowed(seed));
+
+    match (mode, seeds.is_empty()) {
+        (_, true) => built_in.collect(),
+        (SeedMode::Replace, false) => seeds.into_iter().map(Cow::Owned).collect(),
+        (SeedMode::Merge, false) => seeds.into_iter().map(Cow::Owned).chain(built_in).collect(),
+    }
+}
+
+/// the items to draw autocomplete suggestions from, being the user's own recent items followed by
+/// anyone else's. A failed query only loses its own candidates, the built-in suggestions are still
+/// offered alongside whatever loaded. With the database down entirely, the last good candidates
+/// for the user in the guild are better than none at all
+fn autocomplete_candidates(
+    user_items: DatabaseResult<Vec<ShoppingListItemModel>>,
+    recent_items: DatabaseResult<Vec<ShoppingListItemModel>>,
+    cache: Option<&AutocompleteCache>,
+    guild_id: Option<u64>,
+    user_id: u64,
+) -> Vec<ShoppingListItemModel> {
+    let (mut items, user_failed) = match user_items {
+        Ok(items) => (items, false),
+        Err(e) => {
+            error!("error loading recent items for autocomplete: {}", e);
+            (Vec::new(), true)
+        }
+    };
+
+    let (extra_items, extra_failed) = match recent_items {
+        Ok(items) => (items, false),
+        Err(e) => {
+            error!("error loading recent items for autocomplete: {}", e);
+            (Vec::new(), true)
+        }
+    };
+
+    // the user's own items come first, global items only add what isn't already there
+    let mut seen: HashSet<i32> = items.iter().map(|item| item.id).collect();
+    items.extend(extra_items.into_iter().filter(|item| seen.insert(item.id)));
+
+    // only a complete set of candidates is cached, so that one failed query can't replace a good
+    // cache with half the suggestions
+    if let Some(cache) = cache {
+        match (user_failed, extra_failed) {
+            (false, false) => cache.insert(guild_id, user_id, items.clone()),
+            (true, true) => {
+                if let Some(cached) = cache.get(guild_id, user_id) {
+                    items = cached;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    items
+}
+
+/// answer autocomplete for an option of /shop with the choices for the kind of name it takes. An
+/// option discord has but we don't, most likely from a stale command definition, gets no choices,
+/// as an error would just show the user a failed interaction
+async fn autocomplete_response<F, Fut>(option: &str, choices: F) -> CreateAutocompleteResponse
+where
+    F: FnOnce(SuggestionKind) -> Fut,
+    Fut: Future<Output = Vec<AutocompleteChoice>>,
+{
+    let kind = match option {
+        "item" => SuggestionKind::Item,
+        "store" => SuggestionKind::Store,
+        option => {
+            warn!("autocomplete for unknown option `{}` of /shop", option);
+            return CreateAutocompleteResponse::new();
+        }
+    };
+
+    CreateAutocompleteResponse::new().set_choices(choices(kind).await)
+}
+
+/// the items or stores suggested to a user for what they've typed so far, marking items which are
+/// already on the list
+async fn suggestion_choices(
+    kind: SuggestionKind,
+    command: &CommandInteraction,
+    search_phrase: &str,
+    app_state: &AppState,
+    ctx: &Context,
+) -> Vec<AutocompleteChoice> {
+    let user_id: u64 = command.user.id.into();
+    let guild_id: Option<u64> = command.guild_id.map(|g| g.0.into());
+    let limit = CONFIG.guild(guild_id).autocomplete_limit();
+
+    let user_items = app_state
+        .get_recent_shopping_list_items_by_user(user_id, 50)
+        .await;
+    let recent_items = app_state.get_recent_shopping_list_items(50).await;
+    let items = autocomplete_candidates(
+        user_items,
+        recent_items,
+        ctx.data
+            .read()
+            .await
+            .get::<AutocompleteCache>()
+            .map(|cache| cache.as_ref()),
+        guild_id,
+        user_id,
+    );
+
+    let history: Vec<String> = match kind {
+        SuggestionKind::Item => items.into_iter().map(|item| item.item).collect(),
+        SuggestionKind::Store => items.into_iter().filter_map(|item| item.store).collect(),
+    };
+    let names = with_recently_used(
+        recently_used(app_state, user_id, kind, limit).await,
+        ranked_suggestions(
+            history,
+            guild_seeds(app_state, guild_id, kind).await,
+            search_phrase,
+            limit,
+        ),
+        search_phrase,
+        limit,
+    );
+
+    // only items are marked, a store being on the list already is no reason not to pick it
+    let pending = match (kind, guild_id) {
+        (SuggestionKind::Item, Some(guild_id)) => {
+            pending_item_names(app_state, guild_id, user_id).await
+        }
+        _ => HashSet::new(),
+    };
+
+    names
+        .into_iter()
+        .map(|name| AutocompleteChoice {
+            name: match pending.contains(&name.to_lowercase()) {
+                true => mark_pending(&name),
+                false => name.clone(),
+            },
+            value: serde_json::Value::String(name),
+        })
+        .collect()
+}
+
+/// the items or stores a user has added most recently, for the top of their suggestions
+async fn recently_used(
+    app_state: &AppState,
+    user_id: u64,
+    kind: SuggestionKind,
+    limit: usize,
+) -> Vec<String> {
+    match app_state.get_mru(user_id, kind, limit as u64).await {
+        Ok(recent) => recent,
+        Err(e) => {
+            // the other suggestions are still useful without these
+            error!("error loading recently used {}s: {}", kind.as_str(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// put the names a user used most recently which contain the search phrase above the other
+/// suggestions, most recent first, without repeating them further down
+fn with_recently_used(
+    recent: Vec<String>,
+    ranked: Vec<String>,
+    search_phrase: &str,
+    limit: usize,
+) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut names: Vec<String> = recent
+        .into_iter()
+        .filter(|name| name.contains(search_phrase))
+        .chain(ranked)
+        .filter(|name| seen.insert(name.clone()))
+        .collect();
+    names.truncate(limit);
+    names
+}
+
+/// dedupe autocomplete suggestions and order them, preferring those which start with, then
+/// contain, the search phrase, then those within a few typos of it, closest first. Within each of
+/// those, names from the list's history come before the static defaults, so a guild's own stores
+/// and items aren't crowded out by ones it never uses. Remaining ties are broken alphabetically,
+/// so the same suggestions are always offered in the same order whatever order they were loaded
+/// in.
+fn ranked_suggestions<'a>(
+    history: impl IntoIterator<Item = String>,
+    defaults: impl IntoIterator<Item = Cow<'a, str>>,
+    search_phrase: &str,
+    limit: usize,
+) -> Vec<String> {
+    // a default which has also been used is ranked as history
+    let mut seen = HashSet::new();
+    let mut names: Vec<(bool, Cow<str>)> = history
+        .into_iter()
+        .map(|name| (false, Cow::Owned(name)))
+        .chain(defaults.into_iter().map(|name| (true, name)))
+        .filter(|(_, name)| seen.insert(name.clone()))
+        .collect();
+
+    // only names which don't already contain the search are matched loosely, e.g. `brocoli`
+    let allowance = typo_allowance(search_phrase);
+    let typos = |name: &str| match name.contains(search_phrase) {
+        true => None,
+        false => Some(search_distance(search_phrase, name)).filter(|typos| *typos <= allowance),
+    };
+
+    names.sort_by_cached_key(|(is_default, name)| {
+        let distance = typos(name);
+        (
+            !name.starts_with(search_phrase),
+            !name.contains(search_phrase),
+            distance.is_none(),
+            distance,
+            *is_default,
+            name.clone(),
+        )
+    });
+    // only the names suggested are copied, the rest of the defaults are never more than borrowed
+    names.truncate(limit);
+    names
+        .into_iter()
+        .map(|(_, name)| name.into_owned())
+        .collect()
+}
+
 #[async_trait]
 trait Interactable: Sync {
     async fn interactable_create_response(
@@ -126,6 +518,7 @@ trait Interactable: Sync {
     fn user(&self) -> &serenity::model::user::User;
     fn channel_id(&self) -> ChannelId;
     fn guild_id(&self) -> Option<GuildId>;
+    fn app_permissions(&self) -> Option<Permissions>;
 }
 
 #[async_trait]
@@ -161,6 +554,10 @@ impl Interactable for CommandInteraction {
     fn guild_id(&self) -> Option<GuildId> {
         self.guild_id
     }
+
+    fn app_permissions(&self) -> Option<Permissions> {
+        self.app_permissions
+    }
 }
 
 #[async_trait]
@@ -196,10 +593,55 @@ impl Interactable for ComponentInteraction {
     fn guild_id(&self) -> Option<GuildId> {
         self.guild_id
     }
+
+    fn app_permissions(&self) -> Option<Permissions> {
+        self.app_permissions
+    }
+}
+
+#[async_trait]
+impl Interactable for ModalInteraction {
+    async fn interactable_create_response(
+        &self,
+        ctx: &Context,
+        response: CreateInteractionResponse,
+    ) -> Result<(), serenity::Error> {
+        self.create_response(ctx, response).await
+    }
+
+    async fn interactable_create_followup(
+        &self,
+        ctx: &Context,
+        response: CreateInteractionResponseFollowup,
+    ) -> Result<Message, serenity::Error> {
+        self.create_followup(ctx, response).await
+    }
+
+    async fn interactable_get_response(&self, ctx: &Context) -> Result<Message, serenity::Error> {
+        self.get_response(ctx).await
+    }
+
+    fn user(&self) -> &serenity::model::user::User {
+        &self.user
+    }
+
+    fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+
+    fn app_permissions(&self) -> Option<Permissions> {
+        self.app_permissions
+    }
 }
 
 trait Constructable: Default {
     fn add_embed(self, embed: CreateEmbed) -> Self;
+    /// add text which pings no one, even if it mentions someone as an embed would
+    fn add_content(self, content: String) -> Self;
     fn add_components(self, components: Vec<CreateActionRow>) -> Self;
 }
 
@@ -208,6 +650,11 @@ impl Constructable for CreateInteractionResponseFollowup {
         self.embed(embed)
     }
 
+    fn add_content(self, content: String) -> Self {
+        self.content(content)
+            .allowed_mentions(CreateAllowedMentions::new())
+    }
+
     fn add_components(self, components: Vec<CreateActionRow>) -> Self {
         self.components(components)
     }
@@ -218,6 +665,11 @@ impl Constructable for CreateMessage {
         self.embed(embed)
     }
 
+    fn add_content(self, content: String) -> Self {
+        self.content(content)
+            .allowed_mentions(CreateAllowedMentions::new())
+    }
+
     fn add_components(self, components: Vec<CreateActionRow>) -> Self {
         self.components(components)
     }
@@ -276,6 +728,9 @@ async fn push_list_item_to_database<'b, A: Interactable>(
                 quantity: shop.quantity,
                 store: shop.store,
                 notes: shop.notes,
+                recipe_group: shop.recipe,
+                bought_for: shop.bought_for,
+                product_url: shop.product_url,
             },
         )
         .await
@@ -294,646 +749,4131 @@ async fn push_list_item_to_database<'b, A: Interactable>(
         }
         return Err(CommandResponse::NoResponse);
     }
+
+    record_recently_used(state, user_id, &shop).await;
+
+    // digest items have no message of their own, only an ephemeral confirmation
+    if CONFIG.guild(guild_id).list_mode == ListMode::Classic {
+        set_status_reaction(
+            ctx,
+            interaction.channel_id(),
+            MessageId::new(message_id),
+            ItemStatus::Pending,
+        )
+        .await;
+    }
     Ok(())
 }
 
-async fn create_new_shopping<'b, B: Constructable>(
-    shop: &'b Shop<'b>,
-) -> Result<B, CommandResponse> {
-    Ok(B::default()
-        .add_embed(
-            CreateEmbed::new()
-                // .title("Added to shopping list") //XXX: experiment
-                .description(format!(
-                    "Added x{} {}{} to the shopping list{}{}",
-                    shop.quantity,
-                    shop.item,
-                    if shop.personal { " (personal)" } else { "" },
-                    if shop.store.is_some() {
-                        format!(" from {}", shop.store.unwrap())
-                    } else {
-                        "".to_string()
-                    },
-                    if shop.notes.is_some() {
-                        format!("\n**note:** {}", shop.notes.unwrap())
-                    } else {
-                        "".to_string()
-                    },
-                ))
-                .color(EmbedColor::Red as u32),
-        )
-        .add_components(vec![CreateActionRow::Buttons(vec![
+/// put the item and store of an item just added at the top of its user's suggestions. The item is
+/// already added, so failing to record them only leaves the suggestions stale
+async fn record_recently_used(state: &AppState, user_id: u64, shop: &Shop<'_>) {
+    for (kind, value) in [
+        (SuggestionKind::Item, Some(shop.item)),
+        (SuggestionKind::Store, shop.store),
+    ] {
+        if let Some(value) = value {
+            if let Err(e) = state.record_mru(user_id, kind, value).await {
+                error!("error recording recently used {}: {}", kind.as_str(), e);
+            }
+        }
+    }
+}
+
+/// the buttons on an item's message, which depend on the item's quantity and notes
+fn item_components(card: &ShoppingCard) -> Vec<CreateActionRow> {
+    let mut rows = vec![
+        CreateActionRow::Buttons(vec![
             CreateButton::new("bought")
                 .style(serenity::all::ButtonStyle::Success)
                 .label("Bought"),
             CreateButton::new("remove")
                 .style(serenity::all::ButtonStyle::Danger)
                 .label("Remove"),
+            CreateButton::new("change-store")
+                .style(serenity::all::ButtonStyle::Secondary)
+                .label("Change store"),
+            CreateButton::new("duplicate")
+                .style(serenity::all::ButtonStyle::Secondary)
+                .label("Duplicate"),
             CreateButton::new("readd")
                 .style(serenity::all::ButtonStyle::Secondary)
                 .label("Re-add")
                 .disabled(true),
-        ])]))
+        ]),
+        // discord allows at most 5 buttons in a row
+        CreateActionRow::Buttons(
+            vec![
+                CreateButton::new("set-quantity")
+                    .style(serenity::all::ButtonStyle::Secondary)
+                    .label("Set quantity"),
+                CreateButton::new("split")
+                    .style(serenity::all::ButtonStyle::Secondary)
+                    .label("Split"),
+                CreateButton::new("remind")
+                    .style(serenity::all::ButtonStyle::Secondary)
+                    .label("Remind me"),
+                CreateButton::new("quick-edit")
+                    .style(serenity::all::ButtonStyle::Secondary)
+                    .label("Quick edit"),
+            ]
+            .into_iter()
+            .chain(card.product_button())
+            .collect(),
+        ),
+    ];
+
+    // a quantity can't go below 1 or above the most allowed, so the buttons to step it past them
+    // are disabled there
+    let mut buttons = vec![
+        CreateButton::new("qty-dec")
+            .style(serenity::all::ButtonStyle::Secondary)
+            .label("−")
+            .disabled(card.quantity() <= 1),
+        CreateButton::new("qty-inc")
+            .style(serenity::all::ButtonStyle::Secondary)
+            .label("+")
+            .disabled(card.quantity() >= MAX_QUANTITY),
+    ];
+    // only offered when there's a note to take an item out of
+    if card.has_notes() {
+        buttons.push(
+            CreateButton::new("note-to-item")
+                .style(serenity::all::ButtonStyle::Secondary)
+                .label("Note to item"),
+        );
+    }
+    rows.push(CreateActionRow::Buttons(buttons));
+    rows
 }
 
-#[derive(Debug)]
-pub struct Shop<'a> {
-    item: &'a str,
-    personal: bool,
-    quantity: i64,
-    store: Option<&'a str>,
-    notes: Option<&'a str>,
+/// an item's message redrawn in place after the item was changed from it, along with its buttons
+/// as they depend on what was changed
+fn item_update(item: &ShoppingListItemModel, embeds: bool) -> CreateInteractionResponseMessage {
+    let card = ShoppingCard::from_item(item);
+    card.update(embeds).components(item_components(&card))
 }
 
-impl<'a> TryFrom<&'a CommandInteraction> for Shop<'a> {
-    type Error = String;
-    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
-        let options = interaction.data.options();
+/// as [item_update], for editing an item's message from elsewhere
+fn item_edit(item: &ShoppingListItemModel, embeds: bool) -> EditMessage {
+    let card = ShoppingCard::from_item(item);
+    card.edit(embeds).components(item_components(&card))
+}
 
-        let mut item: Option<&str> = None;
-        let mut personal: Option<bool> = None;
-        let mut quantity: Option<i64> = None;
-        let mut store: Option<&str> = None;
-        let mut notes: Option<&str> = None;
+/// the message posted for a new item, as plain text rather than an embed if `embeds` is false.
+/// The buttons are the same either way, as they don't need the bot to be allowed to post embeds
+async fn create_new_shopping<'b, B: Constructable>(
+    card: &'b ShoppingCard<'b>,
+    embeds: bool,
+) -> Result<B, CommandResponse> {
+    let message = match embeds {
+        true => B::default().add_embed(card.embed()),
+        false => B::default().add_content(render_plain(&card.description(), ItemStatus::Pending)),
+    };
+    Ok(message.add_components(item_components(card)))
+}
 
-        for option in options.into_iter() {
-            match (option.name, option.value) {
-                ("item", ResolvedValue::String(val)) => item = Some(val),
-                ("personal", ResolvedValue::Boolean(val)) => personal = Some(val),
-                ("quantity", ResolvedValue::Integer(val)) => quantity = Some(val),
-                ("store", ResolvedValue::String(val)) => store = Some(val),
-                ("notes", ResolvedValue::String(val)) => notes = Some(val),
-                (opt, val) => {
-                    panic!("unexpected option name: `{}` and value `{:?}`", opt, val)
-                }
-            }
+/// replace the card of an item's message with its new status, as an embed or as plain text
+fn status_edit(
+    description: &str,
+    status: ItemStatus,
+    color: EmbedColor,
+    embeds: bool,
+) -> EditMessage {
+    match embeds {
+        true => EditMessage::new().content("").embed(
+            CreateEmbed::new()
+                //XXX: title?
+                .description(status_description(description, status))
+                .color(color as u32),
+        ),
+        false => EditMessage::new()
+            .content(render_plain(description, status))
+            .embeds(vec![]),
+    }
+}
+
+/// the description of the item a message is for, whether it was posted as an embed or as plain
+/// text
+fn message_description(message: &Message) -> Option<&str> {
+    match message.embeds.first() {
+        Some(embed) => embed.description.as_deref(),
+        None => plain_description(&message.content),
+    }
+}
+
+/// the pending items redrawn after one was ticked off or moved, on either the digest or the
+/// ephemeral list shown by /shopping-list
+fn list_update(
+    message: &Message,
+    items: &[ShoppingListItemModel],
+) -> CreateInteractionResponseMessage {
+    let ephemeral = message
+        .flags
+        .map_or(false, |flags| flags.contains(MessageFlags::EPHEMERAL));
+    match ephemeral {
+        true => {
+            let title = message
+                .embeds
+                .first()
+                .and_then(|embed| embed.title.as_deref());
+            shopping_list_message(items, GroupBy::from_title(title))
+        }
+        false => {
+            let (embed, components) = digest_message(items);
+            CreateInteractionResponseMessage::new()
+                .embed(embed)
+                .components(components)
         }
+    }
+}
 
-        if item.is_none() || personal.is_none() {
-            return Err(String::from("item and personal are required"));
+/// the edit applied to an item's message once it has been bought
+pub(super) fn bought_message(description: &str, embeds: bool) -> EditMessage {
+    status_edit(description, ItemStatus::Bought, EmbedColor::Green, embeds).components(vec![
+        CreateActionRow::Buttons(vec![
+            CreateButton::new("readd")
+                .style(serenity::all::ButtonStyle::Secondary)
+                .label("Re-add")
+                .disabled(false),
+            CreateButton::new("make-recurring")
+                .style(serenity::all::ButtonStyle::Secondary)
+                .label("Make recurring"),
+        ]),
+    ])
+}
+
+/// the edit applied to an item's message once it has been removed from the list
+pub(super) fn removed_message(description: &str, embeds: bool) -> EditMessage {
+    status_edit(description, ItemStatus::Removed, EmbedColor::Orange, embeds).components(vec![
+        CreateActionRow::Buttons(vec![CreateButton::new("readd")
+            .style(serenity::all::ButtonStyle::Secondary)
+            .label("Re-add")
+            .disabled(false)]),
+    ])
+}
+
+/// the edit applied to an item's old message once /shopping-complete has posted it again
+fn refreshed_message(description: &str, embeds: bool) -> EditMessage {
+    let description = format!("(REFRESHED) ~~{}~~", description);
+    let edit = match embeds {
+        true => EditMessage::new().content("").embed(
+            CreateEmbed::new()
+                .description(description)
+                .color(EmbedColor::Blue as u32),
+        ),
+        false => EditMessage::new().content(description).embeds(vec![]),
+    };
+    edit.components(vec![])
+}
+
+/// take the item posted in `message_id` off the list as `mode` says. A deleted item's message is
+/// deleted with `delete_message` before its row, so a failure never leaves a card with dead
+/// buttons behind for an item which is gone
+async fn remove_item<F, Fut>(
+    state: &AppState,
+    mode: RemoveMode,
+    user_id: u64,
+    message_id: u64,
+    delete_message: F,
+) -> Result<(), CommandResponse>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<(), CommandResponse>>,
+{
+    if mode == RemoveMode::Strike {
+        return state
+            .set_shopping_list_item_bought(user_id, message_id, true, None)
+            .await
+            .map_err(|e| {
+                CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                ))
+            });
+    }
+
+    match state.get_shopping_list_item_by_message_id(message_id).await {
+        Ok(Some(item)) if item.user_id == user_id as i64 => {}
+        Ok(Some(_)) => {
+            return Err(CommandResponse::BasicFailure(
+                "only the person who added this item can delete it".to_string(),
+            ))
         }
-        let item = item.unwrap();
-        let personal = personal.unwrap();
-        let quantity = quantity.unwrap_or(1);
+        Ok(None) => {
+            return Err(CommandResponse::BasicFailure(
+                "this item is no longer on the list".to_string(),
+            ))
+        }
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )))
+        }
+    }
 
-        Ok(Shop {
-            item,
-            personal,
-            quantity,
-            store,
-            notes,
-        })
+    delete_message().await?;
+
+    match state.delete_shopping_list_item(user_id, message_id).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        ))),
     }
 }
 
-#[async_trait]
-impl<'a> Command<'a> for Shop<'a> {
-    fn name() -> &'static str {
-        "shop"
+/// post a copy of a stored item as a new item on the list, added by the user who interacted
+pub(super) async fn readd_item(
+    interaction: &ComponentInteraction,
+    app_state: &AppState,
+    ctx: &Context,
+    item: &ShoppingListItemModel,
+) -> Result<(), CommandResponse> {
+    create_loading_message(interaction, ctx).await?;
+    let shop = Shop {
+        item: item.item.as_ref(),
+        personal: item.personal,
+        quantity: item.quantity,
+        quantity_source: QuantitySource::Explicit,
+        store: item.store.as_deref(),
+        notes: item.notes.as_deref(),
+        recipe: item.recipe_group.as_deref(),
+        bought_for: item.bought_for.as_deref(),
+        product_url: item.product_url.as_deref(),
+    };
+    let card = shop.card(interaction.guild_id.map(|g| g.0.into()));
+    let resp = create_new_shopping(&card, embeds_allowed(interaction.app_permissions)).await?;
+
+    let guild_id = interaction.guild_id.map(|g| g.0.into());
+    let message = post_then_store(
+        async {
+            interaction.create_followup(&ctx, resp).await.map_err(|e| {
+                CommandResponse::InternalFailure(format!("error communicating with discord: {}", e))
+            })
+        },
+        |message: &Message| {
+            store_posted_item(
+                app_state,
+                &shop,
+                interaction.user.id.into(),
+                message.id.into(),
+                interaction.channel_id.into(),
+                guild_id,
+            )
+        },
+        |message: Message| async move {
+            if let Err(e) = interaction.delete_followup(&ctx, message.id).await {
+                error!("error deleting re-added message {}: {}", message.id, e);
+            }
+        },
+    )
+    .await?;
+
+    if CONFIG.guild(guild_id).list_mode == ListMode::Classic {
+        set_status_reaction(ctx, message.channel_id, message.id, ItemStatus::Pending).await;
     }
+    Ok(())
+}
 
-    fn description() -> &'static str {
-        "add an item to the shopping list"
+/// post the message for an item with `post`, then store the item with `store`. The buttons of a
+/// message without an item do nothing, so if it can't be stored the message is taken back down
+/// with `delete` and the failure returned
+async fn post_then_store<M, S, SFut, D, DFut>(
+    post: impl Future<Output = Result<M, CommandResponse>>,
+    store: S,
+    delete: D,
+) -> Result<M, CommandResponse>
+where
+    S: FnOnce(&M) -> SFut,
+    SFut: Future<Output = DatabaseResult<()>>,
+    D: FnOnce(M) -> DFut,
+    DFut: Future<Output = ()>,
+{
+    let message = post.await?;
+
+    match store(&message).await {
+        Ok(()) => Ok(message),
+        Err(e) => {
+            delete(message).await;
+            Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )))
+        }
     }
+}
 
-    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
-        cmd.add_option(
-            CreateCommandOption::new(
-                CommandOptionType::String,
-                "item",
-                "The item to add to the shopping list",
-            )
-            .required(true)
-            .set_autocomplete(true)
-            .max_length(200)
-            .to_owned(),
-        )
-        .add_option(
-            CreateCommandOption::new(
-                CommandOptionType::Boolean,
-                "personal",
-                "true if the item is just for you",
+/// add an item which has just been posted in `message_id` to the database, retrying a failed
+/// write
+async fn store_posted_item(
+    state: &AppState,
+    shop: &Shop<'_>,
+    user_id: u64,
+    message_id: u64,
+    channel_id: u64,
+    guild_id: Option<u64>,
+) -> DatabaseResult<()> {
+    store_with_retries(
+        message_id,
+        || {
+            state.add_shopping_list_item(
+                user_id,
+                message_id,
+                channel_id,
+                guild_id,
+                NewShoppingListItem {
+                    item: shop.item,
+                    personal: shop.personal,
+                    quantity: shop.quantity,
+                    store: shop.store,
+                    notes: shop.notes,
+                    recipe_group: shop.recipe,
+                    bought_for: shop.bought_for,
+                    product_url: shop.product_url,
+                },
             )
-            .required(true),
+        },
+        || async {
+            state
+                .get_shopping_list_item_by_message_id(message_id)
+                .await
+                .map(|item| item.is_some())
+        },
+    )
+    .await
+}
+
+/// write the item posted in `message_id` with `add`, retrying a failed write. A write can fail
+/// after the row was stored, so each retry first asks `stored` whether it was and the item is
+/// never stored twice
+async fn store_with_retries<A, AFut, C, CFut>(
+    message_id: u64,
+    mut add: A,
+    mut stored: C,
+) -> DatabaseResult<()>
+where
+    A: FnMut() -> AFut,
+    AFut: Future<Output = DatabaseResult<()>>,
+    C: FnMut() -> CFut,
+    CFut: Future<Output = DatabaseResult<bool>>,
+{
+    let mut attempt = 1;
+    loop {
+        if attempt > 1 {
+            match stored().await {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => warn!("error checking for item in {}: {}", message_id, e),
+            }
+        }
+
+        match add().await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < STORE_ATTEMPTS => {
+                warn!(
+                    "error adding item in {}, attempt {} of {}: {}",
+                    message_id, attempt, STORE_ATTEMPTS, e
+                );
+                tokio::time::sleep(STORE_RETRY_DELAY).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// post a due recurring item to its channel, adding it to the shopping list as though the user
+/// who created the recurring item had added it with /shop
+pub async fn post_recurring_item(
+    ctx: &Context,
+    state: &AppState,
+    item: &RecurringItemModel,
+    silent: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let shop = Shop {
+        item: item.item.as_ref(),
+        personal: item.personal,
+        quantity: item.quantity,
+        quantity_source: QuantitySource::Explicit,
+        store: item.store.as_deref(),
+        notes: item.notes.as_deref(),
+        recipe: None,
+        bought_for: None,
+        product_url: None,
+    };
+
+    post_item_to_channel(
+        ctx,
+        state,
+        shop,
+        item.user_id as u64,
+        item.channel_id as u64,
+        Some(item.guild_id as u64),
+        silent,
+    )
+    .await
+}
+
+/// post an item to a channel as a new message rather than in response to an interaction, adding
+/// it to the shopping list as though `user_id` had added it with /shop. A `silent` message
+/// doesn't notify anyone in the channel
+async fn post_item_to_channel(
+    ctx: &Context,
+    state: &AppState,
+    shop: Shop<'_>,
+    user_id: u64,
+    channel_id: u64,
+    guild_id: Option<u64>,
+    silent: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let card = shop.card(guild_id);
+    let embeds = channel_allows_embeds(ctx, ChannelId::new(channel_id)).await;
+    let message = match create_new_shopping::<CreateMessage>(&card, embeds).await {
+        Ok(message) if silent => message.flags(MessageFlags::SUPPRESS_NOTIFICATIONS),
+        Ok(message) => message,
+        Err(e) => return Err(format!("unable to create message: {:?}", e).into()),
+    };
+
+    let message = ChannelId::new(channel_id)
+        .send_message(ctx, message)
+        .await?;
+    set_status_reaction(ctx, message.channel_id, message.id, ItemStatus::Pending).await;
+
+    state
+        .add_shopping_list_item(
+            user_id,
+            message.id.into(),
+            channel_id,
+            guild_id,
+            NewShoppingListItem {
+                item: shop.item,
+                personal: shop.personal,
+                quantity: shop.quantity,
+                store: shop.store,
+                notes: shop.notes,
+                recipe_group: shop.recipe,
+                bought_for: shop.bought_for,
+                product_url: shop.product_url,
+            },
         )
-        .add_option({
-            let mut cmd = CreateCommandOption::new(
-                CommandOptionType::Integer,
-                "quantity",
-                "The quantity of the item to add to the shopping list",
-            )
-            .required(false);
+        .await?;
 
-            for i in 1..26 {
-                cmd = cmd.add_int_choice(i.to_string(), i);
+    Ok(())
+}
+
+/// add many items to the list at once, as though `user_id` had added each with /shop. In the
+/// classic list mode each item is posted to the channel in turn, paced as bulk edits are. In the
+/// digest mode the items are keyed on `response_id`, as a single item is, and the digest is
+/// refreshed once they have all been added
+pub(super) async fn add_items_in_bulk(
+    ctx: &Context,
+    state: &AppState,
+    items: &[(i64, String)],
+    user_id: u64,
+    channel_id: u64,
+    guild_id: u64,
+    response_id: u64,
+) -> BulkResult {
+    let mut result = BulkResult::default();
+    let digest = CONFIG.guild(Some(guild_id)).list_mode == ListMode::Digest;
+
+    for (i, (quantity, item)) in items.iter().enumerate() {
+        let shop = Shop {
+            item,
+            personal: false,
+            quantity: *quantity,
+            quantity_source: QuantitySource::Explicit,
+            store: None,
+            notes: None,
+            recipe: None,
+            bought_for: None,
+            product_url: None,
+        };
+
+        let added = match digest {
+            true => state
+                .add_shopping_list_item(
+                    user_id,
+                    response_id,
+                    channel_id,
+                    Some(guild_id),
+                    NewShoppingListItem {
+                        item: shop.item,
+                        personal: shop.personal,
+                        quantity: shop.quantity,
+                        store: shop.store,
+                        notes: shop.notes,
+                        recipe_group: shop.recipe,
+                        bought_for: shop.bought_for,
+                        product_url: shop.product_url,
+                    },
+                )
+                .await
+                .map_err(|e| e.to_string()),
+            false => {
+                if i > 0 {
+                    tokio::time::sleep(EDIT_INTERVAL).await;
+                }
+                post_item_to_channel(ctx, state, shop, user_id, channel_id, Some(guild_id), false)
+                    .await
+                    .map_err(|e| e.to_string())
             }
-            cmd
-        })
-        .add_option(
-            CreateCommandOption::new(
-                CommandOptionType::String,
+        };
+
+        match added {
+            Ok(()) => result.succeeded += 1,
+            Err(e) => {
+                error!("error adding {} in bulk: {}", item, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    if digest {
+        if let Err(e) = refresh_digest(guild_id, Some(channel_id), ctx, state, true).await {
+            error!("error refreshing digest for {}: {}", guild_id, e);
+        }
+    }
+
+    result
+}
+
+/// find a pending item the user can see whose name is close enough to `name` that they may have
+/// meant to add to it rather than post a new item
+async fn likely_duplicate(
+    state: &AppState,
+    guild_id: u64,
+    user_id: u64,
+    name: &str,
+) -> Result<Option<ShoppingListItemModel>, CommandResponse> {
+    let mut items = match state.get_pending_items(guild_id).await {
+        Ok(items) => items,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    // other users' personal items aren't theirs to add to
+    items.retain(|item| !item.personal || item.user_id == user_id as i64);
+
+    Ok(closest_match(name, &items).cloned())
+}
+
+/// the prompt asking whether an item is a duplicate of `existing`, with buttons to add to the
+/// existing item or post the new one anyway
+fn duplicate_prompt(key: u64, existing: &ShoppingListItemModel) -> CreateInteractionResponse {
+    let name: String = existing.item.chars().take(60).collect();
+
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(format!(
+                "**{}** is already on the list, did you mean to add to it?",
+                describe_item(existing)
+            ))
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(encode_custom_id(
+                    MERGE_PREFIX,
+                    &format!("{}:{}", key, existing.id),
+                ))
+                .style(serenity::all::ButtonStyle::Primary)
+                .label(format!("Add to {}", name)),
+                CreateButton::new(encode_custom_id(ADD_SEPARATELY_PREFIX, &key.to_string()))
+                    .style(serenity::all::ButtonStyle::Secondary)
+                    .label("Add separately"),
+            ])])
+            .ephemeral(true),
+    )
+}
+
+/// take an add which is waiting on a near-duplicate prompt, if it hasn't expired
+async fn take_pending_add(ctx: &Context, key: u64) -> Option<PendingAdd> {
+    ctx.data
+        .read()
+        .await
+        .get::<PendingAddCache>()
+        .and_then(|cache| cache.take(key))
+}
+
+/// answer a near-duplicate prompt by replacing it with a message and removing its buttons
+async fn close_prompt(
+    interaction: &ComponentInteraction,
+    ctx: &Context,
+    content: String,
+) -> Result<CommandResponse, CommandResponse> {
+    if let Err(e) = interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(vec![]),
+            ),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    Ok(CommandResponse::NoResponse)
+}
+
+/// the message id of the item an interaction refers to, the store select menu is sent on its own
+/// message so carries the id of the item in its custom id instead
+fn item_message_id(interaction: &ComponentInteraction) -> u64 {
+    decode_custom_id(&interaction.data.custom_id, SET_STORE_PREFIX)
+        .and_then(|id| id.parse().ok())
+        .unwrap_or_else(|| interaction.message.id.into())
+}
+
+/// whether a component belongs to the digest or a near-duplicate prompt rather than to the message
+/// of an item
+fn is_list_component(custom_id: &str) -> bool {
+    [DIGEST_DONE, DIGEST_MOVE_UP, DIGEST_MOVE_DOWN].contains(&custom_id)
+        || custom_id.starts_with(MERGE_PREFIX)
+        || custom_id.starts_with(ADD_SEPARATELY_PREFIX)
+}
+
+/// the ids of the message an item was posted in, or that an interaction came from
+struct MessageIds {
+    message: u64,
+    /// missing for items recorded before their channel was
+    channel: Option<u64>,
+    guild: Option<u64>,
+}
+
+/// whether an item was posted in the message an interaction came from. The store select menu is
+/// sent on its own message, which names the item's message instead, so only the channel and guild
+/// are compared for it
+fn same_message(item: &MessageIds, interaction: &MessageIds, set_store: bool) -> bool {
+    let message_matches = set_store || item.message == interaction.message;
+    let channel_matches = item
+        .channel
+        .map_or(true, |c| Some(c) == interaction.channel);
+    let guild_matches = item.guild == interaction.guild;
+
+    message_matches && channel_matches && guild_matches
+}
+
+/// the item posted in a message, an item which has since been removed from the list is reported to
+/// the user rather than logged as a database error
+async fn item_for_message(
+    app_state: &AppState,
+    msg_id: u64,
+) -> Result<ShoppingListItemModel, CommandResponse> {
+    match app_state.get_shopping_list_item_by_message_id(msg_id).await {
+        Ok(Some(item)) => Ok(item),
+        Ok(None) => Err(CommandResponse::BasicFailure(
+            "this item is no longer on the list".to_string(),
+        )),
+        Err(e) => Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        ))),
+    }
+}
+
+/// make sure the item found for an interaction was posted in the message being acted on, so that
+/// a database restored out of step with discord can't lead to the wrong message being edited
+fn check_item_message(
+    item: &ShoppingListItemModel,
+    interaction: &ComponentInteraction,
+) -> Result<(), CommandResponse> {
+    let message_id: u64 = interaction.message.id.into();
+    let channel_id: u64 = interaction.channel_id.into();
+    let guild_id: Option<u64> = interaction.guild_id.map(|g| g.0.into());
+
+    let recorded = MessageIds {
+        message: item.message_id as u64,
+        channel: item.channel_id.map(|c| c as u64),
+        guild: item.guild_id.map(|g| g as u64),
+    };
+    let actual = MessageIds {
+        message: message_id,
+        channel: Some(channel_id),
+        guild: guild_id,
+    };
+    let set_store = interaction.data.custom_id.starts_with(SET_STORE_PREFIX);
+    if same_message(&recorded, &actual, set_store) {
+        return Ok(());
+    }
+
+    Err(CommandResponse::InternalFailure(format!(
+        "refusing to act on item {}: it is recorded in message {} of channel {:?} in guild {:?}, but \
+         the interaction came from message {} of channel {} in guild {:?}",
+        item.id, item.message_id, item.channel_id, item.guild_id, message_id, channel_id, guild_id
+    )))
+}
+
+/// the stores offered by the store select menu in alphabetical order, without duplicates. Discord
+/// allows at most 25 options, one of which is used to clear the store, so the rest are cut to fit
+/// around the item's current store
+fn store_menu_names(
+    stores: impl IntoIterator<Item = String>,
+    current: Option<&str>,
+) -> Vec<String> {
+    let mut names: Vec<String> = stores
+        .into_iter()
+        .filter(|store| Some(store.as_str()) != current)
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .collect();
+    names.sort();
+    names.truncate(24 - usize::from(current.is_some()));
+    if let Some(current) = current {
+        names.push(current.to_string());
+        names.sort();
+    }
+    names
+}
+
+/// build a select menu of known stores, for changing the store of the item posted in `message_id`
+async fn store_select_menu(
+    app_state: &AppState,
+    guild_id: Option<u64>,
+    message_id: u64,
+    current: Option<&str>,
+) -> Result<CreateSelectMenu, CommandResponse> {
+    let items = match app_state.get_recent_guild_items(guild_id, 50).await {
+        Ok(items) => items,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    let store_names = store_menu_names(
+        guild_seeds(app_state, guild_id, SuggestionKind::Store)
+            .await
+            .into_iter()
+            .map(Cow::into_owned)
+            .chain(items.into_iter().filter_map(|item| item.store)),
+        current,
+    );
+
+    let mut options = vec![CreateSelectMenuOption::new("No store", NO_STORE_VALUE)
+        .default_selection(current.is_none())];
+    options.extend(store_names.into_iter().map(|store| {
+        let selected = current == Some(store.as_str());
+        CreateSelectMenuOption::new(store.clone(), store).default_selection(selected)
+    }));
+
+    Ok(CreateSelectMenu::new(
+        encode_custom_id(SET_STORE_PREFIX, &message_id.to_string()),
+        CreateSelectMenuKind::String { options },
+    )
+    .placeholder("Choose a store"))
+}
+
+#[derive(Debug, Clone)]
+pub struct Shop<'a> {
+    item: &'a str,
+    personal: bool,
+    quantity: i64,
+    /// where the quantity came from, items copied from another keep its quantity as if it was
+    /// given outright
+    quantity_source: QuantitySource,
+    store: Option<&'a str>,
+    notes: Option<&'a str>,
+    recipe: Option<&'a str>,
+    /// who outside the household the item is being picked up for
+    bought_for: Option<&'a str>,
+    /// a link to the item's page in an online store
+    product_url: Option<&'a str>,
+}
+
+/// build the modal used to tweak a copy of an item before it is posted, prefilled from the item
+fn duplicate_modal(message_id: u64, item: &ShoppingListItemModel) -> CreateModal {
+    let title: String = format!("Duplicate {}", item.item)
+        .chars()
+        .take(45)
+        .collect();
+
+    CreateModal::new(
+        encode_custom_id(DUPLICATE_PREFIX, &message_id.to_string()),
+        title,
+    )
+    .components(vec![
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Short, "Quantity", "quantity")
+                .value(item.quantity.to_string())
+                .min_length(1)
+                .max_length(2)
+                .required(true),
+        ),
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Short, "Store", "store")
+                .value(item.store.clone().unwrap_or_default())
+                .max_length(100)
+                .required(false),
+        ),
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Paragraph, "Notes", "notes")
+                .value(item.notes.clone().unwrap_or_default())
+                .max_length(100)
+                .required(false),
+        ),
+    ])
+}
+
+/// build the modal used to set the quantity of an item to any number, rather than the 1 to 25
+/// offered by /shop
+fn set_quantity_modal(message_id: u64, item: &ShoppingListItemModel) -> CreateModal {
+    let title: String = format!("Set quantity of {}", item.item)
+        .chars()
+        .take(45)
+        .collect();
+
+    CreateModal::new(
+        encode_custom_id(SET_QUANTITY_PREFIX, &message_id.to_string()),
+        title,
+    )
+    .components(vec![CreateActionRow::InputText(
+        CreateInputText::new(InputTextStyle::Short, "Quantity", "quantity")
+            .value(item.quantity.to_string())
+            .min_length(1)
+            .max_length(6)
+            .required(true),
+    )])
+}
+
+/// build the modal used to split part of an item off to another store, suggesting half of it
+fn split_modal(message_id: u64, item: &ShoppingListItemModel) -> CreateModal {
+    let title: String = format!("Split {}", item.item).chars().take(45).collect();
+
+    CreateModal::new(
+        encode_custom_id(SPLIT_PREFIX, &message_id.to_string()),
+        title,
+    )
+    .components(vec![
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Short, "Quantity to split off", "quantity")
+                .value((item.quantity / 2).max(1).to_string())
+                .min_length(1)
+                .max_length(6)
+                .required(true),
+        ),
+        CreateActionRow::InputText(
+            CreateInputText::new(
+                InputTextStyle::Short,
+                "Store for the split off items",
                 "store",
-                "If the item is to be bought or found in a particular store",
             )
-            .required(false)
-            .set_autocomplete(true)
             .max_length(100)
-            .to_owned(),
+            .required(false),
+        ),
+    ])
+}
+
+/// build the modal used to set a reminder about an item, at a time in the guild's timezone
+fn remind_modal(message_id: u64, item: &ShoppingListItemModel) -> CreateModal {
+    let title: String = format!("Remind me about {}", item.item)
+        .chars()
+        .take(45)
+        .collect();
+
+    CreateModal::new(
+        encode_custom_id(REMIND_PREFIX, &message_id.to_string()),
+        title,
+    )
+    .components(vec![CreateActionRow::InputText(
+        CreateInputText::new(InputTextStyle::Short, "When", "when")
+            .placeholder("5pm, 17:30, tomorrow 9am or in 2 hours")
+            .min_length(1)
+            .max_length(30)
+            .required(true),
+    )])
+}
+
+/// build the modal used to change an item's quantity or store by typing e.g. `make it 3`
+fn quick_edit_modal(message_id: u64, item: &ShoppingListItemModel) -> CreateModal {
+    let title: String = format!("Edit {}", item.item).chars().take(45).collect();
+
+    CreateModal::new(
+        encode_custom_id(QUICK_EDIT_PREFIX, &message_id.to_string()),
+        title,
+    )
+    .components(vec![CreateActionRow::InputText(
+        CreateInputText::new(InputTextStyle::Short, "Change", "edit")
+            .placeholder("make it 3, add 2, at Countdown or no store")
+            .min_length(1)
+            .max_length(100)
+            .required(true),
+    )])
+}
+
+/// build the modal used to turn part of an item's note into an item of its own, filled in with the
+/// whole note to be cut down to the part wanted
+fn note_to_item_modal(message_id: u64, item: &ShoppingListItemModel) -> CreateModal {
+    let title: String = format!("Note of {} to item", item.item)
+        .chars()
+        .take(45)
+        .collect();
+
+    CreateModal::new(
+        encode_custom_id(NOTE_TO_ITEM_PREFIX, &message_id.to_string()),
+        title,
+    )
+    .components(vec![CreateActionRow::InputText(
+        CreateInputText::new(
+            InputTextStyle::Short,
+            "Part of the note to add as an item",
+            "part",
         )
-        .add_option(
-            CreateCommandOption::new(
-                CommandOptionType::String,
-                "notes",
-                "Notes about the item to add to the shopping list",
+        .value(
+            item.notes
+                .as_deref()
+                .unwrap_or_default()
+                .chars()
+                .take(100)
+                .collect::<String>(),
+        )
+        .min_length(1)
+        .max_length(100)
+        .required(true),
+    )])
+}
+
+/// take `part` out of a note, ignoring case, returning what is left of the note with the
+/// separators around `part` tidied away, e.g. taking `lemons` from `limes, lemons, mint` leaves
+/// `limes, mint`. None if `part` isn't in the note
+fn take_from_note(notes: &str, part: &str) -> Option<Option<String>> {
+    let part = part.trim();
+    if part.is_empty() {
+        return None;
+    }
+
+    // lowercasing ascii keeps every character the same length, so positions carry over
+    let start = notes
+        .to_ascii_lowercase()
+        .find(&part.to_ascii_lowercase())?;
+    let end = start + part.len();
+
+    let trim = |c: char| c.is_whitespace() || NOTE_SEPARATORS.contains(&c);
+    let before = notes[..start].trim_end_matches(trim);
+    let after = notes[end..].trim_start_matches(trim);
+    let remaining = match (before.is_empty(), after.is_empty()) {
+        (true, true) => None,
+        (false, true) => Some(before.to_string()),
+        (true, false) => Some(after.to_string()),
+        (false, false) => Some(format!("{}, {}", before, after)),
+    };
+    Some(remaining)
+}
+
+/// build the modal used to add a bought item to the list on a schedule. If the item already
+/// recurs, its schedule is filled in and submitting the modal updates it instead
+fn make_recurring_modal(
+    message_id: u64,
+    item: &ShoppingListItemModel,
+    existing: Option<&RecurringItemModel>,
+) -> CreateModal {
+    let title = match existing {
+        Some(_) => format!("Update schedule of {}", item.item),
+        None => format!("Make {} recurring", item.item),
+    };
+    let title: String = title.chars().take(45).collect();
+
+    let mut schedule = CreateInputText::new(InputTextStyle::Short, "Schedule", "schedule")
+        .placeholder("every 2 weeks, weekly mon,thu or monthly 1")
+        .min_length(1)
+        .max_length(100)
+        .required(true);
+    if let Some(existing) = existing {
+        schedule = schedule.value(existing.schedule_spec.clone());
+    }
+
+    CreateModal::new(
+        encode_custom_id(MAKE_RECURRING_PREFIX, &message_id.to_string()),
+        title,
+    )
+    .components(vec![CreateActionRow::InputText(schedule)])
+}
+
+/// read the quantity entered into the set quantity modal, which must be a whole number from 1 to
+/// [MAX_QUANTITY]. Discord limits how much can be typed, but the input isn't trusted to have been
+fn parse_set_quantity(input: Option<&str>) -> Result<i64, String> {
+    let input = match input.map(str::trim).filter(|i| !i.is_empty()) {
+        Some(input) => input,
+        None => return Err("enter a quantity".to_string()),
+    };
+
+    match input.parse::<i64>() {
+        Ok(quantity) if (1..=MAX_QUANTITY).contains(&quantity) => Ok(quantity),
+        Ok(_) => Err(format!("the quantity must be from 1 to {}", MAX_QUANTITY)),
+        Err(_) => Err(format!("`{}` isn't a whole number", input)),
+    }
+}
+
+/// add an item to a guild using the digest list mode, the item is only confirmed to the user who
+/// added it and is shown to everyone else by editing the digest
+async fn add_to_digest<'b>(
+    shop: Shop<'b>,
+    interaction: &'b CommandInteraction,
+    state: &'b AppState,
+    ctx: &'b Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let guild_id: u64 = match interaction.guild_id {
+        Some(g) => g.0.into(),
+        None => {
+            return Err(CommandResponse::BasicFailure(
+                "this command can only be used in a server".to_string(),
+            ))
+        }
+    };
+
+    if let Err(e) = interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Defer(
+                CreateInteractionResponseMessage::new().ephemeral(true),
+            ),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    // the item has no message of its own, so it is keyed on the ephemeral confirmation instead
+    let response_id: u64 = match interaction.get_response(ctx).await {
+        Ok(m) => m.id.into(),
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+    };
+
+    let description = shop.card(Some(guild_id)).description();
+    push_list_item_to_database(shop, state, interaction, ctx, response_id).await?;
+
+    let content = match refresh_digest(
+        guild_id,
+        Some(interaction.channel_id.into()),
+        ctx,
+        state,
+        true,
+    )
+    .await
+    {
+        Ok(_) => format!("Added {} to the shopping list", description),
+        Err(e) => {
+            error!("error refreshing digest for {}: {}", guild_id, e);
+            format!(
+                "Added {} to the shopping list, but the digest couldn't be updated",
+                description
             )
-            .required(false)
-            .max_length(100)
-            .to_owned(),
+        }
+    };
+
+    if let Err(e) = interaction
+        .edit_response(ctx, EditInteractionResponse::new().content(content))
+        .await
+    {
+        error!("error editing digest confirmation: {}", e);
+    }
+
+    Ok(CommandResponse::NoResponse)
+}
+
+/// add a personal item by posting it in its user's private thread, so that nobody else in the
+/// channel sees it. If the thread can't be found or created the item is posted in the channel
+/// instead, and the user is told why
+async fn add_to_private_thread<'b>(
+    shop: Shop<'b>,
+    interaction: &'b CommandInteraction,
+    state: &'b AppState,
+    ctx: &'b Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let guild_id: u64 = match interaction.guild_id {
+        Some(g) => g.0.into(),
+        None => {
+            return Err(CommandResponse::BasicFailure(
+                "this command can only be used in a server".to_string(),
+            ))
+        }
+    };
+    let channel_id: u64 = interaction.channel_id.into();
+    let user_id: u64 = interaction.user.id.into();
+
+    // creating a thread can take longer than discord waits for a response
+    if let Err(e) = interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Defer(
+                CreateInteractionResponseMessage::new().ephemeral(true),
+            ),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    let description = shop.card(Some(guild_id)).description();
+
+    let thread = match find_or_create_private_thread(
+        ctx,
+        state,
+        guild_id,
+        channel_id,
+        user_id,
+        &interaction.user.name,
+    )
+    .await
+    {
+        Ok(thread) => Some(thread),
+        Err(e) => {
+            warn!(
+                "unable to find or create private thread for {}: {}",
+                user_id, e
+            );
+            None
+        }
+    };
+
+    let posted = match thread {
+        Some(thread) => {
+            match post_item_to_channel(
+                ctx,
+                state,
+                shop.clone(),
+                user_id,
+                thread,
+                Some(guild_id),
+                false,
+            )
+            .await
+            {
+                Ok(()) => Some(thread),
+                Err(e) => {
+                    // most likely the thread was deleted, so a new one is made for the next item
+                    warn!("unable to post in private thread {}: {}", thread, e);
+                    if let Err(e) = state
+                        .remove_user_private_thread(guild_id, channel_id, user_id)
+                        .await
+                    {
+                        error!("error forgetting private thread {}: {}", thread, e);
+                    }
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let content = match posted {
+        Some(thread) => format!("Added {} to your private thread <#{}>", description, thread),
+        None => match post_item_to_channel(
+            ctx,
+            state,
+            shop.clone(),
+            user_id,
+            channel_id,
+            Some(guild_id),
+            false,
+        )
+        .await
+        {
+            Ok(()) => format!(
+                "Added {} to the shopping list, your private thread couldn't be used so it was posted here",
+                description
+            ),
+            Err(e) => {
+                error!("error posting item to {}: {}", channel_id, e);
+                if let Err(e) = interaction
+                    .edit_response(
+                        ctx,
+                        EditInteractionResponse::new().content("error communicating with discord"),
+                    )
+                    .await
+                {
+                    error!("error editing private thread confirmation: {}", e);
+                }
+                return Err(CommandResponse::NoResponse);
+            }
+        },
+    };
+    record_recently_used(state, user_id, &shop).await;
+
+    if let Err(e) = interaction
+        .edit_response(ctx, EditInteractionResponse::new().content(content))
+        .await
+    {
+        error!("error editing private thread confirmation: {}", e);
+    }
+
+    Ok(CommandResponse::NoResponse)
+}
+
+/// add an item for a user who sees their /shop responses alone. Ephemeral messages can't be
+/// edited or reacted to by the bot, and their buttons stop working once the interaction expires,
+/// so the confirmation has no buttons and the item is stored as having no message of its own. It
+/// is ticked off through /shopping-list instead, as items whose message has expired are
+async fn add_ephemerally<'b>(
+    shop: Shop<'b>,
+    interaction: &'b CommandInteraction,
+    state: &'b AppState,
+    ctx: &'b Context,
+) -> Result<CommandResponse, CommandResponse> {
+    if let Err(e) = interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Defer(
+                CreateInteractionResponseMessage::new().ephemeral(true),
+            ),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    // as in a digest, the item is keyed on the ephemeral confirmation
+    let response_id: u64 = match interaction.get_response(ctx).await {
+        Ok(m) => m.id.into(),
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+    };
+
+    let guild_id = interaction.guild_id.map(|g| g.0.into());
+    let user_id: u64 = interaction.user.id.into();
+    let description = shop.card(guild_id).description();
+
+    let content = match store_posted_item(
+        state,
+        &shop,
+        user_id,
+        response_id,
+        interaction.channel_id.into(),
+        guild_id,
+    )
+    .await
+    {
+        Ok(()) => {
+            record_recently_used(state, user_id, &shop).await;
+            if let Err(e) = forget_item_message(state, response_id).await {
+                error!("error marking {} as having no message: {}", response_id, e);
+            }
+            ephemeral_confirmation(&description)
+        }
+        Err(e) => {
+            error!("error adding shopping list item: {}", e);
+            "error communicating with database".to_string()
+        }
+    };
+
+    if let Err(e) = interaction
+        .edit_response(ctx, EditInteractionResponse::new().content(content))
+        .await
+    {
+        error!("error editing ephemeral confirmation: {}", e);
+    }
+
+    Ok(CommandResponse::NoResponse)
+}
+
+/// the confirmation shown to a user who sees their /shop responses alone, as text without buttons.
+/// The card's description already says the item was added, and may run over several lines
+fn ephemeral_confirmation(description: &str) -> String {
+    fit_content(format!(
+        "{}\n*only you can see this, tick it off with /shopping-list*",
+        description
+    ))
+}
+
+/// store an item as having no message to keep up to date, so that nothing tries to edit, react to
+/// or delete it
+async fn forget_item_message(state: &AppState, message_id: u64) -> DatabaseResult<()> {
+    match state
+        .get_shopping_list_item_by_message_id(message_id)
+        .await?
+    {
+        Some(item) => state.set_item_messages_expired(&[item.id]).await,
+        None => Ok(()),
+    }
+}
+
+impl<'a> Shop<'a> {
+    /// the card posted for this item in the given guild
+    fn card(&self, guild_id: Option<u64>) -> ShoppingCard<'a> {
+        ShoppingCard::new(self.item, self.quantity)
+            .personal(self.personal)
+            .store(self.store)
+            .notes(self.notes)
+            .recipe(self.recipe)
+            .bought_for(self.bought_for)
+            .product_url(self.product_url)
+            .guild(guild_id)
+    }
+}
+
+/// give an item added without a quantity the quantity its user usually adds it with, if the guild
+/// allows it. A failure to look it up is only logged, leaving the quantity as it was
+async fn with_typical_quantity<'a>(
+    shop: Shop<'a>,
+    state: &AppState,
+    guild_id: Option<u64>,
+    user_id: u64,
+) -> Shop<'a> {
+    let config = CONFIG.guild(guild_id);
+    let guild_id = match guild_id {
+        Some(guild_id) if config.typical_quantity => guild_id,
+        _ => return shop,
+    };
+    // a quantity which was asked for always wins over the usual one
+    if shop.quantity_source < QuantitySource::Typical {
+        return shop;
+    }
+
+    let recent = match state
+        .get_recent_item_quantities(guild_id, user_id, shop.item, TYPICAL_SAMPLE)
+        .await
+    {
+        Ok(recent) => recent,
+        Err(e) => {
+            error!("error loading recent quantities of {}: {}", shop.item, e);
+            return shop;
+        }
+    };
+
+    let (quantity, quantity_source) = QuantityCandidates {
+        typical: typical_quantity(&recent),
+        guild_default: config.default_quantity(),
+        ..QuantityCandidates::default()
+    }
+    .resolve();
+
+    Shop {
+        quantity,
+        quantity_source,
+        ..shop
+    }
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for Shop<'a> {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut item: Option<&str> = None;
+        let mut personal: Option<bool> = None;
+        let mut quantity: Option<i64> = None;
+        let mut store: Option<&str> = None;
+        let mut notes: Option<&str> = None;
+        let mut recipe: Option<&str> = None;
+        let mut bought_for: Option<&str> = None;
+        let mut product_url: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("item", ResolvedValue::String(val)) => item = Some(val),
+                ("personal", ResolvedValue::Boolean(val)) => personal = Some(val),
+                ("quantity", ResolvedValue::Integer(val)) => quantity = Some(val),
+                ("store", ResolvedValue::String(val)) => store = Some(val),
+                ("notes", ResolvedValue::String(val)) => notes = Some(val),
+                ("recipe", ResolvedValue::String(val)) => recipe = Some(val),
+                ("for", ResolvedValue::String(val)) => bought_for = Some(val),
+                ("product_url", ResolvedValue::String(val)) => product_url = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        if item.is_none() || personal.is_none() {
+            return Err(String::from("item and personal are required"));
+        }
+        let item = item.unwrap();
+        let personal = personal.unwrap();
+
+        // the typical quantity needs the database, so is looked up once the command is handled
+        let parsed = parse_quantity_words(item);
+        let (quantity, quantity_source) = QuantityCandidates {
+            explicit: quantity,
+            parsed: parsed.map(|(quantity, _)| quantity),
+            typical: None,
+            guild_default: CONFIG
+                .guild(interaction.guild_id.map(|g| g.0.into()))
+                .default_quantity(),
+        }
+        .resolve();
+
+        // an explicit quantity wins, so "a dozen eggs" with a quantity of 2 is two lots of the item
+        let item = match (quantity_source, parsed) {
+            (QuantitySource::Parsed, Some((_, rest))) => rest,
+            _ => item,
+        };
+
+        Ok(Shop {
+            item,
+            personal,
+            quantity,
+            quantity_source,
+            store,
+            notes,
+            recipe,
+            bought_for,
+            product_url,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for Shop<'a> {
+    fn name() -> &'static str {
+        "shop"
+    }
+
+    fn description() -> &'static str {
+        "add an item to the shopping list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "item",
+                "The item to add to the shopping list",
+            )
+            .required(true)
+            .set_autocomplete(true)
+            .max_length(200)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "personal",
+                "true if the item is just for you",
+            )
+            .required(true),
+        )
+        .add_option({
+            let mut cmd = CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "quantity",
+                "The quantity of the item to add to the shopping list",
+            )
+            .required(false);
+
+            for i in 1..26 {
+                cmd = cmd.add_int_choice(i.to_string(), i);
+            }
+            cmd
+        })
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "store",
+                "If the item is to be bought or found in a particular store",
+            )
+            .required(false)
+            .set_autocomplete(true)
+            .max_length(100)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "notes",
+                "Notes about the item to add to the shopping list",
+            )
+            .required(false)
+            .max_length(100)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "recipe",
+                "The recipe this item is for, so it can be cleared with the rest of the recipe",
+            )
+            .required(false)
+            .max_length(100)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "for",
+                "Who outside the household you're picking this up for, kept out of the list's stats",
+            )
+            .required(false)
+            .max_length(100)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "product_url",
+                "A link to the item online, e.g. its page on the supermarket's website",
+            )
+            .required(false)
+            .max_length(MAX_PRODUCT_URL as u16)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = interaction.guild_id.map(|g| g.0.into());
+        // cleaned first, so that aliases and duplicates match however the item was typed
+        let config = CONFIG.guild(guild_id);
+        let cleaned = clean_item_name(self.item, config.item_charset, config.item_chars)
+            .map_err(CommandResponse::BasicFailure)?;
+        let expanded = expand_alias(state, guild_id, &cleaned).await?;
+        let item_name = expanded.as_deref().unwrap_or(&cleaned);
+        let item = filter_text(ctx, state, guild_id, "item name", item_name).await?;
+        let store = match self.store {
+            Some(store) => Some(filter_text(ctx, state, guild_id, "store", store).await?),
+            None => None,
+        };
+        let notes = match self.notes {
+            Some(notes) => Some(filter_text(ctx, state, guild_id, "notes", notes).await?),
+            None => None,
+        };
+        let bought_for = match self.bought_for {
+            Some(bought_for) => Some(filter_text(ctx, state, guild_id, "for", bought_for).await?),
+            None => None,
+        };
+        let product_url = match self.product_url {
+            Some(url) => Some(clean_product_url(url).map_err(CommandResponse::BasicFailure)?),
+            None => None,
+        };
+        let shop = Shop {
+            item: &item,
+            store: store.as_deref(),
+            notes: notes.as_deref(),
+            bought_for: bought_for.as_deref(),
+            product_url: product_url.as_deref(),
+            ..self
+        };
+        let shop = with_typical_quantity(shop, state, guild_id, interaction.user.id.into()).await;
+
+        if let Some(guild) = guild_id {
+            let user_id: u64 = interaction.user.id.into();
+            if let Some(existing) = likely_duplicate(state, guild, user_id, shop.item).await? {
+                match ctx.data.read().await.get::<PendingAddCache>() {
+                    Some(cache) => {
+                        let key: u64 = interaction.id.into();
+                        cache.insert(
+                            key,
+                            PendingAdd {
+                                item: shop.item.to_string(),
+                                personal: shop.personal,
+                                quantity: shop.quantity,
+                                store: shop.store.map(str::to_string),
+                                notes: shop.notes.map(str::to_string),
+                                recipe: shop.recipe.map(str::to_string),
+                                bought_for: shop.bought_for.map(str::to_string),
+                                product_url: shop.product_url.map(str::to_string),
+                            },
+                        );
+                        return Ok(CommandResponse::ComplexSuccess(duplicate_prompt(
+                            key, &existing,
+                        )));
+                    }
+                    None => error!("pending add cache missing from context"),
+                }
+            }
+        }
+
+        if CONFIG.guild(guild_id).list_mode == ListMode::Digest {
+            return add_to_digest(shop, interaction, state, ctx).await;
+        }
+        if routes_to_private_thread(guild_id, shop.personal) {
+            return add_to_private_thread(shop, interaction, state, ctx).await;
+        }
+        if shop_is_ephemeral(state, guild_id, interaction.user.id.into()).await {
+            return add_ephemerally(shop, interaction, state, ctx).await;
+        }
+
+        let loading_message = create_loading_message(interaction, ctx).await?;
+        let card = shop.card(guild_id);
+        let resp = create_new_shopping(&card, embeds_allowed(interaction.app_permissions)).await?;
+
+        if let Err(e) = interaction.create_followup(&ctx, resp).await {
+            error!("error creating followup: {}", e);
+            return Err(CommandResponse::NoResponse);
+        }
+
+        push_list_item_to_database(shop, state, interaction, ctx, loading_message).await?;
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for Shop<'a> {
+    async fn autocomplete<'c>(
+        command: &'c CommandInteraction,
+        autocomplete: &'c AutocompleteOption,
+        app_state: &'c AppState,
+        ctx: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        Ok(autocomplete_response(autocomplete.name, |kind| {
+            suggestion_choices(kind, command, autocomplete.value, app_state, ctx)
+        })
+        .await)
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for Shop<'a> {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        if is_list_component(&interaction.data.custom_id) {
+            return true;
+        }
+
+        let msg_id = item_message_id(interaction);
+        match app_state.get_shopping_list_item_by_message_id(msg_id).await {
+            Ok(Some(_)) => true,
+            Ok(None) => false,
+            Err(e) => {
+                error!("error communicating with database: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let msg_id = item_message_id(interaction);
+        let user_id: u64 = interaction.user.id.into();
+
+        if !is_list_component(&interaction.data.custom_id) {
+            match app_state.get_shopping_list_item_by_message_id(msg_id).await {
+                Ok(Some(item)) => check_item_message(&item, interaction)?,
+                // a missing item is reported by whichever action was taken on it
+                Ok(None) => {}
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )))
+                }
+            }
+        }
+
+        match interaction.data.custom_id.as_ref() {
+            DIGEST_DONE => {
+                let guild_id: u64 = match interaction.guild_id {
+                    Some(g) => g.0.into(),
+                    None => {
+                        return Err(CommandResponse::InternalFailure(
+                            "Invalid interaction".to_string(),
+                        ))
+                    }
+                };
+                let values = match &interaction.data.kind {
+                    ComponentInteractionDataKind::StringSelect { values } => values,
+                    _ => {
+                        return Err(CommandResponse::InternalFailure(
+                            "Invalid interaction".to_string(),
+                        ));
+                    }
+                };
+
+                let pending = match app_state.get_digest_items(guild_id).await {
+                    Ok(items) => items,
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )))
+                    }
+                };
+
+                // only tick off items which are still pending in this guild, the menu may be stale
+                let ids: Vec<i32> = pending
+                    .iter()
+                    .filter(|item| values.contains(&item.id.to_string()))
+                    .map(|item| item.id)
+                    .collect();
+
+                if let Err(e) = app_state
+                    .set_shopping_list_items_bought(&ids, true, Some(user_id))
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+
+                let (ticked, remaining): (Vec<ShoppingListItemModel>, Vec<ShoppingListItemModel>) =
+                    pending.into_iter().partition(|item| ids.contains(&item.id));
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::UpdateMessage(list_update(
+                            &interaction.message,
+                            &remaining,
+                        )),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+
+                // without a digest this is /shopping-list, so the messages of ticked off items
+                // which haven't expired yet are updated to match
+                if CONFIG.guild(Some(guild_id)).list_mode == ListMode::Classic {
+                    throttled_edit(ctx, &ticked, |item, embeds| {
+                        bought_message(&ShoppingCard::from_item(item).description(), embeds)
+                    })
+                    .await;
+                    set_status_reactions(ctx, &ticked, ItemStatus::Bought).await;
+                }
+            }
+            DIGEST_MOVE_UP | DIGEST_MOVE_DOWN => {
+                let guild_id: u64 = match interaction.guild_id {
+                    Some(g) => g.0.into(),
+                    None => {
+                        return Err(CommandResponse::InternalFailure(
+                            "Invalid interaction".to_string(),
+                        ))
+                    }
+                };
+                let id = match &interaction.data.kind {
+                    ComponentInteractionDataKind::StringSelect { values } => {
+                        values.first().and_then(|v| v.parse::<i32>().ok())
+                    }
+                    _ => None,
+                };
+                let id = match id {
+                    Some(id) => id,
+                    None => {
+                        return Err(CommandResponse::InternalFailure(
+                            "Invalid interaction".to_string(),
+                        ))
+                    }
+                };
+
+                let offset = match interaction.data.custom_id.as_str() {
+                    DIGEST_MOVE_UP => -1,
+                    _ => 1,
+                };
+                // an item which was ticked off or moved by someone else since the menu was shown
+                // is left alone, the digest is still redrawn to show the current order
+                if let Err(e) = app_state.reorder_item(guild_id, id, offset).await {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+
+                let items = match app_state.get_digest_items(guild_id).await {
+                    Ok(items) => items,
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )))
+                    }
+                };
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::UpdateMessage(list_update(
+                            &interaction.message,
+                            &items,
+                        )),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            "bought" => {
+                if let Err(e) = app_state
+                    .set_shopping_list_item_bought(user_id, msg_id, true, Some(user_id))
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+
+                let description = match message_description(&interaction.message) {
+                    Some(description) => description,
+                    None => {
+                        return Err(CommandResponse::InternalFailure(
+                            "error communicating with discord".to_string(),
+                        ));
+                    }
+                };
+
+                let mut edit_message = interaction.message.clone();
+
+                if let Err(e) = edit_message
+                    .edit(
+                        &ctx,
+                        bought_message(description, embeds_allowed(interaction.app_permissions)),
+                    )
+                    .await
+                {
+                    return Err(discord_failure(e, interaction.channel_id, VIEW_CHANNEL));
+                }
+                set_status_reaction(
+                    &ctx,
+                    interaction.channel_id,
+                    interaction.message.id,
+                    ItemStatus::Bought,
+                )
+                .await;
+
+                return Ok(CommandResponse::ComplexSuccess(
+                    CreateInteractionResponse::Acknowledge,
+                ));
+            }
+            "remove" => {
+                let guild_id = interaction.guild_id.map(|g| g.0.into());
+                let mode = CONFIG.guild(guild_id).remove_mode;
+                remove_item(app_state, mode, user_id, msg_id, || async move {
+                    match interaction.message.delete(&ctx).await {
+                        // someone else got to the message first, the item still goes
+                        Err(e) if is_unknown_message(&e) => Ok(()),
+                        Err(e) => Err(discord_failure(e, interaction.channel_id, VIEW_CHANNEL)),
+                        Ok(()) => Ok(()),
+                    }
+                })
+                .await?;
+                if mode == RemoveMode::Delete {
+                    return Ok(CommandResponse::ComplexSuccess(
+                        CreateInteractionResponse::Acknowledge,
+                    ));
+                }
+
+                let description = match message_description(&interaction.message) {
+                    Some(description) => description,
+                    None => {
+                        return Err(CommandResponse::InternalFailure(
+                            "error communicating with discord".to_string(),
+                        ));
+                    }
+                };
+
+                let mut edit_message = interaction.message.clone();
+
+                if let Err(e) = edit_message
+                    .edit(
+                        &ctx,
+                        removed_message(description, embeds_allowed(interaction.app_permissions)),
+                    )
+                    .await
+                {
+                    return Err(discord_failure(e, interaction.channel_id, VIEW_CHANNEL));
+                }
+                set_status_reaction(
+                    &ctx,
+                    interaction.channel_id,
+                    interaction.message.id,
+                    ItemStatus::Removed,
+                )
+                .await;
+
+                return Ok(CommandResponse::ComplexSuccess(
+                    CreateInteractionResponse::Acknowledge,
+                ));
+            }
+            "readd" => {
+                let guild_id = interaction.guild_id.map(|g| g.0.into());
+                let cooldown = CONFIG.guild(guild_id).readd_cooldown();
+                let allowed = match ctx.data.read().await.get::<ReaddCooldowns>() {
+                    Some(cooldowns) => cooldowns.try_readd(msg_id, cooldown),
+                    None => true,
+                };
+                if !allowed {
+                    return Err(CommandResponse::BasicFailure(
+                        "this item was already re-added, check the list before adding it again"
+                            .to_string(),
+                    ));
+                }
+
+                let item = item_for_message(app_state, msg_id).await?;
+
+                if let Err(e) = readd_item(interaction, app_state, ctx, &item).await {
+                    // nothing was added, so the item can be re-added again straight away
+                    if let Some(cooldowns) = ctx.data.read().await.get::<ReaddCooldowns>() {
+                        cooldowns.release(msg_id);
+                    }
+                    return Err(e);
+                }
+            }
+            "duplicate" => {
+                let item = item_for_message(app_state, msg_id).await?;
+
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Modal(duplicate_modal(msg_id, &item)),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            "set-quantity" => {
+                let item = item_for_message(app_state, msg_id).await?;
+
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Modal(set_quantity_modal(msg_id, &item)),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            "qty-inc" | "qty-dec" => {
+                let item = item_for_message(app_state, msg_id).await?;
+
+                let step = match interaction.data.custom_id.as_str() {
+                    "qty-inc" => 1,
+                    _ => -1,
+                };
+                // the button may be stale, e.g. if the quantity was set to 1 in a modal since
+                if step < 0 && item.quantity <= 1 {
+                    return Err(CommandResponse::BasicFailure(
+                        "the quantity can't go below 1, remove the item instead".to_string(),
+                    ));
+                }
+                if step > 0 && item.quantity >= MAX_QUANTITY {
+                    return Err(CommandResponse::BasicFailure(format!(
+                        "the quantity can't go above {}",
+                        MAX_QUANTITY
+                    )));
+                }
+
+                let item = match app_state.step_item_quantity(user_id, item.id, step).await {
+                    Ok(Some(item)) => item,
+                    Ok(None) => {
+                        return Err(CommandResponse::BasicFailure(
+                            "this item has already been bought".to_string(),
+                        ))
+                    }
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                };
+
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::UpdateMessage(item_update(
+                            &item,
+                            embeds_allowed(interaction.app_permissions),
+                        )),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            "remind" => {
+                let item = item_for_message(app_state, msg_id).await?;
+
+                if item.bought {
+                    return Err(CommandResponse::BasicFailure(
+                        "this item has already been bought".to_string(),
+                    ));
+                }
+
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Modal(remind_modal(msg_id, &item)),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            "make-recurring" => {
+                let guild_id: u64 = match interaction.guild_id {
+                    Some(g) => g.0.into(),
+                    None => {
+                        return Err(CommandResponse::BasicFailure(
+                            "this command can only be used in a server".to_string(),
+                        ))
+                    }
+                };
+
+                let item = item_for_message(app_state, msg_id).await?;
+
+                if !item.bought {
+                    return Err(CommandResponse::BasicFailure(
+                        "only bought items can be made recurring".to_string(),
+                    ));
+                }
+
+                let existing = find_recurring_item(app_state, guild_id, &item.item).await?;
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Modal(make_recurring_modal(
+                            msg_id,
+                            &item,
+                            existing.as_ref(),
+                        )),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            "quick-edit" => {
+                let item = item_for_message(app_state, msg_id).await?;
+
+                if item.bought {
+                    return Err(CommandResponse::BasicFailure(
+                        "this item has already been bought".to_string(),
+                    ));
+                }
+
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Modal(quick_edit_modal(msg_id, &item)),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            "note-to-item" => {
+                let item = item_for_message(app_state, msg_id).await?;
+
+                if item.bought {
+                    return Err(CommandResponse::BasicFailure(
+                        "this item has already been bought".to_string(),
+                    ));
+                }
+
+                // the note may have been cleared since the button was shown
+                if item.notes.as_deref().map_or(true, |n| n.trim().is_empty()) {
+                    return Err(CommandResponse::BasicFailure(format!(
+                        "{} has no note to turn into an item",
+                        item.item
+                    )));
+                }
+
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Modal(note_to_item_modal(msg_id, &item)),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            "split" => {
+                let item = item_for_message(app_state, msg_id).await?;
+
+                if item.quantity < 2 {
+                    return Err(CommandResponse::BasicFailure(format!(
+                        "there is only 1 {} on the list, so there is nothing to split",
+                        item.item
+                    )));
+                }
+
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Modal(split_modal(msg_id, &item)),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            "change-store" => {
+                let item = item_for_message(app_state, msg_id).await?;
+
+                let menu = store_select_menu(
+                    app_state,
+                    interaction.guild_id.map(|g| g.0.into()),
+                    msg_id,
+                    item.store.as_deref(),
+                )
+                .await?;
+
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content(format!("Which store should {} come from?", item.item))
+                                .components(vec![CreateActionRow::SelectMenu(menu)])
+                                .ephemeral(true),
+                        ),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            custom_id if custom_id.starts_with(SET_STORE_PREFIX) => {
+                let store = match &interaction.data.kind {
+                    ComponentInteractionDataKind::StringSelect { values } => values
+                        .first()
+                        .filter(|v| v.as_str() != NO_STORE_VALUE)
+                        .map(|v| v.as_str()),
+                    _ => {
+                        return Err(CommandResponse::InternalFailure(
+                            "Invalid interaction".to_string(),
+                        ));
+                    }
+                };
+
+                let changed = match app_state
+                    .update_item_store(interaction.user.id.into(), msg_id, store)
+                    .await
+                {
+                    Ok(changed) => changed,
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                };
+
+                let item = item_for_message(app_state, msg_id).await?;
+
+                // re-render the original message, the buttons are left untouched. An item bought
+                // since the menu was opened is redrawn as it now is
+                if let Err(e) = interaction
+                    .channel_id
+                    .edit_message(
+                        &ctx,
+                        MessageId::new(msg_id),
+                        ShoppingCard::from_item(&item)
+                            .edit(embeds_allowed(interaction.app_permissions)),
+                    )
+                    .await
+                {
+                    return Err(discord_failure(e, interaction.channel_id, VIEW_CHANNEL));
+                }
+
+                if let Err(e) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::UpdateMessage(
+                            CreateInteractionResponseMessage::new()
+                                .content(match (changed, store) {
+                                    (false, _) => format!(
+                                        "{} has already been bought, so its store wasn't changed",
+                                        item.item
+                                    ),
+                                    (true, Some(store)) => {
+                                        format!("{} will now come from {}", item.item, store)
+                                    }
+                                    (true, None) => format!("{} no longer has a store", item.item),
+                                })
+                                .components(vec![]),
+                        ),
+                    )
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with discord: {}",
+                        e
+                    )));
+                }
+            }
+            custom_id if custom_id.starts_with(MERGE_PREFIX) => {
+                let (key, existing_id) = match decode_custom_id(custom_id, MERGE_PREFIX)
+                    .as_deref()
+                    .and_then(|rest| rest.split_once(':'))
+                    .and_then(|(key, id)| Some((key.parse::<u64>().ok()?, id.parse::<i32>().ok()?)))
+                {
+                    Some(parsed) => parsed,
+                    None => {
+                        return Err(CommandResponse::InternalFailure(
+                            "Invalid interaction".to_string(),
+                        ))
+                    }
+                };
+
+                let add = match take_pending_add(ctx, key).await {
+                    Some(add) => add,
+                    None => {
+                        return close_prompt(
+                            interaction,
+                            ctx,
+                            "This prompt has expired, run /shop again".to_string(),
+                        )
+                        .await
+                    }
+                };
+
+                let item = match app_state
+                    .add_item_quantity(user_id, existing_id, add.quantity)
+                    .await
+                {
+                    Ok(Some(item)) => item,
+                    Ok(None) => {
+                        return close_prompt(
+                            interaction,
+                            ctx,
+                            "That item has been bought since, run /shop again to add it"
+                                .to_string(),
+                        )
+                        .await
+                    }
+                    Err(e) => {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+                };
+
+                // the quantity is already saved, so a failure to show it is only logged
+                let guild_id = item.guild_id.map(|g| g as u64);
+                if CONFIG.guild(guild_id).list_mode == ListMode::Digest {
+                    if let Some(guild_id) = guild_id {
+                        if let Err(e) = refresh_digest(guild_id, None, ctx, app_state, true).await {
+                            error!("error refreshing digest for {}: {}", guild_id, e);
+                        }
+                    }
+                } else if let (Some(channel_id), None) = (item.channel_id, item.message_expired_at)
+                {
+                    let channel_id = ChannelId::new(channel_id as u64);
+                    let embeds = channel_allows_embeds(ctx, channel_id).await;
+                    if let Err(e) = channel_id
+                        .edit_message(
+                            &ctx,
+                            MessageId::new(item.message_id as u64),
+                            item_edit(&item, embeds),
+                        )
+                        .await
+                    {
+                        error!("error editing message {}: {}", item.message_id, e);
+                    }
+                }
+
+                return close_prompt(
+                    interaction,
+                    ctx,
+                    format!(
+                        "Added {} more to {}",
+                        add.quantity,
+                        ShoppingCard::from_item(&item).description()
+                    ),
+                )
+                .await;
+            }
+            custom_id if custom_id.starts_with(ADD_SEPARATELY_PREFIX) => {
+                let key = match decode_custom_id(custom_id, ADD_SEPARATELY_PREFIX)
+                    .and_then(|key| key.parse::<u64>().ok())
+                {
+                    Some(key) => key,
+                    None => {
+                        return Err(CommandResponse::InternalFailure(
+                            "Invalid interaction".to_string(),
+                        ))
+                    }
+                };
+
+                let add = match take_pending_add(ctx, key).await {
+                    Some(add) => add,
+                    None => {
+                        return close_prompt(
+                            interaction,
+                            ctx,
+                            "This prompt has expired, run /shop again".to_string(),
+                        )
+                        .await
+                    }
+                };
+
+                let shop = Shop {
+                    item: &add.item,
+                    personal: add.personal,
+                    quantity: add.quantity,
+                    quantity_source: QuantitySource::Explicit,
+                    store: add.store.as_deref(),
+                    notes: add.notes.as_deref(),
+                    recipe: add.recipe.as_deref(),
+                    bought_for: add.bought_for.as_deref(),
+                    product_url: add.product_url.as_deref(),
+                };
+                let guild_id = interaction.guild_id.map(|g| g.0.into());
+                let channel_id: u64 = interaction.channel_id.into();
+                let description = shop.card(guild_id).description();
+
+                if CONFIG.guild(guild_id).list_mode == ListMode::Digest {
+                    // as with /shop, the item is keyed on the ephemeral message confirming it
+                    if let Err(e) = app_state
+                        .add_shopping_list_item(
+                            user_id,
+                            interaction.message.id.into(),
+                            channel_id,
+                            guild_id,
+                            NewShoppingListItem {
+                                item: shop.item,
+                                personal: shop.personal,
+                                quantity: shop.quantity,
+                                store: shop.store,
+                                notes: shop.notes,
+                                recipe_group: shop.recipe,
+                                bought_for: shop.bought_for,
+                                product_url: shop.product_url,
+                            },
+                        )
+                        .await
+                    {
+                        return Err(CommandResponse::InternalFailure(format!(
+                            "error communicating with database: {}",
+                            e
+                        )));
+                    }
+
+                    if let Some(guild_id) = guild_id {
+                        if let Err(e) =
+                            refresh_digest(guild_id, Some(channel_id), ctx, app_state, true).await
+                        {
+                            error!("error refreshing digest for {}: {}", guild_id, e);
+                        }
+                    }
+                } else if let Err(e) =
+                    post_item_to_channel(ctx, app_state, shop, user_id, channel_id, guild_id, false)
+                        .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error adding shopping list item: {}",
+                        e
+                    )));
+                }
+
+                return close_prompt(
+                    interaction,
+                    ctx,
+                    format!("Added {} to the shopping list", description),
+                )
+                .await;
+            }
+            _ => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ));
+            }
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+/// handle the set quantity modal of the item posted in the message `msg_id`
+async fn set_quantity_submit(
+    msg_id: &str,
+    interaction: &ModalInteraction,
+    app_state: &AppState,
+    ctx: &Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let msg_id: u64 = match msg_id.parse() {
+        Ok(id) => id,
+        Err(_) => {
+            return Err(CommandResponse::InternalFailure(
+                "Invalid interaction".to_string(),
+            ))
+        }
+    };
+
+    let input = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == "quantity" => {
+                input.value.as_deref()
+            }
+            _ => None,
+        });
+    let quantity = parse_set_quantity(input).map_err(CommandResponse::BasicFailure)?;
+
+    let item = item_for_message(app_state, msg_id).await?;
+
+    let user_id: u64 = interaction.user.id.into();
+    let item = match app_state
+        .set_item_quantity(user_id, item.id, quantity)
+        .await
+    {
+        Ok(Some(item)) => item,
+        Ok(None) => {
+            return Err(CommandResponse::BasicFailure(
+                "this item has already been bought".to_string(),
+            ))
+        }
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    // the modal was opened from the item's message, so the card can be updated in place
+    if let Err(e) = interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::UpdateMessage(item_update(
+                &item,
+                embeds_allowed(interaction.app_permissions),
+            )),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    Ok(CommandResponse::NoResponse)
+}
+
+/// handle the quick edit modal of the item posted in the message `msg_id`, reading what was typed
+/// into a single change and applying it the same way as the matching button
+async fn quick_edit_submit(
+    msg_id: &str,
+    interaction: &ModalInteraction,
+    app_state: &AppState,
+    ctx: &Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let msg_id: u64 = match msg_id.parse() {
+        Ok(id) => id,
+        Err(_) => {
+            return Err(CommandResponse::InternalFailure(
+                "Invalid interaction".to_string(),
+            ))
+        }
+    };
+
+    let input = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == "edit" => {
+                input.value.as_deref()
+            }
+            _ => None,
+        });
+    let edit = match input {
+        Some(input) => parse_quick_edit(input).map_err(CommandResponse::BasicFailure)?,
+        None => {
+            return Err(CommandResponse::BasicFailure(format!(
+                "enter a change, {}",
+                QUICK_EDIT_USAGE
+            )))
+        }
+    };
+
+    let item = item_for_message(app_state, msg_id).await?;
+    if item.bought {
+        return Err(CommandResponse::BasicFailure(
+            "this item has already been bought".to_string(),
+        ));
+    }
+
+    let user_id: u64 = interaction.user.id.into();
+    let updated = match edit {
+        QuickEdit::SetQuantity(quantity) => {
+            app_state
+                .set_item_quantity(user_id, item.id, quantity)
+                .await
+        }
+        QuickEdit::AddQuantity(quantity) => {
+            app_state
+                .add_item_quantity(user_id, item.id, quantity)
+                .await
+        }
+        QuickEdit::SetStore(store) => {
+            let guild_id = interaction.guild_id.map(|g| g.0.into());
+            let store = filter_text(ctx, app_state, guild_id, "store", &store).await?;
+            match app_state
+                .update_item_store(user_id, msg_id, Some(&*store))
+                .await
+            {
+                Ok(true) => app_state.get_shopping_list_item_by_message_id(msg_id).await,
+                Ok(false) => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+        QuickEdit::ClearStore => match app_state.update_item_store(user_id, msg_id, None).await {
+            Ok(true) => app_state.get_shopping_list_item_by_message_id(msg_id).await,
+            Ok(false) => Ok(None),
+            Err(e) => Err(e),
+        },
+    };
+    let item = match updated {
+        Ok(Some(item)) => item,
+        Ok(None) => {
+            return Err(CommandResponse::BasicFailure(
+                "this item has already been bought".to_string(),
+            ))
+        }
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    // the modal was opened from the item's message, so the card can be updated in place
+    if let Err(e) = interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::UpdateMessage(item_update(
+                &item,
+                embeds_allowed(interaction.app_permissions),
+            )),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    Ok(CommandResponse::NoResponse)
+}
+
+/// handle the make recurring modal of the bought item posted in the message `msg_id`, adding the
+/// item to the list on the schedule entered, or updating the schedule if the item already recurs
+async fn make_recurring_submit(
+    msg_id: &str,
+    interaction: &ModalInteraction,
+    app_state: &AppState,
+) -> Result<CommandResponse, CommandResponse> {
+    let (msg_id, guild_id): (u64, u64) = match (msg_id.parse(), interaction.guild_id) {
+        (Ok(id), Some(g)) => (id, g.0.into()),
+        _ => {
+            return Err(CommandResponse::InternalFailure(
+                "Invalid interaction".to_string(),
+            ))
+        }
+    };
+
+    let spec = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == "schedule" => {
+                input.value.as_deref()
+            }
+            _ => None,
+        })
+        .unwrap_or_default()
+        .trim();
+    let schedule = match Schedule::parse(spec) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            return Err(CommandResponse::BasicFailure(format!(
+                "{}, {}",
+                e, SCHEDULE_USAGE
+            )))
+        }
+    };
+
+    let item = item_for_message(app_state, msg_id).await?;
+
+    let next_run = schedule.next_run(chrono::Utc::now(), CONFIG.guild(Some(guild_id)).timezone);
+    let recurring = NewRecurringItem {
+        item: &item.item,
+        store: item.store.as_deref(),
+        notes: item.notes.as_deref(),
+        quantity: item.quantity,
+        personal: item.personal,
+        schedule_spec: spec,
+        next_run,
+    };
+
+    // checked again rather than trusting the modal, in case the item was made recurring since
+    let existing = find_recurring_item(app_state, guild_id, &item.item).await?;
+    let result = match &existing {
+        Some(existing) => {
+            app_state
+                .update_recurring_item(existing.id, recurring)
+                .await
+        }
+        None => {
+            app_state
+                .add_recurring_item(
+                    interaction.user.id.into(),
+                    interaction.channel_id.into(),
+                    guild_id,
+                    recurring,
+                )
+                .await
+        }
+    };
+    if let Err(e) = result {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        )));
+    }
+
+    Ok(CommandResponse::BasicSuccess(format!(
+        "{} will {} added to the shopping list {}, starting <t:{}:f>",
+        describe_item(&item),
+        match existing {
+            Some(_) => "now be",
+            None => "be",
+        },
+        schedule,
+        next_run.timestamp()
+    )))
+}
+
+/// handle the reminder modal of the item posted in the message `msg_id`, storing a reminder for the
+/// user at the time they entered
+async fn remind_submit(
+    msg_id: &str,
+    interaction: &ModalInteraction,
+    app_state: &AppState,
+) -> Result<CommandResponse, CommandResponse> {
+    let (msg_id, guild_id): (u64, u64) = match (msg_id.parse(), interaction.guild_id) {
+        (Ok(id), Some(g)) => (id, g.0.into()),
+        _ => {
+            return Err(CommandResponse::InternalFailure(
+                "Invalid interaction".to_string(),
+            ))
+        }
+    };
+
+    let input = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == "when" => {
+                input.value.as_deref()
+            }
+            _ => None,
+        })
+        .unwrap_or_default();
+    let remind_at = parse_reminder_time(
+        input,
+        chrono::Utc::now(),
+        CONFIG.guild(Some(guild_id)).timezone,
+    )
+    .map_err(CommandResponse::BasicFailure)?;
+
+    let item = item_for_message(app_state, msg_id).await?;
+    if item.bought {
+        return Err(CommandResponse::BasicFailure(
+            "this item has already been bought".to_string(),
+        ));
+    }
+
+    if let Err(e) = app_state
+        .set_item_reminder(
+            guild_id,
+            item.id,
+            interaction.user.id.into(),
+            remind_at.naive_utc(),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        )));
+    }
+
+    Ok(CommandResponse::BasicSuccess(format!(
+        "I'll remind you about {} <t:{}:f> (<t:{}:R>), unless it's bought first",
+        describe_item(&item),
+        remind_at.timestamp(),
+        remind_at.timestamp()
+    )))
+}
+
+/// handle the split modal of the item posted in the message `msg_id`, taking the quantity split off
+/// from the item and posting it as a new item at the chosen store
+async fn split_submit(
+    msg_id: &str,
+    interaction: &ModalInteraction,
+    app_state: &AppState,
+    ctx: &Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let msg_id: u64 = match msg_id.parse() {
+        Ok(id) => id,
+        Err(_) => {
+            return Err(CommandResponse::InternalFailure(
+                "Invalid interaction".to_string(),
+            ))
+        }
+    };
+
+    let mut quantity: Option<&str> = None;
+    let mut store: Option<&str> = None;
+    for component in interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+    {
+        if let ActionRowComponent::InputText(input) = component {
+            let value = input
+                .value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty());
+            match input.custom_id.as_str() {
+                "quantity" => quantity = value,
+                "store" => store = value,
+                _ => {}
+            }
+        }
+    }
+    let quantity = parse_set_quantity(quantity).map_err(CommandResponse::BasicFailure)?;
+
+    let original = item_for_message(app_state, msg_id).await?;
+
+    if quantity >= original.quantity {
+        return Err(CommandResponse::BasicFailure(format!(
+            "there are only {} {} on the list, split off fewer than that",
+            original.quantity, original.item
+        )));
+    }
+
+    let guild_id = interaction.guild_id.map(|g| g.0.into());
+    let store = match store {
+        Some(store) => Some(filter_text(ctx, app_state, guild_id, "store", store).await?),
+        None => None,
+    };
+
+    // the split is a new pending item, only its quantity and store differ from the original
+    let shop = Shop {
+        item: original.item.as_ref(),
+        personal: original.personal,
+        quantity,
+        quantity_source: QuantitySource::Explicit,
+        store: store.as_deref(),
+        notes: original.notes.as_deref(),
+        recipe: original.recipe_group.as_deref(),
+        bought_for: original.bought_for.as_deref(),
+        product_url: original.product_url.as_deref(),
+    };
+
+    // the split is posted before anything is saved, as its row needs the message it's in
+    create_loading_message(interaction, ctx).await?;
+    let card = shop.card(guild_id);
+    let resp = create_new_shopping(&card, embeds_allowed(interaction.app_permissions)).await?;
+
+    let message = match interaction.create_followup(&ctx, resp).await {
+        Ok(m) => m,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+    };
+
+    let user_id: u64 = interaction.user.id.into();
+    let split = app_state
+        .split_item(
+            user_id,
+            original.id,
+            quantity,
+            shop.store,
+            message.id.into(),
+            interaction.channel_id.into(),
+        )
+        .await;
+    let updated = match split {
+        Ok(Some((updated, _))) => updated,
+        Ok(None) => {
+            // the buttons of a message without an item do nothing, so it is taken back down
+            if let Err(e) = interaction.delete_followup(&ctx, message.id).await {
+                error!("error deleting split message {}: {}", message.id, e);
+            }
+            return Err(CommandResponse::BasicFailure(
+                "this item has been bought or changed since, check it and try again".to_string(),
+            ));
+        }
+        Err(e) => {
+            if let Err(e) = interaction.delete_followup(&ctx, message.id).await {
+                error!("error deleting split message {}: {}", message.id, e);
+            }
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    // the split is already saved, so failing to show the original's new quantity is only logged
+    if let Err(e) = interaction
+        .channel_id
+        .edit_message(
+            &ctx,
+            MessageId::new(msg_id),
+            item_edit(&updated, embeds_allowed(interaction.app_permissions)),
+        )
+        .await
+    {
+        error!("error editing message {}: {}", msg_id, e);
+    }
+
+    record_recently_used(app_state, user_id, &shop).await;
+    if CONFIG.guild(guild_id).list_mode == ListMode::Classic {
+        set_status_reaction(ctx, message.channel_id, message.id, ItemStatus::Pending).await;
+    }
+
+    Ok(CommandResponse::NoResponse)
+}
+
+async fn note_to_item_submit(
+    msg_id: &str,
+    interaction: &ModalInteraction,
+    app_state: &AppState,
+    ctx: &Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let msg_id: u64 = match msg_id.parse() {
+        Ok(id) => id,
+        Err(_) => {
+            return Err(CommandResponse::InternalFailure(
+                "Invalid interaction".to_string(),
+            ))
+        }
+    };
+
+    let mut part: Option<&str> = None;
+    for component in interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+    {
+        if let ActionRowComponent::InputText(input) = component {
+            if input.custom_id == "part" {
+                part = input
+                    .value
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty());
+            }
+        }
+    }
+    let part = match part {
+        Some(part) => part,
+        None => {
+            return Err(CommandResponse::BasicFailure(
+                "enter the part of the note to add as an item".to_string(),
+            ))
+        }
+    };
+
+    let original = item_for_message(app_state, msg_id).await?;
+
+    let notes = match original.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        Some(notes) => notes,
+        None => {
+            return Err(CommandResponse::BasicFailure(format!(
+                "{} has no note to turn into an item",
+                original.item
+            )))
+        }
+    };
+    let remaining = match take_from_note(notes, part) {
+        Some(remaining) => remaining,
+        None => {
+            return Err(CommandResponse::BasicFailure(format!(
+                "`{}` isn't in the note of {}, the note is `{}`",
+                part, original.item, notes
+            )))
+        }
+    };
+
+    // the new item is cleaned as /shop would clean it, so "a dozen eggs" is 12 eggs
+    let guild_id = interaction.guild_id.map(|g| g.0.into());
+    let config = CONFIG.guild(guild_id);
+    let parsed = parse_quantity_words(part);
+    let (quantity, quantity_source) = QuantityCandidates {
+        explicit: None,
+        parsed: parsed.map(|(quantity, _)| quantity),
+        typical: None,
+        guild_default: config.default_quantity(),
+    }
+    .resolve();
+    let name = match (quantity_source, parsed) {
+        (QuantitySource::Parsed, Some((_, rest))) => rest,
+        _ => part,
+    };
+    let cleaned = clean_item_name(name, config.item_charset, config.item_chars)
+        .map_err(CommandResponse::BasicFailure)?;
+    if cleaned.trim().is_empty() {
+        return Err(CommandResponse::BasicFailure(format!(
+            "`{}` doesn't leave an item name once cleaned up",
+            part
+        )));
+    }
+    let expanded = expand_alias(app_state, guild_id, &cleaned).await?;
+    let item = filter_text(
+        ctx,
+        app_state,
+        guild_id,
+        "item name",
+        expanded.as_deref().unwrap_or(&cleaned),
+    )
+    .await?;
+
+    let user_id: u64 = interaction.user.id.into();
+    let updated = match app_state
+        .update_item_notes(user_id, original.id, remaining.as_deref())
+        .await
+    {
+        Ok(Some(item)) => item,
+        Ok(None) => {
+            return Err(CommandResponse::BasicFailure(
+                "this item has been bought since, check it and try again".to_string(),
+            ))
+        }
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    // the note is already saved, so failing to show it is only logged
+    if let Err(e) = interaction
+        .channel_id
+        .edit_message(
+            &ctx,
+            MessageId::new(msg_id),
+            item_edit(&updated, embeds_allowed(interaction.app_permissions)),
+        )
+        .await
+    {
+        error!("error editing message {}: {}", msg_id, e);
+    }
+
+    let shop = Shop {
+        item: &item,
+        personal: original.personal,
+        quantity,
+        quantity_source,
+        store: original.store.as_deref(),
+        notes: None,
+        recipe: original.recipe_group.as_deref(),
+        bought_for: original.bought_for.as_deref(),
+        product_url: None,
+    };
+
+    create_loading_message(interaction, ctx).await?;
+    let card = shop.card(guild_id);
+    let resp = create_new_shopping(&card, embeds_allowed(interaction.app_permissions)).await?;
+
+    let message = match interaction.create_followup(&ctx, resp).await {
+        Ok(m) => m,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+    };
+
+    push_list_item_to_database(shop, app_state, interaction, ctx, message.id.into()).await?;
+
+    Ok(CommandResponse::NoResponse)
+}
+
+#[async_trait]
+impl<'a> ModalCommand<'a> for Shop<'a> {
+    async fn modal_answerable<'b>(
+        interaction: &'b ModalInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction.data.custom_id.starts_with(DUPLICATE_PREFIX)
+            || interaction.data.custom_id.starts_with(SET_QUANTITY_PREFIX)
+            || interaction.data.custom_id.starts_with(SPLIT_PREFIX)
+            || interaction.data.custom_id.starts_with(REMIND_PREFIX)
+            || interaction.data.custom_id.starts_with(QUICK_EDIT_PREFIX)
+            || interaction
+                .data
+                .custom_id
+                .starts_with(MAKE_RECURRING_PREFIX)
+            || interaction.data.custom_id.starts_with(NOTE_TO_ITEM_PREFIX)
+    }
+
+    async fn modal_submit<'b>(
+        interaction: &'b ModalInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        if let Some(msg_id) = decode_custom_id(&interaction.data.custom_id, SET_QUANTITY_PREFIX) {
+            return set_quantity_submit(&msg_id, interaction, app_state, ctx).await;
+        }
+        if let Some(msg_id) = decode_custom_id(&interaction.data.custom_id, SPLIT_PREFIX) {
+            return split_submit(&msg_id, interaction, app_state, ctx).await;
+        }
+        if let Some(msg_id) = decode_custom_id(&interaction.data.custom_id, REMIND_PREFIX) {
+            return remind_submit(&msg_id, interaction, app_state).await;
+        }
+        if let Some(msg_id) = decode_custom_id(&interaction.data.custom_id, QUICK_EDIT_PREFIX) {
+            return quick_edit_submit(&msg_id, interaction, app_state, ctx).await;
+        }
+        if let Some(msg_id) = decode_custom_id(&interaction.data.custom_id, MAKE_RECURRING_PREFIX) {
+            return make_recurring_submit(&msg_id, interaction, app_state).await;
+        }
+        if let Some(msg_id) = decode_custom_id(&interaction.data.custom_id, NOTE_TO_ITEM_PREFIX) {
+            return note_to_item_submit(&msg_id, interaction, app_state, ctx).await;
+        }
+
+        let msg_id: u64 = match decode_custom_id(&interaction.data.custom_id, DUPLICATE_PREFIX)
+            .and_then(|id| id.parse().ok())
+        {
+            Some(id) => id,
+            None => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ))
+            }
+        };
+
+        let original = item_for_message(app_state, msg_id).await?;
+
+        let mut quantity: Option<&str> = None;
+        let mut store: Option<&str> = None;
+        let mut notes: Option<&str> = None;
+        for component in interaction
+            .data
+            .components
+            .iter()
+            .flat_map(|row| row.components.iter())
+        {
+            if let ActionRowComponent::InputText(input) = component {
+                let value = input
+                    .value
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty());
+                match input.custom_id.as_str() {
+                    "quantity" => quantity = value,
+                    "store" => store = value,
+                    "notes" => notes = value,
+                    _ => {}
+                }
+            }
+        }
+
+        // the same range offered by the quantity option of /shop
+        let quantity = match quantity.and_then(|q| q.parse::<i64>().ok()) {
+            Some(q) if (1..26).contains(&q) => q,
+            _ => {
+                return Err(CommandResponse::BasicFailure(
+                    "the quantity must be a whole number from 1 to 25".to_string(),
+                ))
+            }
+        };
+
+        let guild_id = interaction.guild_id.map(|g| g.0.into());
+        let notes = match notes {
+            Some(notes) => Some(filter_text(ctx, app_state, guild_id, "notes", notes).await?),
+            None => None,
+        };
+
+        // the copy is a new pending item, only its details are taken from the original
+        let shop = Shop {
+            item: original.item.as_ref(),
+            personal: original.personal,
+            quantity,
+            quantity_source: QuantitySource::Explicit,
+            store,
+            notes: notes.as_deref(),
+            recipe: original.recipe_group.as_deref(),
+            bought_for: original.bought_for.as_deref(),
+            product_url: original.product_url.as_deref(),
+        };
+
+        create_loading_message(interaction, ctx).await?;
+        let card = shop.card(guild_id);
+        let resp = create_new_shopping(&card, embeds_allowed(interaction.app_permissions)).await?;
+
+        let message = match interaction.create_followup(&ctx, resp).await {
+            Ok(m) => m,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with discord: {}",
+                    e
+                )));
+            }
+        };
+
+        push_list_item_to_database(shop, app_state, interaction, ctx, message.id.into()).await?;
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+/// Post every pending item again once a shopping trip is over, so what's still to buy is at the
+/// bottom of the channel rather than scattered among what was bought. The old messages are struck
+/// through and marked as refreshed
+pub struct ShoppingComplete;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShoppingComplete {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(ShoppingComplete)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShoppingComplete {
+    fn name() -> &'static str {
+        "shopping-complete"
+    }
+
+    fn description() -> &'static str {
+        "Run this command once you have completed shopping, to post what's left to buy again"
+    }
+
+    fn get_application_command_options(command: CreateCommand) -> CreateCommand {
+        command
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        cmd_interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match cmd_interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        if CONFIG.guild(Some(guild_id)).list_mode == ListMode::Digest {
+            return Err(CommandResponse::BasicFailure(
+                "this server's list is kept in a digest, so there are no item messages to refresh"
+                    .to_string(),
+            ));
+        }
+
+        // collect every non-bought item from the shopping list
+        let items = match app_state.get_pending_items(guild_id).await {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(
+                "There's nothing left on the shopping list to refresh".to_string(),
+            ));
+        }
+
+        if let Err(e) = cmd_interaction.create_response(&ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("-----------------------------------\n**Shopping Complete!**\n-----------------------------------")
+            )
+        ).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        let channel = cmd_interaction.channel_id;
+        let mut refreshed = 0;
+        let mut failed = 0;
+        let mut deleted = 0;
+
+        // for each item, send a message to the shopping channel
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(EDIT_INTERVAL).await;
+            }
+
+            // the old message is read before anything changes, so a message someone has deleted
+            // is found out before the item is posted again
+            let old = match (item.channel_id, item.message_expired_at) {
+                (Some(c), None) => match ChannelId::new(c as u64)
+                    .message(&ctx, MessageId::new(item.message_id as u64))
+                    .await
+                {
+                    Ok(m) => Some(m),
+                    Err(e) if is_unknown_message(&e) => {
+                        deleted += 1;
+                        None
+                    }
+                    Err(e) => {
+                        error!("error getting old message {}: {}", item.message_id, e);
+                        None
+                    }
+                },
+                _ => None,
+            };
+
+            let shop = Shop {
+                item: item.item.as_ref(),
+                personal: item.personal,
+                quantity: item.quantity,
+                quantity_source: QuantitySource::Explicit,
+                store: item.store.as_deref(),
+                notes: item.notes.as_deref(),
+                recipe: item.recipe_group.as_deref(),
+                bought_for: item.bought_for.as_deref(),
+                product_url: item.product_url.as_deref(),
+            };
+
+            // the fresh messages are silent, as everyone was notified of the items the first time
+            if let Err(e) = post_item_to_channel(
+                ctx,
+                app_state,
+                shop,
+                item.user_id as u64,
+                channel.into(),
+                Some(guild_id),
+                true,
+            )
+            .await
+            {
+                error!("error posting item {} again: {}", item.id, e);
+                failed += 1;
+                continue;
+            }
+
+            // mark old item as bought in the database, without crediting anyone with buying it
+            if let Err(e) = app_state
+                .set_shopping_list_items_bought(&[item.id], true, None)
+                .await
+            {
+                error!("error marking refreshed item {} bought: {}", item.id, e);
+                failed += 1;
+                continue;
+            }
+            refreshed += 1;
+
+            // edit the old message to show that it has been refreshed, in the form it was posted in
+            let old = match old {
+                Some(old) => old,
+                None => continue,
+            };
+            if let Some(description) = message_description(&old) {
+                let edit = refreshed_message(description, !old.embeds.is_empty());
+                if let Err(e) = old.channel_id.edit_message(&ctx, old.id, edit).await {
+                    error!("error editing old message {}: {}", old.id, e);
+                }
+            }
+        }
+
+        let mut summary = format!("Refreshed {} item(s)", refreshed);
+        if deleted > 0 {
+            summary.push_str(&format!(
+                ", {} old message(s) had already been deleted",
+                deleted
+            ));
+        }
+        if failed > 0 {
+            summary.push_str(&format!(", {} item(s) could not be refreshed", failed));
+        }
+
+        if let Err(e) = cmd_interaction
+            .create_followup(
+                &ctx,
+                CreateInteractionResponseFollowup::new()
+                    .content(summary)
+                    .ephemeral(true),
+            )
+            .await
+        {
+            error!("error sending shopping complete summary: {}", e);
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::ConnectionTrait;
+
+    use super::*;
+    use crate::{discord_bot::database::shopping::test_item, state::test_database_urls};
+
+    /// the user who added the test items, as [test_item] does
+    const USER: u64 = 3;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn item(id: i32, name: &str, store: Option<&str>) -> ShoppingListItemModel {
+        ShoppingListItemModel {
+            store: store.map(str::to_string),
+            ..test_item(id, name)
+        }
+    }
+
+    fn failed() -> DatabaseResult<Vec<ShoppingListItemModel>> {
+        Err("the database is down".into())
+    }
+
+    fn store_suggestions(items: Vec<ShoppingListItemModel>) -> Vec<String> {
+        let built_in = EXTRA_STORE_NAMES.iter().map(|&store| Cow::Borrowed(store));
+        ranked_suggestions(
+            items.into_iter().filter_map(|item| item.store),
+            built_in,
+            "",
+            25,
         )
     }
 
-    async fn handle_application_command<'b>(
-        self,
-        interaction: &'b CommandInteraction,
-        state: &'b AppState,
-        ctx: &'b Context,
-    ) -> Result<CommandResponse, CommandResponse> {
-        let loading_message = create_loading_message(interaction, ctx).await?;
-        let resp = create_new_shopping(&self).await?;
+    #[test]
+    fn suggestions_are_ranked_by_prefix_then_contains_then_typos() {
+        let history = names(&["bread", "silk", "oat milk", "milk chocolate"]);
+        assert_eq!(
+            ranked_suggestions(history, vec![], "milk", 25),
+            ["milk chocolate", "oat milk", "silk", "bread"]
+        );
+    }
 
-        if let Err(e) = interaction.create_followup(&ctx, resp).await {
-            error!("error creating followup: {}", e);
-            return Err(CommandResponse::NoResponse);
+    #[test]
+    fn misspelled_searches_suggest_the_closest_names() {
+        let history = names(&["carrots", "brown rice", "broccoli"]);
+        assert_eq!(
+            ranked_suggestions(history, vec![], "brocoli", 25)[0],
+            "broccoli"
+        );
+
+        let history = names(&["potato", "tomato paste", "tomato"]);
+        assert_eq!(
+            ranked_suggestions(history, vec![], "tomatoe", 25),
+            ["tomato", "tomato paste", "potato"]
+        );
+    }
+
+    #[test]
+    fn history_comes_before_defaults_and_is_not_repeated() {
+        let history = names(&["oat milk", "soy milk"]);
+        let defaults = ["almond milk", "soy milk"].map(Cow::Borrowed);
+        assert_eq!(
+            ranked_suggestions(history, defaults, "milk", 25),
+            ["oat milk", "soy milk", "almond milk"]
+        );
+    }
+
+    #[test]
+    fn suggestions_are_truncated_to_the_limit() {
+        let history: Vec<String> = (0..40).map(|i| format!("item {:02}", i)).collect();
+        let suggestions = ranked_suggestions(history, vec![], "item", 25);
+        assert_eq!(suggestions.len(), 25);
+        assert_eq!(suggestions[0], "item 00");
+        assert_eq!(suggestions[24], "item 24");
+    }
+
+    #[test]
+    fn store_menus_keep_the_current_store() {
+        let stores = (0..30).map(|i| format!("store {:02}", i));
+        let menu = store_menu_names(stores, Some("zoo"));
+        assert_eq!(menu.len(), 24);
+        assert_eq!(menu.first().unwrap(), "store 00");
+        assert_eq!(menu.last().unwrap(), "zoo");
+
+        let stores = names(&["market", "bakery", "market", "bakery"]);
+        assert_eq!(
+            store_menu_names(stores.clone(), Some("market")),
+            ["bakery", "market"]
+        );
+        assert_eq!(store_menu_names(stores, None), ["bakery", "market"]);
+        assert_eq!(
+            store_menu_names((0..30).map(|i| i.to_string()), None).len(),
+            24
+        );
+    }
+
+    #[test]
+    fn set_quantities_must_be_whole_numbers_in_range() {
+        assert_eq!(parse_set_quantity(Some(" 12 ")), Ok(12));
+        assert_eq!(parse_set_quantity(Some("999999")), Ok(MAX_QUANTITY));
+
+        assert_eq!(
+            parse_set_quantity(None),
+            Err("enter a quantity".to_string())
+        );
+        assert_eq!(
+            parse_set_quantity(Some("  ")),
+            Err("enter a quantity".to_string())
+        );
+        assert_eq!(
+            parse_set_quantity(Some("lots")),
+            Err("`lots` isn't a whole number".to_string())
+        );
+        assert!(parse_set_quantity(Some("1.5")).is_err());
+        for input in ["0", "-3", "1000000", "9223372036854775807"] {
+            assert_eq!(
+                parse_set_quantity(Some(input)),
+                Err("the quantity must be from 1 to 999999".to_string()),
+                "{}",
+                input
+            );
         }
+    }
 
-        push_list_item_to_database(self, state, interaction, ctx, loading_message).await?;
+    #[tokio::test]
+    async fn custom_seeds_are_suggested() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            state
+                .add_seed(1, SuggestionKind::Item, "kombucha")
+                .await
+                .unwrap();
+            let seeds = state.get_seeds(1, SuggestionKind::Item).await.unwrap();
 
-        Ok(CommandResponse::NoResponse)
+            // merged seeds are suggested alongside the built-in ones
+            let merged = seed_suggestions(SuggestionKind::Item, seeds.clone(), SeedMode::Merge);
+            assert_eq!(merged.len(), EXTRA_ITEMS.len() + 1, "{:?}", backend);
+
+            // only the guild's own seeds are copied for each keystroke, not the built-in ones
+            let copied = merged
+                .iter()
+                .filter(|seed| matches!(seed, Cow::Owned(_)))
+                .count();
+            assert_eq!(copied, 1, "{:?}", backend);
+
+            let suggested = ranked_suggestions(vec![], merged, "komb", 25);
+            assert_eq!(suggested[0], "kombucha", "{:?}", backend);
+
+            // replacing seeds are suggested on their own
+            let replaced = seed_suggestions(SuggestionKind::Item, seeds, SeedMode::Replace);
+            assert_eq!(replaced, ["kombucha"], "{:?}", backend);
+
+            // a guild without seeds of its own gets the built-in ones
+            let seeds = state.get_seeds(1, SuggestionKind::Store).await.unwrap();
+            let stores = seed_suggestions(SuggestionKind::Store, seeds, SeedMode::Replace);
+            assert_eq!(stores.len(), EXTRA_STORE_NAMES.len(), "{:?}", backend);
+        }
     }
-}
 
-#[async_trait]
-impl<'a> AutocompleteCommand<'a> for Shop<'a> {
-    async fn autocomplete<'c>(
-        command: &'c CommandInteraction,
-        autocomplete: &'c AutocompleteOption,
-        app_state: &'c AppState,
-        _: &'c Context,
-    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
-        let mut response = CreateAutocompleteResponse::new();
-        let user_id: u64 = command.user.id.into();
+    #[test]
+    fn a_failed_store_query_still_offers_suggestions() {
+        let cache = AutocompleteCache::default();
+        autocomplete_candidates(
+            Ok(vec![item(1, "bread", Some("Bakery"))]),
+            Ok(vec![item(2, "milk", Some("Dairy"))]),
+            Some(&cache),
+            Some(1),
+            USER,
+        );
 
-        let mut items = match app_state
-            .get_recent_shopping_list_items_by_user(user_id, 50)
-            .await
-        {
-            Ok(items) => items,
-            Err(e) => {
-                return Err(CommandResponse::InternalFailure(format!(
-                    "error communicating with database: {}",
-                    e
-                )));
-            }
+        // the user's items are still suggested, alongside the built-in stores
+        let items = autocomplete_candidates(
+            Ok(vec![item(3, "fish", Some("Market"))]),
+            failed(),
+            Some(&cache),
+            Some(1),
+            USER,
+        );
+        let stores = store_suggestions(items);
+        assert_eq!(stores[0], "Market");
+        assert_eq!(stores.len(), EXTRA_STORE_NAMES.len() + 1);
+
+        // with every query failing, the last complete candidates loaded for the guild are used,
+        // rather than those of the query which half failed
+        let items = autocomplete_candidates(failed(), failed(), Some(&cache), Some(1), USER);
+        let stores = store_suggestions(items);
+        assert!(stores.contains(&"Bakery".to_string()));
+        assert!(stores.contains(&"Dairy".to_string()));
+        assert!(!stores.contains(&"Market".to_string()));
+
+        // but not those of another guild, which only get the built-in stores
+        let items = autocomplete_candidates(failed(), failed(), Some(&cache), Some(9), USER);
+        assert!(items.is_empty());
+        let stores = store_suggestions(items);
+        assert_eq!(stores.len(), EXTRA_STORE_NAMES.len());
+        assert!(stores.contains(&"Countdown".to_string()));
+    }
+
+    #[test]
+    fn cached_candidates_are_only_served_to_the_user_they_were_loaded_for() {
+        let cache = AutocompleteCache::default();
+        let mut personal = item(1, "prescription", None);
+        personal.personal = true;
+        autocomplete_candidates(
+            Ok(vec![personal]),
+            Ok(vec![item(2, "milk", None)]),
+            Some(&cache),
+            Some(1),
+            USER,
+        );
+
+        // someone else in the guild doesn't get the user's own items while the database is down
+        let items = autocomplete_candidates(failed(), failed(), Some(&cache), Some(1), USER + 1);
+        assert!(items.is_empty());
+
+        let items = autocomplete_candidates(failed(), failed(), Some(&cache), Some(1), USER);
+        let items: Vec<&str> = items.iter().map(|item| item.item.as_str()).collect();
+        assert_eq!(items, ["prescription", "milk"]);
+    }
+
+    #[test]
+    fn the_users_items_come_before_everyone_elses() {
+        let items = autocomplete_candidates(
+            Ok(vec![item(2, "milk", None)]),
+            Ok(vec![item(1, "bread", None), item(2, "milk", None)]),
+            None,
+            Some(1),
+            USER,
+        );
+        let items: Vec<&str> = items.iter().map(|item| item.item.as_str()).collect();
+        assert_eq!(items, ["milk", "bread"]);
+    }
+
+    #[test]
+    fn items_are_only_acted_on_from_their_own_message() {
+        let item = MessageIds {
+            message: 10,
+            channel: Some(2),
+            guild: Some(1),
+        };
+        let from = |message, channel, guild| MessageIds {
+            message,
+            channel: Some(channel),
+            guild,
         };
 
-        let extra_items = match app_state.get_recent_shopping_list_items(50).await {
-            Ok(items) => items,
-            Err(e) => {
-                return Err(CommandResponse::InternalFailure(format!(
-                    "error communicating with database: {}",
-                    e
-                )));
-            }
+        assert!(same_message(&item, &from(10, 2, Some(1)), false));
+
+        // a different message, channel or guild is refused
+        assert!(!same_message(&item, &from(11, 2, Some(1)), false));
+        assert!(!same_message(&item, &from(10, 3, Some(1)), false));
+        assert!(!same_message(&item, &from(10, 2, Some(4)), false));
+        assert!(!same_message(&item, &from(10, 2, None), false));
+
+        // items recorded before their channel was match any channel
+        let unknown_channel = MessageIds {
+            channel: None,
+            ..from(10, 2, Some(1))
         };
+        assert!(same_message(&unknown_channel, &from(10, 7, Some(1)), false));
+    }
 
-        for item in extra_items {
-            if !items.contains(&item) {
-                items.push(item);
-            }
-        }
-
-        let search_phrase = autocomplete.value;
-
-        match autocomplete.name {
-            "item" => {
-                let mut item_names: HashSet<String> =
-                    items.into_iter().map(|item| item.item).collect();
-                item_names.extend(EXTRA_ITEMS.iter().map(|item| item.to_string()));
-
-                //sort item names, preferring items that start with, then contain, the current search phrase
-                let mut item_names: Vec<String> = item_names.into_iter().collect();
-                item_names.sort_by(|a, b| {
-                    let a_start = a.starts_with(search_phrase);
-                    let b_start = b.starts_with(search_phrase);
-                    let a_contains = a.contains(search_phrase);
-                    let b_contains = b.contains(search_phrase);
-
-                    if a_start && !b_start {
-                        Ordering::Less
-                    } else if !a_start && b_start {
-                        Ordering::Greater
-                    } else if a_contains && !b_contains {
-                        Ordering::Less
-                    } else if !a_contains && b_contains {
-                        Ordering::Greater
-                    } else {
-                        a.cmp(b)
-                    }
-                });
-                item_names.truncate(25);
-
-                let choices: Vec<AutocompleteChoice> = item_names
-                    .into_iter()
-                    .map(|item| AutocompleteChoice {
-                        name: item.clone(),
-                        value: serde_json::Value::String(item),
-                    })
-                    .collect();
+    #[test]
+    fn the_store_menu_is_exempt_from_the_message_check() {
+        let item = MessageIds {
+            message: 10,
+            channel: Some(2),
+            guild: Some(1),
+        };
+        let menu = MessageIds {
+            message: 99,
+            channel: Some(2),
+            guild: Some(1),
+        };
+        assert!(same_message(&item, &menu, true));
+        assert!(!same_message(&item, &menu, false));
 
-                response = response.set_choices(choices);
-            }
-            "store" => {
-                let mut store_names: HashSet<String> =
-                    items.into_iter().filter_map(|item| item.store).collect();
-                store_names.extend(EXTRA_STORE_NAMES.iter().map(|store| store.to_string()));
-
-                //sort store names, preferring stores that start with, then contain, the current search phrase
-                let mut store_names: Vec<String> = store_names.into_iter().collect();
-                store_names.sort_by(|a, b| {
-                    let a_start = a.starts_with(search_phrase);
-                    let b_start = b.starts_with(search_phrase);
-                    let a_contains = a.contains(search_phrase);
-                    let b_contains = b.contains(search_phrase);
-
-                    if a_start && !b_start {
-                        Ordering::Less
-                    } else if !a_start && b_start {
-                        Ordering::Greater
-                    } else if a_contains && !b_contains {
-                        Ordering::Less
-                    } else if !a_contains && b_contains {
-                        Ordering::Greater
-                    } else {
-                        a.cmp(b)
-                    }
-                });
-                store_names.truncate(25);
-
-                let choices: Vec<AutocompleteChoice> = store_names
-                    .into_iter()
-                    .map(|store| AutocompleteChoice {
-                        name: store.clone(),
-                        value: serde_json::Value::String(store),
-                    })
-                    .collect();
+        // but it must still be in the item's channel and guild
+        let elsewhere = MessageIds {
+            channel: Some(3),
+            ..menu
+        };
+        assert!(!same_message(&item, &elsewhere, true));
+    }
 
-                response = response.set_choices(choices);
-            }
-            _ => {
-                return Err(CommandResponse::InternalFailure(
-                    "Invalid autocomplete option".to_string(),
-                ));
+    /// an item posted in message 10 by user 3
+    async fn posted_item(state: &AppState) {
+        state
+            .add_shopping_list_item(
+                3,
+                10,
+                2,
+                Some(1),
+                NewShoppingListItem {
+                    item: "milk",
+                    store: None,
+                    notes: None,
+                    quantity: 1,
+                    personal: false,
+                    recipe_group: None,
+                    bought_for: None,
+                    product_url: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn only_similar_enough_items_are_likely_duplicates() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            for (message_id, user_id, name, personal) in [
+                (10, 3, "tomatoes", false),
+                (11, 4, "toothpaste", true),
+                (12, 3, "shampoo", true),
+            ] {
+                state
+                    .add_shopping_list_item(
+                        user_id,
+                        message_id,
+                        2,
+                        Some(1),
+                        NewShoppingListItem {
+                            item: name,
+                            store: None,
+                            notes: None,
+                            quantity: 1,
+                            personal,
+                            recipe_group: None,
+                            bought_for: None,
+                            product_url: None,
+                        },
+                    )
+                    .await
+                    .unwrap();
             }
+            let duplicate = |name: &'static str| {
+                let state = state.clone();
+                async move {
+                    likely_duplicate(&state, 1, 3, name)
+                        .await
+                        .unwrap()
+                        .map(|item| item.item)
+                }
+            };
+
+            // one letter off of eight is above the threshold, two is below it
+            assert_eq!(
+                duplicate("Tomatos").await.as_deref(),
+                Some("tomatoes"),
+                "{:?}",
+                backend
+            );
+            assert_eq!(duplicate("tomato").await, None, "{:?}", backend);
+
+            // the user's own personal items count, but not anyone else's
+            assert_eq!(
+                duplicate("shampo").await.as_deref(),
+                Some("shampoo"),
+                "{:?}",
+                backend
+            );
+            assert_eq!(duplicate("toothpast").await, None, "{:?}", backend);
+
+            // nor do items in another guild
+            let elsewhere = likely_duplicate(&state, 9, 3, "tomatoes").await.unwrap();
+            assert!(elsewhere.is_none(), "{:?}", backend);
         }
+    }
+
+    #[tokio::test]
+    async fn striking_an_item_keeps_its_message_and_row() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            posted_item(&state).await;
+
+            // a struck item keeps its message
+            let deleted = std::cell::Cell::new(false);
+            let removed = remove_item(&state, RemoveMode::Strike, 3, 10, || async {
+                deleted.set(true);
+                Ok(())
+            })
+            .await;
+            assert!(removed.is_ok(), "{:?}", backend);
+            assert!(!deleted.get(), "{:?}", backend);
 
-        Ok(response)
+            let item = state.get_shopping_list_item_by_message_id(10).await;
+            let item = item.unwrap().expect("the item is kept");
+            assert!(item.bought, "{:?}", backend);
+            assert_eq!(item.bought_by, None, "{:?}", backend);
+        }
     }
-}
 
-#[async_trait]
-impl<'a> InteractionCommand<'a> for Shop<'a> {
-    async fn answerable<'b>(
-        interaction: &'b ComponentInteraction,
-        app_state: &'b AppState,
-        _: &'b Context,
-    ) -> bool {
-        let msg_id: u64 = interaction.message.id.into();
-        match app_state.get_shopping_list_item_by_message_id(msg_id).await {
-            Ok(Some(_)) => true,
-            Ok(None) => false,
-            Err(e) => {
-                error!("error communicating with database: {}", e);
-                false
-            }
+    #[tokio::test]
+    async fn deleting_an_item_deletes_its_message_first() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            posted_item(&state).await;
+
+            // only the person who added it can delete it, and nothing is touched otherwise
+            let deleted = std::cell::Cell::new(false);
+            let removed = remove_item(&state, RemoveMode::Delete, 4, 10, || async {
+                deleted.set(true);
+                Ok(())
+            })
+            .await;
+            assert!(
+                matches!(removed, Err(CommandResponse::BasicFailure(_))),
+                "{:?}",
+                backend
+            );
+            assert!(!deleted.get(), "{:?}", backend);
+
+            // a message which can't be deleted keeps its item, so its buttons still work
+            let removed = remove_item(&state, RemoveMode::Delete, 3, 10, || async {
+                Err(CommandResponse::InternalFailure(
+                    "discord is down".to_string(),
+                ))
+            })
+            .await;
+            assert!(removed.is_err(), "{:?}", backend);
+            let item = state.get_shopping_list_item_by_message_id(10).await;
+            assert!(item.unwrap().is_some(), "{:?}", backend);
+
+            let removed = remove_item(&state, RemoveMode::Delete, 3, 10, || async {
+                deleted.set(true);
+                Ok(())
+            })
+            .await;
+            assert!(removed.is_ok(), "{:?}", backend);
+            assert!(deleted.get(), "{:?}", backend);
+            let item = state.get_shopping_list_item_by_message_id(10).await;
+            assert!(item.unwrap().is_none(), "{:?}", backend);
         }
     }
 
-    async fn interaction<'b>(
-        interaction: &'b ComponentInteraction,
-        app_state: &'b AppState,
-        ctx: &'b Context,
-    ) -> Result<CommandResponse, CommandResponse> {
-        let msg_id: u64 = interaction.message.id.into();
-        let user_id: u64 = interaction.user.id.into();
+    #[tokio::test]
+    async fn a_removed_item_is_reported_to_the_user() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            posted_item(&state).await;
 
-        match interaction.data.custom_id.as_ref() {
-            "bought" => {
-                if let Err(e) = app_state
-                    .set_shopping_list_item_bought(user_id, msg_id, true)
-                    .await
-                {
-                    return Err(CommandResponse::InternalFailure(format!(
-                        "error communicating with database: {}",
-                        e
-                    )));
-                }
+            let item = item_for_message(&state, 10).await.ok();
+            assert_eq!(
+                item.map(|item| item.item).as_deref(),
+                Some("milk"),
+                "{:?}",
+                backend
+            );
+            assert!(
+                matches!(
+                    item_for_message(&state, 11).await,
+                    Err(CommandResponse::BasicFailure(_))
+                ),
+                "{:?}",
+                backend
+            );
+        }
+    }
 
-                let ex_embed = match interaction.message.embeds.get(0) {
-                    Some(embed) => embed,
-                    None => {
-                        return Err(CommandResponse::InternalFailure(
-                            "error communicating with discord".to_string(),
-                        ));
-                    }
-                };
+    #[tokio::test]
+    async fn unknown_options_get_no_choices() {
+        let loaded = std::cell::Cell::new(false);
+        let response = autocomplete_response("notes", |_| async {
+            loaded.set(true);
+            Vec::new()
+        })
+        .await;
+        assert!(!loaded.get(), "nothing is loaded for an unknown option");
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            serde_json::json!({ "choices": [] })
+        );
+    }
 
-                let mut edit_message = interaction.message.clone();
+    #[tokio::test]
+    async fn options_are_answered_with_their_kind_of_suggestion() {
+        for (option, expected) in [
+            ("item", SuggestionKind::Item),
+            ("store", SuggestionKind::Store),
+        ] {
+            let response = autocomplete_response(option, |kind| async move {
+                assert_eq!(kind, expected);
+                vec![AutocompleteChoice {
+                    name: "Countdown".to_string(),
+                    value: serde_json::Value::String("Countdown".to_string()),
+                }]
+            })
+            .await;
+            let response = serde_json::to_value(response).unwrap();
+            assert_eq!(response["choices"][0]["value"], "Countdown", "{}", option);
+        }
+    }
 
-                if let Err(e) = edit_message
-                    .edit(
-                        &ctx,
-                        EditMessage::new()
-                            .embed(
-                                CreateEmbed::new()
-                                    //XXX: title?
-                                    .description(format!(
-                                        "(BOUGHT) ~~{}~~",
-                                        ex_embed
-                                            .description
-                                            .as_ref()
-                                            .expect("description not found")
-                                    ))
-                                    .color(EmbedColor::Green as u32),
-                            )
-                            .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
-                                "readd",
-                            )
-                            .style(serenity::all::ButtonStyle::Secondary)
-                            .label("Re-add")
-                            .disabled(false)])]),
-                    )
-                    .await
-                {
-                    return Err(CommandResponse::InternalFailure(format!(
-                        "error communicating with discord: {}",
-                        e
-                    )));
+    #[test]
+    fn quantity_buttons_are_disabled_at_their_bounds() {
+        let disabled = |quantity| {
+            let rows = serde_json::to_value(item_components(&ShoppingCard::new("milk", quantity)))
+                .unwrap();
+            let button = |id: &str| {
+                rows.as_array()
+                    .unwrap()
+                    .iter()
+                    .flat_map(|row| row["components"].as_array().unwrap())
+                    .find(|button| button["custom_id"] == id)
+                    .map(|button| button["disabled"] == true)
+                    .unwrap()
+            };
+            (button("qty-dec"), button("qty-inc"))
+        };
+
+        assert_eq!(disabled(1), (true, false));
+        assert_eq!(disabled(2), (false, false));
+        assert_eq!(disabled(MAX_QUANTITY - 1), (false, false));
+        assert_eq!(disabled(MAX_QUANTITY), (false, true));
+    }
+
+    #[tokio::test]
+    async fn both_visibilities_give_a_valid_response() {
+        let notes = "n".repeat(3000);
+        let card = ShoppingCard::new("milk", 2).notes(Some(&notes));
+
+        // posted to the channel, with buttons to act on the item
+        for embeds in [true, false] {
+            let followup: CreateInteractionResponseFollowup =
+                create_new_shopping(&card, embeds).await.unwrap();
+            let followup = serde_json::to_value(followup).unwrap();
+            assert!(!followup["components"].as_array().unwrap().is_empty());
+            match embeds {
+                true => assert!(followup["embeds"][0]["description"].is_string()),
+                false => {
+                    let content = followup["content"].as_str().unwrap();
+                    assert!(content.chars().count() <= 2000, "{}", content.len());
                 }
+            }
+        }
+
+        // shown to the user alone, as text which fits in a message
+        let confirmation = ephemeral_confirmation(&card.description());
+        assert!(confirmation.starts_with("Added x2 milk to the shopping list"));
+        assert_eq!(confirmation.chars().count(), 2000);
+        let confirmation = ephemeral_confirmation(&ShoppingCard::new("milk", 2).description());
+        assert_eq!(
+            confirmation,
+            "Added x2 milk to the shopping list\n*only you can see this, tick it off with /shopping-list*"
+        );
+    }
 
-                interaction
-                    .create_response(&ctx, CreateInteractionResponse::Acknowledge)
+    #[tokio::test]
+    async fn a_users_visibility_is_kept_until_they_reset_it() {
+        use crate::discord_bot::database::visibility::SerenityVisibilityDatabase;
+
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+
+            assert_eq!(state.get_shop_ephemeral(1, 3).await.unwrap(), None);
+            for ephemeral in [true, false] {
+                state
+                    .set_shop_ephemeral(1, 3, Some(ephemeral))
                     .await
                     .unwrap();
+                assert_eq!(
+                    state.get_shop_ephemeral(1, 3).await.unwrap(),
+                    Some(ephemeral),
+                    "{:?}",
+                    backend
+                );
             }
-            "remove" => {
-                // mark as bought in database
-                if let Err(e) = app_state
-                    .set_shopping_list_item_bought(user_id, msg_id, true)
-                    .await
-                {
-                    return Err(CommandResponse::InternalFailure(format!(
-                        "error communicating with database: {}",
-                        e
-                    )));
-                }
+            assert_eq!(
+                state.get_shop_ephemeral(2, 3).await.unwrap(),
+                None,
+                "another guild uses its own default on {:?}",
+                backend
+            );
 
-                let ex_embed = match interaction.message.embeds.get(0) {
-                    Some(embed) => embed,
-                    None => {
-                        return Err(CommandResponse::InternalFailure(
-                            "error communicating with discord".to_string(),
-                        ));
-                    }
-                };
+            state.set_shop_ephemeral(1, 3, None).await.unwrap();
+            assert_eq!(
+                state.get_shop_ephemeral(1, 3).await.unwrap(),
+                None,
+                "{:?}",
+                backend
+            );
+        }
+    }
 
-                let mut edit_message = interaction.message.clone();
+    #[tokio::test(start_paused = true)]
+    async fn a_failed_write_is_retried_without_storing_twice() {
+        let adds = &std::cell::Cell::new(0);
+        let stored = &std::cell::Cell::new(false);
 
-                if let Err(e) = edit_message
-                    .edit(
-                        &ctx,
-                        EditMessage::new()
-                            .embed(
-                                CreateEmbed::new()
-                                    .color(EmbedColor::Orange as u32)
-                                    .description(format!(
-                                        "(REMOVED) {}",
-                                        ex_embed
-                                            .description
-                                            .as_ref()
-                                            .expect("description not found")
-                                    )),
-                            )
-                            .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
-                                "readd",
-                            )
-                            .style(serenity::all::ButtonStyle::Secondary)
-                            .label("Re-add")
-                            .disabled(false)])]),
-                    )
-                    .await
-                {
-                    return Err(CommandResponse::InternalFailure(format!(
-                        "error communicating with discord: {}",
-                        e
-                    )));
+        // a write which fails twice then succeeds
+        let result = store_with_retries(
+            10,
+            || async move {
+                adds.set(adds.get() + 1);
+                match adds.get() {
+                    3 => Ok(()),
+                    _ => Err("database is locked".into()),
                 }
+            },
+            || async { Ok(false) },
+        )
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(adds.get(), 3);
 
-                interaction
-                    .create_response(&ctx, CreateInteractionResponse::Acknowledge)
-                    .await
-                    .unwrap();
-            }
-            "readd" => {
-                let item = match app_state.get_shopping_list_item_by_message_id(msg_id).await {
-                    Ok(Some(item)) => item,
-                    Ok(None) => {
-                        return Err(CommandResponse::InternalFailure(
-                            "error communicating with database".to_string(),
-                        ));
-                    }
-                    Err(e) => {
-                        return Err(CommandResponse::InternalFailure(format!(
-                            "error communicating with database: {}",
-                            e
-                        )));
-                    }
-                };
+        // a write which was stored despite reporting a failure isn't made again
+        adds.set(0);
+        let result = store_with_retries(
+            10,
+            || async move {
+                adds.set(adds.get() + 1);
+                stored.set(true);
+                Err("connection reset".into())
+            },
+            || async move { Ok(stored.get()) },
+        )
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(adds.get(), 1);
 
-                create_loading_message(interaction, ctx).await?;
-                let shop = Shop {
-                    item: item.item.as_ref(),
-                    personal: item.personal,
-                    quantity: item.quantity,
-                    store: item.store.as_deref(),
-                    notes: item.notes.as_deref(),
-                };
-                let resp = create_new_shopping(&shop).await?;
+        adds.set(0);
+        let result = store_with_retries(
+            10,
+            || async move {
+                adds.set(adds.get() + 1);
+                Err("database is down".into())
+            },
+            || async { Ok(false) },
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(adds.get(), STORE_ATTEMPTS);
+    }
 
-                let msg_id = match interaction.create_followup(&ctx, resp).await {
-                    Ok(m) => m,
-                    Err(e) => {
-                        return Err(CommandResponse::InternalFailure(format!(
-                            "error communicating with discord: {}",
-                            e
-                        )));
-                    }
-                };
+    #[tokio::test]
+    async fn a_message_whose_item_cant_be_stored_is_taken_down() {
+        let deleted = std::cell::Cell::new(None);
 
-                push_list_item_to_database(shop, app_state, interaction, ctx, msg_id.id.into())
-                    .await?;
-            }
-            _ => {
-                return Err(CommandResponse::InternalFailure(
-                    "Invalid interaction".to_string(),
-                ));
-            }
-        }
+        let result = post_then_store(
+            async { Ok(10) },
+            |_: &u64| async { Err("database is down".into()) },
+            |message| {
+                deleted.set(Some(message));
+                async {}
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(CommandResponse::InternalFailure(_))));
+        assert_eq!(
+            deleted.get(),
+            Some(10),
+            "no message is left without an item"
+        );
 
-        Ok(CommandResponse::NoResponse)
+        deleted.set(None);
+        let result = post_then_store(
+            async { Ok(11) },
+            |_: &u64| async { Ok(()) },
+            |message| {
+                deleted.set(Some(message));
+                async {}
+            },
+        )
+        .await;
+        assert!(matches!(result, Ok(11)));
+        assert_eq!(deleted.get(), None);
+
+        // nothing is stored for a message which was never posted
+        let result = post_then_store(
+            async { Err::<u64, _>(CommandResponse::NoResponse) },
+            |_: &u64| async { panic!("stored an item without a message") },
+            |_| async { panic!("deleted a message which wasn't posted") },
+        )
+        .await;
+        assert!(matches!(result, Err(CommandResponse::NoResponse)));
     }
 }
-
-// pub struct ShoppingComplete;
-
-// impl<'a> TryFrom<&'a CommandInteraction> for ShoppingComplete {
-//     type Error = String;
-
-//     fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
-//         Ok(ShoppingComplete)
-//     }
-// }
-
-// #[async_trait]
-// impl<'a> Command<'a> for ShoppingComplete {
-//     fn name() -> &'static str {
-//         "shopping-complete"
-//     }
-
-//     fn description() -> &'static str {
-//         "Run this command once you have completed shopping"
-//     }
-
-//     fn get_application_command_options(command: CreateCommand) -> CreateCommand {
-//         command
-//     }
-
-//     async fn handle_application_command<'b>(
-//         self,
-//         cmd_interaction: &'b CommandInteraction,
-//         app_state: &'b AppState,
-//         ctx: &'b Context,
-//     ) -> Result<CommandResponse, CommandResponse> {
-//         // TODO: actually make use of the shopping list -> shopping list item table
-//         // to separate what items are actually available to be bought when this command runs
-//         if let Err(e) = cmd_interaction.create_response(&ctx,
-//             CreateInteractionResponse::Message(
-//                 CreateInteractionResponseMessage::new()
-//                     .content("-----------------------------------\n**Shopping Complete!**\n-----------------------------------")
-//             )
-//         ).await {
-//             error!("error communicating with discord to create initial response: {}", e);
-//             return Err(CommandResponse::InternalFailure(format!(
-//                 "error communicating with discord: {}",
-//                 e
-//             )));
-//         }
-
-//         // collect every non-bought item from the shopping list
-//         let items = match app_state.get_unbought_shopping_list_items().await {
-//             Ok(items) => items,
-//             Err(e) => {
-//                 return Err(CommandResponse::InternalFailure(format!(
-//                     "error communicating with database: {}",
-//                     e
-//                 )));
-//             }
-//         };
-
-//         let channel = cmd_interaction.channel_id();
-
-//         // for each item, send a message to the shopping channel
-//         for item in items {
-//             let shop = Shop {
-//                 item: item.item.as_ref(),
-//                 personal: item.personal,
-//                 quantity: item.quantity,
-//                 store: item.store.as_deref(),
-//                 notes: item.notes.as_deref(),
-//             };
-
-//             let resp = create_new_shopping(&shop).await?;
-
-//             let new_msg = match channel.send_message(&ctx, resp).await {
-//                 Ok(m) => m,
-//                 Err(e) => {
-//                     error!(
-//                         "error communicating with discord to send shopping list item: {}",
-//                         e
-//                     );
-//                     return Err(CommandResponse::InternalFailure(format!(
-//                         "error communicating with discord: {}",
-//                         e
-//                     )));
-//                 }
-//             };
-
-//             push_list_item_to_database(shop, app_state, cmd_interaction, ctx, new_msg.id.into())
-//                 .await?;
-
-//             // mark old item as bought in the database
-//             if let Err(e) = app_state
-//                 .set_shopping_list_item_bought(item.user_id as u64, item.message_id as u64, true)
-//                 .await
-//             {
-//                 return Err(CommandResponse::InternalFailure(format!(
-//                     "error communicating with database: {}",
-//                     e
-//                 )));
-//             }
-
-//             let ex_embed = match channel.message(&ctx, item.message_id as u64).await {
-//                 Ok(m) => m.embeds.first().unwrap().clone(),
-//                 Err(e) => {
-//                     error!("error communicating with discord to get old message: {}", e);
-//                     return Err(CommandResponse::InternalFailure(format!(
-//                         "error communicating with discord: {}",
-//                         e
-//                     )));
-//                 }
-//             };
-
-//             // edit the old message to show that it has been refreshed
-//             if let Err(e) = channel
-//                 .edit_message(
-//                     ctx,
-//                     item.message_id as u64,
-//                     EditMessage::new()
-//                         .embed(
-//                             CreateEmbed::new()
-//                                 .description(format!(
-//                                     "(REFRESHED) ~~{}~~",
-//                                     ex_embed
-//                                         .description
-//                                         .as_ref()
-//                                         .expect("description not found")
-//                                 ))
-//                                 .color(EmbedColor::Blue as u32),
-//                         )
-//                         .components(vec![]),
-//                 )
-//                 .await
-//             {
-//                 error!(
-//                     "error communicating with discord to edit old message: {}",
-//                     e
-//                 );
-//                 return Err(CommandResponse::InternalFailure(format!(
-//                     "error communicating with discord to edit old message: {}",
-//                     e
-//                 )));
-//             }
-//         }
-
-//         Ok(CommandResponse::NoResponse)
-//     }
-// }
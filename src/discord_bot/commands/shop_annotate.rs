@@ -0,0 +1,364 @@
+use serenity::{
+    all::{
+        ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, ResolvedValue,
+    },
+    async_trait,
+    builder::{
+        CreateActionRow, CreateButton, CreateCommand, CreateCommandOption,
+        CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            bulk::throttled_edit,
+            card::ShoppingCard,
+            custom_id::{decode_custom_id, encode_custom_id},
+            shopping::describe_item,
+        },
+        database::shopping::{NoteUpdate, SerenityShoppingDatabase, ShoppingListItemModel},
+        utils::{PendingAnnotation, PendingAnnotationCache},
+    },
+    state::AppState,
+};
+
+use super::{
+    command::{Command, InteractionCommand},
+    shop_import_photo::close_preview,
+    util::CommandResponse,
+    word_filter::filter_text,
+};
+
+/// the custom id prefix of the button applying a note to the matched items, followed by its key
+const ANNOTATE_CONFIRM_PREFIX: &str = "annotate-confirm:";
+
+/// the custom id prefix of the button discarding a note, followed by its key
+const ANNOTATE_CANCEL_PREFIX: &str = "annotate-cancel:";
+
+/// the most matched items listed in the confirmation, the rest are only counted
+const MAX_LISTED_ITEMS: usize = 15;
+
+/// Append or set a note on every pending item matching a search, e.g. to mark them as on sale
+pub struct ShopAnnotateCommand<'a> {
+    query: &'a str,
+    note: &'a str,
+    update: NoteUpdate,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopAnnotateCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut query: Option<&str> = None;
+        let mut note: Option<&str> = None;
+        let mut update = NoteUpdate::Append;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("query", ResolvedValue::String(val)) => query = Some(val),
+                ("note", ResolvedValue::String(val)) => note = Some(val),
+                ("mode", ResolvedValue::String("append")) => update = NoteUpdate::Append,
+                ("mode", ResolvedValue::String("replace")) => update = NoteUpdate::Replace,
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            query: query.ok_or_else(|| String::from("query is required"))?,
+            note: note.ok_or_else(|| String::from("note is required"))?,
+            update,
+        })
+    }
+}
+
+/// whether an item's name contains the query, regardless of case
+fn matches_query(item: &ShoppingListItemModel, query: &str) -> bool {
+    item.item.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// the message showing which items will be changed, with buttons to change them or back out
+fn confirmation(
+    key: u64,
+    items: &[ShoppingListItemModel],
+    note: &str,
+    update: NoteUpdate,
+) -> CreateInteractionResponseMessage {
+    let action = match update {
+        NoteUpdate::Append => format!("Add `{}` to the note of", note),
+        NoteUpdate::Replace => format!("Set the note to `{}` on", note),
+    };
+    let mut content = format!(
+        "{} {} item(s)?\n{}",
+        action,
+        items.len(),
+        items
+            .iter()
+            .take(MAX_LISTED_ITEMS)
+            .map(|item| format!("- {}", describe_item(item)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    );
+    if items.len() > MAX_LISTED_ITEMS {
+        content.push_str(&format!(
+            "\n*...and {} more*",
+            items.len() - MAX_LISTED_ITEMS
+        ));
+    }
+
+    CreateInteractionResponseMessage::new()
+        .content(content)
+        .components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(encode_custom_id(ANNOTATE_CONFIRM_PREFIX, &key.to_string()))
+                .label(format!("Update {} item(s)", items.len()))
+                .style(ButtonStyle::Primary),
+            CreateButton::new(encode_custom_id(ANNOTATE_CANCEL_PREFIX, &key.to_string()))
+                .label("Cancel")
+                .style(ButtonStyle::Secondary),
+        ])])
+        .ephemeral(true)
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopAnnotateCommand<'a> {
+    fn name() -> &'static str {
+        "shop-annotate"
+    }
+
+    fn description() -> &'static str {
+        "add or set a note on every pending item matching a search"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "query",
+                "Part of the name of the items to update",
+            )
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "note",
+                "The note to put on the items",
+            )
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "mode",
+                "Whether to add to or replace any note the items already have, adds by default",
+            )
+            .required(false)
+            .add_string_choice("append", "append")
+            .add_string_choice("replace", "replace"),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let query = self.query.trim();
+        let note = self.note.trim();
+        if query.is_empty() || note.is_empty() {
+            return Err(CommandResponse::BasicFailure(
+                "the query and note can't be blank".to_string(),
+            ));
+        }
+        let note = filter_text(ctx, app_state, Some(guild_id), "note", note).await?;
+
+        // other people's personal items are theirs to annotate
+        let user_id: u64 = interaction.user.id.into();
+        let items: Vec<ShoppingListItemModel> = match app_state.get_pending_items(guild_id).await {
+            Ok(items) => items
+                .into_iter()
+                .filter(|item| !item.personal || item.user_id == user_id as i64)
+                .filter(|item| matches_query(item, query))
+                .collect(),
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "There are no pending items matching `{}`",
+                query
+            )));
+        }
+
+        let key: u64 = interaction.id.into();
+        match ctx.data.read().await.get::<PendingAnnotationCache>() {
+            Some(cache) => cache.insert(
+                key,
+                PendingAnnotation {
+                    user_id,
+                    ids: items.iter().map(|item| item.id).collect(),
+                    note: note.to_string(),
+                    update: self.update,
+                },
+            ),
+            None => {
+                return Err(CommandResponse::InternalFailure(
+                    "pending annotation cache missing from context".to_string(),
+                ))
+            }
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(confirmation(key, &items, &note, self.update)),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for ShopAnnotateCommand<'a> {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction
+            .data
+            .custom_id
+            .starts_with(ANNOTATE_CONFIRM_PREFIX)
+            || interaction
+                .data
+                .custom_id
+                .starts_with(ANNOTATE_CANCEL_PREFIX)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let custom_id = interaction.data.custom_id.as_str();
+        let (confirmed, key) = match decode_custom_id(custom_id, ANNOTATE_CONFIRM_PREFIX) {
+            Some(key) => (true, key),
+            None => (
+                false,
+                decode_custom_id(custom_id, ANNOTATE_CANCEL_PREFIX).unwrap_or_default(),
+            ),
+        };
+
+        let key = match key.parse::<u64>() {
+            Ok(key) => key,
+            Err(_) => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ))
+            }
+        };
+
+        let annotation = ctx
+            .data
+            .read()
+            .await
+            .get::<PendingAnnotationCache>()
+            .and_then(|cache| cache.take(key));
+        let user_id: u64 = interaction.user.id.into();
+        let annotation = match annotation {
+            Some(annotation) if annotation.user_id == user_id => annotation,
+            _ => {
+                close_preview(
+                    interaction,
+                    ctx,
+                    "This has expired, run /shop-annotate again".to_string(),
+                )
+                .await?;
+                return Ok(CommandResponse::NoResponse);
+            }
+        };
+
+        if !confirmed {
+            close_preview(interaction, ctx, "No notes were changed".to_string()).await?;
+            return Ok(CommandResponse::NoResponse);
+        }
+
+        // the buttons are removed first, so that the note can't be applied twice
+        close_preview(
+            interaction,
+            ctx,
+            format!("Updating {} item(s)...", annotation.ids.len()),
+        )
+        .await?;
+
+        let items = match app_state
+            .bulk_update_notes(
+                user_id,
+                &annotation.ids,
+                &annotation.note,
+                annotation.update,
+            )
+            .await
+        {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let result = throttled_edit(ctx, &items, |item, embeds| {
+            ShoppingCard::from_item(item).edit(embeds)
+        })
+        .await;
+
+        let mut content = format!(
+            "Updated the note on {} item(s){}",
+            items.len(),
+            result.failure_note()
+        );
+        let bought = annotation.ids.len() - items.len();
+        if bought > 0 {
+            content.push_str(&format!(
+                ", {} had been bought in the meantime and were left alone",
+                bought
+            ));
+        }
+
+        if let Err(e) = interaction
+            .edit_response(ctx, EditInteractionResponse::new().content(content))
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
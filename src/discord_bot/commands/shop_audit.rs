@@ -0,0 +1,392 @@
+use chrono::{Duration, Local, TimeZone};
+use serenity::{
+    all::{
+        ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, ResolvedValue,
+    },
+    async_trait,
+    builder::{
+        CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+        CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            bulk::throttled_edit,
+            card::ShoppingCard,
+            custom_id::{decode_custom_id, encode_custom_id},
+            embed::EmbedColor,
+            shopping::describe_item,
+            status::{set_status_reactions, ItemStatus},
+        },
+        database::shopping::{SerenityShoppingDatabase, ShoppingListItemModel},
+    },
+    state::AppState,
+};
+
+use super::{
+    command::{Command, InteractionCommand},
+    shop::removed_message,
+    util::CommandResponse,
+};
+
+/// the custom id prefix of the remove buttons of /shop-audit, followed by the age searched for,
+/// the page shown, the id of the item and the store, e.g. `audit-remove:14:0:42:countdown`
+const AUDIT_REMOVE_PREFIX: &str = "audit-remove:";
+
+/// the custom id prefix of the page buttons of /shop-audit, followed by the age searched for, the
+/// page to show and the store, e.g. `audit-page:14:1:countdown`
+const AUDIT_PAGE_PREFIX: &str = "audit-page:";
+
+/// how old an item must be to be shown when no age is given
+const DEFAULT_OLDER_THAN_DAYS: i64 = 14;
+
+/// the oldest age which can be searched for, far beyond any item worth keeping
+const MAX_OLDER_THAN_DAYS: i64 = 3650;
+
+/// the longest store name accepted, so that the custom ids of the buttons stay within the 100
+/// characters discord allows
+const AUDIT_MAX_STORE: u16 = 60;
+
+/// how many items are shown on each page, one row of remove buttons
+const ITEMS_PER_PAGE: usize = 5;
+
+/// Find stale items from a store, so that they can be cleaned off the list
+pub struct ShopAuditCommand<'a> {
+    store: &'a str,
+    older_than_days: i64,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopAuditCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut store: Option<&str> = None;
+        let mut older_than_days: Option<i64> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("store", ResolvedValue::String(val)) => store = Some(val),
+                ("older_than_days", ResolvedValue::Integer(val)) => older_than_days = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            store: store.ok_or_else(|| String::from("store is required"))?,
+            older_than_days: older_than_days.unwrap_or(DEFAULT_OLDER_THAN_DAYS),
+        })
+    }
+}
+
+/// the pending items from a store which are older than the given number of days
+async fn audit_items(
+    app_state: &AppState,
+    guild_id: u64,
+    store: &str,
+    older_than_days: i64,
+) -> Result<Vec<ShoppingListItemModel>, CommandResponse> {
+    let older_than = Local::now().naive_local() - Duration::days(older_than_days);
+
+    match app_state.find_items(guild_id, store, older_than).await {
+        Ok(items) => Ok(items),
+        Err(e) => Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        ))),
+    }
+}
+
+/// the embed listing one page of matching items and their ages, with a remove button for each and
+/// buttons to move between pages
+fn audit_message(
+    store: &str,
+    older_than_days: i64,
+    page: usize,
+    items: &[ShoppingListItemModel],
+) -> CreateInteractionResponseMessage {
+    let pages = (items.len() + ITEMS_PER_PAGE - 1) / ITEMS_PER_PAGE;
+    let page = page.min(pages.saturating_sub(1));
+    let start = page * ITEMS_PER_PAGE;
+    let shown = &items[start..(start + ITEMS_PER_PAGE).min(items.len())];
+
+    let description = shown
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let mut line = format!("{}. {}", start + i + 1, describe_item(item));
+            if let Some(added) = Local.from_local_datetime(&item.created_at).earliest() {
+                line.push_str(&format!(", added <t:{}:R>", added.timestamp()));
+            }
+            line
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let embed = CreateEmbed::new()
+        .title(format!(
+            "Items from {} older than {} day(s)",
+            store, older_than_days
+        ))
+        .description(description)
+        .footer(CreateEmbedFooter::new(format!(
+            "page {} of {}, {} item(s) in total",
+            page + 1,
+            pages,
+            items.len()
+        )))
+        .color(EmbedColor::Red as u32);
+
+    let remove_buttons: Vec<CreateButton> = shown
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            CreateButton::new(encode_custom_id(
+                AUDIT_REMOVE_PREFIX,
+                &format!("{}:{}:{}:{}", older_than_days, page, item.id, store),
+            ))
+            .label(format!("Remove #{}", start + i + 1))
+            .style(ButtonStyle::Danger)
+        })
+        .collect();
+
+    let mut components = vec![CreateActionRow::Buttons(remove_buttons)];
+    if pages > 1 {
+        components.push(CreateActionRow::Buttons(vec![
+            CreateButton::new(encode_custom_id(
+                AUDIT_PAGE_PREFIX,
+                &format!("{}:{}:{}", older_than_days, page.saturating_sub(1), store),
+            ))
+            .label("Previous")
+            .style(ButtonStyle::Secondary)
+            .disabled(page == 0),
+            CreateButton::new(encode_custom_id(
+                AUDIT_PAGE_PREFIX,
+                &format!("{}:{}:{}", older_than_days, page + 1, store),
+            ))
+            .label("Next")
+            .style(ButtonStyle::Secondary)
+            .disabled(page + 1 >= pages),
+        ]));
+    }
+
+    CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .components(components)
+        .ephemeral(true)
+}
+
+/// the message shown when nothing matches, either initially or once every item has been removed
+fn no_matches(store: &str, older_than_days: i64) -> String {
+    format!(
+        "There are no pending items from {} older than {} day(s)",
+        store, older_than_days
+    )
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopAuditCommand<'a> {
+    fn name() -> &'static str {
+        "shop-audit"
+    }
+
+    fn description() -> &'static str {
+        "find items from a store which have been waiting a long time, to clean them up"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(CommandOptionType::String, "store", "The store to audit")
+                .required(true)
+                .max_length(AUDIT_MAX_STORE)
+                .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "older_than_days",
+                "Only show items added more than this many days ago, 14 by default",
+            )
+            .required(false)
+            .min_int_value(0)
+            .max_int_value(MAX_OLDER_THAN_DAYS as u64),
+        )
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        // discord enforces the range too, but an outdated command definition may not
+        if !(0..=MAX_OLDER_THAN_DAYS).contains(&self.older_than_days) {
+            return Err(CommandResponse::BasicFailure(format!(
+                "older_than_days must be from 0 to {}",
+                MAX_OLDER_THAN_DAYS
+            )));
+        }
+
+        let store = self.store.trim();
+        if store.is_empty() || store.chars().count() > AUDIT_MAX_STORE as usize {
+            return Err(CommandResponse::BasicFailure(format!(
+                "the store must be 1 to {} characters long",
+                AUDIT_MAX_STORE
+            )));
+        }
+
+        let items = audit_items(app_state, guild_id, store, self.older_than_days).await?;
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(no_matches(
+                store,
+                self.older_than_days,
+            )));
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(audit_message(
+                store,
+                self.older_than_days,
+                0,
+                &items,
+            )),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for ShopAuditCommand<'a> {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction.data.custom_id.starts_with(AUDIT_REMOVE_PREFIX)
+            || interaction.data.custom_id.starts_with(AUDIT_PAGE_PREFIX)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let custom_id = interaction.data.custom_id.as_str();
+
+        let remove = decode_custom_id(custom_id, AUDIT_REMOVE_PREFIX);
+        let turn_page = decode_custom_id(custom_id, AUDIT_PAGE_PREFIX);
+
+        // the store comes last as it may itself contain a colon
+        let (older_than_days, page, item_id, store) = match remove.as_deref() {
+            Some(rest) => {
+                let mut parts = rest.splitn(4, ':');
+                (
+                    parts.next().and_then(|d| d.parse::<i64>().ok()),
+                    parts.next().and_then(|p| p.parse::<usize>().ok()),
+                    parts.next().and_then(|i| i.parse::<u64>().ok()),
+                    parts.next(),
+                )
+            }
+            None => {
+                let mut parts = turn_page.as_deref().unwrap_or_default().splitn(3, ':');
+                (
+                    parts.next().and_then(|d| d.parse::<i64>().ok()),
+                    parts.next().and_then(|p| p.parse::<usize>().ok()),
+                    None,
+                    parts.next(),
+                )
+            }
+        };
+
+        let (older_than_days, page, store) = match (older_than_days, page, store) {
+            (Some(days), Some(page), Some(store)) => (days, page, store),
+            _ => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ))
+            }
+        };
+
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ))
+            }
+        };
+
+        if let Some(item_id) = item_id {
+            let item = match app_state.get_shopping_list_item_by_id(item_id).await {
+                Ok(item) => item,
+                Err(e) => {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+            };
+
+            // the list may have changed since it was shown, so an item bought elsewhere is left alone
+            if let Some(item) = item.filter(|i| !i.bought && i.guild_id == Some(guild_id as i64)) {
+                if let Err(e) = app_state
+                    .set_shopping_list_items_bought(&[item.id], true, None)
+                    .await
+                {
+                    return Err(CommandResponse::InternalFailure(format!(
+                        "error communicating with database: {}",
+                        e
+                    )));
+                }
+
+                throttled_edit(ctx, std::slice::from_ref(&item), |item, embeds| {
+                    removed_message(&ShoppingCard::from_item(item).description(), embeds)
+                })
+                .await;
+                set_status_reactions(ctx, std::slice::from_ref(&item), ItemStatus::Removed).await;
+            }
+        }
+
+        let items = audit_items(app_state, guild_id, store, older_than_days).await?;
+        let response = match items.is_empty() {
+            true => CreateInteractionResponseMessage::new()
+                .content(no_matches(store, older_than_days))
+                .embeds(vec![])
+                .components(vec![]),
+            false => audit_message(store, older_than_days, page, &items),
+        };
+
+        if let Err(e) = interaction
+            .create_response(&ctx, CreateInteractionResponse::UpdateMessage(response))
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
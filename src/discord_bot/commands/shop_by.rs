@@ -0,0 +1,137 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    model::user::User,
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::shopping::shopping_list_embed, database::shopping::SerenityShoppingDatabase,
+    },
+    state::AppState,
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// List the items a specific user currently has pending on the shopping list
+pub struct ShopByCommand<'a> {
+    user: &'a User,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopByCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut user: Option<&User> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("user", ResolvedValue::User(val, _)) => user = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        match user {
+            Some(user) => Ok(Self { user }),
+            None => Err(String::from("user is required")),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopByCommand<'a> {
+    fn name() -> &'static str {
+        "shop-by"
+    }
+
+    fn description() -> &'static str {
+        "show the items a user has pending on the shopping list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::User,
+                "user",
+                "The user whose pending items to show",
+            )
+            .required(true),
+        )
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+        let target_id: u64 = self.user.id.into();
+
+        let mut items = match app_state
+            .get_pending_items_by_user(guild_id, target_id)
+            .await
+        {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        // personal items are only visible to their owner, or to an administrator
+        let is_owner = interaction.user.id == self.user.id;
+        let is_admin = interaction
+            .member
+            .as_ref()
+            .and_then(|m| m.permissions)
+            .map(|p| p.administrator())
+            .unwrap_or(false);
+        if !is_owner && !is_admin {
+            items.retain(|item| !item.personal);
+        }
+
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "{} has nothing pending on the shopping list",
+                self.user.name
+            )));
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .embed(shopping_list_embed(
+                        &format!("Pending items added by {}", self.user.name),
+                        &items,
+                    ))
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
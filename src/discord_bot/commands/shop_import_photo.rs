@@ -0,0 +1,515 @@
+use log::error;
+use serenity::{
+    all::{
+        ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, ResolvedValue,
+    },
+    async_trait,
+    builder::{
+        CreateActionRow, CreateButton, CreateCommand, CreateCommandOption,
+        CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    model::prelude::Attachment,
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            custom_id::{decode_custom_id, encode_custom_id},
+            quantity::parse_quantity_words,
+        },
+        utils::{PendingImport, PendingImportCache},
+    },
+    state::AppState,
+};
+
+use super::{
+    command::{Command, InteractionCommand},
+    shop::add_items_in_bulk,
+    util::CommandResponse,
+    word_filter::filter_text,
+};
+
+/// the custom id prefix of the button adding the items of a preview, followed by its key
+const IMPORT_CONFIRM_PREFIX: &str = "import-confirm:";
+
+/// the custom id prefix of the button discarding a preview, followed by its key
+const IMPORT_CANCEL_PREFIX: &str = "import-cancel:";
+
+/// the largest photo accepted, well within what the ocr service will read
+const MAX_PHOTO_BYTES: u32 = 8 * 1024 * 1024;
+
+/// the most items imported from one photo, matching templates
+const MAX_IMPORT_ITEMS: usize = 25;
+
+/// the longest item name kept, matching /shop
+const MAX_IMPORT_ITEM: usize = 100;
+
+/// Read a photo of a handwritten or printed list, and add its items once they've been checked
+pub struct ShopImportPhotoCommand<'a> {
+    photo: &'a Attachment,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopImportPhotoCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut photo: Option<&Attachment> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("photo", ResolvedValue::Attachment(val)) => photo = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            photo: photo.ok_or_else(|| String::from("photo is required"))?,
+        })
+    }
+}
+
+/// read one line of a photographed list as an item, e.g. `- 2x milk` or `3. a dozen eggs`.
+/// Lines without any letters, such as dates and crossed out scribbles, are skipped
+fn parse_photo_line(line: &str) -> Option<(i64, String)> {
+    // bullets, checkboxes and numbering are how a list is written, not part of the item
+    let mut line = line
+        .trim()
+        .trim_start_matches(|c: char| "-*•·–☐□✓✔".contains(c) || c.is_whitespace());
+    for checkbox in ["[ ]", "[x]", "[X]"] {
+        line = line.strip_prefix(checkbox).unwrap_or(line).trim_start();
+    }
+    if let Some((number, rest)) = line.split_once(['.', ')']) {
+        let numbered = !number.is_empty() && number.chars().all(|c| c.is_ascii_digit());
+        // `1.5 litres of milk` is a quantity rather than numbering
+        if numbered && rest.starts_with(char::is_whitespace) {
+            line = rest.trim();
+        }
+    }
+
+    if !line.chars().any(char::is_alphabetic) {
+        return None;
+    }
+
+    let leading = line.split_once(' ').and_then(|(number, rest)| {
+        let number = number.trim_end_matches(['x', 'X']);
+        let number = number.strip_prefix(['x', 'X']).unwrap_or(number);
+        Some((number.parse::<i64>().ok()?, rest.trim()))
+    });
+    let trailing = line.rsplit_once(' ').and_then(|(rest, number)| {
+        let number = number.strip_prefix(['x', 'X'])?;
+        Some((number.parse::<i64>().ok()?, rest.trim()))
+    });
+
+    let (quantity, item) = leading
+        .or(trailing)
+        .or_else(|| parse_quantity_words(line))
+        .filter(|(quantity, item)| (1..26).contains(quantity) && !item.is_empty())
+        .unwrap_or((1, line));
+
+    Some((quantity, item.chars().take(MAX_IMPORT_ITEM).collect()))
+}
+
+/// the items of a photographed list, and how many more lines there were than can be imported
+fn parse_photo_text(text: &str) -> (Vec<(i64, String)>, usize) {
+    let mut items: Vec<(i64, String)> = text.lines().filter_map(parse_photo_line).collect();
+    let skipped = items.len().saturating_sub(MAX_IMPORT_ITEMS);
+    items.truncate(MAX_IMPORT_ITEMS);
+    (items, skipped)
+}
+
+/// the message showing what will be added, with buttons to add it or throw it away
+fn preview(key: u64, items: &[(i64, String)], skipped: usize) -> EditInteractionResponse {
+    let mut content = format!(
+        "Found {} item(s) in the photo, check them before they're added:\n{}",
+        items.len(),
+        items
+            .iter()
+            .map(|(quantity, item)| format!("- {} {}", quantity, item))
+            .collect::<Vec<String>>()
+            .join("\n")
+    );
+    if skipped > 0 {
+        content.push_str(&format!(
+            "\n\n{} more item(s) were left off, at most {} can be imported at once",
+            skipped, MAX_IMPORT_ITEMS
+        ));
+    }
+
+    EditInteractionResponse::new()
+        .content(content)
+        .components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(encode_custom_id(IMPORT_CONFIRM_PREFIX, &key.to_string()))
+                .label(format!("Add {} item(s)", items.len()))
+                .style(ButtonStyle::Primary),
+            CreateButton::new(encode_custom_id(IMPORT_CANCEL_PREFIX, &key.to_string()))
+                .label("Cancel")
+                .style(ButtonStyle::Secondary),
+        ])])
+}
+
+/// replace a preview with a message, removing its buttons
+pub(super) async fn close_preview(
+    interaction: &ComponentInteraction,
+    ctx: &Context,
+    content: String,
+) -> Result<(), CommandResponse> {
+    if let Err(e) = interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(vec![]),
+            ),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopImportPhotoCommand<'a> {
+    fn name() -> &'static str {
+        "shop-import-photo"
+    }
+
+    fn description() -> &'static str {
+        "add the items from a photo of a handwritten or printed list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Attachment,
+                "photo",
+                "A photo of the list, with one item on each line",
+            )
+            .required(true),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let provider = match app_state.ocr.as_ref() {
+            Some(provider) => provider,
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "reading photos isn't set up for this bot, ask its host to add `[ocr]`"
+                        .to_string(),
+                ))
+            }
+        };
+
+        let is_image = self
+            .photo
+            .content_type
+            .as_deref()
+            .map_or(false, |content_type| content_type.starts_with("image/"));
+        if !is_image {
+            return Err(CommandResponse::BasicFailure(
+                "the attachment must be a photo".to_string(),
+            ));
+        }
+        if self.photo.size > MAX_PHOTO_BYTES {
+            return Err(CommandResponse::BasicFailure(format!(
+                "the photo must be smaller than {} MB",
+                MAX_PHOTO_BYTES / 1024 / 1024
+            )));
+        }
+
+        // downloading and reading the photo takes longer than discord waits for a response
+        if let Err(e) = interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::Defer(
+                    CreateInteractionResponseMessage::new().ephemeral(true),
+                ),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        let photo = match self.photo.download().await {
+            Ok(photo) => photo,
+            Err(e) => {
+                error!("error downloading photo {}: {}", self.photo.id, e);
+                return Err(CommandResponse::BasicFailure(
+                    "the photo couldn't be downloaded from discord, please try again".to_string(),
+                ));
+            }
+        };
+
+        let text = match provider.read_text(&photo).await {
+            Ok(text) => text,
+            Err(e) => {
+                error!("error reading photo {}: {}", self.photo.id, e);
+                return Err(CommandResponse::BasicFailure(
+                    "the photo couldn't be read, please try again or add the items with /shop"
+                        .to_string(),
+                ));
+            }
+        };
+
+        let text = filter_text(ctx, app_state, Some(guild_id), "photo", &text).await?;
+        let (items, skipped) = parse_photo_text(&text);
+        if items.is_empty() {
+            return Err(CommandResponse::BasicFailure(
+                "no items could be found in the photo, try a clearer photo with one item on each line"
+                    .to_string(),
+            ));
+        }
+
+        let key: u64 = interaction.id.into();
+        match ctx.data.read().await.get::<PendingImportCache>() {
+            Some(cache) => cache.insert(
+                key,
+                PendingImport {
+                    user_id: interaction.user.id.into(),
+                    items: items.clone(),
+                },
+            ),
+            None => {
+                return Err(CommandResponse::InternalFailure(
+                    "pending import cache missing from context".to_string(),
+                ))
+            }
+        }
+
+        if let Err(e) = interaction
+            .edit_response(ctx, preview(key, &items, skipped))
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for ShopImportPhotoCommand<'a> {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction
+            .data
+            .custom_id
+            .starts_with(IMPORT_CONFIRM_PREFIX)
+            || interaction.data.custom_id.starts_with(IMPORT_CANCEL_PREFIX)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let custom_id = interaction.data.custom_id.as_str();
+        let (confirmed, key) = match decode_custom_id(custom_id, IMPORT_CONFIRM_PREFIX) {
+            Some(key) => (true, key),
+            None => (
+                false,
+                decode_custom_id(custom_id, IMPORT_CANCEL_PREFIX).unwrap_or_default(),
+            ),
+        };
+
+        let (key, guild_id) = match (key.parse::<u64>(), interaction.guild_id) {
+            (Ok(key), Some(g)) => (key, g.0.into()),
+            _ => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ))
+            }
+        };
+
+        let import = ctx
+            .data
+            .read()
+            .await
+            .get::<PendingImportCache>()
+            .and_then(|cache| cache.take(key));
+        let user_id: u64 = interaction.user.id.into();
+        let import = match import {
+            Some(import) if import.user_id == user_id => import,
+            _ => {
+                close_preview(
+                    interaction,
+                    ctx,
+                    "This import has expired, send the photo again with /shop-import-photo"
+                        .to_string(),
+                )
+                .await?;
+                return Ok(CommandResponse::NoResponse);
+            }
+        };
+
+        if !confirmed {
+            close_preview(interaction, ctx, "Nothing was added".to_string()).await?;
+            return Ok(CommandResponse::NoResponse);
+        }
+
+        // the buttons are removed first, so that the items can't be added twice
+        close_preview(
+            interaction,
+            ctx,
+            format!("Adding {} item(s)...", import.items.len()),
+        )
+        .await?;
+
+        let result = add_items_in_bulk(
+            ctx,
+            app_state,
+            &import.items,
+            user_id,
+            interaction.channel_id.into(),
+            guild_id,
+            interaction.message.id.into(),
+        )
+        .await;
+
+        let content = match result.failed {
+            0 => format!(
+                "Added {} item(s) from the photo to the shopping list",
+                result.succeeded
+            ),
+            failed => format!(
+                "Added {} item(s) from the photo to the shopping list, {} could not be added",
+                result.succeeded, failed
+            ),
+        };
+
+        if let Err(e) = interaction
+            .edit_response(ctx, EditInteractionResponse::new().content(content))
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::{provider, OcrConfig};
+
+    fn item(quantity: i64, item: &str) -> (i64, String) {
+        (quantity, item.to_string())
+    }
+
+    #[test]
+    fn bullets_and_checkboxes_are_not_part_of_the_item() {
+        assert_eq!(parse_photo_line("- 2x milk"), Some(item(2, "milk")));
+        assert_eq!(parse_photo_line("* eggs"), Some(item(1, "eggs")));
+        assert_eq!(parse_photo_line("• bread x3"), Some(item(3, "bread")));
+        assert_eq!(parse_photo_line("☐ apples"), Some(item(1, "apples")));
+        assert_eq!(parse_photo_line("[ ] butter"), Some(item(1, "butter")));
+        assert_eq!(parse_photo_line("- [x] cheese"), Some(item(1, "cheese")));
+    }
+
+    #[test]
+    fn numbering_is_not_a_quantity() {
+        assert_eq!(parse_photo_line("1. flour"), Some(item(1, "flour")));
+        assert_eq!(parse_photo_line("2) 3 lemons"), Some(item(3, "lemons")));
+        assert_eq!(parse_photo_line("3. a dozen eggs"), Some(item(12, "eggs")));
+        assert_eq!(
+            parse_photo_line("1.5 litres of milk"),
+            Some(item(1, "1.5 litres of milk"))
+        );
+    }
+
+    #[test]
+    fn unlikely_quantities_are_kept_in_the_name() {
+        assert_eq!(
+            parse_photo_line("100 tea bags"),
+            Some(item(1, "100 tea bags"))
+        );
+        let long = "a".repeat(MAX_IMPORT_ITEM + 50);
+        assert_eq!(
+            parse_photo_line(&long),
+            Some(item(1, &long[..MAX_IMPORT_ITEM]))
+        );
+    }
+
+    #[test]
+    fn lines_without_letters_are_skipped() {
+        assert_eq!(parse_photo_line(""), None);
+        assert_eq!(parse_photo_line("12/03"), None);
+        assert_eq!(parse_photo_line("----"), None);
+        assert_eq!(parse_photo_line("[ ] 2."), None);
+    }
+
+    #[test]
+    fn at_most_25_items_are_imported() {
+        let text = (1..=30)
+            .map(|i| format!("{}. thing {}\n-----", i, i))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let (items, skipped) = parse_photo_text(&text);
+        assert_eq!(items.len(), MAX_IMPORT_ITEMS);
+        assert_eq!(skipped, 5);
+        assert_eq!(items[0], item(1, "thing 1"));
+        assert_eq!(items[24], item(1, "thing 25"));
+
+        let (items, skipped) = parse_photo_text("milk\n\n- eggs\n");
+        assert_eq!(items, [item(1, "milk"), item(1, "eggs")]);
+        assert_eq!(skipped, 0);
+    }
+
+    #[tokio::test]
+    async fn stub_photos_are_read_as_the_configured_text() {
+        let ocr = provider(Some(&OcrConfig::Stub {
+            text: "Shopping\n- 2x milk\n☐ a pair of gloves\n14/05".to_string(),
+        }))
+        .unwrap();
+
+        let text = ocr.read_text(&[]).await.unwrap();
+        assert_eq!(
+            parse_photo_text(&text),
+            (
+                vec![item(1, "Shopping"), item(2, "milk"), item(2, "gloves")],
+                0
+            )
+        );
+    }
+}
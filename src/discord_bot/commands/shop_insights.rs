@@ -0,0 +1,148 @@
+use chrono::Weekday;
+use serenity::{
+    all::CommandInteraction,
+    async_trait,
+    builder::{
+        CreateCommand, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{common::embed::EmbedColor, database::shopping::SerenityShoppingDatabase},
+    state::{AppState, CONFIG},
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// Show patterns in how the shopping list is used, such as which items are bought together
+pub struct ShopInsightsCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopInsightsCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+fn day_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// an hour of the day as a range on a 12 hour clock, e.g. `6pm-7pm`
+fn hour_range(hour: u32) -> String {
+    let twelve_hour = |hour: u32| match hour % 24 {
+        0 => "12am".to_string(),
+        12 => "12pm".to_string(),
+        h if h < 12 => format!("{}am", h),
+        h => format!("{}pm", h - 12),
+    };
+    format!("{}-{}", twelve_hour(hour), twelve_hour(hour + 1))
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopInsightsCommand {
+    fn name() -> &'static str {
+        "shop-insights"
+    }
+
+    fn description() -> &'static str {
+        "show which items are often added together, and when the list is busiest"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let config = CONFIG.guild(Some(guild_id));
+        if !config.analytics {
+            return Err(CommandResponse::BasicFailure(
+                "this server has turned off looking for patterns in its shopping list".to_string(),
+            ));
+        }
+
+        let insights = match app_state.get_insights(guild_id, config.timezone).await {
+            Ok(Some(insights)) => insights,
+            Ok(None) => {
+                return Ok(CommandResponse::BasicSuccess(
+                    "There isn't enough shopping history yet, check back once a few more trips have been added"
+                        .to_string(),
+                ))
+            }
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let pairs = match insights.pairs.is_empty() {
+            true => "No items are regularly added together yet".to_string(),
+            false => insights
+                .pairs
+                .iter()
+                .map(|(first, second, trips)| format!("{} + {}, {} trips", first, second, trips))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        };
+        let (day, day_items) = insights.busiest_day;
+        let (hour, hour_items) = insights.busiest_hour;
+
+        let embed = CreateEmbed::new()
+            .title("Shopping insights")
+            .field("Often added together", pairs, false)
+            .field(
+                "Busiest day",
+                format!("{}, {} item(s)", day_name(day), day_items),
+                true,
+            )
+            .field(
+                "Busiest time",
+                format!("{}, {} item(s)", hour_range(hour), hour_items),
+                true,
+            )
+            .footer(CreateEmbedFooter::new(format!(
+                "from the last {} shared item(s) added, in {}",
+                insights.items, config.timezone
+            )))
+            .color(EmbedColor::Red as u32);
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
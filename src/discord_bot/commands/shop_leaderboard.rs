@@ -0,0 +1,217 @@
+use chrono::{Duration, Local};
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+        CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{common::embed::EmbedColor, database::shopping::SerenityShoppingDatabase},
+    state::{AppState, CONFIG},
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// the most people shown on the leaderboard
+const MAX_SHOPPERS: usize = 10;
+
+/// how far back the leaderboard counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    fn days(&self) -> i64 {
+        match self {
+            Period::Week => 7,
+            Period::Month => 30,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Period::Week => "week",
+            Period::Month => "month",
+        }
+    }
+}
+
+/// Show who has bought the most items recently
+pub struct ShopLeaderboardCommand {
+    period: Period,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopLeaderboardCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut period = Period::Week;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("period", ResolvedValue::String("week")) => period = Period::Week,
+                ("period", ResolvedValue::String("month")) => period = Period::Month,
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self { period })
+    }
+}
+
+/// the rank of each shopper, where people who bought as many items share a rank and the next
+/// rank is skipped, e.g. 1, 1, 3. `counts` must be sorted with the most items first
+fn rank_shoppers(counts: &[(u64, usize)]) -> Vec<(usize, u64, usize)> {
+    let mut ranked: Vec<(usize, u64, usize)> = Vec::with_capacity(counts.len());
+    for (i, &(user, count)) in counts.iter().enumerate() {
+        let rank = match ranked.last() {
+            Some(&(rank, _, previous)) if previous == count => rank,
+            _ => i + 1,
+        };
+        ranked.push((rank, user, count));
+    }
+    ranked
+}
+
+/// a medal for the top three ranks, otherwise the rank itself
+fn rank_marker(rank: usize) -> String {
+    match rank {
+        1 => "🥇".to_string(),
+        2 => "🥈".to_string(),
+        3 => "🥉".to_string(),
+        rank => format!("`#{}`", rank),
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopLeaderboardCommand {
+    fn name() -> &'static str {
+        "shop-leaderboard"
+    }
+
+    fn description() -> &'static str {
+        "show who has bought the most items this week or month"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "period",
+                "How far back to count, this week if not given",
+            )
+            .required(false)
+            .add_string_choice("this week", "week")
+            .add_string_choice("this month", "month"),
+        )
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let config = CONFIG.guild(Some(guild_id));
+        if !config.leaderboard {
+            return Err(CommandResponse::BasicFailure(
+                "this server hasn't turned on the shopping leaderboard".to_string(),
+            ));
+        }
+
+        let since = Local::now().naive_local() - Duration::days(self.period.days());
+        let counts = match app_state
+            .get_shopper_leaderboard(guild_id, since, config.leaderboard_personal)
+            .await
+        {
+            Ok(counts) => counts,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+        if counts.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "Nobody has bought anything this {}, the leaderboard is up for grabs",
+                self.period.name()
+            )));
+        }
+
+        let lines: Vec<String> = rank_shoppers(&counts)
+            .into_iter()
+            .take(MAX_SHOPPERS)
+            .map(|(rank, user, count)| {
+                format!("{} <@{}>, {} item(s)", rank_marker(rank), user, count)
+            })
+            .collect();
+
+        let footer = match config.leaderboard_personal {
+            true => "counting every item bought, personal or not",
+            false => "counting shared items only, personal items aren't included",
+        };
+
+        let embed = CreateEmbed::new()
+            .title(format!("Top shoppers this {}", self.period.name()))
+            .description(lines.join("\n"))
+            .footer(CreateEmbedFooter::new(footer))
+            .color(EmbedColor::Green as u32);
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().embed(embed),
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tied_shoppers_share_a_rank_and_the_next_is_skipped() {
+        let ranked = rank_shoppers(&[(1, 5), (2, 5), (3, 2), (4, 1)]);
+        assert_eq!(ranked, [(1, 1, 5), (1, 2, 5), (3, 3, 2), (4, 4, 1)]);
+    }
+
+    #[test]
+    fn nobody_is_ranked_on_an_empty_leaderboard() {
+        assert!(rank_shoppers(&[]).is_empty());
+    }
+
+    #[test]
+    fn the_top_three_ranks_get_medals() {
+        assert_eq!(rank_marker(1), "🥇");
+        assert_eq!(rank_marker(2), "🥈");
+        assert_eq!(rank_marker(3), "🥉");
+        assert_eq!(rank_marker(4), "`#4`");
+    }
+}
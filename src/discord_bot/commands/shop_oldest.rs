@@ -0,0 +1,291 @@
+use chrono::{Local, TimeZone};
+use serenity::{
+    all::{
+        ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, ResolvedValue,
+    },
+    async_trait,
+    builder::{
+        CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+        CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            bulk::throttled_edit,
+            card::ShoppingCard,
+            custom_id::{decode_custom_id, encode_custom_id},
+            embed::EmbedColor,
+            shopping::describe_item,
+            status::{set_status_reactions, ItemStatus},
+        },
+        database::shopping::{SerenityShoppingDatabase, ShoppingListItemModel},
+    },
+    state::AppState,
+};
+
+use super::{
+    command::{Command, InteractionCommand},
+    shop::bought_message,
+    util::CommandResponse,
+};
+
+/// the custom id prefix of the bought buttons of /shop-oldest, followed by how many items were
+/// asked for and the id of the item, e.g. `oldest-bought:5:42`
+const OLDEST_BOUGHT_PREFIX: &str = "oldest-bought:";
+
+/// how many items are shown when no count is given
+const DEFAULT_COUNT: u64 = 5;
+
+/// the most items which can be shown, as discord allows at most 25 buttons on a message
+const MAX_COUNT: i64 = 25;
+
+/// discord allows at most 5 buttons in an action row
+const BUTTONS_PER_ROW: usize = 5;
+
+/// Show the items which have been waiting longest to be bought
+pub struct ShopOldestCommand {
+    count: u64,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopOldestCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut count: Option<i64> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("count", ResolvedValue::Integer(val)) => count = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            count: count
+                .map(|c| c.clamp(1, MAX_COUNT) as u64)
+                .unwrap_or(DEFAULT_COUNT),
+        })
+    }
+}
+
+/// the embed listing the oldest items and their ages, with a bought button for each
+fn oldest_message(count: u64, items: &[ShoppingListItemModel]) -> CreateInteractionResponseMessage {
+    let description = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let mut line = format!("{}. {}", i + 1, describe_item(item));
+            if let Some(added) = Local.from_local_datetime(&item.created_at).earliest() {
+                line.push_str(&format!(", added <t:{}:R>", added.timestamp()));
+            }
+            line
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let mut embed = CreateEmbed::new()
+        .title("Oldest items on the shopping list")
+        .description(description)
+        .color(EmbedColor::Red as u32);
+    if (items.len() as u64) < count {
+        embed = embed.footer(CreateEmbedFooter::new(format!(
+            "only {} item(s) are waiting to be bought",
+            items.len()
+        )));
+    }
+
+    let buttons: Vec<CreateButton> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            CreateButton::new(encode_custom_id(
+                OLDEST_BOUGHT_PREFIX,
+                &format!("{}:{}", count, item.id),
+            ))
+            .label(format!("Bought #{}", i + 1))
+            .style(ButtonStyle::Success)
+        })
+        .collect();
+    let components = buttons
+        .chunks(BUTTONS_PER_ROW)
+        .map(|row| CreateActionRow::Buttons(row.to_vec()))
+        .collect();
+
+    CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .components(components)
+        .ephemeral(true)
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopOldestCommand {
+    fn name() -> &'static str {
+        "shop-oldest"
+    }
+
+    fn description() -> &'static str {
+        "show the items which have been waiting longest to be bought"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "count",
+                "How many items to show, 5 by default",
+            )
+            .required(false)
+            .min_int_value(1)
+            .max_int_value(MAX_COUNT as u64),
+        )
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let items = match app_state.get_oldest_pending(guild_id, self.count).await {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(
+                "Nothing is waiting to be bought".to_string(),
+            ));
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(oldest_message(self.count, &items)),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for ShopOldestCommand {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction.data.custom_id.starts_with(OLDEST_BOUGHT_PREFIX)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let (count, item_id) =
+            match decode_custom_id(&interaction.data.custom_id, OLDEST_BOUGHT_PREFIX)
+                .as_deref()
+                .and_then(|rest| rest.split_once(':'))
+                .and_then(|(count, id)| Some((count.parse::<u64>().ok()?, id.parse::<u64>().ok()?)))
+            {
+                Some(parsed) => parsed,
+                None => {
+                    return Err(CommandResponse::InternalFailure(
+                        "Invalid interaction".to_string(),
+                    ))
+                }
+            };
+
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ))
+            }
+        };
+
+        let item = match app_state.get_shopping_list_item_by_id(item_id).await {
+            Ok(item) => item,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        // the list may have changed since it was shown, so an item bought elsewhere is left alone
+        if let Some(item) = item.filter(|i| !i.bought && i.guild_id == Some(guild_id as i64)) {
+            if let Err(e) = app_state
+                .set_shopping_list_items_bought(&[item.id], true, Some(interaction.user.id.into()))
+                .await
+            {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+
+            throttled_edit(ctx, std::slice::from_ref(&item), |item, embeds| {
+                bought_message(&ShoppingCard::from_item(item).description(), embeds)
+            })
+            .await;
+            set_status_reactions(ctx, std::slice::from_ref(&item), ItemStatus::Bought).await;
+        }
+
+        let items = match app_state.get_oldest_pending(guild_id, count).await {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        let response = match items.is_empty() {
+            true => CreateInteractionResponseMessage::new()
+                .content("Nothing is waiting to be bought")
+                .embeds(vec![])
+                .components(vec![]),
+            false => oldest_message(count, &items),
+        };
+
+        if let Err(e) = interaction
+            .create_response(&ctx, CreateInteractionResponse::UpdateMessage(response))
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
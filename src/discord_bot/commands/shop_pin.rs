@@ -0,0 +1,69 @@
+use serenity::{all::CommandInteraction, async_trait, builder::CreateCommand, prelude::Context};
+
+use crate::{
+    discord_bot::common::pin::{pin_in_channel, PinOutcome},
+    state::AppState,
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// Keep the most urgent item on the shopping list pinned in this channel
+pub struct ShopPinTopCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopPinTopCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopPinTopCommand {
+    fn name() -> &'static str {
+        "shop-pin-top"
+    }
+
+    fn description() -> &'static str {
+        "keep the oldest item on the shopping list pinned in this channel"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        match pin_in_channel(guild_id, interaction.channel_id.into(), ctx, app_state).await {
+            Ok(PinOutcome::TooManyPins) => Err(CommandResponse::BasicFailure(
+                "This channel already has as many pins as discord allows, unpin something and try again"
+                    .to_string(),
+            )),
+            Ok(PinOutcome::Cleared) => Ok(CommandResponse::BasicSuccess(
+                "The shopping list is empty, the oldest item will be pinned here once something is added"
+                    .to_string(),
+            )),
+            Ok(_) => Ok(CommandResponse::BasicSuccess(
+                "The oldest item on the shopping list is pinned, and will be kept up to date"
+                    .to_string(),
+            )),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error pinning top item: {}",
+                e
+            ))),
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use log::error;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+        CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            distance::{format_duration, request_maps_data},
+            shopping::describe_item,
+        },
+        database::shopping::{SerenityShoppingDatabase, ShoppingListItemModel},
+    },
+    state::{AppState, Destination, CONFIG},
+};
+
+use super::{command::Command, distance::normalise_address, util::CommandResponse};
+
+/// the time allowed for finding a park, getting a trolley and checking out at each store
+const MINUTES_PER_STORE: i64 = 10;
+
+/// the time allowed for finding each item in a store
+const SECONDS_PER_ITEM: i64 = 60;
+
+/// the most stores given a field of their own, leaving room in the embed for the summary fields
+const MAX_STORE_FIELDS: usize = 20;
+
+/// the longest value discord allows in an embed field
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// the items to buy from one store, and the destination it was matched to if there is one
+struct StoreStop<'i> {
+    store: &'i str,
+    destination: Option<&'static Destination>,
+    items: Vec<&'i ShoppingListItemModel>,
+}
+
+/// group items by the store they're from, matching each store to a destination of the same name.
+/// Items without a store are returned separately, as they can be bought anywhere
+fn group_by_store(
+    items: &[ShoppingListItemModel],
+) -> (Vec<StoreStop<'_>>, Vec<&ShoppingListItemModel>) {
+    let mut stops: Vec<StoreStop> = Vec::new();
+    let mut anywhere = Vec::new();
+
+    for item in items.iter() {
+        let store = match item.store.as_deref().map(str::trim) {
+            Some(store) if !store.is_empty() => store,
+            _ => {
+                anywhere.push(item);
+                continue;
+            }
+        };
+
+        match stops
+            .iter_mut()
+            .find(|stop| stop.store.eq_ignore_ascii_case(store))
+        {
+            Some(stop) => stop.items.push(item),
+            None => stops.push(StoreStop {
+                store,
+                destination: CONFIG
+                    .destinations
+                    .iter()
+                    .find(|d| d.label.eq_ignore_ascii_case(store)),
+                items: vec![item],
+            }),
+        }
+    }
+
+    (stops, anywhere)
+}
+
+/// one line per item, cut short with a count of the rest if they won't fit in a field
+fn item_lines(items: &[&ShoppingListItemModel]) -> String {
+    let mut lines = String::new();
+
+    for (i, item) in items.iter().enumerate() {
+        let line = format!("- {}\n", describe_item(item));
+
+        // leave enough room for the truncation marker
+        if lines.len() + line.len() > MAX_FIELD_LENGTH - 32 {
+            lines.push_str(&format!("*...and {} more*", items.len() - i));
+            break;
+        }
+
+        lines.push_str(&line);
+    }
+
+    lines
+}
+
+/// Plan a shopping trip from an address, with the travel time to each store on the list
+pub struct ShopPlanCommand<'a> {
+    address: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopPlanCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut address: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("address", ResolvedValue::String(val)) => address = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            address: address.ok_or_else(|| String::from("address is required"))?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopPlanCommand<'a> {
+    fn name() -> &'static str {
+        "shop-plan"
+    }
+
+    fn description() -> &'static str {
+        "plan a shopping trip from an address, with the travel time to each store on the list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "address",
+                "The address to start the trip from",
+            )
+            .required(true)
+            .max_length(200)
+            .to_owned(),
+        )
+    }
+
+    // travel times are only looked up, unlike /distance nothing is recorded
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let address = normalise_address(self.address).map_err(CommandResponse::BasicFailure)?;
+
+        // the plan is only shown to the user who asked, so their own personal items are included
+        let user_id: u64 = interaction.user.id.into();
+        let items: Vec<ShoppingListItemModel> = match app_state.get_pending_items(guild_id).await {
+            Ok(items) => items
+                .into_iter()
+                .filter(|item| !item.personal || item.user_id == user_id as i64)
+                .collect(),
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(
+                "The shopping list is empty, there's no trip to plan".to_string(),
+            ));
+        }
+
+        // the maps api can be slow, so defer the response while waiting for it
+        if let Err(e) = interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::Defer(
+                    CreateInteractionResponseMessage::new().ephemeral(true),
+                ),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        let (stops, anywhere) = group_by_store(&items);
+        let destinations: Vec<Destination> = stops
+            .iter()
+            .filter_map(|stop| stop.destination.cloned())
+            .collect();
+
+        // one request covers every known store, a failure leaves each without a travel time
+        let mut origin = address.clone();
+        let mut durations: Vec<Option<(String, i64)>> = vec![None; destinations.len()];
+        if !destinations.is_empty() {
+            match request_maps_data(address.clone(), &destinations, app_state).await {
+                Ok(data) => {
+                    if let Some(resolved) = data.origin_addresses.first() {
+                        origin = resolved.clone();
+                    }
+                    for (i, element) in data
+                        .rows
+                        .iter()
+                        .flat_map(|row| row.elements.iter())
+                        .enumerate()
+                        .filter(|(i, element)| *i < durations.len() && element.is_ok())
+                    {
+                        durations[i] = Some((
+                            format!("{}, {}", element.distance.text, element.travel_time().text),
+                            element.travel_time().value as i64,
+                        ));
+                    }
+                }
+                Err(e) => error!("failed to load travel times for {}: {}", address, e),
+            }
+        }
+
+        let mut embed = CreateEmbed::new()
+            .title(format!("Shopping trip from {}", origin))
+            .color(0x4285F4);
+
+        let mut travel = durations.iter();
+        let mut longest_drive: Option<i64> = None;
+        let mut any_unknown = false;
+        for (i, stop) in stops.iter().enumerate() {
+            let name = match stop.destination {
+                Some(_) => match travel.next().cloned().flatten() {
+                    Some((text, seconds)) => {
+                        longest_drive = longest_drive.max(Some(seconds));
+                        format!("{} ({})", stop.store, text)
+                    }
+                    None => format!("{} (no route found)", stop.store),
+                },
+                None => {
+                    any_unknown = true;
+                    format!("{} (not a known destination)", stop.store)
+                }
+            };
+            if i < MAX_STORE_FIELDS {
+                embed = embed.field(name, item_lines(&stop.items), false);
+            }
+        }
+        if !anywhere.is_empty() {
+            embed = embed.field("From any store", item_lines(&anywhere), false);
+        }
+
+        // without store to store times, the trip is taken to be there and back to the furthest
+        // store with the others on the way
+        let store_count = stops.len().max(1);
+        let shopping =
+            store_count as i64 * MINUTES_PER_STORE * 60 + items.len() as i64 * SECONDS_PER_ITEM;
+        let estimate = match longest_drive {
+            Some(drive) => format!(
+                "about {}, {} driving and {} in {} store(s)",
+                format_duration(drive * 2 + shopping),
+                format_duration(drive * 2),
+                format_duration(shopping),
+                store_count
+            ),
+            None if destinations.is_empty() => {
+                "unknown, none of the stores on the list are known destinations".to_string()
+            }
+            None => format!(
+                "unknown, no route could be found from `{}` to any of the stores",
+                address
+            ),
+        };
+        embed = embed.field("Estimated trip", estimate, false);
+
+        let mut footer = "assumes the other stores are on the way to the furthest".to_string();
+        if any_unknown {
+            footer.push_str(", add stores to `destinations` in config.toml to time them");
+        }
+        embed = embed.footer(CreateEmbedFooter::new(footer));
+
+        if let Err(e) = interaction
+            .edit_response(ctx, EditInteractionResponse::new().embed(embed))
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
@@ -0,0 +1,147 @@
+use serenity::{
+    all::CommandInteraction,
+    async_trait,
+    builder::{
+        CreateCommand, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::embed::EmbedColor,
+        database::shopping::{ListProgress, SerenityShoppingDatabase},
+    },
+    state::AppState,
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// the number of characters in a progress bar
+const BAR_WIDTH: usize = 20;
+
+/// the most stores given a field of their own, leaving room in the embed for the overall bar
+const MAX_STORE_FIELDS: usize = 20;
+
+/// Show how much of the current shopping list has been bought
+pub struct ShopProgressCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopProgressCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+/// a bar filled in proportion to how many of the items have been bought, e.g. `█████░░░░░`
+fn progress_bar(bought: usize, total: usize) -> String {
+    let filled = match total {
+        0 => 0,
+        total => (bought * BAR_WIDTH + total / 2) / total,
+    };
+    format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+}
+
+/// e.g. `12 of 20 items bought (60%)`
+fn progress_summary(bought: usize, total: usize) -> String {
+    format!(
+        "{} of {} item(s) bought ({}%)",
+        bought,
+        total,
+        (bought * 100) / total.max(1)
+    )
+}
+
+/// the embed showing the progress of the list overall, then of each store on it
+fn progress_embed(progress: &ListProgress) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title("Shopping progress")
+        .description(format!(
+            "{}\n`{}`",
+            progress_summary(progress.bought, progress.total),
+            progress_bar(progress.bought, progress.total)
+        ))
+        .color(match progress.bought == progress.total {
+            true => EmbedColor::Green as u32,
+            false => EmbedColor::Orange as u32,
+        });
+
+    // a breakdown is only worth showing when the items come from more than one place
+    if progress.stores.len() > 1 {
+        for store in progress.stores.iter().take(MAX_STORE_FIELDS) {
+            embed = embed.field(
+                store.store.as_deref().unwrap_or("From any store"),
+                format!(
+                    "{}\n`{}`",
+                    progress_summary(store.bought, store.total),
+                    progress_bar(store.bought, store.total)
+                ),
+                false,
+            );
+        }
+    }
+
+    embed.footer(CreateEmbedFooter::new(
+        "counting everything added since the oldest item still on the list",
+    ))
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopProgressCommand {
+    fn name() -> &'static str {
+        "shop-progress"
+    }
+
+    fn description() -> &'static str {
+        "show how much of the current shopping list has been bought"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let progress = match app_state.get_progress(guild_id).await {
+            Ok(progress) => progress,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+        if progress.total == 0 {
+            return Ok(CommandResponse::BasicSuccess(
+                "The shopping list is empty, there's nothing left to buy".to_string(),
+            ));
+        }
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .embed(progress_embed(&progress))
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
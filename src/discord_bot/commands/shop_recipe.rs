@@ -0,0 +1,147 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{CreateCommand, CreateCommandOption},
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            bulk::throttled_edit,
+            card::ShoppingCard,
+            status::{set_status_reactions, ItemStatus},
+        },
+        database::shopping::SerenityShoppingDatabase,
+    },
+    state::AppState,
+};
+
+use super::{
+    command::Command,
+    shop::removed_message,
+    util::{defer_ephemeral, finish_deferred, CommandResponse},
+};
+
+/// Remove every pending item which was added for a given recipe
+pub struct ShopClearRecipeCommand<'a> {
+    recipe: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopClearRecipeCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut recipe: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("recipe", ResolvedValue::String(val)) => recipe = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        match recipe {
+            Some(recipe) => Ok(Self { recipe }),
+            None => Err(String::from("recipe is required")),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopClearRecipeCommand<'a> {
+    fn name() -> &'static str {
+        "shop-clear-recipe"
+    }
+
+    fn description() -> &'static str {
+        "remove all remaining items added for a recipe"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "recipe",
+                "The recipe to clear from the shopping list",
+            )
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let items = match app_state
+            .get_items_by_recipe_group(guild_id, self.recipe)
+            .await
+        {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "There are no items left on the shopping list for `{}`",
+                self.recipe
+            )));
+        }
+
+        let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+        if let Err(e) = app_state
+            .set_shopping_list_items_bought(&ids, true, None)
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        // editing every message can take longer than discord waits for a response
+        defer_ephemeral(interaction, ctx).await?;
+        let result = throttled_edit(ctx, &items, |item, embeds| {
+            removed_message(&ShoppingCard::from_item(item).description(), embeds)
+        })
+        .await;
+        set_status_reactions(ctx, &items, ItemStatus::Removed).await;
+
+        finish_deferred(
+            interaction,
+            ctx,
+            format!(
+                "Removed {} item(s) for `{}` from the shopping list{}",
+                items.len(),
+                self.recipe,
+                result.failure_note()
+            ),
+        )
+        .await
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::Utc;
+use serenity::{all::CommandInteraction, async_trait, builder::CreateCommand, prelude::Context};
+
+use crate::state::{AppState, CONFIG};
+
+use super::{command::Command, util::CommandResponse};
+
+/// Create a read-only link to the shopping list, for people who aren't on discord
+pub struct ShopShareCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopShareCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopShareCommand {
+    fn name() -> &'static str {
+        "shop-share"
+    }
+
+    fn description() -> &'static str {
+        "create a read-only link to the shopping list, for people who aren't on discord"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let base_url = match CONFIG.share.base_url.as_deref() {
+            Some(base_url) => base_url.trim_end_matches('/'),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "sharing isn't set up for this bot, ask its host to set `share.base_url`"
+                        .to_string(),
+                ))
+            }
+        };
+
+        let ttl = CONFIG.share.ttl();
+        let token = app_state.share_links.create(guild_id, ttl);
+        let expires = Utc::now().timestamp() + ttl.as_secs() as i64;
+
+        Ok(CommandResponse::BasicSuccess(format!(
+            "Anyone with this link can see the shopping list until <t:{}:f>, personal items are left off:\n{}/share/{}",
+            expires, base_url, token
+        )))
+    }
+}
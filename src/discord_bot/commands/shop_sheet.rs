@@ -0,0 +1,157 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{CreateCommand, CreateCommandOption},
+    prelude::Context,
+};
+
+use crate::{discord_bot::database::sheets::SerenitySheetDatabase, state::AppState};
+
+use super::{command::Command, util::CommandResponse};
+
+/// the part of a sheet's url before its id
+const SHEET_URL_PREFIX: &str = "https://docs.google.com/spreadsheets/d/";
+
+/// the id of a sheet, given either on its own or as the sheet's url
+fn parse_sheet_id(sheet: &str) -> Option<&str> {
+    let sheet = sheet.trim();
+    let id = match sheet.strip_prefix(SHEET_URL_PREFIX) {
+        Some(rest) => rest.split(['/', '?', '#']).next().unwrap_or_default(),
+        None => sheet,
+    };
+
+    // ids are url safe base64, anything else would change the path of the request
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then_some(id)
+}
+
+/// Copy items to a google sheet as they are bought, so the flat keeps its own record of them
+pub struct ShopSheetConnectCommand<'a> {
+    sheet: &'a str,
+    refresh_token: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopSheetConnectCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut sheet: Option<&str> = None;
+        let mut refresh_token: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("sheet", ResolvedValue::String(val)) => sheet = Some(val),
+                ("refresh-token", ResolvedValue::String(val)) => refresh_token = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            sheet: sheet.ok_or_else(|| String::from("sheet is required"))?,
+            refresh_token: refresh_token
+                .ok_or_else(|| String::from("refresh-token is required"))?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopSheetConnectCommand<'a> {
+    fn name() -> &'static str {
+        "shop-sheet-connect"
+    }
+
+    fn description() -> &'static str {
+        "copy items to a google sheet as they are bought, replacing any sheet already connected"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "sheet",
+                "The sheet's id, or its url",
+            )
+            .required(true),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "refresh-token",
+                "A refresh token allowed to edit the sheet, from authorising the bot's google client",
+            )
+            .required(true),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        if app_state.sheets.is_none() {
+            return Err(CommandResponse::BasicFailure(
+                "syncing to a sheet isn't set up for this bot, ask its host to add `[sheets]`"
+                    .to_string(),
+            ));
+        }
+
+        let sheet_id = match parse_sheet_id(self.sheet) {
+            Some(sheet_id) => sheet_id,
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "that doesn't look like a google sheet, paste its url or the id from it"
+                        .to_string(),
+                ))
+            }
+        };
+
+        let refresh_token = self.refresh_token.trim();
+        if refresh_token.is_empty() {
+            return Err(CommandResponse::BasicFailure(
+                "the refresh token can't be empty".to_string(),
+            ));
+        }
+
+        // the channel is where the bot says so if the sheet stops accepting its rows
+        if let Err(e) = app_state
+            .set_sheet_connection(
+                guild_id,
+                interaction.channel_id.into(),
+                sheet_id,
+                refresh_token,
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::BasicSuccess(
+            "Connected! Items bought from now on will be added to the sheet within a few minutes, \
+             along with who bought them"
+                .to_string(),
+        ))
+    }
+}
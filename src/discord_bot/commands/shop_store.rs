@@ -0,0 +1,820 @@
+use log::error;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ComponentInteraction, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateActionRow, CreateButton, CreateCommand, CreateCommandOption,
+        CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            bulk::{throttled_edit, BulkResult},
+            card::ShoppingCard,
+            custom_id::{decode_custom_id, encode_custom_id},
+            digest::refresh_digest,
+            status::{set_status_reactions, ItemStatus},
+        },
+        database::shopping::{SerenityShoppingDatabase, ShoppingListItemModel},
+    },
+    state::{AppState, ListMode, CONFIG},
+};
+
+use super::{
+    command::{Command, InteractionCommand},
+    shop::bought_message,
+    util::{defer_ephemeral, finish_deferred, CommandResponse},
+    word_filter::filter_text,
+};
+
+/// the longest store name accepted by /shop-bought-all and /shop-done
+const BOUGHT_ALL_MAX_STORE: u16 = 80;
+
+/// the custom id prefix of the confirmation button for /shop-done and /shop-bought-all, followed
+/// by the payload built by [confirmation_payload]
+const DONE_PREFIX: &str = "shop-done:";
+
+/// the custom id of the button which cancels a /shop-done or /shop-bought-all
+const DONE_CANCEL: &str = "shop-done-cancel";
+
+/// mark items as bought by `user` in one update, then show them as bought wherever the guild's list
+/// is shown. Only the messages which couldn't be edited are reported, the items are bought either way
+async fn mark_items_bought(
+    ctx: &Context,
+    app_state: &AppState,
+    guild_id: u64,
+    user: u64,
+    items: &[ShoppingListItemModel],
+) -> Result<BulkResult, CommandResponse> {
+    let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+    if let Err(e) = app_state
+        .set_shopping_list_items_bought(&ids, true, Some(user))
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with database: {}",
+            e
+        )));
+    }
+
+    let result = throttled_edit(ctx, items, |item, embeds| {
+        bought_message(&ShoppingCard::from_item(item).description(), embeds)
+    })
+    .await;
+    set_status_reactions(ctx, items, ItemStatus::Bought).await;
+
+    if CONFIG.guild(Some(guild_id)).list_mode == ListMode::Digest {
+        if let Err(e) = refresh_digest(guild_id, None, ctx, app_state, true).await {
+            error!("error refreshing digest for {}: {}", guild_id, e);
+        }
+    }
+
+    Ok(result)
+}
+
+/// close a confirmation of a sweeping change without making it
+async fn cancel_confirmation(
+    interaction: &ComponentInteraction,
+    ctx: &Context,
+) -> Result<CommandResponse, CommandResponse> {
+    if let Err(e) = interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content("Nothing was marked as bought")
+                    .components(vec![]),
+            ),
+        )
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+    Ok(CommandResponse::NoResponse)
+}
+
+/// the confirmation sent before marking items as bought, with a button to go ahead which has the
+/// custom id `confirm_id`
+fn bought_confirmation(prompt: String, confirm_id: String, cancel_id: &str) -> CommandResponse {
+    CommandResponse::ComplexSuccess(CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(prompt)
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(confirm_id)
+                    .style(serenity::all::ButtonStyle::Success)
+                    .label("Mark bought"),
+                CreateButton::new(cancel_id)
+                    .style(serenity::all::ButtonStyle::Secondary)
+                    .label("Cancel"),
+            ])])
+            .ephemeral(true),
+    ))
+}
+
+/// the payload of a confirmation button, the ids of the items counted in its prompt followed by
+/// the store they're from, or nothing for the whole list
+fn confirmation_payload(items: &[ShoppingListItemModel], store: Option<&str>) -> String {
+    let ids: Vec<String> = items.iter().map(|item| item.id.to_string()).collect();
+    format!("{}:{}", ids.join(","), store.unwrap_or_default())
+}
+
+/// the item ids and store of a payload built by [confirmation_payload]
+fn parse_confirmation(payload: &str) -> Option<(Vec<i32>, Option<&str>)> {
+    let (ids, store) = payload.split_once(':')?;
+    let ids = ids
+        .split(',')
+        .map(|id| id.parse().ok())
+        .collect::<Option<Vec<i32>>>()?;
+    Some((ids, Some(store).filter(|store| !store.is_empty())))
+}
+
+/// the items counted in a confirmation which are still pending. Items added since it was sent are
+/// left alone, so no more are marked than the user agreed to
+async fn confirmed_items(
+    app_state: &AppState,
+    guild_id: u64,
+    ids: &[i32],
+) -> Result<Vec<ShoppingListItemModel>, CommandResponse> {
+    let items = pending_items(app_state, guild_id, None).await?;
+    Ok(items
+        .into_iter()
+        .filter(|item| ids.contains(&item.id))
+        .collect())
+}
+
+/// Make every pending item from a store available from anywhere, e.g. when the store has closed
+pub struct ShopUnstoreCommand<'a> {
+    store: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopUnstoreCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut store: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("store", ResolvedValue::String(val)) => store = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        match store {
+            Some(store) => Ok(Self { store }),
+            None => Err(String::from("store is required")),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopUnstoreCommand<'a> {
+    fn name() -> &'static str {
+        "shop-unstore"
+    }
+
+    fn description() -> &'static str {
+        "make all pending items from a store available from anywhere"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "store",
+                "The store to remove from pending items",
+            )
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let items = match app_state
+            .clear_store_on_items(interaction.user.id.into(), guild_id, self.store)
+            .await
+        {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "There are no pending items from {}",
+                self.store
+            )));
+        }
+
+        // editing every message can take longer than discord waits for a response
+        defer_ephemeral(interaction, ctx).await?;
+        let result = throttled_edit(ctx, &items, |item, embeds| {
+            ShoppingCard::from_item(item).edit(embeds)
+        })
+        .await;
+
+        finish_deferred(
+            interaction,
+            ctx,
+            format!(
+                "{} item(s) are no longer tied to {}{}",
+                items.len(),
+                self.store,
+                result.failure_note()
+            ),
+        )
+        .await
+    }
+}
+
+/// Move every pending item from one store to another for today's trip, e.g. when going to a
+/// different branch. Unlike renaming a store, items already bought keep the store they came from
+pub struct ShopRestoreStoreCommand<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopRestoreStoreCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut from: Option<&str> = None;
+        let mut to: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("from", ResolvedValue::String(val)) => from = Some(val),
+                ("to", ResolvedValue::String(val)) => to = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            from: from.ok_or_else(|| String::from("from is required"))?,
+            to: to.ok_or_else(|| String::from("to is required"))?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopRestoreStoreCommand<'a> {
+    fn name() -> &'static str {
+        "shop-restore-store"
+    }
+
+    fn description() -> &'static str {
+        "move all pending items from one store to another, leaving bought items as they were"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "from",
+                "The store to move pending items from",
+            )
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "to", "The store to move them to")
+                .required(true)
+                .max_length(100)
+                .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let to = self.to.trim();
+        if to.is_empty() {
+            return Err(CommandResponse::BasicFailure(
+                "the store to move the items to needs to be given".to_string(),
+            ));
+        }
+        if to == self.from {
+            return Err(CommandResponse::BasicFailure(format!(
+                "the items are already from {}",
+                to
+            )));
+        }
+        let to = filter_text(ctx, app_state, Some(guild_id), "store", to).await?;
+
+        let items = match app_state
+            .move_pending_items_store(interaction.user.id.into(), guild_id, self.from, &to)
+            .await
+        {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(format!(
+                "There are no pending items from {}",
+                self.from
+            )));
+        }
+
+        // editing every message can take longer than discord waits for a response
+        defer_ephemeral(interaction, ctx).await?;
+        let result = throttled_edit(ctx, &items, |item, embeds| {
+            ShoppingCard::from_item(item).edit(embeds)
+        })
+        .await;
+
+        if CONFIG.guild(Some(guild_id)).list_mode == ListMode::Digest {
+            if let Err(e) = refresh_digest(guild_id, None, ctx, app_state, true).await {
+                error!("error refreshing digest for {}: {}", guild_id, e);
+            }
+        }
+
+        finish_deferred(
+            interaction,
+            ctx,
+            format!(
+                "{} item(s) moved from {} to {}{}",
+                items.len(),
+                self.from,
+                to,
+                result.failure_note()
+            ),
+        )
+        .await
+    }
+}
+
+/// Mark every pending item from a store as bought, e.g. after a trip to that store
+pub struct ShopBoughtAllCommand<'a> {
+    store: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopBoughtAllCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut store: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("store", ResolvedValue::String(val)) => store = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        match store {
+            Some(store) => Ok(Self { store }),
+            None => Err(String::from("store is required")),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopBoughtAllCommand<'a> {
+    fn name() -> &'static str {
+        "shop-bought-all"
+    }
+
+    fn description() -> &'static str {
+        "mark every pending item from a store as bought"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "store",
+                "The store which has been shopped at",
+            )
+            .required(true)
+            .max_length(BOUGHT_ALL_MAX_STORE)
+            .to_owned(),
+        )
+    }
+
+    /// the same as /shop-done with a store, whose confirmation it shares
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        ShopDoneCommand {
+            store: Some(self.store),
+        }
+        .handle_application_command(interaction, app_state, ctx)
+        .await
+    }
+}
+
+/// the pending items of a guild, either all of them or only those from `store`
+async fn pending_items(
+    app_state: &AppState,
+    guild_id: u64,
+    store: Option<&str>,
+) -> Result<Vec<ShoppingListItemModel>, CommandResponse> {
+    let items = match store {
+        Some(store) => app_state.get_pending_items_by_store(guild_id, store).await,
+        None => app_state.get_pending_items(guild_id).await,
+    };
+    items.map_err(|e| {
+        CommandResponse::InternalFailure(format!("error communicating with database: {}", e))
+    })
+}
+
+/// Mark every pending item as bought, or every pending item from one store, e.g. after buying
+/// everything on the list
+pub struct ShopDoneCommand<'a> {
+    store: Option<&'a str>,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopDoneCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut store: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("store", ResolvedValue::String(val)) => store = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self { store })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopDoneCommand<'a> {
+    fn name() -> &'static str {
+        "shop-done"
+    }
+
+    fn description() -> &'static str {
+        "mark everything on the shopping list as bought"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "store",
+                "Only mark the items from this store as bought",
+            )
+            .required(false)
+            .max_length(BOUGHT_ALL_MAX_STORE)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let items = pending_items(app_state, guild_id, self.store).await?;
+        let (prompt, empty) = match self.store {
+            Some(store) => (
+                format!("Mark {} item(s) from {} as bought?", items.len(), store),
+                format!("There are no pending items from {}", store),
+            ),
+            None => (
+                format!(
+                    "Mark all {} item(s) on the shopping list as bought?",
+                    items.len()
+                ),
+                "The shopping list is empty, there's nothing to mark as bought".to_string(),
+            ),
+        };
+
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(empty));
+        }
+
+        Ok(bought_confirmation(
+            prompt,
+            encode_custom_id(DONE_PREFIX, &confirmation_payload(&items, self.store)),
+            DONE_CANCEL,
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for ShopDoneCommand<'a> {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction.data.custom_id.starts_with(DONE_PREFIX)
+            || interaction.data.custom_id == DONE_CANCEL
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        if interaction.data.custom_id == DONE_CANCEL {
+            return cancel_confirmation(interaction, ctx).await;
+        }
+        let payload = decode_custom_id(&interaction.data.custom_id, DONE_PREFIX);
+        let (ids, store) = match payload.as_deref().and_then(parse_confirmation) {
+            Some(confirmation) => confirmation,
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "This confirmation has expired, run the command again".to_string(),
+                ))
+            }
+        };
+
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ))
+            }
+        };
+
+        // editing every message can take longer than discord waits for a response
+        if let Err(e) = interaction
+            .create_response(&ctx, CreateInteractionResponse::Acknowledge)
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        // items may have been bought or added since the confirmation was sent
+        let items = confirmed_items(app_state, guild_id, &ids).await?;
+        let content = match items.is_empty() {
+            true => "Nothing was left to mark as bought".to_string(),
+            false => {
+                let result =
+                    mark_items_bought(ctx, app_state, guild_id, interaction.user.id.into(), &items)
+                        .await?;
+                match store {
+                    Some(store) => format!(
+                        "Marked {} item(s) from {} as bought{}",
+                        items.len(),
+                        store,
+                        result.failure_note()
+                    ),
+                    None => format!(
+                        "Marked all {} item(s) on the shopping list as bought{}",
+                        items.len(),
+                        result.failure_note()
+                    ),
+                }
+            }
+        };
+
+        if let Err(e) = interaction
+            .edit_response(
+                &ctx,
+                EditInteractionResponse::new()
+                    .content(content)
+                    .components(vec![]),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::ConnectionTrait;
+
+    use super::*;
+    use crate::{discord_bot::database::shopping::NewShoppingListItem, state::test_database_urls};
+
+    const GUILD: u64 = 1;
+
+    async fn add(app_state: &AppState, message_id: u64, item: &str, store: Option<&str>) {
+        app_state
+            .add_shopping_list_item(
+                3,
+                message_id,
+                2,
+                Some(GUILD),
+                NewShoppingListItem {
+                    item,
+                    store,
+                    notes: None,
+                    quantity: 1,
+                    personal: false,
+                    recipe_group: None,
+                    bought_for: None,
+                    product_url: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    fn names(items: &[ShoppingListItemModel]) -> Vec<&str> {
+        let mut names: Vec<&str> = items.iter().map(|item| item.item.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn confirmations_carry_their_items_and_store() {
+        let payload = "4,7,9:Pak'n Save: Albany";
+        assert_eq!(
+            parse_confirmation(payload),
+            Some((vec![4, 7, 9], Some("Pak'n Save: Albany")))
+        );
+        assert_eq!(parse_confirmation("4:"), Some((vec![4], None)));
+        assert_eq!(parse_confirmation("market"), None);
+        assert_eq!(parse_confirmation(":market"), None);
+    }
+
+    #[tokio::test]
+    async fn only_the_confirmed_items_from_a_store_are_bought() {
+        for url in test_database_urls() {
+            let app_state = AppState::for_tests(&url).await;
+            let backend = app_state.database.get_database_backend();
+            add(&app_state, 10, "milk", Some("market")).await;
+            add(&app_state, 11, "eggs", Some("market")).await;
+            add(&app_state, 12, "bread", Some("bakery")).await;
+
+            // a store with nothing pending gets no confirmation at all
+            let none = pending_items(&app_state, GUILD, Some("butcher")).await;
+            assert!(none.unwrap().is_empty(), "{:?}", backend);
+
+            let market = pending_items(&app_state, GUILD, Some("market")).await;
+            let market = market.unwrap();
+            assert_eq!(names(&market), ["eggs", "milk"], "{:?}", backend);
+            let payload = confirmation_payload(&market, Some("market"));
+
+            // added while the confirmation was open, so not counted in it
+            add(&app_state, 13, "cheese", Some("market")).await;
+
+            let (ids, store) = parse_confirmation(&payload).unwrap();
+            assert_eq!(store, Some("market"), "{:?}", backend);
+            let confirmed = confirmed_items(&app_state, GUILD, &ids).await.unwrap();
+            assert_eq!(names(&confirmed), ["eggs", "milk"], "{:?}", backend);
+
+            let ids: Vec<i32> = confirmed.iter().map(|item| item.id).collect();
+            app_state
+                .set_shopping_list_items_bought(&ids, true, Some(3))
+                .await
+                .unwrap();
+            let pending = pending_items(&app_state, GUILD, None).await.unwrap();
+            assert_eq!(names(&pending), ["bread", "cheese"], "{:?}", backend);
+
+            // pressing the button again marks nothing more
+            let (ids, _) = parse_confirmation(&payload).unwrap();
+            let confirmed = confirmed_items(&app_state, GUILD, &ids).await.unwrap();
+            assert!(confirmed.is_empty(), "{:?}", backend);
+        }
+    }
+
+    #[tokio::test]
+    async fn the_whole_list_can_be_marked_bought() {
+        for url in test_database_urls() {
+            let app_state = AppState::for_tests(&url).await;
+            let backend = app_state.database.get_database_backend();
+            add(&app_state, 10, "milk", Some("market")).await;
+            add(&app_state, 11, "bread", Some("bakery")).await;
+            add(&app_state, 12, "batteries", None).await;
+            app_state
+                .add_shopping_list_item(
+                    3,
+                    13,
+                    2,
+                    Some(GUILD + 1),
+                    NewShoppingListItem {
+                        item: "eggs",
+                        store: None,
+                        notes: None,
+                        quantity: 1,
+                        personal: false,
+                        recipe_group: None,
+                        bought_for: None,
+                        product_url: None,
+                    },
+                )
+                .await
+                .unwrap();
+
+            let list = pending_items(&app_state, GUILD, None).await.unwrap();
+            assert_eq!(
+                names(&list),
+                ["batteries", "bread", "milk"],
+                "{:?}",
+                backend
+            );
+            let payload = confirmation_payload(&list, None);
+            add(&app_state, 14, "cheese", Some("market")).await;
+
+            let (ids, store) = parse_confirmation(&payload).unwrap();
+            assert_eq!(store, None, "{:?}", backend);
+            let confirmed = confirmed_items(&app_state, GUILD, &ids).await.unwrap();
+            let ids: Vec<i32> = confirmed.iter().map(|item| item.id).collect();
+            app_state
+                .set_shopping_list_items_bought(&ids, true, Some(3))
+                .await
+                .unwrap();
+
+            // only what was on the list when it was confirmed, and only in this guild
+            let pending = pending_items(&app_state, GUILD, None).await.unwrap();
+            assert_eq!(names(&pending), ["cheese"], "{:?}", backend);
+            let elsewhere = pending_items(&app_state, GUILD + 1, None).await.unwrap();
+            assert_eq!(names(&elsewhere), ["eggs"], "{:?}", backend);
+        }
+    }
+}
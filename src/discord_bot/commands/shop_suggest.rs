@@ -0,0 +1,216 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ComponentInteraction, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+        CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            custom_id::{decode_custom_id, encode_custom_id},
+            embed::EmbedColor,
+            shopping::describe_item,
+        },
+        database::shopping::SerenityShoppingDatabase,
+    },
+    state::AppState,
+};
+
+use super::{
+    command::{Command, InteractionCommand},
+    shop::readd_item,
+    util::CommandResponse,
+};
+
+/// the custom id prefix of the quick-add buttons, followed by the id of the item to copy
+const SUGGEST_ADD_PREFIX: &str = "suggest-add:";
+
+/// the most suggestions shown at once, all of which fit in a single row of buttons
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Suggest staples which are usually bought by now, but aren't on the list
+pub struct ShopSuggestCommand {
+    mine: bool,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopSuggestCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut mine: Option<bool> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("mine", ResolvedValue::Boolean(val)) => mine = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            mine: mine.unwrap_or(false),
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopSuggestCommand {
+    fn name() -> &'static str {
+        "shop-suggest"
+    }
+
+    fn description() -> &'static str {
+        "suggest items which are usually bought by now but aren't on the list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "mine",
+                "only consider items you have added, rather than the whole household",
+            )
+            .required(false),
+        )
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+        let user = self.mine.then(|| interaction.user.id.into());
+
+        let mut staples = match app_state.get_overdue_staples(user, guild_id).await {
+            Ok(staples) => staples,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if staples.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(
+                "Nothing looks forgotten, or there isn't enough shopping history to tell yet"
+                    .to_string(),
+            ));
+        }
+        staples.truncate(MAX_SUGGESTIONS);
+
+        let description = staples
+            .iter()
+            .map(|staple| {
+                format!(
+                    "- {}, usually every {:.0} day(s), last added {:.0} day(s) ago",
+                    describe_item(&staple.latest),
+                    staple.cadence_days,
+                    staple.days_since
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let buttons = staples
+            .iter()
+            .map(|staple| {
+                let label: String = format!("Add {}", staple.latest.item)
+                    .chars()
+                    .take(80)
+                    .collect();
+                CreateButton::new(encode_custom_id(
+                    SUGGEST_ADD_PREFIX,
+                    &staple.latest.id.to_string(),
+                ))
+                .style(serenity::all::ButtonStyle::Secondary)
+                .label(label)
+            })
+            .collect();
+
+        Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("You might be running low on")
+                            .description(description)
+                            .color(EmbedColor::Orange as u32),
+                    )
+                    .components(vec![CreateActionRow::Buttons(buttons)])
+                    .ephemeral(true),
+            ),
+        ))
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for ShopSuggestCommand {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        interaction.data.custom_id.starts_with(SUGGEST_ADD_PREFIX)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let id: u64 = match decode_custom_id(&interaction.data.custom_id, SUGGEST_ADD_PREFIX)
+            .and_then(|id| id.parse().ok())
+        {
+            Some(id) => id,
+            None => {
+                return Err(CommandResponse::InternalFailure(
+                    "Invalid interaction".to_string(),
+                ))
+            }
+        };
+
+        let item = match app_state.get_shopping_list_item_by_id(id).await {
+            Ok(Some(item)) => item,
+            // the suggestion outlived the item, which has since been deleted
+            Ok(None) => {
+                return Err(CommandResponse::BasicFailure(
+                    "that item no longer exists, add it with /shop instead".to_string(),
+                ));
+            }
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        readd_item(interaction, app_state, ctx, &item).await?;
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
@@ -0,0 +1,587 @@
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateInteractionResponse,
+        CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{quantity::parse_quantity_words, validation::ValidationReport},
+        database::shop_template::SerenityShopTemplateDatabase,
+    },
+    state::AppState,
+};
+
+use super::{
+    command::Command, shop::add_items_in_bulk, util::CommandResponse, word_filter::filter_text,
+};
+
+/// the most items a template can hold, so that applying one doesn't flood the channel
+const MAX_TEMPLATE_ITEMS: usize = 25;
+
+/// the longest template name accepted
+const MAX_TEMPLATE_NAME: u16 = 50;
+
+/// the longest item name accepted, matching /shop
+const MAX_TEMPLATE_ITEM: usize = 100;
+
+/// the guild a command was used in, templates are always tied to a guild
+fn guild_id(interaction: &CommandInteraction) -> Result<u64, CommandResponse> {
+    match interaction.guild_id {
+        Some(g) => Ok(g.0.into()),
+        None => Err(CommandResponse::BasicFailure(
+            "this command can only be used in a server".to_string(),
+        )),
+    }
+}
+
+/// normalise a template name before it is stored or looked up, so that `BBQ run` and `bbq run ` are
+/// the same template
+fn normalise_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// split a comma separated list of items into the quantity and name of each, e.g.
+/// `milk, 2 bread, a dozen eggs`. Every problem with the list is reported at once, numbering the
+/// items from 1, so a long list can be fixed in one go
+fn parse_template_items(items: &str) -> Result<Vec<(i64, String)>, ValidationReport> {
+    let mut parsed = Vec::new();
+    let mut report = ValidationReport::new();
+
+    let entries = items.split(',').map(str::trim).filter(|e| !e.is_empty());
+    for (i, entry) in entries.enumerate() {
+        let (quantity, item) = entry
+            .split_once(' ')
+            .and_then(|(number, rest)| Some((number.parse::<i64>().ok()?, rest.trim())))
+            .or_else(|| parse_quantity_words(entry))
+            .unwrap_or((1, entry));
+
+        if !(1..26).contains(&quantity) {
+            report.add(i + 1, entry, "the quantity must be from 1 to 25");
+        }
+        if item.is_empty() || item.chars().count() > MAX_TEMPLATE_ITEM {
+            report.add(
+                i + 1,
+                entry,
+                format!(
+                    "item names must be 1 to {} characters long",
+                    MAX_TEMPLATE_ITEM
+                ),
+            );
+        }
+        parsed.push((quantity, item.to_string()));
+    }
+
+    match parsed.len() {
+        0 => report.add_general("a template needs at least one item"),
+        n if n > MAX_TEMPLATE_ITEMS => report.add_general(format!(
+            "a template can hold at most {} items, this one has {}",
+            MAX_TEMPLATE_ITEMS, n
+        )),
+        _ => {}
+    }
+
+    match report.is_empty() {
+        true => Ok(parsed),
+        false => Err(report),
+    }
+}
+
+/// the failure to respond with when the items of a template aren't valid
+fn invalid_items(report: ValidationReport) -> CommandResponse {
+    CommandResponse::ComplexSuccess(report.response("The items couldn't be saved"))
+}
+
+/// the form items are stored in, which reads back the same through [parse_template_items]
+fn format_template_items(items: &[(i64, String)]) -> String {
+    items
+        .iter()
+        .map(|(quantity, item)| format!("{} {}", quantity, item))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// check the items given for a template and filter them, returning them in their stored form
+async fn template_items(
+    interaction: &CommandInteraction,
+    app_state: &AppState,
+    ctx: &Context,
+    items: &str,
+) -> Result<String, CommandResponse> {
+    let items = filter_text(ctx, app_state, Some(guild_id(interaction)?), "items", items).await?;
+    let items = parse_template_items(&items).map_err(invalid_items)?;
+    Ok(format_template_items(&items))
+}
+
+/// the options shared by the commands which set the items of a template
+fn name_and_items_options(cmd: CreateCommand, name: &str, items: &str) -> CreateCommand {
+    cmd.add_option(
+        CreateCommandOption::new(CommandOptionType::String, "name", name)
+            .required(true)
+            .max_length(MAX_TEMPLATE_NAME)
+            .to_owned(),
+    )
+    .add_option(
+        CreateCommandOption::new(CommandOptionType::String, "items", items)
+            .required(true)
+            .to_owned(),
+    )
+}
+
+/// read the name, and the items if the command takes them, from the options of a template command
+fn name_and_items(interaction: &CommandInteraction) -> Result<(&str, Option<&str>), String> {
+    let mut name: Option<&str> = None;
+    let mut items: Option<&str> = None;
+
+    for option in interaction.data.options().into_iter() {
+        match (option.name, option.value) {
+            ("name", ResolvedValue::String(val)) => name = Some(val),
+            ("items", ResolvedValue::String(val)) => items = Some(val),
+            (opt, val) => {
+                return Err(format!(
+                    "unexpected option name: `{}` and value `{:?}`",
+                    opt, val
+                ))
+            }
+        }
+    }
+
+    Ok((name.ok_or_else(|| String::from("name is required"))?, items))
+}
+
+/// Save a set of items which are often bought together, to add them all at once later
+pub struct ShopTemplateCreateCommand<'a> {
+    name: &'a str,
+    items: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopTemplateCreateCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let (name, items) = name_and_items(interaction)?;
+        Ok(Self {
+            name,
+            items: items.ok_or_else(|| String::from("items is required"))?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopTemplateCreateCommand<'a> {
+    fn name() -> &'static str {
+        "shop-template-create"
+    }
+
+    fn description() -> &'static str {
+        "save a set of items which are often bought together, e.g. for a weekly shop"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        name_and_items_options(
+            cmd,
+            "The name of the template, e.g. bbq run",
+            "The items, separated by commas, e.g. sausages, 2 bread, a dozen eggs",
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let name = normalise_name(self.name);
+        if name.is_empty() {
+            return Err(CommandResponse::BasicFailure(
+                "the template needs a name".to_string(),
+            ));
+        }
+        let items = template_items(interaction, app_state, ctx, self.items).await?;
+
+        match app_state
+            .add_shop_template(interaction.user.id.into(), guild_id, &name, &items)
+            .await
+        {
+            Ok(true) => Ok(CommandResponse::BasicSuccess(format!(
+                "Saved `{}` with {}, add them with /shop-template-apply",
+                name, items
+            ))),
+            Ok(false) => Err(CommandResponse::BasicFailure(format!(
+                "`{}` already exists, change it with /shop-template-edit",
+                name
+            ))),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ))),
+        }
+    }
+}
+
+/// Replace the items of a template
+pub struct ShopTemplateEditCommand<'a> {
+    name: &'a str,
+    items: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopTemplateEditCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let (name, items) = name_and_items(interaction)?;
+        Ok(Self {
+            name,
+            items: items.ok_or_else(|| String::from("items is required"))?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopTemplateEditCommand<'a> {
+    fn name() -> &'static str {
+        "shop-template-edit"
+    }
+
+    fn description() -> &'static str {
+        "replace the items of a template"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        name_and_items_options(
+            cmd,
+            "The name of the template to change",
+            "Every item the template should hold, separated by commas",
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let name = normalise_name(self.name);
+        let items = template_items(interaction, app_state, ctx, self.items).await?;
+
+        match app_state
+            .update_shop_template(guild_id, &name, &items)
+            .await
+        {
+            Ok(true) => Ok(CommandResponse::BasicSuccess(format!(
+                "`{}` now holds {}",
+                name, items
+            ))),
+            Ok(false) => Err(CommandResponse::BasicFailure(format!(
+                "there is no template called `{}`",
+                name
+            ))),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ))),
+        }
+    }
+}
+
+/// Delete a template
+pub struct ShopTemplateDeleteCommand<'a> {
+    name: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopTemplateDeleteCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let (name, _) = name_and_items(interaction)?;
+        Ok(Self { name })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopTemplateDeleteCommand<'a> {
+    fn name() -> &'static str {
+        "shop-template-delete"
+    }
+
+    fn description() -> &'static str {
+        "delete a template, items already added from it stay on the list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "name",
+                "The name of the template to delete",
+            )
+            .required(true)
+            .max_length(MAX_TEMPLATE_NAME)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let name = normalise_name(self.name);
+
+        match app_state.remove_shop_template(guild_id, &name).await {
+            Ok(true) => Ok(CommandResponse::BasicSuccess(format!("Deleted `{}`", name))),
+            Ok(false) => Err(CommandResponse::BasicFailure(format!(
+                "there is no template called `{}`",
+                name
+            ))),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ))),
+        }
+    }
+}
+
+/// List the templates saved in this server
+pub struct ShopTemplateListCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopTemplateListCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopTemplateListCommand {
+    fn name() -> &'static str {
+        "shop-template-list"
+    }
+
+    fn description() -> &'static str {
+        "list the templates saved in this server"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+
+        let templates = match app_state.get_shop_templates(guild_id).await {
+            Ok(templates) => templates,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if templates.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(
+                "No templates are saved in this server, create one with /shop-template-create"
+                    .to_string(),
+            ));
+        }
+
+        Ok(CommandResponse::BasicSuccess(format!(
+            "Templates in this server:\n{}",
+            templates
+                .iter()
+                .map(|t| format!("- `{}`: {}", t.name, t.items))
+                .collect::<Vec<String>>()
+                .join("\n")
+        )))
+    }
+}
+
+/// Add every item of a template to the shopping list
+pub struct ShopTemplateApplyCommand<'a> {
+    name: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopTemplateApplyCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let (name, _) = name_and_items(interaction)?;
+        Ok(Self { name })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopTemplateApplyCommand<'a> {
+    fn name() -> &'static str {
+        "shop-template-apply"
+    }
+
+    fn description() -> &'static str {
+        "add every item of a template to the shopping list"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "name",
+                "The name of the template to add",
+            )
+            .required(true)
+            .max_length(MAX_TEMPLATE_NAME)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let name = normalise_name(self.name);
+
+        let template = match app_state.get_shop_template(guild_id, &name).await {
+            Ok(Some(template)) => template,
+            Ok(None) => {
+                return Err(CommandResponse::BasicFailure(format!(
+                    "there is no template called `{}`, see /shop-template-list",
+                    name
+                )))
+            }
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        // templates are checked when saved, so this only fails if the limits have since changed
+        let items = parse_template_items(&template.items).map_err(|report| {
+            CommandResponse::ComplexSuccess(report.response("The template can't be applied"))
+        })?;
+
+        // posting is paced, so a large template takes longer than discord waits for a response
+        if let Err(e) = interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::Defer(
+                    CreateInteractionResponseMessage::new().ephemeral(true),
+                ),
+            )
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        let response_id: u64 = match interaction.get_response(ctx).await {
+            Ok(m) => m.id.into(),
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with discord: {}",
+                    e
+                )));
+            }
+        };
+
+        let result = add_items_in_bulk(
+            ctx,
+            app_state,
+            &items,
+            interaction.user.id.into(),
+            interaction.channel_id.into(),
+            guild_id,
+            response_id,
+        )
+        .await;
+
+        let content = match result.failed {
+            0 => format!(
+                "Added {} item(s) from `{}` to the shopping list",
+                result.succeeded, name
+            ),
+            failed => format!(
+                "Added {} item(s) from `{}` to the shopping list, {} could not be added",
+                result.succeeded, name, failed
+            ),
+        };
+
+        if let Err(e) = interaction
+            .edit_response(ctx, EditInteractionResponse::new().content(content))
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::ConnectionTrait;
+
+    use super::*;
+    use crate::state::test_database_urls;
+
+    #[tokio::test]
+    async fn a_saved_template_applies_the_items_it_was_created_with() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+
+            // created as /shop-template-create stores it
+            let items = parse_template_items("2 sausages, a dozen eggs, buns").unwrap();
+            let items = format_template_items(&items);
+            let name = normalise_name(" BBQ run ");
+            let added = state.add_shop_template(3, 1, &name, &items).await.unwrap();
+            assert!(added, "{:?}", backend);
+            let again = state.add_shop_template(3, 1, &name, &items).await.unwrap();
+            assert!(!again, "{:?}", backend);
+
+            // and read back as /shop-template-apply adds it
+            let template = state.get_shop_template(1, "bbq run").await.unwrap();
+            let applied = parse_template_items(&template.unwrap().items).unwrap();
+            assert_eq!(
+                applied,
+                [
+                    (2, "sausages".to_string()),
+                    (12, "eggs".to_string()),
+                    (1, "buns".to_string())
+                ],
+                "{:?}",
+                backend
+            );
+
+            let template = state.get_shop_template(2, "bbq run").await.unwrap();
+            assert!(template.is_none(), "{:?}", backend);
+        }
+    }
+}
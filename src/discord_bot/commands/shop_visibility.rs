@@ -0,0 +1,142 @@
+use log::error;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{CreateCommand, CreateCommandOption},
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::database::visibility::SerenityVisibilityDatabase,
+    state::{AppState, ListMode, ShopVisibility, CONFIG},
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// whether a user's /shop responses are seen only by them, their own choice winning over the
+/// guild's. A failure to look up their choice is only logged, using the guild's instead
+pub(super) async fn shop_is_ephemeral(
+    state: &AppState,
+    guild_id: Option<u64>,
+    user_id: u64,
+) -> bool {
+    let default = CONFIG.guild(guild_id).shop_visibility == ShopVisibility::Ephemeral;
+    let guild_id = match guild_id {
+        Some(guild_id) => guild_id,
+        None => return default,
+    };
+
+    match state.get_shop_ephemeral(guild_id, user_id).await {
+        Ok(choice) => choice.unwrap_or(default),
+        Err(e) => {
+            error!("error loading /shop visibility of {}: {}", user_id, e);
+            default
+        }
+    }
+}
+
+/// Choose whether your /shop responses are posted to the channel or only shown to you
+pub struct ShopVisibilityCommand {
+    /// none to go back to the guild's default
+    ephemeral: Option<bool>,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShopVisibilityCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut visibility: Option<&str> = None;
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("visibility", ResolvedValue::String(val)) => visibility = Some(val),
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        let ephemeral = match visibility {
+            Some("public") => Some(false),
+            Some("ephemeral") => Some(true),
+            Some("default") => None,
+            Some(val) => return Err(format!("unexpected visibility `{}`", val)),
+            None => return Err(String::from("visibility is required")),
+        };
+        Ok(Self { ephemeral })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShopVisibilityCommand {
+    fn name() -> &'static str {
+        "shop-visibility"
+    }
+
+    fn description() -> &'static str {
+        "choose whether items you add with /shop are posted to the channel or only shown to you"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "visibility",
+                "Who sees the items you add",
+            )
+            .required(true)
+            .add_string_choice("everyone, with buttons", "public")
+            .add_string_choice("only me, ticked off with /shopping-list", "ephemeral")
+            .add_string_choice("the server default", "default"),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        if let Err(e) = app_state
+            .set_shop_ephemeral(guild_id, interaction.user.id.into(), self.ephemeral)
+            .await
+        {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+
+        let ephemeral = self
+            .ephemeral
+            .unwrap_or(CONFIG.guild(Some(guild_id)).shop_visibility == ShopVisibility::Ephemeral);
+        let shown = match ephemeral {
+            true => {
+                "only be shown to you, tick them off with /shopping-list as they won't have buttons"
+            }
+            false => "be posted to the channel for everyone, with buttons",
+        };
+        let note = match CONFIG.guild(Some(guild_id)).list_mode {
+            ListMode::Classic => "",
+            ListMode::Digest => ", once the server stops keeping the whole list in one message",
+        };
+        Ok(CommandResponse::BasicSuccess(format!(
+            "Items you add with /shop will {}{}",
+            shown, note
+        )))
+    }
+}
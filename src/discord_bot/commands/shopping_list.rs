@@ -0,0 +1,424 @@
+use log::error;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::{
+            digest::digest_components,
+            embed::EmbedColor,
+            shopping::{describe_item, describe_item_without_store},
+        },
+        database::shopping::{SerenityShoppingDatabase, ShoppingListItemModel},
+    },
+    state::AppState,
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// the title of the first embed of the list, when grouped by store
+const TITLE: &str = "Shopping list";
+
+/// the title of the first embed of the list, when shown in the order items were added
+const FLAT_TITLE: &str = "Shopping list (ungrouped)";
+
+/// shown instead of an embed when nothing is pending
+const EMPTY_LIST: &str = "The shopping list is empty, add something with /shop";
+
+/// the heading of the items which can be bought anywhere
+const ANY_STORE: &str = "Any store";
+
+/// the longest embed description discord allows, less room for a continued heading
+const MAX_DESCRIPTION_LENGTH: usize = 4096 - 64;
+
+/// the most characters discord allows across all the embeds of one message
+const MAX_MESSAGE_LENGTH: usize = 6000;
+
+/// the most embeds discord allows in one message
+const MAX_EMBEDS: usize = 10;
+
+/// how the items of the list are arranged. Items have no category, so the store they're bought
+/// from is the only grouping there is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum GroupBy {
+    /// a section for each store
+    #[default]
+    Store,
+    /// one list, in the order items were added
+    Flat,
+}
+
+impl GroupBy {
+    fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "store" => Some(GroupBy::Store),
+            "flat" => Some(GroupBy::Flat),
+            _ => None,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            GroupBy::Store => TITLE,
+            GroupBy::Flat => FLAT_TITLE,
+        }
+    }
+
+    /// the grouping a list was shown with, from the title of its first embed, so that it is kept
+    /// when the list is redrawn
+    pub(super) fn from_title(title: Option<&str>) -> Self {
+        match title {
+            Some(FLAT_TITLE) => GroupBy::Flat,
+            _ => GroupBy::Store,
+        }
+    }
+}
+
+/// arrange items into sections, each with the heading it is shown under. A flat list is one
+/// section without a heading
+fn group_items(
+    items: &[ShoppingListItemModel],
+    group_by: GroupBy,
+) -> Vec<(Option<&str>, Vec<&ShoppingListItemModel>)> {
+    match group_by {
+        GroupBy::Store => group_by_store(items)
+            .into_iter()
+            .map(|(store, group)| (Some(store.unwrap_or(ANY_STORE)), group))
+            .collect(),
+        GroupBy::Flat => vec![(None, items.iter().collect())],
+    }
+}
+
+/// group items by the store they're from, in the order each store first appears in the list.
+/// Items without a store come last, as they can be bought anywhere
+fn group_by_store(
+    items: &[ShoppingListItemModel],
+) -> Vec<(Option<&str>, Vec<&ShoppingListItemModel>)> {
+    let mut groups: Vec<(Option<&str>, Vec<&ShoppingListItemModel>)> = Vec::new();
+    let mut anywhere = Vec::new();
+
+    for item in items.iter() {
+        let store = match item.store.as_deref().map(str::trim) {
+            Some(store) if !store.is_empty() => store,
+            _ => {
+                anywhere.push(item);
+                continue;
+            }
+        };
+
+        match groups
+            .iter_mut()
+            .find(|(group, _)| group.map_or(false, |g| g.eq_ignore_ascii_case(store)))
+        {
+            Some((_, group)) => group.push(item),
+            None => groups.push((Some(store), vec![item])),
+        }
+    }
+
+    if !anywhere.is_empty() {
+        groups.push((None, anywhere));
+    }
+    groups
+}
+
+/// the descriptions of the embeds showing the list, split into pages which each fit in a message
+struct Pages {
+    pages: Vec<Vec<String>>,
+    /// the characters used by the embeds of the last page so far
+    page_length: usize,
+}
+
+impl Pages {
+    fn new(title: &str) -> Self {
+        Self {
+            pages: vec![vec![String::new()]],
+            page_length: title.len(),
+        }
+    }
+
+    /// whether text of this length fits in the last embed without starting another
+    fn fits(&self, length: usize) -> bool {
+        let description = self.pages.last().and_then(|page| page.last());
+        description.map_or(0, String::len) + length <= MAX_DESCRIPTION_LENGTH
+            && self.page_length + length <= MAX_MESSAGE_LENGTH
+    }
+
+    /// add text to the last embed, starting a new embed or page first if it doesn't fit
+    fn push(&mut self, text: &str) {
+        if !self.fits(text.len()) {
+            let page = self.pages.last_mut().expect("there is always a page");
+            if page.len() < MAX_EMBEDS && self.page_length + text.len() <= MAX_MESSAGE_LENGTH {
+                page.push(String::new());
+            } else {
+                self.pages.push(vec![String::new()]);
+                self.page_length = 0;
+            }
+        }
+
+        let page = self.pages.last_mut().expect("there is always a page");
+        page.last_mut()
+            .expect("there is always an embed")
+            .push_str(text);
+        self.page_length += text.len();
+    }
+}
+
+/// the list as pages of embeds, grouped as asked. A section whose items are split across embeds
+/// has its heading repeated, so every embed can be read on its own
+pub(super) fn shopping_list_pages(
+    items: &[ShoppingListItemModel],
+    group_by: GroupBy,
+) -> Vec<Vec<CreateEmbed>> {
+    let mut pages = Pages::new(group_by.title());
+
+    for (heading, group) in group_items(items, group_by) {
+        for (i, item) in group.iter().enumerate() {
+            // the store is only left out where it is already the heading
+            let line = match heading {
+                Some(_) => format!("- {}\n", describe_item_without_store(item)),
+                None => format!("- {}\n", describe_item(item)),
+            };
+            let text = match (heading, i) {
+                (None, _) => line,
+                (Some(heading), 0) => format!("**{}**\n{}", heading, line),
+                (Some(heading), _) if !pages.fits(line.len()) => {
+                    format!("**{} (continued)**\n{}", heading, line)
+                }
+                _ => line,
+            };
+            pages.push(&text);
+        }
+    }
+
+    pages
+        .pages
+        .into_iter()
+        .enumerate()
+        .map(|(p, page)| {
+            page.into_iter()
+                .enumerate()
+                .map(|(i, description)| {
+                    let embed = CreateEmbed::new()
+                        .description(description)
+                        .color(EmbedColor::Red as u32);
+                    match (p, i) {
+                        (0, 0) => embed.title(group_by.title()),
+                        _ => embed,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// the first page of the list with the menus used to tick items off, as first shown and as
+/// updated when an item is ticked off or moved. Any further pages are sent separately
+pub(super) fn shopping_list_message(
+    items: &[ShoppingListItemModel],
+    group_by: GroupBy,
+) -> CreateInteractionResponseMessage {
+    let message = CreateInteractionResponseMessage::new().components(digest_components(items));
+    match shopping_list_pages(items, group_by).into_iter().next() {
+        Some(embeds) if !items.is_empty() => message.content("").embeds(embeds),
+        _ => message.content(EMPTY_LIST).embeds(vec![]),
+    }
+}
+
+/// Show every pending item, including those whose message has expired from the channel, grouped
+/// by store unless asked otherwise. Items are ticked off with the same menu as the digest, which
+/// is handled by [super::shop::Shop]
+pub struct ShoppingListCommand {
+    group_by: GroupBy,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ShoppingListCommand {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        let options = interaction.data.options();
+
+        let mut group_by = GroupBy::default();
+
+        for option in options.into_iter() {
+            match (option.name, option.value) {
+                ("group-by", ResolvedValue::String(val)) => {
+                    group_by = GroupBy::from_option(val)
+                        .ok_or_else(|| format!("unexpected grouping `{}`", val))?
+                }
+                (opt, val) => {
+                    return Err(format!(
+                        "unexpected option name: `{}` and value `{:?}`",
+                        opt, val
+                    ))
+                }
+            }
+        }
+
+        Ok(Self { group_by })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ShoppingListCommand {
+    fn name() -> &'static str {
+        "shopping-list"
+    }
+
+    fn description() -> &'static str {
+        "show everything on the shopping list, and tick off what's been bought"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "group-by",
+                "How to arrange the list, by store unless chosen",
+            )
+            .required(false)
+            .add_string_choice("store", "store")
+            .add_string_choice("flat", "flat"),
+        )
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g) => g.0.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "this command can only be used in a server".to_string(),
+                ))
+            }
+        };
+
+        let items = match app_state.get_digest_items(guild_id).await {
+            Ok(items) => items,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if items.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(EMPTY_LIST.to_string()));
+        }
+
+        let pages = shopping_list_pages(&items, self.group_by);
+        let response = CreateInteractionResponse::Message(
+            shopping_list_message(&items, self.group_by).ephemeral(true),
+        );
+        if pages.len() == 1 {
+            return Ok(CommandResponse::ComplexSuccess(response));
+        }
+
+        if let Err(e) = interaction.create_response(ctx, response).await {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )));
+        }
+
+        // the rest of a long list follows in messages of its own, as one can't hold it all
+        for embeds in pages.into_iter().skip(1) {
+            if let Err(e) = interaction
+                .create_followup(
+                    ctx,
+                    CreateInteractionResponseFollowup::new()
+                        .embeds(embeds)
+                        .ephemeral(true),
+                )
+                .await
+            {
+                error!("error sending the rest of the shopping list: {}", e);
+                break;
+            }
+        }
+
+        Ok(CommandResponse::NoResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discord_bot::database::shopping::test_item;
+
+    fn item(id: i32, name: &str, store: Option<&str>) -> ShoppingListItemModel {
+        ShoppingListItemModel {
+            store: store.map(str::to_string),
+            ..test_item(id, name)
+        }
+    }
+
+    /// each section's heading with the names of its items
+    fn sections(
+        items: &[ShoppingListItemModel],
+        group_by: GroupBy,
+    ) -> Vec<(Option<&str>, Vec<&str>)> {
+        group_items(items, group_by)
+            .into_iter()
+            .map(|(heading, group)| {
+                (
+                    heading,
+                    group.iter().map(|item| item.item.as_str()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn list() -> Vec<ShoppingListItemModel> {
+        vec![
+            item(1, "milk", None),
+            item(2, "bread", Some("Bakery")),
+            item(3, "fish", Some("Market")),
+            item(4, "eggs", Some(" ")),
+            item(5, "rolls", Some("bakery")),
+        ]
+    }
+
+    #[test]
+    fn items_are_grouped_by_store_in_the_order_stores_appear() {
+        assert_eq!(
+            sections(&list(), GroupBy::Store),
+            [
+                (Some("Bakery"), vec!["bread", "rolls"]),
+                (Some("Market"), vec!["fish"]),
+                (Some(ANY_STORE), vec!["milk", "eggs"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_flat_list_is_one_section_in_the_order_items_were_added() {
+        assert_eq!(
+            sections(&list(), GroupBy::Flat),
+            [(None, vec!["milk", "bread", "fish", "eggs", "rolls"])]
+        );
+    }
+
+    #[test]
+    fn the_grouping_is_kept_when_the_list_is_redrawn() {
+        for group_by in [GroupBy::Store, GroupBy::Flat] {
+            assert_eq!(GroupBy::from_title(Some(group_by.title())), group_by);
+        }
+        assert_eq!(GroupBy::from_title(None), GroupBy::Store);
+    }
+}
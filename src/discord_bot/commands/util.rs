@@ -1,7 +1,17 @@
 //! Various utilities to assist with writing application commands for the DIANA bot
 
 use log::{debug, error, info, warn};
-use serenity::builder::{CreateInteractionResponse, CreateInteractionResponseMessage};
+use serenity::{
+    all::{ChannelId, CommandInteraction},
+    builder::{
+        CreateInteractionResponse, CreateInteractionResponseFollowup,
+        CreateInteractionResponseMessage, EditInteractionResponse,
+    },
+    http::HttpError,
+    prelude::Context,
+};
+
+use crate::state::CONFIG;
 
 #[derive(Debug, Clone, Copy)]
 #[allow(dead_code, clippy::missing_docs_in_private_items)]
@@ -38,6 +48,9 @@ pub enum CommandResponse {
     /// but will instead log it to the console, and return a generic "internal error" resposne
     /// to the user
     InternalFailure(String),
+    /// the handler has already answered the interaction itself, e.g. by deferring and editing the
+    /// response, opening a modal or updating a message, so nothing more is sent. As an error it
+    /// means the failure has already been reported to the user, and is not logged again
     NoResponse,
 }
 
@@ -102,4 +115,262 @@ impl CommandResponse {
             CommandResponse::NoResponse => None,
         }
     }
+
+    /// generate a followup to send instead of the response, for when the handler answered the
+    /// interaction and then failed. A complex success can't be sent as a followup, so has none
+    pub fn generate_followup(&self) -> Option<CreateInteractionResponseFollowup> {
+        let content = match self {
+            CommandResponse::BasicSuccess(message) => message.clone(),
+            CommandResponse::BasicFailure(message) => message.clone(),
+            CommandResponse::ComplexFailure { response, .. } => response.clone(),
+            CommandResponse::InternalFailure(_) => "An internal error occurred.".to_string(),
+            CommandResponse::ComplexSuccess(_) | CommandResponse::NoResponse => return None,
+        };
+
+        Some(
+            CreateInteractionResponseFollowup::new()
+                .ephemeral(true)
+                .content(content),
+        )
+    }
+}
+
+/// the error code discord returns when an interaction which has already been answered is answered
+/// again, see https://discord.com/developers/docs/topics/opcodes-and-status-codes#json
+const ALREADY_ACKNOWLEDGED: isize = 40060;
+
+/// whether a response failed because the interaction had already been answered
+pub fn is_already_acknowledged(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(http) => {
+            let http: &HttpError = http;
+            matches!(http, HttpError::UnsuccessfulRequest(r) if r.error.code == ALREADY_ACKNOWLEDGED)
+        }
+        _ => false,
+    }
+}
+
+/// the broad cause of a failed request to discord
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscordErrorKind {
+    /// the bot is missing a permission, or access to the channel, needed for the request
+    Permission,
+    /// the request was rejected by discord's rate limits
+    RateLimited,
+    /// anything else, such as a network failure or an invalid request
+    Other,
+}
+
+/// classify an error returned by discord, so that failures an admin can fix are reported as such
+pub fn classify_discord_error(e: &serenity::Error) -> DiscordErrorKind {
+    match e {
+        serenity::Error::Http(http) => {
+            let http: &HttpError = http;
+            match http {
+                HttpError::UnsuccessfulRequest(r) => match r.status_code.as_u16() {
+                    403 => DiscordErrorKind::Permission,
+                    429 => DiscordErrorKind::RateLimited,
+                    _ => DiscordErrorKind::Other,
+                },
+                _ => DiscordErrorKind::Other,
+            }
+        }
+        _ => DiscordErrorKind::Other,
+    }
+}
+
+/// the response to a failed request to discord, when the bot is missing `permission` in
+/// `channel_id` the user is told which permission to grant and where
+pub fn discord_failure(
+    e: serenity::Error,
+    channel_id: ChannelId,
+    permission: &str,
+) -> CommandResponse {
+    match classify_discord_error(&e) {
+        DiscordErrorKind::Permission => CommandResponse::ComplexFailure {
+            response: format!(
+                "I don't have permission to do that in <#{}>, ask an admin to give me the **{}** permission there",
+                channel_id, permission
+            ),
+            kind: FailureMessageKind::Warn,
+            log_message: format!("missing {} permission in {}: {}", permission, channel_id, e),
+        },
+        DiscordErrorKind::RateLimited => CommandResponse::ComplexFailure {
+            response: "Discord is rate limiting me, try again in a moment".to_string(),
+            kind: FailureMessageKind::Warn,
+            log_message: format!("rate limited by discord: {}", e),
+        },
+        DiscordErrorKind::Other => {
+            CommandResponse::InternalFailure(format!("error communicating with discord: {}", e))
+        }
+    }
+}
+
+/// check that the user of a command runs the bot, as set by `owner_id` in the config. Used by
+/// commands which affect every server the bot is in, where owning one of them isn't enough
+pub(super) fn require_bot_owner(interaction: &CommandInteraction) -> Result<(), CommandResponse> {
+    check_bot_owner(interaction.user.id.into(), CONFIG.owner_id)
+}
+
+fn check_bot_owner(user: u64, owner: Option<u64>) -> Result<(), CommandResponse> {
+    match owner {
+        Some(owner) if owner == user => Ok(()),
+        Some(_) => Err(CommandResponse::BasicFailure(
+            "only the owner of the bot can use this command".to_string(),
+        )),
+        None => Err(CommandResponse::BasicFailure(
+            "only the owner of the bot can use this command, and no `owner_id` is set in its config"
+                .to_string(),
+        )),
+    }
+}
+
+/// the guild a command was used in, once the user has been checked to be the guild's owner. Used
+/// by commands which affect everyone in the guild, so are restricted further than other admin
+/// commands
+pub(super) async fn owner_guild_id(
+    interaction: &CommandInteraction,
+    ctx: &Context,
+) -> Result<u64, CommandResponse> {
+    let guild = match interaction.guild_id {
+        Some(g) => g,
+        None => {
+            return Err(CommandResponse::BasicFailure(
+                "this command can only be used in a server".to_string(),
+            ))
+        }
+    };
+
+    let owner_id = match guild.to_partial_guild(ctx).await {
+        Ok(guild) => guild.owner_id,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with discord: {}",
+                e
+            )))
+        }
+    };
+
+    if owner_id != interaction.user.id {
+        return Err(CommandResponse::BasicFailure(
+            "only the owner of the server can use this command".to_string(),
+        ));
+    }
+
+    Ok(guild.0.into())
+}
+
+/// the response to anything which would change the bot's data while it is in maintenance, see
+/// `/maintenance`
+pub(super) fn maintenance_notice() -> CommandResponse {
+    CommandResponse::ComplexFailure {
+        response: "The bot is down for maintenance, so nothing can be changed right now. Lists \
+                   and other read-only commands still work"
+            .to_string(),
+        kind: FailureMessageKind::Info,
+        log_message: "refused a change during maintenance".to_string(),
+    }
+}
+
+/// answer a command with a private "thinking" message, for handlers which edit many messages and
+/// could take longer than the 3 seconds discord waits for a response. Finish with
+/// [finish_deferred]
+pub(super) async fn defer_ephemeral(
+    interaction: &CommandInteraction,
+    ctx: &Context,
+) -> Result<(), CommandResponse> {
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Defer(
+                CreateInteractionResponseMessage::new().ephemeral(true),
+            ),
+        )
+        .await
+        .map_err(|e| {
+            CommandResponse::InternalFailure(format!("error communicating with discord: {}", e))
+        })
+}
+
+/// replace the message of a command answered with [defer_ephemeral]
+pub(super) async fn finish_deferred(
+    interaction: &CommandInteraction,
+    ctx: &Context,
+    content: String,
+) -> Result<CommandResponse, CommandResponse> {
+    if let Err(e) = interaction
+        .edit_response(ctx, EditInteractionResponse::new().content(content))
+        .await
+    {
+        return Err(CommandResponse::InternalFailure(format!(
+            "error communicating with discord: {}",
+            e
+        )));
+    }
+
+    Ok(CommandResponse::NoResponse)
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::http::ErrorResponse;
+
+    use super::*;
+
+    /// an error discord gives with the given status code
+    async fn discord_error(status: u16) -> serenity::Error {
+        let response = http::Response::builder()
+            .status(status)
+            .body(r#"{"code": 0, "message": "request failed"}"#)
+            .unwrap();
+        let response = ErrorResponse::from_response(response.into(), reqwest::Method::POST).await;
+        HttpError::UnsuccessfulRequest(response).into()
+    }
+
+    #[test]
+    fn only_the_bots_owner_passes_the_owner_check() {
+        assert!(check_bot_owner(7, Some(7)).is_ok());
+        assert!(matches!(
+            check_bot_owner(8, Some(7)),
+            Err(CommandResponse::BasicFailure(_))
+        ));
+        // without an owner set nobody can use the command, not even a server's owner
+        assert!(matches!(
+            check_bot_owner(7, None),
+            Err(CommandResponse::BasicFailure(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn discord_errors_are_classified_by_status() {
+        assert_eq!(
+            classify_discord_error(&discord_error(403).await),
+            DiscordErrorKind::Permission
+        );
+        assert_eq!(
+            classify_discord_error(&discord_error(429).await),
+            DiscordErrorKind::RateLimited
+        );
+        assert_eq!(
+            classify_discord_error(&discord_error(500).await),
+            DiscordErrorKind::Other
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_permissions_are_reported_to_the_user() {
+        let channel = ChannelId::new(5);
+        let response = discord_failure(discord_error(403).await, channel, "Manage Messages");
+        match response {
+            CommandResponse::ComplexFailure { response, .. } => {
+                assert!(response.contains("<#5>"));
+                assert!(response.contains("**Manage Messages**"));
+            }
+            response => panic!("expected a permission failure, got {:?}", response),
+        }
+        assert!(matches!(
+            discord_failure(discord_error(500).await, channel, "Manage Messages"),
+            CommandResponse::InternalFailure(_)
+        ));
+    }
 }
@@ -0,0 +1,360 @@
+use std::{borrow::Cow, sync::Arc};
+
+use log::error;
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedValue},
+    async_trait,
+    builder::{CreateCommand, CreateCommandOption},
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::{
+        common::word_filter::WordFilter, database::word_filter::SerenityWordFilterDatabase,
+        utils::WordFilterCache,
+    },
+    state::{AppState, FilterMode, CONFIG},
+};
+
+use super::{command::Command, util::CommandResponse};
+
+/// the guild a command was used in, word lists are always tied to a guild
+fn guild_id(interaction: &CommandInteraction) -> Result<u64, CommandResponse> {
+    match interaction.guild_id {
+        Some(g) => Ok(g.0.into()),
+        None => Err(CommandResponse::BasicFailure(
+            "this command can only be used in a server".to_string(),
+        )),
+    }
+}
+
+/// the single `word` option shared by the commands which edit the word list
+fn word_option<'a>(interaction: &'a CommandInteraction) -> Result<&'a str, String> {
+    let mut word: Option<&str> = None;
+
+    for option in interaction.data.options().into_iter() {
+        match (option.name, option.value) {
+            ("word", ResolvedValue::String(val)) => word = Some(val),
+            (opt, val) => {
+                return Err(format!(
+                    "unexpected option name: `{}` and value `{:?}`",
+                    opt, val
+                ))
+            }
+        }
+    }
+
+    word.ok_or_else(|| String::from("word is required"))
+}
+
+/// the compiled word filter for a guild, loading it from the database if it isn't cached
+async fn guild_word_filter(
+    ctx: &Context,
+    app_state: &AppState,
+    guild_id: u64,
+) -> Result<Arc<WordFilter>, CommandResponse> {
+    let cache = ctx.data.read().await.get::<WordFilterCache>().cloned();
+    if let Some(filter) = cache.as_ref().and_then(|c| c.get(guild_id)) {
+        return Ok(filter);
+    }
+
+    let words = match app_state.get_filtered_words(guild_id).await {
+        Ok(words) => words,
+        Err(e) => {
+            return Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            )));
+        }
+    };
+
+    let filter = Arc::new(WordFilter::new(words));
+    if let Some(cache) = cache {
+        cache.insert(guild_id, filter.clone());
+    }
+    Ok(filter)
+}
+
+/// drop the cached filter of a guild after its word list has been edited
+async fn invalidate_word_filter(ctx: &Context, guild_id: u64) {
+    match ctx.data.read().await.get::<WordFilterCache>() {
+        Some(cache) => cache.invalidate(guild_id),
+        None => error!("word filter cache missing from context"),
+    }
+}
+
+/// check free text provided by a user against the word filter of the guild, masking or
+/// rejecting it depending on the guild's settings. `field` names the text in the rejection.
+pub(super) async fn filter_text<'t>(
+    ctx: &Context,
+    app_state: &AppState,
+    guild_id: Option<u64>,
+    field: &str,
+    text: &'t str,
+) -> Result<Cow<'t, str>, CommandResponse> {
+    let guild_id = match guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(Cow::Borrowed(text)),
+    };
+
+    let filter = guild_word_filter(ctx, app_state, guild_id).await?;
+    apply_filter(
+        &filter,
+        CONFIG.guild(Some(guild_id)).word_filter,
+        field,
+        text,
+    )
+}
+
+/// mask the filtered words in some text, or reject it outright, depending on the guild's mode
+fn apply_filter<'t>(
+    filter: &WordFilter,
+    mode: FilterMode,
+    field: &str,
+    text: &'t str,
+) -> Result<Cow<'t, str>, CommandResponse> {
+    match mode {
+        FilterMode::Mask => Ok(filter.mask(text)),
+        FilterMode::Reject => match filter.find(text) {
+            Some(_) => Err(CommandResponse::BasicFailure(format!(
+                "the {} contains a word which isn't allowed in this server, please rephrase it",
+                field
+            ))),
+            None => Ok(Cow::Borrowed(text)),
+        },
+    }
+}
+
+/// Stop a word from being used in item names and notes
+pub struct FilterAddCommand<'a> {
+    word: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for FilterAddCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            word: word_option(interaction)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for FilterAddCommand<'a> {
+    fn name() -> &'static str {
+        "filter-add"
+    }
+
+    fn description() -> &'static str {
+        "stop a word from being used in item names and notes"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(CommandOptionType::String, "word", "The word to filter")
+                .required(true)
+                .max_length(100)
+                .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+
+        let word = match WordFilter::normalise(self.word) {
+            Some(word) => word,
+            None => {
+                return Err(CommandResponse::BasicFailure(
+                    "only a single word of letters and numbers can be filtered".to_string(),
+                ))
+            }
+        };
+
+        match app_state.add_filtered_word(guild_id, &word).await {
+            Ok(true) => {
+                invalidate_word_filter(ctx, guild_id).await;
+                Ok(CommandResponse::BasicSuccess(format!(
+                    "`{}` will now be filtered",
+                    word
+                )))
+            }
+            Ok(false) => Ok(CommandResponse::BasicSuccess(format!(
+                "`{}` is already filtered",
+                word
+            ))),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ))),
+        }
+    }
+}
+
+/// Allow a previously filtered word to be used again
+pub struct FilterRemoveCommand<'a> {
+    word: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for FilterRemoveCommand<'a> {
+    type Error = String;
+
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            word: word_option(interaction)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for FilterRemoveCommand<'a> {
+    fn name() -> &'static str {
+        "filter-remove"
+    }
+
+    fn description() -> &'static str {
+        "allow a filtered word to be used again"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd.add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "word",
+                "The word to stop filtering",
+            )
+            .required(true)
+            .max_length(100)
+            .to_owned(),
+        )
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        ctx: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+        let word = self.word.trim().to_lowercase();
+
+        match app_state.remove_filtered_word(guild_id, &word).await {
+            Ok(true) => {
+                invalidate_word_filter(ctx, guild_id).await;
+                Ok(CommandResponse::BasicSuccess(format!(
+                    "`{}` is no longer filtered",
+                    word
+                )))
+            }
+            Ok(false) => Err(CommandResponse::BasicFailure(format!(
+                "`{}` isn't filtered",
+                word
+            ))),
+            Err(e) => Err(CommandResponse::InternalFailure(format!(
+                "error communicating with database: {}",
+                e
+            ))),
+        }
+    }
+}
+
+/// List the words filtered in this server
+pub struct FilterListCommand;
+
+impl<'a> TryFrom<&'a CommandInteraction> for FilterListCommand {
+    type Error = String;
+
+    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for FilterListCommand {
+    fn name() -> &'static str {
+        "filter-list"
+    }
+
+    fn description() -> &'static str {
+        "list the words which can't be used in item names and notes"
+    }
+
+    fn get_application_command_options(cmd: CreateCommand) -> CreateCommand {
+        cmd
+    }
+
+    fn allowed_in_maintenance() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = guild_id(interaction)?;
+
+        let words = match app_state.get_filtered_words(guild_id).await {
+            Ok(words) => words,
+            Err(e) => {
+                return Err(CommandResponse::InternalFailure(format!(
+                    "error communicating with database: {}",
+                    e
+                )));
+            }
+        };
+
+        if words.is_empty() {
+            return Ok(CommandResponse::BasicSuccess(
+                "No words are filtered in this server".to_string(),
+            ));
+        }
+
+        let mode = match CONFIG.guild(Some(guild_id)).word_filter {
+            FilterMode::Reject => "rejected",
+            FilterMode::Mask => "masked",
+        };
+        Ok(CommandResponse::BasicSuccess(format!(
+            "Text containing these words is {}: {}",
+            mode,
+            words
+                .iter()
+                .map(|w| format!("||{}||", w))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter() -> WordFilter {
+        WordFilter::new(["darn".to_string()])
+    }
+
+    #[test]
+    fn filtered_words_are_masked() {
+        let masked = apply_filter(&filter(), FilterMode::Mask, "notes", "the Darn milk");
+        assert_eq!(masked.unwrap(), "the **** milk");
+        let clean = apply_filter(&filter(), FilterMode::Mask, "notes", "oat milk");
+        assert!(matches!(clean, Ok(Cow::Borrowed("oat milk"))));
+    }
+
+    #[test]
+    fn filtered_words_are_rejected() {
+        match apply_filter(&filter(), FilterMode::Reject, "store", "darn mart") {
+            Err(CommandResponse::BasicFailure(message)) => assert!(message.contains("store")),
+            result => panic!("expected the store to be rejected, got {:?}", result),
+        }
+        let clean = apply_filter(&filter(), FilterMode::Reject, "store", "darnell's mart");
+        assert_eq!(clean.unwrap(), "darnell's mart");
+    }
+}
@@ -0,0 +1,138 @@
+//! Editing the posted messages of many items at once, paced so that large batches don't trip
+//! discord's rate limits.
+
+use std::{collections::HashMap, time::Duration};
+
+use log::error;
+use serenity::{
+    all::{ChannelId, MessageId},
+    builder::EditMessage,
+    http::HttpError,
+    prelude::Context,
+};
+
+use crate::discord_bot::database::shopping::ShoppingListItemModel;
+
+use super::card::channel_allows_embeds;
+
+/// the pause between consecutive message edits, or posts when adding many items at once
+pub const EDIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// the outcome of a bulk edit
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BulkResult {
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+impl BulkResult {
+    /// a note to append to a response when some messages couldn't be updated, empty otherwise
+    pub fn failure_note(&self) -> String {
+        match self.failed {
+            0 => String::new(),
+            n => format!(" ({} message(s) could not be updated)", n),
+        }
+    }
+}
+
+/// apply an edit to the posted message of each item, one at a time. A failed edit is logged and
+/// counted rather than stopping the batch, as the database has already been updated. Items whose
+/// message has expired have nothing to edit, and are skipped. The edit is told whether the bot
+/// can post embeds in the item's channel, which is checked once per channel
+pub async fn throttled_edit<F>(
+    ctx: &Context,
+    items: &[ShoppingListItemModel],
+    edit: F,
+) -> BulkResult
+where
+    F: Fn(&ShoppingListItemModel, bool) -> EditMessage,
+{
+    let mut result = BulkResult::default();
+    let mut embeds: HashMap<ChannelId, bool> = HashMap::new();
+
+    let live = items
+        .iter()
+        .filter(|item| item.message_expired_at.is_none());
+    for (i, item) in live.enumerate() {
+        if i > 0 {
+            tokio::time::sleep(EDIT_INTERVAL).await;
+        }
+
+        let channel_id = match item.channel_id {
+            Some(c) => ChannelId::new(c as u64),
+            None => {
+                result.failed += 1;
+                continue;
+            }
+        };
+
+        let allowed = match embeds.get(&channel_id) {
+            Some(allowed) => *allowed,
+            None => {
+                let allowed = channel_allows_embeds(ctx, channel_id).await;
+                embeds.insert(channel_id, allowed);
+                allowed
+            }
+        };
+
+        match channel_id
+            .edit_message(
+                ctx,
+                MessageId::new(item.message_id as u64),
+                edit(item, allowed),
+            )
+            .await
+        {
+            Ok(_) => result.succeeded += 1,
+            Err(e) => {
+                error!("error editing message {}: {}", item.message_id, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    result
+}
+
+/// whether a request failed because the message no longer exists
+pub fn is_unknown_message(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(http) => {
+            let http: &HttpError = http;
+            matches!(http, HttpError::UnsuccessfulRequest(r) if r.status_code.as_u16() == 404)
+        }
+        _ => false,
+    }
+}
+
+/// delete the posted message of each item, one at a time, paced as edits are, returning the ids
+/// of the items whose message is gone. A message which has already been deleted by someone else,
+/// or which expired, counts as deleted.
+pub async fn throttled_delete(ctx: &Context, items: &[ShoppingListItemModel]) -> Vec<i32> {
+    let (expired, live): (Vec<&ShoppingListItemModel>, Vec<&ShoppingListItemModel>) = items
+        .iter()
+        .partition(|item| item.message_expired_at.is_some());
+    let mut deleted: Vec<i32> = expired.iter().map(|item| item.id).collect();
+
+    for (i, item) in live.into_iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(EDIT_INTERVAL).await;
+        }
+
+        let channel_id = match item.channel_id {
+            Some(c) => ChannelId::new(c as u64),
+            None => continue,
+        };
+
+        match channel_id
+            .delete_message(ctx, MessageId::new(item.message_id as u64))
+            .await
+        {
+            Ok(_) => deleted.push(item.id),
+            Err(e) if is_unknown_message(&e) => deleted.push(item.id),
+            Err(e) => error!("error deleting message {}: {}", item.message_id, e),
+        }
+    }
+
+    deleted
+}
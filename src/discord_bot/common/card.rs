@@ -0,0 +1,357 @@
+//! The embed posted for each item added to the shopping list, or the plain text posted instead in
+//! channels where the bot isn't allowed to post embeds.
+
+use chrono::{Local, NaiveDateTime, TimeZone};
+use log::error;
+use serenity::{
+    all::{ChannelId, Permissions},
+    builder::{CreateButton, CreateEmbed, CreateInteractionResponseMessage, EditMessage},
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::database::shopping::ShoppingListItemModel,
+    state::{CONFIG, DEFAULT_QUANTITY_FORMAT},
+};
+
+use super::{embed::EmbedColor, emoji::item_emoji, status::ItemStatus};
+
+/// the longest message content discord allows, embed descriptions may be longer
+const MAX_CONTENT: usize = 2000;
+
+/// builds the embed shown on the message of a pending item
+#[derive(Debug, Clone)]
+pub struct ShoppingCard<'a> {
+    item: &'a str,
+    quantity: i64,
+    personal: bool,
+    store: Option<&'a str>,
+    notes: Option<&'a str>,
+    recipe: Option<&'a str>,
+    /// who outside the household the item is being picked up for
+    bought_for: Option<&'a str>,
+    /// a link to the item's page in an online store, shown as a button rather than in the embed
+    product_url: Option<&'a str>,
+    emoji: bool,
+    /// how the item and its quantity are shown, with `{qty}` and `{item}` placeholders
+    quantity_format: &'static str,
+    /// who last changed the item, and when, as a unix timestamp
+    modified: Option<(u64, i64)>,
+}
+
+impl<'a> ShoppingCard<'a> {
+    pub fn new(item: &'a str, quantity: i64) -> Self {
+        Self {
+            item,
+            quantity,
+            personal: false,
+            store: None,
+            notes: None,
+            recipe: None,
+            bought_for: None,
+            product_url: None,
+            emoji: false,
+            quantity_format: DEFAULT_QUANTITY_FORMAT,
+            modified: None,
+        }
+    }
+
+    /// build the card for an item which has already been stored
+    pub fn from_item(item: &'a ShoppingListItemModel) -> Self {
+        Self::new(&item.item, item.quantity)
+            .personal(item.personal)
+            .store(item.store.as_deref())
+            .notes(item.notes.as_deref())
+            .recipe(item.recipe_group.as_deref())
+            .bought_for(item.bought_for.as_deref())
+            .product_url(item.product_url.as_deref())
+            .guild(item.guild_id.map(|g| g as u64))
+            .modified(item.modified_by.map(|u| u as u64), item.modified_at)
+    }
+
+    pub fn personal(mut self, personal: bool) -> Self {
+        self.personal = personal;
+        self
+    }
+
+    pub fn store(mut self, store: Option<&'a str>) -> Self {
+        self.store = store;
+        self
+    }
+
+    pub fn notes(mut self, notes: Option<&'a str>) -> Self {
+        self.notes = notes;
+        self
+    }
+
+    pub fn recipe(mut self, recipe: Option<&'a str>) -> Self {
+        self.recipe = recipe;
+        self
+    }
+
+    pub fn bought_for(mut self, bought_for: Option<&'a str>) -> Self {
+        self.bought_for = bought_for;
+        self
+    }
+
+    pub fn product_url(mut self, product_url: Option<&'a str>) -> Self {
+        self.product_url = product_url;
+        self
+    }
+
+    /// the "View product" button linking to the item's page, if it has one
+    pub fn product_button(&self) -> Option<CreateButton> {
+        self.product_url
+            .map(|url| CreateButton::new_link(url).label("View product"))
+    }
+
+    pub fn quantity(&self) -> i64 {
+        self.quantity
+    }
+
+    /// whether the item has a note which could be turned into an item of its own
+    pub fn has_notes(&self) -> bool {
+        self.notes.map_or(false, |notes| !notes.trim().is_empty())
+    }
+
+    /// record who last changed the item, times are stored in local time like `created_at`
+    pub fn modified(mut self, user: Option<u64>, at: Option<NaiveDateTime>) -> Self {
+        self.modified = user.zip(
+            at.and_then(|at| Local.from_local_datetime(&at).earliest())
+                .map(|at| at.timestamp()),
+        );
+        self
+    }
+
+    /// apply the settings of the guild the card is posted in
+    pub fn guild(mut self, guild_id: Option<u64>) -> Self {
+        let config = CONFIG.guild(guild_id);
+        self.emoji = config.item_emoji;
+        self.quantity_format = &config.quantity_format;
+        self
+    }
+
+    /// the text of the card, e.g. `🥛 Added x2 milk to the shopping list from Countdown`
+    pub fn description(&self) -> String {
+        let mut description = String::new();
+        if let Some(emoji) = self.emoji.then(|| item_emoji(self.item)).flatten() {
+            description.push_str(emoji);
+            description.push(' ');
+        }
+
+        description.push_str(&format!(
+            "Added {}{} to the shopping list",
+            self.quantity_format
+                .replace("{qty}", &self.quantity.to_string())
+                .replace("{item}", self.item),
+            if self.personal { " (personal)" } else { "" },
+        ));
+        if let Some(store) = self.store {
+            description.push_str(&format!(" from {}", store));
+        }
+        if let Some(notes) = self.notes {
+            description.push_str(&format!("\n**note:** {}", suppress_link_previews(notes)));
+        }
+        if let Some(recipe) = self.recipe {
+            description.push_str(&format!("\n**recipe:** {}", recipe));
+        }
+        if let Some(bought_for) = self.bought_for {
+            description.push_str(&format!("\n**for:** {}", bought_for));
+        }
+
+        description
+    }
+
+    pub fn embed(&self) -> CreateEmbed {
+        let embed = CreateEmbed::new()
+            .description(self.description())
+            .color(EmbedColor::Red as u32);
+
+        match self.modified {
+            Some((user, at)) => {
+                embed.field("Last edited", format!("by <@{}> <t:{}:R>", user, at), false)
+            }
+            None => embed,
+        }
+    }
+
+    /// the edit showing the card on the item's message, leaving its buttons untouched. Any text
+    /// or embed the message had before is replaced, in case the bot's permissions have changed
+    pub fn edit(&self, embeds: bool) -> EditMessage {
+        match embeds {
+            true => EditMessage::new().content("").embed(self.embed()),
+            false => EditMessage::new()
+                .content(render_plain(&self.description(), ItemStatus::Pending))
+                .embeds(vec![]),
+        }
+    }
+
+    /// as [`ShoppingCard::edit`], for updating the message an interaction came from
+    pub fn update(&self, embeds: bool) -> CreateInteractionResponseMessage {
+        match embeds {
+            true => CreateInteractionResponseMessage::new()
+                .content("")
+                .embed(self.embed()),
+            false => CreateInteractionResponseMessage::new()
+                .content(render_plain(&self.description(), ItemStatus::Pending))
+                .embeds(vec![]),
+        }
+    }
+}
+
+/// the description of an item once it has moved on from pending, e.g. `(BOUGHT) ~~x2 milk~~`
+pub fn status_description(description: &str, status: ItemStatus) -> String {
+    match status {
+        ItemStatus::Pending => description.to_string(),
+        ItemStatus::Bought => format!("(BOUGHT) ~~{}~~", description),
+        ItemStatus::Removed => format!("(REMOVED) {}", description),
+    }
+}
+
+/// an item's message as plain text, for channels where the bot can't post embeds. The status is
+/// shown with its emoji in place of the embed's colour, e.g. `✅ (BOUGHT) ~~x2 milk~~`
+pub fn render_plain(description: &str, status: ItemStatus) -> String {
+    fit_content(format!(
+        "{} {}",
+        status.symbol(),
+        status_description(description, status)
+    ))
+}
+
+/// cut text down to the longest message content discord allows, marking where it was cut
+pub fn fit_content(text: String) -> String {
+    match text.chars().count() > MAX_CONTENT {
+        true => text.chars().take(MAX_CONTENT - 1).chain(['…']).collect(),
+        false => text,
+    }
+}
+
+/// the description of a pending item from the text of its plain message, the reverse of
+/// [`render_plain`]
+pub fn plain_description(content: &str) -> Option<&str> {
+    content
+        .strip_prefix(ItemStatus::Pending.symbol())
+        .map(str::trim_start)
+        .filter(|description| !description.is_empty())
+}
+
+/// whether the bot can post embeds where it was interacted with, from the permissions discord
+/// sends with the interaction. Outside of a server there are none, and embeds are always allowed
+pub fn embeds_allowed(permissions: Option<Permissions>) -> bool {
+    permissions.map_or(true, |permissions| permissions.embed_links())
+}
+
+/// whether the bot can post embeds in a channel, for messages sent without an interaction to read
+/// its permissions from. A channel which can't be checked is assumed to allow them
+pub async fn channel_allows_embeds(ctx: &Context, channel_id: ChannelId) -> bool {
+    let channel = match channel_id.to_channel(ctx).await {
+        Ok(channel) => channel.guild(),
+        Err(e) => {
+            error!("error loading channel {}: {}", channel_id, e);
+            return true;
+        }
+    };
+
+    let bot = ctx.cache.current_user().id;
+    match channel.map(|channel| channel.permissions_for_user(ctx, bot)) {
+        Some(Ok(permissions)) => permissions.embed_links(),
+        _ => true,
+    }
+}
+
+/// wrap every link in `text` with `<...>`, so that discord doesn't add a preview of it to the
+/// message, e.g. `see https://example.com` becomes `see <https://example.com>`. Links inside a
+/// markdown link, `[recipe](https://example.com)`, are wrapped inside the brackets.
+fn suppress_link_previews(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    let next_link = |s: &str| {
+        ["http://", "https://"]
+            .iter()
+            .filter_map(|scheme| s.find(scheme))
+            .min()
+    };
+
+    while let Some(start) = next_link(rest) {
+        let (before, link) = rest.split_at(start);
+        out.push_str(before);
+
+        let preceding = before.chars().next_back();
+        let end = match preceding {
+            // the end of a markdown link is the closing bracket
+            Some('(') => link.find(|c: char| c == ')' || c.is_whitespace()),
+            _ => link.find(char::is_whitespace),
+        }
+        .unwrap_or(link.len());
+        let (url, after) = link.split_at(end);
+
+        match preceding {
+            // already suppressed, or part of a longer word which isn't a link
+            Some('<') => out.push_str(url),
+            Some(c) if c != '(' && !c.is_whitespace() => out.push_str(url),
+            _ => out.push_str(&format!("<{}>", url)),
+        }
+        rest = after;
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantities_are_shown_in_the_guilds_format() {
+        let mut card = ShoppingCard::new("milk", 2);
+        assert_eq!(card.description(), "Added x2 milk to the shopping list");
+
+        card.quantity_format = "{item} ×{qty}";
+        assert_eq!(card.description(), "Added milk ×2 to the shopping list");
+
+        // a placeholder can be repeated, and the rest of the format is kept as it is
+        card.quantity_format = "{qty} × {item} ({qty})";
+        assert_eq!(
+            card.personal(true).description(),
+            "Added 2 × milk (2) (personal) to the shopping list"
+        );
+    }
+
+    #[test]
+    fn plain_messages_show_the_status_with_its_emoji() {
+        assert_eq!(render_plain("x2 milk", ItemStatus::Pending), "⏳ x2 milk");
+        assert_eq!(
+            render_plain("x2 milk", ItemStatus::Bought),
+            "✅ (BOUGHT) ~~x2 milk~~"
+        );
+        assert_eq!(
+            render_plain("x2 milk", ItemStatus::Removed),
+            "❌ (REMOVED) x2 milk"
+        );
+
+        // only a pending item's description can be read back
+        let pending = render_plain("x2 milk", ItemStatus::Pending);
+        assert_eq!(plain_description(&pending), Some("x2 milk"));
+        let bought = render_plain("x2 milk", ItemStatus::Bought);
+        assert_eq!(plain_description(&bought), None);
+    }
+
+    #[test]
+    fn long_plain_messages_are_cut_to_fit() {
+        let description = "a".repeat(MAX_CONTENT * 2);
+        let text = render_plain(&description, ItemStatus::Pending);
+        assert_eq!(text.chars().count(), MAX_CONTENT);
+        assert!(text.ends_with('…'));
+    }
+
+    #[test]
+    fn embeds_are_only_posted_with_permission_to() {
+        assert!(embeds_allowed(None), "outside of a server");
+        assert!(embeds_allowed(Some(Permissions::EMBED_LINKS)));
+        assert!(embeds_allowed(Some(Permissions::all())));
+        assert!(!embeds_allowed(Some(Permissions::SEND_MESSAGES)));
+        assert!(!embeds_allowed(Some(Permissions::empty())));
+    }
+}
@@ -0,0 +1,145 @@
+//! Building the custom ids of buttons, select menus and modals, which discord limits to 100
+//! characters. A custom id is a prefix naming what the component does followed by its payload,
+//! such as the message id of an item or the name of a store. Payloads which would take the id over
+//! the limit are swapped for a short token, and the payload is kept in memory until it is needed.
+
+use std::{
+    borrow::Cow,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+
+/// the longest custom id discord allows
+pub const MAX_CUSTOM_ID: usize = 100;
+
+/// marks a payload which has been swapped for a token. Payloads which happen to start with it are
+/// always swapped, so that they can't be mistaken for a token
+const TOKEN_MARKER: char = '~';
+
+/// how long a swapped payload is kept. Components which need one are only on short-lived prompts,
+/// so anything older has long since stopped being answerable
+const TOKEN_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+lazy_static! {
+    /// payloads too long for a custom id, keyed by the token which replaced them
+    static ref TOKENS: Mutex<HashMap<String, (Instant, String)>> = Mutex::new(HashMap::new());
+}
+
+/// the custom id of a component, `prefix` followed by `payload`. The payload is swapped for a
+/// token if the id would otherwise be too long
+pub fn encode_custom_id(prefix: &str, payload: &str) -> String {
+    if prefix.len() + payload.len() <= MAX_CUSTOM_ID && !payload.starts_with(TOKEN_MARKER) {
+        return format!("{}{}", prefix, payload);
+    }
+
+    // the same payload always gets the same token, so rebuilding a prompt doesn't add another
+    let mut hasher = DefaultHasher::new();
+    payload.hash(&mut hasher);
+    let token = format!("{}{:016x}", TOKEN_MARKER, hasher.finish());
+
+    // prefixes are constants, so one too long for a token is a bug rather than bad input, and an
+    // id over the limit would only be rejected by discord when the whole message is sent
+    let custom_id = format!("{}{}", prefix, token);
+    assert!(
+        custom_id.len() <= MAX_CUSTOM_ID,
+        "prefix `{}` leaves no room for a token",
+        prefix
+    );
+
+    let mut tokens = TOKENS.lock().unwrap();
+    tokens.retain(|_, (at, _)| at.elapsed() < TOKEN_TTL);
+    tokens.insert(token, (Instant::now(), payload.to_string()));
+
+    custom_id
+}
+
+/// the payload of a custom id built by [encode_custom_id] with the same prefix. None if the id
+/// has another prefix, or its payload was swapped for a token which has since been forgotten, such
+/// as by a restart
+pub fn decode_custom_id<'c>(custom_id: &'c str, prefix: &str) -> Option<Cow<'c, str>> {
+    let payload = custom_id.strip_prefix(prefix)?;
+    if !payload.starts_with(TOKEN_MARKER) {
+        return Some(Cow::Borrowed(payload));
+    }
+
+    TOKENS
+        .lock()
+        .unwrap()
+        .get(payload)
+        .filter(|(at, _)| at.elapsed() < TOKEN_TTL)
+        .map(|(_, payload)| Cow::Owned(payload.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREFIX: &str = "test-prefix:";
+
+    #[test]
+    fn short_payloads_are_kept_in_the_id() {
+        let custom_id = encode_custom_id(PREFIX, "1234567890");
+        assert_eq!(custom_id, "test-prefix:1234567890");
+        assert_eq!(
+            decode_custom_id(&custom_id, PREFIX),
+            Some(Cow::Borrowed("1234567890"))
+        );
+    }
+
+    #[test]
+    fn payloads_which_just_fit_are_kept_in_the_id() {
+        let payload = "a".repeat(MAX_CUSTOM_ID - PREFIX.len());
+        let custom_id = encode_custom_id(PREFIX, &payload);
+        assert_eq!(custom_id.len(), MAX_CUSTOM_ID);
+        assert_eq!(
+            decode_custom_id(&custom_id, PREFIX).as_deref(),
+            Some(&*payload)
+        );
+    }
+
+    #[test]
+    fn long_payloads_are_swapped_for_a_token() {
+        let payload = "a very long store name ".repeat(10);
+        let custom_id = encode_custom_id(PREFIX, &payload);
+        assert!(custom_id.len() <= MAX_CUSTOM_ID);
+        assert!(custom_id.starts_with(&format!("{}{}", PREFIX, TOKEN_MARKER)));
+        assert_eq!(
+            decode_custom_id(&custom_id, PREFIX).as_deref(),
+            Some(&*payload)
+        );
+
+        // rebuilding the same prompt gives the same id
+        assert_eq!(encode_custom_id(PREFIX, &payload), custom_id);
+    }
+
+    #[test]
+    fn payloads_starting_with_the_marker_are_swapped_for_a_token() {
+        let custom_id = encode_custom_id(PREFIX, "~tilde");
+        assert_ne!(custom_id, "test-prefix:~tilde");
+        assert_eq!(
+            decode_custom_id(&custom_id, PREFIX).as_deref(),
+            Some("~tilde")
+        );
+    }
+
+    #[test]
+    fn ids_with_another_prefix_or_a_forgotten_token_are_not_decoded() {
+        let custom_id = encode_custom_id(PREFIX, "1234");
+        assert_eq!(decode_custom_id(&custom_id, "other-prefix:"), None);
+        assert_eq!(
+            decode_custom_id("test-prefix:~0000000000000000", PREFIX),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "leaves no room for a token")]
+    fn a_prefix_too_long_for_a_token_is_refused() {
+        let prefix = "p".repeat(MAX_CUSTOM_ID - 4);
+        encode_custom_id(&prefix, "a payload too long to fit");
+    }
+}
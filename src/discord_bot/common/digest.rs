@@ -0,0 +1,318 @@
+//! Keeps the whole shopping list of a guild in a single pinned message, for guilds which use the
+//! digest list mode rather than a message per item.
+
+use log::error;
+use serenity::{
+    all::{ChannelId, MessageId},
+    builder::{
+        CreateActionRow, CreateEmbed, CreateMessage, CreateSelectMenu, CreateSelectMenuKind,
+        CreateSelectMenuOption, EditMessage,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::database::{
+        digest::SerenityDigestDatabase,
+        shopping::{SerenityShoppingDatabase, ShoppingListItemModel},
+    },
+    state::AppState,
+};
+
+use super::{
+    embed::EmbedColor,
+    shopping::{describe_item, shopping_list_description},
+};
+
+/// the custom id of the select menu used to tick items off the digest
+pub const DIGEST_DONE: &str = "digest-done";
+
+/// the custom id of the select menu used to move an item one place up the digest
+pub const DIGEST_MOVE_UP: &str = "digest-up";
+
+/// the custom id of the select menu used to move an item one place down the digest
+pub const DIGEST_MOVE_DOWN: &str = "digest-down";
+
+/// discord allows at most 25 options in a select menu, so only the first items can be ticked off
+/// or moved
+const MAX_OPTIONS: usize = 25;
+
+/// the description shown when there is nothing on the list
+const EMPTY_LIST: &str = "The shopping list is empty";
+
+/// the outcome of refreshing the digest of a guild
+#[derive(Debug, PartialEq, Eq)]
+pub enum DigestOutcome {
+    /// the guild has no digest, and no channel was given to post one in
+    Disabled,
+    /// the list hasn't changed since the digest was last rendered
+    Unchanged,
+    /// the existing digest was edited in place
+    Edited,
+    /// a new digest was posted, as there wasn't one or it had been deleted
+    Posted,
+}
+
+/// the text of the digest, compared against the posted message to tell whether it needs editing
+fn digest_description(items: &[ShoppingListItemModel]) -> String {
+    match items.is_empty() {
+        true => EMPTY_LIST.to_string(),
+        false => shopping_list_description(items),
+    }
+}
+
+/// a select menu offering each of the given items, labelled as they are shown in the digest
+fn item_menu(
+    custom_id: &str,
+    placeholder: &str,
+    items: &[ShoppingListItemModel],
+) -> CreateSelectMenu {
+    let options: Vec<CreateSelectMenuOption> = items
+        .iter()
+        .take(MAX_OPTIONS)
+        .map(|item| {
+            let label: String = describe_item(item).chars().take(100).collect();
+            CreateSelectMenuOption::new(label, item.id.to_string())
+        })
+        .collect();
+
+    CreateSelectMenu::new(custom_id, CreateSelectMenuKind::String { options })
+        .placeholder(placeholder)
+}
+
+/// the embed and components of the digest message for the pending items of a guild, which should
+/// be in digest order
+pub fn digest_message(items: &[ShoppingListItemModel]) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let embed = CreateEmbed::new()
+        .title("Shopping list")
+        .description(digest_description(items))
+        .color(match items.is_empty() {
+            true => EmbedColor::Green as u32,
+            false => EmbedColor::Red as u32,
+        });
+
+    (embed, digest_components(items))
+}
+
+/// the menus used to tick off and reorder the pending items of a guild, which should be in digest
+/// order. There are none when nothing is pending
+pub fn digest_components(items: &[ShoppingListItemModel]) -> Vec<CreateActionRow> {
+    if items.is_empty() {
+        return vec![];
+    }
+
+    let count = items.len().min(MAX_OPTIONS) as u8;
+    let done = item_menu(DIGEST_DONE, "Tick off bought or removed items", items)
+        .min_values(1)
+        .max_values(count);
+    let mut components = vec![CreateActionRow::SelectMenu(done)];
+
+    // the first item can't move up and the last can't move down, so each is left off its menu
+    if items.len() > 1 {
+        components.push(CreateActionRow::SelectMenu(item_menu(
+            DIGEST_MOVE_UP,
+            "Move an item up",
+            &items[1..],
+        )));
+        components.push(CreateActionRow::SelectMenu(item_menu(
+            DIGEST_MOVE_DOWN,
+            "Move an item down",
+            &items[..items.len() - 1],
+        )));
+    }
+
+    components
+}
+
+/// post a new digest in a channel and pin it, recording it as the digest of the guild
+async fn post_digest(
+    guild_id: u64,
+    channel_id: ChannelId,
+    ctx: &Context,
+    state: &AppState,
+    items: &[ShoppingListItemModel],
+) -> Result<DigestOutcome, Box<dyn std::error::Error + Send + Sync>> {
+    let (embed, components) = digest_message(items);
+    let message = channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new().embed(embed).components(components),
+        )
+        .await?;
+
+    // an unpinned digest still works, so a failure here shouldn't lose the message
+    if let Err(e) = message.pin(ctx).await {
+        error!("error pinning digest for {}: {}", guild_id, e);
+    }
+
+    state
+        .set_digest(guild_id, channel_id.into(), message.id.into())
+        .await?;
+    Ok(DigestOutcome::Posted)
+}
+
+/// bring the digest of a guild up to date with its pending items, posting it in `channel_id` if
+/// the guild has no digest yet. Unless `force` is set, the digest is only edited if it has changed.
+pub async fn refresh_digest(
+    guild_id: u64,
+    channel_id: Option<u64>,
+    ctx: &Context,
+    state: &AppState,
+    force: bool,
+) -> Result<DigestOutcome, Box<dyn std::error::Error + Send + Sync>> {
+    let items = state.get_digest_items(guild_id).await?;
+
+    let digest = match state.get_digest(guild_id).await? {
+        Some(digest) => digest,
+        None => {
+            return match channel_id {
+                Some(channel_id) => {
+                    post_digest(guild_id, ChannelId::new(channel_id), ctx, state, &items).await
+                }
+                None => Ok(DigestOutcome::Disabled),
+            }
+        }
+    };
+    let channel_id = ChannelId::new(digest.channel_id as u64);
+    let message_id = MessageId::new(digest.message_id as u64);
+
+    if !force {
+        let description = digest_description(&items);
+        match channel_id.message(ctx, message_id).await {
+            Ok(message)
+                if message
+                    .embeds
+                    .first()
+                    .and_then(|e| e.description.as_deref())
+                    == Some(description.as_str()) =>
+            {
+                return Ok(DigestOutcome::Unchanged)
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("digest for {} is missing, reposting: {}", guild_id, e);
+                return post_digest(guild_id, channel_id, ctx, state, &items).await;
+            }
+        }
+    }
+
+    let (embed, components) = digest_message(&items);
+    match channel_id
+        .edit_message(
+            ctx,
+            message_id,
+            EditMessage::new().embed(embed).components(components),
+        )
+        .await
+    {
+        Ok(_) => Ok(DigestOutcome::Edited),
+        Err(e) => {
+            error!("unable to edit digest for {}, reposting: {}", guild_id, e);
+            post_digest(guild_id, channel_id, ctx, state, &items).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::discord_bot::database::shopping::test_item;
+
+    fn item(id: i32, name: &str) -> ShoppingListItemModel {
+        ShoppingListItemModel {
+            message_id: 0,
+            channel_id: None,
+            ..test_item(id, name)
+        }
+    }
+
+    /// the custom id of each menu with the ids of the items it offers
+    fn menus(components: Vec<CreateActionRow>) -> Vec<(String, Vec<String>)> {
+        components
+            .into_iter()
+            .map(|row| {
+                let row = serde_json::to_value(row).unwrap();
+                let menu = &row["components"][0];
+                let options = menu["options"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|option| option["value"].as_str().unwrap().to_string())
+                    .collect();
+                (menu["custom_id"].as_str().unwrap().to_string(), options)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn an_empty_list_has_no_menus() {
+        let (embed, components) = digest_message(&[]);
+        let embed = serde_json::to_value(embed).unwrap();
+        assert_eq!(embed["description"], EMPTY_LIST);
+        assert_eq!(embed["color"], EmbedColor::Green as u32);
+        assert!(components.is_empty());
+    }
+
+    #[test]
+    fn the_digest_follows_the_items_on_the_list() {
+        let list = vec![item(1, "milk"), item(2, "bread"), item(3, "eggs")];
+        let (embed, components) = digest_message(&list);
+        let embed = serde_json::to_value(embed).unwrap();
+        assert_eq!(embed["description"], "- x1 milk\n- x1 bread\n- x1 eggs\n");
+        assert_eq!(embed["color"], EmbedColor::Red as u32);
+        assert_eq!(
+            menus(components),
+            vec![
+                (
+                    DIGEST_DONE.to_string(),
+                    vec!["1".into(), "2".into(), "3".into()]
+                ),
+                (DIGEST_MOVE_UP.to_string(), vec!["2".into(), "3".into()]),
+                (DIGEST_MOVE_DOWN.to_string(), vec!["1".into(), "2".into()]),
+            ]
+        );
+
+        // a single item can only be ticked off
+        assert_eq!(
+            menus(digest_components(&list[..1])),
+            vec![(DIGEST_DONE.to_string(), vec!["1".to_string()])]
+        );
+    }
+
+    #[test]
+    fn only_a_changed_list_changes_the_digest() {
+        let list = vec![item(1, "milk"), item(2, "bread")];
+        let before = digest_description(&list);
+        assert_eq!(
+            digest_description(&[item(1, "milk"), item(2, "bread")]),
+            before
+        );
+
+        let reordered = vec![item(2, "bread"), item(1, "milk")];
+        assert_ne!(digest_description(&reordered), before);
+
+        let mut bought = list.clone();
+        bought.remove(0);
+        assert_eq!(digest_description(&bought), "- x1 bread\n");
+
+        let mut added = list;
+        added.push(item(3, "eggs"));
+        assert_eq!(
+            digest_description(&added),
+            "- x1 milk\n- x1 bread\n- x1 eggs\n"
+        );
+
+        assert_eq!(digest_description(&[]), EMPTY_LIST);
+    }
+
+    #[test]
+    fn menus_offer_at_most_25_items() {
+        let list: Vec<_> = (1..=30).map(|id| item(id, "milk")).collect();
+        let row = serde_json::to_value(digest_components(&list).remove(0)).unwrap();
+        let menu = &row["components"][0];
+        assert_eq!(menu["options"].as_array().map(Vec::len), Some(MAX_OPTIONS));
+        assert_eq!(menu["max_values"], Value::from(MAX_OPTIONS));
+    }
+}
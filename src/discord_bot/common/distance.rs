@@ -1,46 +1,254 @@
+use log::error;
 use serenity::builder::{CreateEmbed, CreateEmbedFooter};
 
 use crate::{
-    google_api::maps::GoogleMapsData,
-    state::{AppState, CONFIG},
+    discord_bot::database::distance::{NewDistanceResult, SerenityDistanceDatabase},
+    google_api::maps::{GoogleMapError, GoogleMapsData},
+    state::{AppState, Destination, CONFIG},
 };
 
-pub async fn load_maps_data_to_embed(
+/// look up the travel time from an address to each destination, through the shared maps api queue
+pub async fn request_maps_data(
     address: String,
+    destinations: &[Destination],
     state: &AppState,
-) -> Result<CreateEmbed, Box<dyn std::error::Error + Send + Sync + 'static>> {
+) -> Result<GoogleMapsData, Box<dyn std::error::Error + Send + Sync + 'static>> {
     // create a oneshot channel to await the response
     let (tx, rx) = tokio::sync::oneshot::channel();
 
     // make a global request for the address
     state
         .maps_api()
-        .add_to_queue(address, &CONFIG.destinations, tx)
+        .add_to_queue(address, destinations, tx)
         .await;
 
-    // wait for the oneshot channel to return (maximum of 20 seconds)
-    let data: GoogleMapsData =
-        tokio::time::timeout(std::time::Duration::from_secs(20), rx).await???;
+    // wait for the oneshot channel to return, for as long as every attempt at the request could take
+    let data: GoogleMapsData = tokio::time::timeout(CONFIG.maps.max_wait(), rx).await???;
+    Ok(data)
+}
+
+/// whether a lookup failed because google maps took too long, rather than because of the address
+pub fn timed_out(e: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<GoogleMapError>(),
+        Some(GoogleMapError::Timeout)
+    ) || e.is::<tokio::time::error::Elapsed>()
+}
+
+pub async fn load_maps_data_to_embed(
+    address: String,
+    state: &AppState,
+) -> Result<CreateEmbed, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let data = request_maps_data(address.clone(), &CONFIG.destinations, state).await?;
+
+    // keep a record of every lookup, so travel times can be compared over time
+    let results = distance_results(&data, &CONFIG.destinations);
+    if let Err(e) = state.add_distance_results(&address, results).await {
+        error!("failed to record distance results for {}: {}", address, e);
+    }
+
+    let phrase = &CONFIG.phrases[rand::random::<usize>() % CONFIG.phrases.len()];
+    Ok(distance_embed(&data, &CONFIG.destinations, phrase))
+}
+
+/// the travel time to each destination which could be reached, ready to be recorded
+fn distance_results<'a>(
+    data: &GoogleMapsData,
+    destinations: &'a [Destination],
+) -> Vec<NewDistanceResult<'a>> {
+    data.rows
+        .iter()
+        .flat_map(|row| row.elements.iter().enumerate())
+        .filter(|(_, element)| element.is_ok())
+        .map(|(i, element)| NewDistanceResult {
+            destination: &destinations[i].label,
+            distance: element.distance.value,
+            duration: element.travel_time().value,
+        })
+        .collect()
+}
 
+/// show the route to each destination, with the total travel time and `phrase` in the footer
+fn distance_embed(
+    data: &GoogleMapsData,
+    destinations: &[Destination],
+    phrase: &str,
+) -> CreateEmbed {
     let embed = CreateEmbed::default();
 
     let mut embed = embed
         .title(&data.origin_addresses[0])
         .footer(
-            CreateEmbedFooter::new(&CONFIG.phrases[rand::random::<usize>() % CONFIG.phrases.len()])
+            CreateEmbedFooter::new(phrase)
                 .icon_url("https://cdn.iconscout.com/icon/free/png-256/google-map-461800.png"),
         )
         .color(0x4285F4);
 
     for row in data.rows.iter() {
         for (i, element) in row.elements.iter().enumerate() {
-            embed = embed.field(
-                &CONFIG.destinations[i].label,
-                format!("{} ({})", element.distance.text, element.duration.text),
-                true,
-            );
+            let value = match element.is_ok() {
+                true => format!("{} ({})", element.distance.text, element.travel_time().text),
+                false => "no route found".to_string(),
+            };
+            embed = embed.field(&destinations[i].label, value, true);
+        }
+    }
+
+    let durations: Vec<i64> = data
+        .rows
+        .iter()
+        .flat_map(|row| row.elements.iter())
+        .filter(|element| element.is_ok())
+        .map(|element| element.travel_time().value as i64)
+        .collect();
+    if let Some(rollup) = travel_time_rollup(&durations) {
+        embed = embed.field("Total travel time", rollup, false);
+    }
+
+    embed
+}
+
+/// summarise the travel time to every destination which could be reached, e.g.
+/// `1 hr 30 min across 3 destinations, 30 min on average`
+fn travel_time_rollup(durations: &[i64]) -> Option<String> {
+    if durations.is_empty() {
+        return None;
+    }
+
+    let total: i64 = durations.iter().sum();
+    Some(format!(
+        "{} across {} destination(s), {} on average",
+        format_duration(total),
+        durations.len(),
+        format_duration(total / durations.len() as i64)
+    ))
+}
+
+/// format a duration in seconds into a short human readable form, e.g. `1 hr 5 min`
+pub fn format_duration(seconds: i64) -> String {
+    let minutes = (seconds + 30) / 60;
+    if minutes < 60 {
+        format!("{} min", minutes)
+    } else {
+        format!("{} hr {} min", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::google_api::maps::{
+        GoogleMapsDistance, GoogleMapsDuration, GoogleMapsElement, GoogleMapsRow,
+    };
+
+    fn destinations() -> Vec<Destination> {
+        ["Work", "Gym"]
+            .iter()
+            .map(|label| Destination {
+                label: label.to_string(),
+                address: format!("{}, Auckland", label),
+            })
+            .collect()
+    }
+
+    fn element(status: &str, meters: u32, seconds: u32) -> GoogleMapsElement {
+        GoogleMapsElement {
+            distance: GoogleMapsDistance {
+                text: format!("{} m", meters),
+                value: meters,
+            },
+            duration: GoogleMapsDuration {
+                text: format_duration(seconds as i64),
+                value: seconds,
+            },
+            duration_in_traffic: None,
+            status: status.to_string(),
+        }
+    }
+
+    fn lookup(elements: Vec<GoogleMapsElement>) -> GoogleMapsData {
+        GoogleMapsData {
+            destination_addresses: vec!["Work, Auckland".to_string(), "Gym, Auckland".to_string()],
+            origin_addresses: vec!["1 Queen Street, Auckland".to_string()],
+            rows: vec![GoogleMapsRow { elements }],
+            status: "OK".to_string(),
         }
     }
 
-    Ok(embed)
+    #[test]
+    fn durations_are_rounded_to_the_minute() {
+        assert_eq!(format_duration(0), "0 min");
+        assert_eq!(format_duration(29), "0 min");
+        assert_eq!(format_duration(30), "1 min");
+        assert_eq!(format_duration(59 * 60), "59 min");
+        assert_eq!(format_duration(3600), "1 hr 0 min");
+        assert_eq!(format_duration(3900), "1 hr 5 min");
+    }
+
+    #[test]
+    fn travel_times_are_rolled_up() {
+        assert_eq!(travel_time_rollup(&[]), None);
+        assert_eq!(
+            travel_time_rollup(&[1800, 3600]).as_deref(),
+            Some("1 hr 30 min across 2 destination(s), 45 min on average")
+        );
+    }
+
+    #[test]
+    fn only_reachable_destinations_are_recorded() {
+        let destinations = destinations();
+        let data = lookup(vec![
+            element("ZERO_RESULTS", 0, 0),
+            element("OK", 2500, 600),
+        ]);
+
+        let results = distance_results(&data, &destinations);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].destination, "Gym");
+        assert_eq!(results[0].distance, 2500);
+        assert_eq!(results[0].duration, 600);
+    }
+
+    #[test]
+    fn the_embed_shows_every_destination() {
+        let destinations = destinations();
+        let data = lookup(vec![
+            element("OK", 12000, 1800),
+            element("ZERO_RESULTS", 0, 0),
+        ]);
+
+        let embed = serde_json::to_value(distance_embed(&data, &destinations, "drive safe"))
+            .expect("embeds serialise");
+        assert_eq!(embed["title"], "1 Queen Street, Auckland");
+        assert_eq!(embed["footer"]["text"], "drive safe");
+
+        let fields: Vec<(&str, &str)> = embed["fields"]
+            .as_array()
+            .expect("the embed has fields")
+            .iter()
+            .map(|field| {
+                (
+                    field["name"].as_str().unwrap(),
+                    field["value"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("Work", "12000 m (30 min)"),
+                ("Gym", "no route found"),
+                (
+                    "Total travel time",
+                    "30 min across 1 destination(s), 30 min on average"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn timeouts_are_told_apart_from_bad_addresses() {
+        assert!(timed_out(&GoogleMapError::Timeout));
+        assert!(!timed_out(&GoogleMapError::InvalidAddress));
+    }
 }
@@ -2,5 +2,5 @@ pub enum EmbedColor {
     Green = 0x00FF00,
     Orange = 0xFFA500,
     Red = 0xFF0000,
-    // Blue = 0x0000FF,
+    Blue = 0x0000FF,
 }
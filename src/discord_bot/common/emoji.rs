@@ -0,0 +1,145 @@
+//! Picks an emoji to decorate an item with, based on its name.
+
+/// keywords and the emoji they map to, checked in order so more specific phrases must come first
+const ITEM_EMOJI: &[(&str, &str)] = &[
+    ("peanut butter", "🥜"),
+    ("orange juice", "🧃"),
+    ("apple juice", "🧃"),
+    ("ice cream", "🍦"),
+    ("toilet paper", "🧻"),
+    ("paper towel", "🧻"),
+    ("juice", "🧃"),
+    ("milk", "🥛"),
+    ("bread", "🍞"),
+    ("loaf", "🍞"),
+    ("bagel", "🥯"),
+    ("croissant", "🥐"),
+    ("tomato", "🍅"),
+    ("egg", "🥚"),
+    ("cheese", "🧀"),
+    ("butter", "🧈"),
+    ("chocolate", "🍫"),
+    ("coffee", "☕"),
+    ("tea", "🍵"),
+    ("pineapple", "🍍"),
+    ("apple", "🍎"),
+    ("banana", "🍌"),
+    ("orange", "🍊"),
+    ("lemon", "🍋"),
+    ("grape", "🍇"),
+    ("strawberry", "🍓"),
+    ("cherry", "🍒"),
+    ("watermelon", "🍉"),
+    ("avocado", "🥑"),
+    ("carrot", "🥕"),
+    ("potato", "🥔"),
+    ("onion", "🧅"),
+    ("garlic", "🧄"),
+    ("lettuce", "🥬"),
+    ("spinach", "🥬"),
+    ("broccoli", "🥦"),
+    ("cucumber", "🥒"),
+    ("corn", "🌽"),
+    ("mushroom", "🍄"),
+    ("capsicum", "🫑"),
+    ("chicken", "🍗"),
+    ("beef", "🥩"),
+    ("steak", "🥩"),
+    ("mince", "🥩"),
+    ("bacon", "🥓"),
+    ("fish", "🐟"),
+    ("rice", "🍚"),
+    ("pasta", "🍝"),
+    ("spaghetti", "🍝"),
+    ("flour", "🌾"),
+    ("honey", "🍯"),
+    ("salt", "🧂"),
+    ("biscuit", "🍪"),
+    ("cookie", "🍪"),
+    ("cake", "🍰"),
+    ("pizza", "🍕"),
+    ("beer", "🍺"),
+    ("wine", "🍷"),
+    ("soap", "🧼"),
+    ("toothbrush", "🪥"),
+    ("sponge", "🧽"),
+    ("battery", "🔋"),
+];
+
+/// find an emoji for an item, e.g. `🥛` for `milk 2L`. Keywords are matched against whole words
+/// so that unrelated items which merely contain a keyword (e.g. `teaspoon`) are left alone.
+pub fn item_emoji(name: &str) -> Option<&'static str> {
+    let words = normalise_words(name);
+
+    ITEM_EMOJI.iter().find_map(|(keyword, emoji)| {
+        let keyword = normalise_words(keyword);
+        words
+            .windows(keyword.len())
+            .any(|window| window == keyword.as_slice())
+            .then_some(*emoji)
+    })
+}
+
+/// split a name into lowercase singular words, so that `Cherry Tomatoes` matches `tomato`
+fn normalise_words(name: &str) -> Vec<String> {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(singular)
+        .collect()
+}
+
+/// a rough singular form of an english word, good enough for grocery names
+fn singular(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if let Some(stem) = word.strip_suffix("oes") {
+        format!("{}o", stem)
+    } else if word.len() > 3 && word.ends_with('s') && !word.ends_with("ss") {
+        word[..word.len() - 1].to_string()
+    } else {
+        word.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_are_matched_by_whole_words() {
+        assert_eq!(item_emoji("milk 2L"), Some("🥛"));
+        assert_eq!(item_emoji("Free range EGGS"), Some("🥚"));
+        assert_eq!(item_emoji("Strawberries"), Some("🍓"));
+        assert_eq!(item_emoji("sourdough loaf"), Some("🍞"));
+    }
+
+    #[test]
+    fn specific_phrases_win_over_their_words() {
+        // `tomato` comes before `cherry` in the list
+        assert_eq!(item_emoji("Cherry Tomatoes"), Some("🍅"));
+        assert_eq!(item_emoji("crunchy peanut butter"), Some("🥜"));
+        assert_eq!(item_emoji("orange juice"), Some("🧃"));
+        assert_eq!(item_emoji("pineapple chunks"), Some("🍍"));
+    }
+
+    #[test]
+    fn near_misses_are_left_alone() {
+        assert_eq!(item_emoji("teaspoon"), None);
+        assert_eq!(item_emoji("buttermilk"), None);
+        assert_eq!(item_emoji("eggplant"), None);
+        assert_eq!(item_emoji("dishwasher tablets"), None);
+        assert_eq!(item_emoji(""), None);
+    }
+
+    #[test]
+    fn plurals_are_made_singular() {
+        assert_eq!(singular("cherries"), "cherry");
+        assert_eq!(singular("potatoes"), "potato");
+        assert_eq!(singular("eggs"), "egg");
+        // short words and double s endings aren't plurals
+        assert_eq!(singular("gas"), "gas");
+        assert_eq!(singular("glass"), "glass");
+        assert_eq!(singular("milk"), "milk");
+    }
+}
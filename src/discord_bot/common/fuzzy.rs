@@ -0,0 +1,123 @@
+//! Loose matching of item names, to catch near-duplicates such as `tomatos` and `tomatoes`, and
+//! misspelled searches such as `brocoli`.
+
+use crate::discord_bot::database::shopping::ShoppingListItemModel;
+
+/// how similar two names must be, from 0 to 1, before they are treated as the same item
+pub const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// the number of single character insertions, deletions or substitutions needed to turn `a`
+/// into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+
+    // only the previous row of the table is needed to fill in the next
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// how alike two item names are, from 0 for nothing in common to 1 for the same name, ignoring
+/// case and surrounding whitespace
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = a.trim().to_lowercase();
+    let b = b.trim().to_lowercase();
+
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    1.0 - edit_distance(&a, &b) as f64 / longest as f64
+}
+
+/// how many typos a search may have and still match a name, none for searches too short to tell a
+/// typo from a different word
+pub fn typo_allowance(search: &str) -> usize {
+    search.trim().chars().count() / 3
+}
+
+/// the fewest typos in `search` for it to match `name`, ignoring case. The search is compared with
+/// the whole name and each of its words, as well as the start of each, so that a word which has
+/// only been partly typed still matches
+pub fn search_distance(search: &str, name: &str) -> usize {
+    let search = search.trim().to_lowercase();
+    let name = name.to_lowercase();
+    let length = search.chars().count();
+
+    std::iter::once(name.as_str())
+        .chain(name.split_whitespace())
+        .flat_map(|word| {
+            let start: String = word.chars().take(length).collect();
+            [edit_distance(&search, word), edit_distance(&search, &start)]
+        })
+        .min()
+        .unwrap_or(usize::MAX)
+}
+
+/// the item whose name is most like `name`, if any is similar enough to be a likely duplicate
+pub fn closest_match<'a>(
+    name: &str,
+    items: &'a [ShoppingListItemModel],
+) -> Option<&'a ShoppingListItemModel> {
+    items
+        .iter()
+        .map(|item| (similarity(name, &item.item), item))
+        .filter(|(score, _)| *score >= SIMILARITY_THRESHOLD)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, item)| item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distance_counts_single_character_changes() {
+        assert_eq!(edit_distance("milk", "milk"), 0);
+        assert_eq!(edit_distance("", "milk"), 4);
+        assert_eq!(edit_distance("milk", ""), 4);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("tomatos", "tomatoes"), 1);
+        // characters rather than bytes
+        assert_eq!(edit_distance("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn short_searches_allow_no_typos() {
+        assert_eq!(typo_allowance(""), 0);
+        assert_eq!(typo_allowance("ab"), 0);
+        assert_eq!(typo_allowance("milk"), 1);
+        assert_eq!(typo_allowance("  milk  "), 1);
+        assert_eq!(typo_allowance("brocoli"), 2);
+    }
+
+    #[test]
+    fn search_distance_matches_words_and_their_starts() {
+        assert_eq!(search_distance("brocoli", "Broccoli"), 1);
+        assert_eq!(search_distance("MILK", "milk"), 0);
+        // the start of a later word
+        assert_eq!(search_distance("choc", "milk chocolate"), 0);
+        assert_eq!(search_distance("chocolat", "dark chocolate bar"), 0);
+        assert_eq!(search_distance("tomatoe", "tomato paste"), 1);
+        assert_eq!(search_distance("xyz", "milk"), 3);
+    }
+
+    #[test]
+    fn similarity_ignores_case_and_whitespace() {
+        assert_eq!(similarity(" Milk ", "milk"), 1.0);
+        assert_eq!(similarity("", ""), 1.0);
+        assert!(similarity("tomatos", "tomatoes") >= SIMILARITY_THRESHOLD);
+        assert!(similarity("milk", "bread") < SIMILARITY_THRESHOLD);
+    }
+}
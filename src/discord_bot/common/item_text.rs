@@ -0,0 +1,197 @@
+//! Cleaning up item names before they are stored, so that invisible or unusual characters can't
+//! garble how an item is shown or stop it matching the same item typed normally.
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::state::{CharMode, ItemCharset};
+
+/// punctuation allowed in item names by the `text` charset, on top of letters, numbers and spaces
+const TEXT_PUNCTUATION: &[char] = &[
+    '\'', '"', '-', '&', '.', ',', '(', ')', '/', '%', '+', '!', '?', ':', '#', '*',
+];
+
+/// characters which take up no space or change the direction of text, and are never allowed
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{FEFF}'
+    )
+}
+
+/// the zero width joiner, which joins emoji such as the members of 👨‍👩‍👧 into one
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// whether a character could be part of an emoji, e.g. a pictograph, a skin tone or a variation
+/// selector, rather than a letter, digit, space or anything invisible
+fn is_emoji_part(c: char) -> bool {
+    !c.is_ascii()
+        && !c.is_alphanumeric()
+        && !c.is_whitespace()
+        && !c.is_control()
+        && c != ZERO_WIDTH_JOINER
+        && !is_invisible(c)
+}
+
+/// whether the character at `i` is a zero width joiner between two parts of an emoji, which is
+/// kept so the emoji isn't split apart. A joiner anywhere else is as invisible as any other
+fn joins_emoji(chars: &[char], i: usize) -> bool {
+    chars[i] == ZERO_WIDTH_JOINER
+        && i > 0
+        && is_emoji_part(chars[i - 1])
+        && chars.get(i + 1).map_or(false, |c| is_emoji_part(*c))
+}
+
+/// whether a character may appear in an item name under a charset
+fn is_allowed(c: char, charset: ItemCharset) -> bool {
+    if c.is_control() || is_invisible(c) {
+        return false;
+    }
+    match charset {
+        ItemCharset::Any => true,
+        ItemCharset::Text => c.is_alphanumeric() || c == ' ' || TEXT_PUNCTUATION.contains(&c),
+    }
+}
+
+/// normalise an item name to NFC, so that `é` written as `e` and a combining accent is the same
+/// item as `é` written as one character, then strip or reject the characters it isn't allowed.
+/// Any whitespace counts as a space, and runs of them are collapsed into one. Emoji joined with a
+/// zero width joiner are kept whole where emoji are allowed
+pub fn clean_item_name(name: &str, charset: ItemCharset, mode: CharMode) -> Result<String, String> {
+    let chars: Vec<char> = name
+        .nfc()
+        .map(|c| match c.is_whitespace() {
+            true => ' ',
+            false => c,
+        })
+        .collect();
+    let allowed = |i: usize| {
+        is_allowed(chars[i], charset) || (charset == ItemCharset::Any && joins_emoji(&chars, i))
+    };
+
+    if mode == CharMode::Reject {
+        if let Some(c) = (0..chars.len()).find(|i| !allowed(*i)).map(|i| chars[i]) {
+            return Err(match c.is_control() || is_invisible(c) {
+                true => {
+                    "the item name contains an invisible character, please retype it".to_string()
+                }
+                false => format!(
+                    "the item name contains `{}`, which isn't allowed in this server",
+                    c
+                ),
+            });
+        }
+    }
+
+    let cleaned = (0..chars.len())
+        .filter(|i| allowed(*i))
+        .map(|i| chars[i])
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    match cleaned.is_empty() {
+        true => Err("the item name is blank once unsupported characters are removed".to_string()),
+        false => Ok(cleaned),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(name: &str, charset: ItemCharset) -> Result<String, String> {
+        clean_item_name(name, charset, CharMode::Strip)
+    }
+
+    fn reject(name: &str, charset: ItemCharset) -> Result<String, String> {
+        clean_item_name(name, charset, CharMode::Reject)
+    }
+
+    #[test]
+    fn names_are_normalised() {
+        // `e` and a combining accent becomes the single character `é`
+        assert_eq!(
+            strip("cafe\u{301} latte", ItemCharset::Any),
+            Ok("caf\u{e9} latte".to_string())
+        );
+        assert_eq!(
+            strip("  oat\tmilk \n\u{a0} 2L ", ItemCharset::Any),
+            Ok("oat milk 2L".to_string())
+        );
+        assert_eq!(
+            reject("cafe\u{301}", ItemCharset::Text),
+            Ok("caf\u{e9}".to_string())
+        );
+    }
+
+    #[test]
+    fn invisible_characters_are_never_allowed() {
+        for name in [
+            "mi\u{200b}lk",
+            "mi\u{200d}lk",
+            "\u{200d}milk",
+            "\u{202e}milk",
+            "milk\u{feff}",
+            "mi\u{7}lk",
+        ] {
+            assert_eq!(strip(name, ItemCharset::Any), Ok("milk".to_string()));
+            let refused = reject(name, ItemCharset::Any).unwrap_err();
+            assert!(refused.contains("invisible"), "{}", refused);
+        }
+    }
+
+    #[test]
+    fn any_charset_allows_emoji_and_symbols() {
+        assert_eq!(
+            reject("🥛 milk <3", ItemCharset::Any),
+            Ok("🥛 milk <3".to_string())
+        );
+    }
+
+    #[test]
+    fn joined_emoji_are_kept_whole() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        let rainbow = "\u{1f3f3}\u{fe0f}\u{200d}\u{1f308}";
+        for name in [family, rainbow] {
+            let snacks = format!("{} snacks", name);
+            assert_eq!(strip(&snacks, ItemCharset::Any), Ok(snacks.clone()));
+            assert_eq!(reject(&snacks, ItemCharset::Any), Ok(snacks.clone()));
+            assert_eq!(strip(&snacks, ItemCharset::Text), Ok("snacks".to_string()));
+        }
+
+        // a joiner with nothing to join on one side is stripped
+        assert_eq!(
+            strip("🥛\u{200d} milk", ItemCharset::Any),
+            Ok("🥛 milk".to_string())
+        );
+        assert!(reject("🥛\u{200d}milk", ItemCharset::Any).is_err());
+    }
+
+    #[test]
+    fn text_charset_allows_letters_numbers_and_common_punctuation() {
+        for name in ["Pack'n'Save (2-pack) 50%", "牛乳", "fish & chips, #2!"] {
+            assert_eq!(reject(name, ItemCharset::Text), Ok(name.to_string()));
+        }
+
+        assert_eq!(
+            strip("🥛 milk <@123>", ItemCharset::Text),
+            Ok("milk 123".to_string())
+        );
+        let refused = reject("milk 🥛", ItemCharset::Text).unwrap_err();
+        assert!(refused.contains("`🥛`"), "{}", refused);
+    }
+
+    #[test]
+    fn names_left_blank_are_refused() {
+        assert!(strip("\u{200b} \t", ItemCharset::Any).is_err());
+        assert!(strip("🥛🥚", ItemCharset::Text).is_err());
+        assert!(strip("", ItemCharset::Any).is_err());
+    }
+}
@@ -1,2 +1,39 @@
+#[cfg(feature = "shopping")]
+pub mod bulk;
+#[cfg(feature = "shopping")]
+pub mod card;
+#[cfg(feature = "shopping")]
+pub mod custom_id;
+#[cfg(feature = "shopping")]
+pub mod digest;
+#[cfg(feature = "maps")]
 pub mod distance;
 pub mod embed;
+#[cfg(feature = "shopping")]
+pub mod emoji;
+#[cfg(feature = "shopping")]
+pub mod fuzzy;
+#[cfg(feature = "shopping")]
+pub mod item_text;
+#[cfg(feature = "shopping")]
+pub mod pin;
+#[cfg(feature = "shopping")]
+pub mod private_thread;
+#[cfg(feature = "shopping")]
+pub mod product_link;
+#[cfg(feature = "shopping")]
+pub mod quantity;
+#[cfg(feature = "shopping")]
+pub mod quick_edit;
+#[cfg(feature = "shopping")]
+pub mod reminder;
+#[cfg(feature = "shopping")]
+pub mod schedule;
+#[cfg(feature = "shopping")]
+pub mod shopping;
+#[cfg(feature = "shopping")]
+pub mod status;
+#[cfg(feature = "shopping")]
+pub mod validation;
+#[cfg(feature = "shopping")]
+pub mod word_filter;
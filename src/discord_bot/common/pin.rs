@@ -0,0 +1,165 @@
+//! Keeps the most urgent item on the shopping list pinned in a channel.
+
+use chrono::{Local, TimeZone};
+use log::error;
+use serenity::{
+    all::{ChannelId, MessageId},
+    builder::{CreateEmbed, CreateMessage},
+    http::HttpError,
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::database::{
+        pins::SerenityPinDatabase,
+        shopping::{SerenityShoppingDatabase, ShoppingListItemModel},
+    },
+    state::AppState,
+};
+
+use super::{embed::EmbedColor, shopping::describe_item};
+
+/// the error code discord returns when a channel already has the maximum number of pins
+const MAX_PINS_REACHED: isize = 30003;
+
+/// the outcome of refreshing the pinned item of a guild
+#[derive(Debug, PartialEq, Eq)]
+pub enum PinOutcome {
+    /// pinning hasn't been set up for the guild
+    Disabled,
+    /// the top item is unchanged, so nothing was done
+    Unchanged,
+    /// the new top item was pinned
+    Pinned,
+    /// the list is empty, so the previous pin was removed
+    Cleared,
+    /// the top item was posted, but the channel already has as many pins as discord allows
+    TooManyPins,
+}
+
+/// the message pinned for the top item, linking back to where it was added
+fn top_item_message(guild_id: u64, item: &ShoppingListItemModel) -> CreateMessage {
+    let mut description = describe_item(item);
+    if let Some(channel_id) = item.channel_id {
+        description.push_str(&format!(
+            "\n[jump to item](https://discord.com/channels/{}/{}/{})",
+            guild_id, channel_id, item.message_id
+        ));
+    }
+    if let Some(added) = Local.from_local_datetime(&item.created_at).earliest() {
+        description.push_str(&format!("\nadded <t:{}:R>", added.timestamp()));
+    }
+
+    CreateMessage::new().embed(
+        CreateEmbed::new()
+            .title("Top of the shopping list")
+            .description(description)
+            .color(EmbedColor::Red as u32),
+    )
+}
+
+/// whether an error was caused by the channel already having the maximum number of pins
+fn is_max_pins(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(http) => {
+            let http: &HttpError = http;
+            matches!(http, HttpError::UnsuccessfulRequest(r) if r.error.code == MAX_PINS_REACHED)
+        }
+        _ => false,
+    }
+}
+
+/// unpin and delete a previously pinned message, failures are only logged as the message may
+/// already have been removed by hand
+async fn remove_pin_message(ctx: &Context, channel_id: ChannelId, message_id: MessageId) {
+    if let Err(e) = channel_id.unpin(ctx, message_id).await {
+        error!("error unpinning top item message {}: {}", message_id, e);
+    }
+    if let Err(e) = channel_id.delete_message(ctx, message_id).await {
+        error!("error deleting top item message {}: {}", message_id, e);
+    }
+}
+
+/// start pinning the top item of the guild in a channel, moving the pin if it was previously
+/// kept in another channel
+pub async fn pin_in_channel(
+    guild_id: u64,
+    channel_id: u64,
+    ctx: &Context,
+    state: &AppState,
+) -> Result<PinOutcome, Box<dyn std::error::Error + Send + Sync>> {
+    match state.get_pin(guild_id).await? {
+        Some(pin) if pin.channel_id as u64 != channel_id => {
+            if let Some(message_id) = pin.message_id {
+                remove_pin_message(
+                    ctx,
+                    ChannelId::new(pin.channel_id as u64),
+                    MessageId::new(message_id as u64),
+                )
+                .await;
+            }
+            state.set_pin(guild_id, channel_id, None, None).await?;
+        }
+        Some(_) => {}
+        None => state.set_pin(guild_id, channel_id, None, None).await?,
+    }
+
+    refresh_pin(guild_id, ctx, state, true).await
+}
+
+/// re-pin the top item of the guild if it has changed since it was last pinned, or always when
+/// `force` is set
+pub async fn refresh_pin(
+    guild_id: u64,
+    ctx: &Context,
+    state: &AppState,
+    force: bool,
+) -> Result<PinOutcome, Box<dyn std::error::Error + Send + Sync>> {
+    let pin = match state.get_pin(guild_id).await? {
+        Some(pin) => pin,
+        None => return Ok(PinOutcome::Disabled),
+    };
+
+    let top = state.get_top_item(guild_id).await?;
+    if !force && top.as_ref().map(|i| i.id) == pin.item_id {
+        return Ok(PinOutcome::Unchanged);
+    }
+
+    // the old message is replaced rather than edited, so that the new pin is announced
+    let channel_id = ChannelId::new(pin.channel_id as u64);
+    if let Some(message_id) = pin.message_id {
+        remove_pin_message(ctx, channel_id, MessageId::new(message_id as u64)).await;
+    }
+
+    let item = match top {
+        Some(item) => item,
+        None => {
+            state
+                .set_pin(guild_id, channel_id.into(), None, None)
+                .await?;
+            return Ok(PinOutcome::Cleared);
+        }
+    };
+
+    let message = channel_id
+        .send_message(ctx, top_item_message(guild_id, &item))
+        .await?;
+    let pinned = message.pin(ctx).await;
+
+    // the message is recorded even if it couldn't be pinned, so it is cleaned up next time and
+    // pinning isn't retried every tick until the top item changes
+    state
+        .set_pin(
+            guild_id,
+            channel_id.into(),
+            Some(message.id.into()),
+            Some(item.id),
+        )
+        .await?;
+
+    match pinned {
+        Ok(()) => Ok(PinOutcome::Pinned),
+        Err(e) if is_max_pins(&e) => Ok(PinOutcome::TooManyPins),
+        Err(e) => Err(e.into()),
+    }
+}
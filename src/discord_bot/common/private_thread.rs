@@ -0,0 +1,189 @@
+//! Keeping personal items out of shared channels, by posting them in a private thread for each
+//! user which only they and the bot can see.
+
+use std::future::Future;
+
+use log::error;
+use serenity::{
+    all::{ChannelId, ChannelType, UserId},
+    builder::CreateThread,
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::database::private_threads::SerenityPrivateThreadDatabase,
+    state::{AppState, GuildConfig, ListMode, CONFIG},
+};
+
+/// the longest thread name discord allows
+const MAX_THREAD_NAME: usize = 100;
+
+type ThreadResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// whether an item should be posted in its user's private thread rather than the channel. Items
+/// in a digest have no message of their own to move, and threads only exist within a guild
+pub fn routes_to_private_thread(guild_id: Option<u64>, personal: bool) -> bool {
+    routes_with(CONFIG.guild(guild_id), guild_id, personal)
+}
+
+/// [routes_to_private_thread] with the settings of the guild given
+fn routes_with(config: &GuildConfig, guild_id: Option<u64>, personal: bool) -> bool {
+    personal
+        && guild_id.is_some()
+        && config.personal_threads
+        && config.list_mode == ListMode::Classic
+}
+
+/// the private thread a user's personal items from a channel are posted in, created and the user
+/// added to it the first time they need one
+pub async fn find_or_create_private_thread(
+    ctx: &Context,
+    state: &AppState,
+    guild_id: u64,
+    channel_id: u64,
+    user_id: u64,
+    user_name: &str,
+) -> ThreadResult<u64> {
+    if let Some(thread) = state
+        .get_user_private_thread(guild_id, channel_id, user_id)
+        .await?
+    {
+        return Ok(thread);
+    }
+
+    let name: String = format!("{}'s personal items", user_name)
+        .chars()
+        .take(MAX_THREAD_NAME)
+        .collect();
+    let thread = ChannelId::new(channel_id)
+        .create_thread(
+            ctx,
+            CreateThread::new(name)
+                .kind(ChannelType::PrivateThread)
+                .invitable(false),
+        )
+        .await?;
+
+    set_up_created_thread(
+        thread.id.into(),
+        || async move {
+            thread
+                .id
+                .add_thread_member(ctx, UserId::new(user_id))
+                .await
+                .map_err(Into::into)
+        },
+        |thread| state.set_user_private_thread(guild_id, channel_id, user_id, thread),
+        |thread| async move {
+            if let Err(e) = ChannelId::new(thread).delete(ctx).await {
+                error!("unable to delete private thread {}: {}", thread, e);
+            }
+        },
+    )
+    .await
+}
+
+/// finish setting up a thread which has just been created, adding its user with `add_member` then
+/// storing it with `store`. A thread which isn't stored is never used again, and one its user
+/// isn't in is no use to them, so if either fails the thread is deleted with `delete` rather than
+/// left behind, and the failure returned
+async fn set_up_created_thread<A, AFut, S, SFut, D, DFut>(
+    thread: u64,
+    add_member: A,
+    store: S,
+    delete: D,
+) -> ThreadResult<u64>
+where
+    A: FnOnce() -> AFut,
+    AFut: Future<Output = ThreadResult<()>>,
+    S: FnOnce(u64) -> SFut,
+    SFut: Future<Output = ThreadResult<()>>,
+    D: FnOnce(u64) -> DFut,
+    DFut: Future<Output = ()>,
+{
+    let set_up = async {
+        add_member().await?;
+        store(thread).await
+    };
+
+    match set_up.await {
+        Ok(()) => Ok(thread),
+        Err(e) => {
+            delete(thread).await;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// set up thread 7 with the member added and the thread stored succeeding or failing as
+    /// given, returning the outcome, whether it was stored and the threads deleted
+    async fn set_up(add_member: bool, store: bool) -> (ThreadResult<u64>, bool, Vec<u64>) {
+        let stored = &Mutex::new(false);
+        let deleted = &Mutex::new(Vec::new());
+        let result = set_up_created_thread(
+            7,
+            || async move {
+                match add_member {
+                    true => Ok(()),
+                    false => Err("missing permissions".into()),
+                }
+            },
+            |_| async move {
+                match store {
+                    true => {
+                        *stored.lock().unwrap() = true;
+                        Ok(())
+                    }
+                    false => Err("the database is down".into()),
+                }
+            },
+            |thread| async move { deleted.lock().unwrap().push(thread) },
+        )
+        .await;
+
+        let deleted = deleted.lock().unwrap().clone();
+        (result, *stored.lock().unwrap(), deleted)
+    }
+
+    #[tokio::test]
+    async fn a_thread_which_cant_be_set_up_is_deleted() {
+        let (result, stored, deleted) = set_up(true, true).await;
+        assert_eq!(result.unwrap(), 7);
+        assert!(stored);
+        assert!(deleted.is_empty());
+
+        // the user couldn't be added, so the thread is never stored
+        let (result, stored, deleted) = set_up(false, true).await;
+        assert!(result.is_err());
+        assert!(!stored);
+        assert_eq!(deleted, [7]);
+
+        // nothing would find the thread again to use it
+        let (result, _, deleted) = set_up(true, false).await;
+        assert!(result.is_err());
+        assert_eq!(deleted, [7]);
+    }
+
+    #[test]
+    fn only_personal_items_in_a_guild_with_threads_are_routed() {
+        let mut config = GuildConfig::default();
+        assert!(!routes_with(&config, Some(1), true), "threads are off");
+
+        config.personal_threads = true;
+        assert!(routes_with(&config, Some(1), true));
+        assert!(!routes_with(&config, Some(1), false), "a shared item");
+        assert!(!routes_with(&config, None, true), "outside of a guild");
+
+        config.list_mode = ListMode::Digest;
+        assert!(
+            !routes_with(&config, Some(1), true),
+            "a digest has no item messages"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! Checking the link to an item's product page, such as the supermarket's online listing, before
+//! it is stored and shown as a button on the item.
+
+use reqwest::Url;
+
+/// the longest link accepted, discord allows at most 512 characters in the link of a button
+pub const MAX_PRODUCT_URL: usize = 512;
+
+/// check that a product link is a web address which can be shown as a button, returning it as it
+/// will be stored
+pub fn clean_product_url(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    if url.len() > MAX_PRODUCT_URL {
+        return Err(format!(
+            "the product link must be at most {} characters",
+            MAX_PRODUCT_URL
+        ));
+    }
+
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            return Err(format!(
+                "`{}` isn't a link, it should look like https://example.com/product",
+                url
+            ))
+        }
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("the product link must start with http:// or https://".to_string());
+    }
+    if parsed.host_str().map_or(true, str::is_empty) {
+        return Err(format!("`{}` doesn't link to a website", url));
+    }
+
+    // parsing can lengthen a link, e.g. by escaping characters in it
+    let cleaned = parsed.to_string();
+    match cleaned.len() > MAX_PRODUCT_URL {
+        true => Err(format!(
+            "the product link must be at most {} characters",
+            MAX_PRODUCT_URL
+        )),
+        false => Ok(cleaned),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discord_bot::common::card::ShoppingCard;
+
+    #[test]
+    fn links_which_cant_be_buttons_are_rejected() {
+        for url in [
+            "milk",
+            "ftp://example.com/milk",
+            "javascript:alert(1)",
+            "https://",
+        ] {
+            assert!(clean_product_url(url).is_err(), "{}", url);
+        }
+        let long = format!("https://example.com/{}", "a".repeat(MAX_PRODUCT_URL));
+        assert!(clean_product_url(&long).is_err());
+    }
+
+    #[test]
+    fn a_valid_link_is_shown_as_a_button() {
+        let url = clean_product_url(" https://shop.example.com/milk?size=2l ").unwrap();
+        assert_eq!(url, "https://shop.example.com/milk?size=2l");
+
+        let card = ShoppingCard::new("milk", 1).product_url(Some(&url));
+        let button = serde_json::to_value(card.product_button().unwrap()).unwrap();
+        assert_eq!(button["url"], url.as_str());
+        assert_eq!(button["label"], "View product");
+        assert!(ShoppingCard::new("milk", 1).product_button().is_none());
+    }
+}
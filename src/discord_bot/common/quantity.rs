@@ -0,0 +1,190 @@
+//! Working out the quantity of a new item when it isn't given outright, from the places a
+//! quantity can come from in order of precedence.
+
+use std::collections::HashMap;
+
+/// the quantity used when nothing else gives one
+pub const FALLBACK_QUANTITY: i64 = 1;
+
+/// the largest quantity which can be set or added at once, from a quick edit or the set quantity
+/// modal
+pub const MAX_QUANTITY: i64 = 999_999;
+
+/// the most recent adds of an item looked at when finding a user's typical quantity of it
+pub const TYPICAL_SAMPLE: usize = 5;
+
+/// the number of times a quantity must appear in the sample to be a user's typical quantity, so
+/// that a one-off bulk buy isn't repeated on every add
+const TYPICAL_MIN_OCCURRENCES: usize = 2;
+
+/// words which may be used in place of a quantity, longest first so that `half dozen` isn't read
+/// as `dozen`
+const QUANTITY_WORDS: &[(&str, i64)] = &[
+    ("half a dozen", 6),
+    ("half dozen", 6),
+    ("dozen", 12),
+    ("couple", 2),
+    ("pair", 2),
+];
+
+/// where the quantity of a new item came from, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuantitySource {
+    /// the quantity option of the command
+    Explicit,
+    /// a quantity written as words at the start of the item, e.g. `a dozen eggs`
+    Parsed,
+    /// the quantity the user usually adds the item with
+    Typical,
+    /// the default quantity of the guild
+    GuildDefault,
+    /// none of the above, so [FALLBACK_QUANTITY]
+    Fallback,
+}
+
+/// the quantities a new item could be given, each from a different [QuantitySource]. Sources
+/// which gave nothing, or gave a quantity below 1, are passed over
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QuantityCandidates {
+    pub explicit: Option<i64>,
+    pub parsed: Option<i64>,
+    pub typical: Option<i64>,
+    pub guild_default: Option<i64>,
+}
+
+impl QuantityCandidates {
+    /// the quantity to use and where it came from, taking the first source in order of
+    /// precedence which gave one
+    pub fn resolve(&self) -> (i64, QuantitySource) {
+        [
+            (self.explicit, QuantitySource::Explicit),
+            (self.parsed, QuantitySource::Parsed),
+            (self.typical, QuantitySource::Typical),
+            (self.guild_default, QuantitySource::GuildDefault),
+        ]
+        .into_iter()
+        .find_map(|(quantity, source)| quantity.filter(|q| *q > 0).map(|q| (q, source)))
+        .unwrap_or((FALLBACK_QUANTITY, QuantitySource::Fallback))
+    }
+}
+
+/// the quantity a user usually adds an item with, given the quantities of their most recent adds
+/// of it, most recent first. Ties go to the quantity used most recently
+pub fn typical_quantity(recent: &[i64]) -> Option<i64> {
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for quantity in recent.iter().take(TYPICAL_SAMPLE) {
+        *counts.entry(*quantity).or_default() += 1;
+    }
+
+    let most = counts.values().copied().max()?;
+    if most < TYPICAL_MIN_OCCURRENCES {
+        return None;
+    }
+
+    recent
+        .iter()
+        .take(TYPICAL_SAMPLE)
+        .find(|quantity| counts[quantity] == most)
+        .copied()
+}
+
+/// strip `prefix` from the start of `text` ignoring case, if it is followed by a space
+fn strip_word_prefix<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    let rest = &text[prefix.len()..];
+    match head.eq_ignore_ascii_case(prefix) && rest.starts_with(char::is_whitespace) {
+        true => Some(rest.trim_start()),
+        false => None,
+    }
+}
+
+/// read a quantity written as words at the start of an item, e.g. `a dozen eggs` or
+/// `a pair of gloves`, returning the quantity and the rest of the item name
+pub fn parse_quantity_words(item: &str) -> Option<(i64, &str)> {
+    let item = item.trim();
+    let unprefixed = strip_word_prefix(item, "a")
+        .or_else(|| strip_word_prefix(item, "an"))
+        .unwrap_or(item);
+
+    QUANTITY_WORDS.iter().find_map(|(word, quantity)| {
+        let rest = strip_word_prefix(unprefixed, word)?;
+        let rest = strip_word_prefix(rest, "of").unwrap_or(rest);
+        Some((*quantity, rest))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantity_words_are_read_from_the_start_of_an_item() {
+        assert_eq!(parse_quantity_words("a dozen eggs"), Some((12, "eggs")));
+        assert_eq!(parse_quantity_words("dozen eggs"), Some((12, "eggs")));
+        assert_eq!(
+            parse_quantity_words("A Pair of gloves"),
+            Some((2, "gloves"))
+        );
+        assert_eq!(
+            parse_quantity_words("a couple  lemons "),
+            Some((2, "lemons"))
+        );
+        assert_eq!(parse_quantity_words("half a dozen eggs"), Some((6, "eggs")));
+        assert_eq!(parse_quantity_words("a half dozen eggs"), Some((6, "eggs")));
+    }
+
+    #[test]
+    fn unknown_phrases_are_left_alone() {
+        assert_eq!(parse_quantity_words("eggs"), None);
+        assert_eq!(parse_quantity_words("a bag of flour"), None);
+        assert_eq!(parse_quantity_words("a few apples"), None);
+        // the word must stand on its own, and be followed by the item
+        assert_eq!(parse_quantity_words("pairing knife"), None);
+        assert_eq!(parse_quantity_words("dozens of eggs"), None);
+        assert_eq!(parse_quantity_words("a dozen"), None);
+    }
+
+    #[test]
+    fn items_shorter_than_a_phrase_are_left_alone() {
+        assert_eq!(parse_quantity_words(""), None);
+        assert_eq!(parse_quantity_words("a"), None);
+        assert_eq!(parse_quantity_words("half"), None);
+        // the phrase would end part way through a character
+        assert_eq!(parse_quantity_words("pai\u{e9} eggs"), None);
+        assert_eq!(parse_quantity_words("\u{1f95a}\u{1f95a} eggs"), None);
+    }
+
+    #[test]
+    fn the_first_source_with_a_quantity_wins() {
+        let candidates = QuantityCandidates {
+            explicit: None,
+            parsed: Some(12),
+            typical: Some(2),
+            guild_default: Some(3),
+        };
+        assert_eq!(candidates.resolve(), (12, QuantitySource::Parsed));
+
+        let candidates = QuantityCandidates {
+            explicit: Some(0),
+            parsed: None,
+            typical: Some(-1),
+            guild_default: Some(3),
+        };
+        assert_eq!(candidates.resolve(), (3, QuantitySource::GuildDefault));
+
+        assert_eq!(
+            QuantityCandidates::default().resolve(),
+            (FALLBACK_QUANTITY, QuantitySource::Fallback)
+        );
+    }
+
+    #[test]
+    fn typical_quantities_need_repeating() {
+        assert_eq!(typical_quantity(&[]), None);
+        assert_eq!(typical_quantity(&[6, 2, 3]), None);
+        assert_eq!(typical_quantity(&[1, 2, 2, 3]), Some(2));
+        // ties go to the most recent, and only the sample is looked at
+        assert_eq!(typical_quantity(&[3, 2, 2, 3]), Some(3));
+        assert_eq!(typical_quantity(&[1, 2, 3, 4, 5, 1]), None);
+    }
+}
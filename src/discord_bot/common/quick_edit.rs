@@ -0,0 +1,101 @@
+//! Reading a short free-text edit of an item, such as `make it 3` or `at Countdown`, into the
+//! change it asks for.
+
+use super::quantity::MAX_QUANTITY;
+
+/// the phrases understood, shown when an edit can't be read
+pub const QUICK_EDIT_USAGE: &str =
+    "try `make it 3`, `add 2`, `at Countdown` or `no store`, one change at a time";
+
+/// a change to an item asked for in plain words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickEdit {
+    /// `make it 3`, `set to 3`, `x3` or just `3`
+    SetQuantity(i64),
+    /// `add 2`, `+2` or `2 more`
+    AddQuantity(i64),
+    /// `at Countdown` or `from Countdown`
+    SetStore(String),
+    /// `no store` or `anywhere`
+    ClearStore,
+}
+
+/// strip a prefix from text regardless of case, the prefixes are all ascii
+fn strip_prefix_ci<'t>(text: &'t str, prefix: &str) -> Option<&'t str> {
+    match text.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&text[prefix.len()..]),
+        _ => None,
+    }
+}
+
+/// strip a suffix from text regardless of case, the suffixes are all ascii
+fn strip_suffix_ci<'t>(text: &'t str, suffix: &str) -> Option<&'t str> {
+    let start = text.len().checked_sub(suffix.len())?;
+    match text.get(start..) {
+        Some(end) if end.eq_ignore_ascii_case(suffix) => Some(&text[..start]),
+        _ => None,
+    }
+}
+
+/// read a quantity, which must be a positive whole number
+fn parse_quantity(text: &str) -> Result<i64, String> {
+    match text.trim().parse::<i64>() {
+        Ok(quantity) if (1..=MAX_QUANTITY).contains(&quantity) => Ok(quantity),
+        Ok(_) => Err(format!("the quantity must be from 1 to {}", MAX_QUANTITY)),
+        Err(_) => Err(format!(
+            "`{}` isn't a whole number, {}",
+            text.trim(),
+            QUICK_EDIT_USAGE
+        )),
+    }
+}
+
+/// read a quick edit, rejecting anything which could be read more than one way
+pub fn parse_quick_edit(input: &str) -> Result<QuickEdit, String> {
+    let text = input.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let text = text.trim_end_matches(['.', '!']);
+    if text.is_empty() {
+        return Err(format!("enter a change, {}", QUICK_EDIT_USAGE));
+    }
+
+    if ["no store", "any store", "anywhere"]
+        .iter()
+        .any(|phrase| text.eq_ignore_ascii_case(phrase))
+    {
+        return Ok(QuickEdit::ClearStore);
+    }
+
+    for prefix in ["at ", "from "] {
+        if let Some(store) = strip_prefix_ci(text, prefix).map(str::trim) {
+            // `at 3` could be a store or a quantity
+            if store.is_empty() || store.parse::<i64>().is_ok() {
+                return Err(format!("`{}` isn't clear, {}", text, QUICK_EDIT_USAGE));
+            }
+            return Ok(QuickEdit::SetStore(store.to_string()));
+        }
+    }
+
+    for prefix in ["add ", "+"] {
+        if let Some(rest) = strip_prefix_ci(text, prefix) {
+            let rest = strip_suffix_ci(rest, " more").unwrap_or(rest);
+            return parse_quantity(rest).map(QuickEdit::AddQuantity);
+        }
+    }
+    if let Some(rest) = strip_suffix_ci(text, " more") {
+        return parse_quantity(rest).map(QuickEdit::AddQuantity);
+    }
+
+    for prefix in ["make it ", "set it to ", "set to ", "change to ", "x"] {
+        if let Some(rest) = strip_prefix_ci(text, prefix) {
+            return parse_quantity(rest).map(QuickEdit::SetQuantity);
+        }
+    }
+    if text.parse::<i64>().is_ok() {
+        return parse_quantity(text).map(QuickEdit::SetQuantity);
+    }
+
+    Err(format!(
+        "I didn't understand `{}`, {}",
+        text, QUICK_EDIT_USAGE
+    ))
+}
@@ -0,0 +1,72 @@
+//! Reminding a user about an item on the shopping list at a time they chose.
+
+use serenity::{
+    all::{ChannelId, MessageFlags, UserId},
+    builder::{CreateEmbed, CreateMessage},
+    prelude::Context,
+};
+
+use crate::discord_bot::database::shopping::ShoppingListItemModel;
+
+use super::{embed::EmbedColor, shopping::describe_item};
+
+/// the reminder sent about an item, linking back to its message
+fn reminder_embed(guild_id: u64, item: &ShoppingListItemModel) -> CreateEmbed {
+    let mut description = describe_item(item);
+    if let Some(channel_id) = item.channel_id {
+        description.push_str(&format!(
+            "\n[jump to item](https://discord.com/channels/{}/{}/{})",
+            guild_id, channel_id, item.message_id
+        ));
+    }
+
+    CreateEmbed::new()
+        .title("Reminder: still on the shopping list")
+        .description(description)
+        .color(EmbedColor::Red as u32)
+}
+
+/// a new message, which doesn't notify anyone when `silent`
+fn new_message(silent: bool) -> CreateMessage {
+    match silent {
+        true => CreateMessage::new().flags(MessageFlags::SUPPRESS_NOTIFICATIONS),
+        false => CreateMessage::new(),
+    }
+}
+
+/// remind a user about an item, by direct message where possible. Users who don't accept direct
+/// messages from the server are pinged in the channel the item was posted in instead. A `silent`
+/// reminder is sent without notifying the user, e.g. during quiet hours
+pub async fn send_reminder(
+    ctx: &Context,
+    guild_id: u64,
+    user_id: u64,
+    item: &ShoppingListItemModel,
+    silent: bool,
+) -> Result<(), serenity::Error> {
+    let dm = match UserId::new(user_id).create_dm_channel(ctx).await {
+        Ok(channel) => {
+            channel
+                .send_message(
+                    ctx,
+                    new_message(silent).embed(reminder_embed(guild_id, item)),
+                )
+                .await
+        }
+        Err(e) => Err(e),
+    };
+
+    match (dm, item.channel_id) {
+        (Ok(_), _) => Ok(()),
+        (Err(_), Some(channel_id)) => ChannelId::new(channel_id as u64)
+            .send_message(
+                ctx,
+                new_message(silent)
+                    .content(format!("<@{}>", user_id))
+                    .embed(reminder_embed(guild_id, item)),
+            )
+            .await
+            .map(|_| ()),
+        (Err(e), None) => Err(e),
+    }
+}
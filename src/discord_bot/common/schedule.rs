@@ -0,0 +1,382 @@
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+/// the local time of day that recurring items are posted
+const POST_HOUR: u32 = 8;
+
+/// explains the accepted schedule formats, shown when a schedule fails to parse
+pub const SCHEDULE_USAGE: &str =
+    "schedules look like `daily`, `every 3 days`, `every 2 weeks`, `weekly mon,thu`, `monthly 1` or `monthly last`";
+
+/// explains the accepted reminder times, shown when a reminder time fails to parse
+pub const REMINDER_USAGE: &str =
+    "reminder times look like `5pm`, `5:30pm`, `17:30`, `tomorrow 9am` or `in 2 hours`";
+
+/// the furthest ahead a reminder can be set
+const MAX_REMINDER_MINUTES: i64 = 7 * 24 * 60;
+
+/// when a recurring item should be added to the shopping list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// every n days
+    Days(u32),
+    /// on each of the given days of the week
+    Weekdays(Vec<Weekday>),
+    /// on the given day of every month, clamped to the end of shorter months, `None` is the last day
+    DayOfMonth(Option<u32>),
+}
+
+impl Schedule {
+    /// parse a schedule specification, see [`SCHEDULE_USAGE`] for the accepted formats
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim().to_lowercase();
+        let mut words = spec.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        let rest: Vec<&str> = words.collect();
+
+        let schedule = match (keyword, rest.as_slice()) {
+            ("daily", []) => Schedule::Days(1),
+            ("weekly", []) => Schedule::Days(7),
+            ("every", [unit]) if matches!(*unit, "day" | "week") => {
+                Schedule::Days(interval_days(1, unit)?)
+            }
+            ("every", [count, unit]) if count.chars().all(|c| c.is_ascii_digit()) => {
+                let count: u32 = count
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a valid number", count))?;
+                Schedule::Days(interval_days(count, unit)?)
+            }
+            ("weekly" | "every", days) if !days.is_empty() => {
+                // days may be split by commas, spaces or both, e.g. `mon,thu`, `mon thu`, `mon, thu`
+                let mut weekdays = Vec::new();
+                for day in days
+                    .iter()
+                    .flat_map(|d| d.split(','))
+                    .filter(|d| !d.is_empty())
+                {
+                    let day: Weekday = day
+                        .parse()
+                        .map_err(|_| format!("`{}` is not a day of the week", day))?;
+                    if !weekdays.contains(&day) {
+                        weekdays.push(day);
+                    }
+                }
+                if weekdays.is_empty() {
+                    return Err(format!("`{}` doesn't name any days of the week", spec));
+                }
+                Schedule::Weekdays(weekdays)
+            }
+            ("monthly", ["last"]) => Schedule::DayOfMonth(None),
+            ("monthly", [day]) => match day.parse::<u32>() {
+                Ok(day) if (1..=31).contains(&day) => Schedule::DayOfMonth(Some(day)),
+                _ => return Err(format!("`{}` is not a day of the month", day)),
+            },
+            _ => return Err(format!("`{}` is not a valid schedule", spec)),
+        };
+
+        Ok(schedule)
+    }
+
+    /// whether the schedule fires on the given date
+    fn matches(&self, date: NaiveDate) -> bool {
+        match self {
+            Schedule::Days(_) => true,
+            Schedule::Weekdays(days) => days.contains(&date.weekday()),
+            Schedule::DayOfMonth(day) => {
+                date.day() == day_in_month(date.year(), date.month(), *day)
+            }
+        }
+    }
+
+    /// the first date strictly after `date` that the schedule fires on
+    pub fn next_after(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Schedule::Days(n) => date + Duration::days(*n as i64),
+            Schedule::Weekdays(_) => (1..=7)
+                .map(|i| date + Duration::days(i))
+                .find(|d| self.matches(*d))
+                .unwrap_or(date + Duration::days(7)),
+            Schedule::DayOfMonth(day) => {
+                let this_month = day_in_month(date.year(), date.month(), *day);
+                if this_month > date.day() {
+                    date.with_day(this_month).unwrap_or(date)
+                } else {
+                    let (year, month) = match date.month() {
+                        12 => (date.year() + 1, 1),
+                        m => (date.year(), m + 1),
+                    };
+                    NaiveDate::from_ymd_opt(year, month, day_in_month(year, month, *day))
+                        .unwrap_or(date)
+                }
+            }
+        }
+    }
+
+    /// the next time after `now` that the schedule fires, as a utc time. Periods which were
+    /// missed entirely (e.g. while the bot was offline) are skipped rather than caught up on.
+    pub fn next_run(&self, now: DateTime<Utc>, timezone: Tz) -> NaiveDateTime {
+        let today = now.with_timezone(&timezone).date_naive();
+        let mut date = if self.matches(today) {
+            today
+        } else {
+            self.next_after(today)
+        };
+
+        while post_time(date, timezone) <= now {
+            date = self.next_after(date);
+        }
+
+        post_time(date, timezone).naive_utc()
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Days(1) => write!(f, "every day"),
+            Schedule::Days(7) => write!(f, "every week"),
+            Schedule::Days(n) if n % 7 == 0 => write!(f, "every {} weeks", n / 7),
+            Schedule::Days(n) => write!(f, "every {} days", n),
+            Schedule::Weekdays(days) => write!(
+                f,
+                "every {}",
+                days.iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Schedule::DayOfMonth(None) => write!(f, "on the last day of each month"),
+            Schedule::DayOfMonth(Some(day)) => write!(f, "on day {} of each month", day),
+        }
+    }
+}
+
+/// convert a count of days or weeks into a number of days
+fn interval_days(count: u32, unit: &str) -> Result<u32, String> {
+    let days = match unit.trim_end_matches('s') {
+        "day" => count,
+        "week" => count.saturating_mul(7),
+        _ => {
+            return Err(format!(
+                "`{}` is not a unit of time, use days or weeks",
+                unit
+            ))
+        }
+    };
+
+    if !(1..=366).contains(&days) {
+        return Err("the interval must be between 1 day and a year".to_string());
+    }
+    Ok(days)
+}
+
+/// the number of days in the given month
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = match month {
+        12 => (year + 1, 1),
+        m => (year, m + 1),
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|d| d.pred_opt())
+        .map(|d| d.day())
+        .unwrap_or(28)
+}
+
+/// resolve a day of the month, clamping it to the length of the month
+fn day_in_month(year: i32, month: u32, day: Option<u32>) -> u32 {
+    let last = days_in_month(year, month);
+    day.map(|d| d.min(last)).unwrap_or(last)
+}
+
+/// the time recurring items are posted on the given local date
+fn post_time(date: NaiveDate, timezone: Tz) -> DateTime<Utc> {
+    local_time(
+        date,
+        NaiveTime::from_hms_opt(POST_HOUR, 0, 0).unwrap_or_default(),
+        timezone,
+    )
+}
+
+/// a local date and time in a timezone, as a utc time
+fn local_time(date: NaiveDate, time: NaiveTime, timezone: Tz) -> DateTime<Utc> {
+    let local = date.and_time(time);
+    match timezone.from_local_datetime(&local).earliest() {
+        Some(time) => time.with_timezone(&Utc),
+        // the time fell in a daylight savings gap, so treat it as utc instead
+        None => Utc.from_utc_datetime(&local),
+    }
+}
+
+/// read a time of day such as `5pm`, `5:30 pm` or `17:30`
+fn parse_time_of_day(text: &str) -> Option<NaiveTime> {
+    let text = text.replace(' ', "");
+    let (text, offset) = match (text.strip_suffix("am"), text.strip_suffix("pm")) {
+        (Some(text), _) => (text, Some(0)),
+        (_, Some(text)) => (text, Some(12)),
+        _ => (text.as_str(), None),
+    };
+
+    let (hour, minute) = match text.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        Some(_) => return None,
+        None => (text.parse::<u32>().ok()?, 0),
+    };
+    let hour = match offset {
+        Some(offset) if (1..=12).contains(&hour) => hour % 12 + offset,
+        Some(_) => return None,
+        None => hour,
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// read when a reminder should be sent, see [`REMINDER_USAGE`] for the accepted formats. Times of
+/// day are in the guild's timezone, and one which has already passed today is taken to mean
+/// tomorrow
+pub fn parse_reminder_time(
+    input: &str,
+    now: DateTime<Utc>,
+    timezone: Tz,
+) -> Result<DateTime<Utc>, String> {
+    let input = input.trim().to_lowercase();
+    let invalid = || format!("`{}` is not a time I understand, {}", input, REMINDER_USAGE);
+
+    if let Some(rest) = input.strip_prefix("in ") {
+        let rest = rest.trim();
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (count, unit) = rest.split_at(split);
+        let count: i64 = count.parse().map_err(|_| invalid())?;
+        let minutes = match unit.trim() {
+            "m" | "min" | "mins" | "minute" | "minutes" => count,
+            "h" | "hr" | "hrs" | "hour" | "hours" => count.saturating_mul(60),
+            "d" | "day" | "days" => count.saturating_mul(24 * 60),
+            _ => return Err(invalid()),
+        };
+        if !(1..=MAX_REMINDER_MINUTES).contains(&minutes) {
+            return Err("reminders can be set from a minute to a week ahead".to_string());
+        }
+        return Ok(now + Duration::minutes(minutes));
+    }
+
+    let (tomorrow, time) = match input.strip_prefix("tomorrow") {
+        Some(rest) => (true, rest.trim()),
+        None => (false, input.as_str()),
+    };
+    let time = parse_time_of_day(time).ok_or_else(invalid)?;
+
+    let today = now.with_timezone(&timezone).date_naive();
+    let date = match tomorrow {
+        true => today + Duration::days(1),
+        false => today,
+    };
+    match local_time(date, time, timezone) {
+        at if at <= now => Ok(local_time(date + Duration::days(1), time, timezone)),
+        at => Ok(at),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn weekdays_are_split_on_commas_and_spaces() {
+        let expected = Schedule::Weekdays(vec![Weekday::Mon, Weekday::Thu]);
+        for spec in [
+            "weekly mon,thu",
+            "weekly mon thu",
+            "every mon, thu",
+            "every mon ,thu",
+            "Weekly Mon,,Thu,mon",
+        ] {
+            assert_eq!(Schedule::parse(spec), Ok(expected.clone()), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn weekdays_must_name_a_day() {
+        for spec in ["every ,", "weekly ,", "weekly , ,"] {
+            assert!(Schedule::parse(spec).is_err(), "{}", spec);
+        }
+        assert!(Schedule::parse("weekly mon,someday").is_err());
+    }
+
+    #[test]
+    fn intervals_and_days_of_month_parse() {
+        assert_eq!(Schedule::parse("daily"), Ok(Schedule::Days(1)));
+        assert_eq!(Schedule::parse("every 2 weeks"), Ok(Schedule::Days(14)));
+        assert_eq!(
+            Schedule::parse("monthly last"),
+            Ok(Schedule::DayOfMonth(None))
+        );
+        assert_eq!(
+            Schedule::parse("monthly 31"),
+            Ok(Schedule::DayOfMonth(Some(31)))
+        );
+        assert!(Schedule::parse("monthly 32").is_err());
+        assert!(Schedule::parse("every 0 days").is_err());
+    }
+
+    #[test]
+    fn the_31st_is_clamped_to_the_end_of_february() {
+        let schedule = Schedule::DayOfMonth(Some(31));
+        assert_eq!(schedule.next_after(date(2023, 1, 31)), date(2023, 2, 28));
+        assert_eq!(schedule.next_after(date(2024, 1, 31)), date(2024, 2, 29));
+        assert_eq!(schedule.next_after(date(2023, 2, 28)), date(2023, 3, 31));
+        assert_eq!(schedule.next_after(date(2023, 4, 30)), date(2023, 5, 31));
+    }
+
+    #[test]
+    fn monthly_last_is_the_last_day_of_each_month() {
+        let schedule = Schedule::DayOfMonth(None);
+        assert_eq!(schedule.next_after(date(2023, 2, 1)), date(2023, 2, 28));
+        assert_eq!(schedule.next_after(date(2023, 2, 28)), date(2023, 3, 31));
+        assert_eq!(schedule.next_after(date(2023, 3, 31)), date(2023, 4, 30));
+        assert_eq!(schedule.next_after(date(2024, 2, 28)), date(2024, 2, 29));
+    }
+
+    #[test]
+    fn december_rolls_over_into_the_next_year() {
+        assert_eq!(
+            Schedule::DayOfMonth(Some(15)).next_after(date(2023, 12, 20)),
+            date(2024, 1, 15)
+        );
+        assert_eq!(
+            Schedule::DayOfMonth(None).next_after(date(2023, 12, 31)),
+            date(2024, 1, 31)
+        );
+        assert_eq!(
+            Schedule::Weekdays(vec![Weekday::Mon]).next_after(date(2023, 12, 29)),
+            date(2024, 1, 1)
+        );
+    }
+
+    #[test]
+    fn next_run_skips_a_post_time_which_has_passed() {
+        let timezone: Tz = "Pacific/Auckland".parse().unwrap();
+        // midday in auckland, after the day's post time
+        let now = Utc.with_ymd_and_hms(2023, 6, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            Schedule::Days(1).next_run(now, timezone),
+            Utc.with_ymd_and_hms(2023, 6, 1, 20, 0, 0)
+                .unwrap()
+                .naive_utc()
+        );
+
+        let now = Utc.with_ymd_and_hms(2023, 2, 10, 0, 0, 0).unwrap();
+        assert_eq!(
+            Schedule::DayOfMonth(Some(31)).next_run(now, Tz::UTC),
+            Utc.with_ymd_and_hms(2023, 2, 28, 8, 0, 0)
+                .unwrap()
+                .naive_utc()
+        );
+    }
+}
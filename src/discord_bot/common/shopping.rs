@@ -0,0 +1,59 @@
+//! Shared rendering for commands which display several shopping list items at once.
+
+use serenity::builder::CreateEmbed;
+
+use crate::discord_bot::database::shopping::ShoppingListItemModel;
+
+use super::embed::EmbedColor;
+
+/// the maximum length of an embed description, as enforced by discord
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+/// describe a single item as one line of a list, e.g. `x2 milk (personal) from Countdown`
+pub fn describe_item(item: &ShoppingListItemModel) -> String {
+    let mut line = describe_item_without_store(item);
+    if let Some(store) = item.store.as_ref() {
+        line.push_str(&format!(" from {}", store));
+    }
+    line
+}
+
+/// describe an item as one line of a list which is already grouped by store, e.g.
+/// `x2 milk (personal)`
+pub fn describe_item_without_store(item: &ShoppingListItemModel) -> String {
+    let mut line = format!("x{} {}", item.quantity, item.item);
+    if item.personal {
+        line.push_str(" (personal)");
+    }
+    if let Some(bought_for) = item.bought_for.as_ref() {
+        line.push_str(&format!(" (for {})", bought_for));
+    }
+    line
+}
+
+/// render a list of items into a single embed, truncating the list if it will not fit
+pub fn shopping_list_embed(title: &str, items: &[ShoppingListItemModel]) -> CreateEmbed {
+    CreateEmbed::new()
+        .title(title)
+        .description(shopping_list_description(items))
+        .color(EmbedColor::Red as u32)
+}
+
+/// render a list of items as lines of an embed description, truncating the list if it will not fit
+pub fn shopping_list_description(items: &[ShoppingListItemModel]) -> String {
+    let mut description = String::new();
+
+    for (i, item) in items.iter().enumerate() {
+        let line = format!("- {}\n", describe_item(item));
+
+        // leave enough room for the truncation marker
+        if description.len() + line.len() > MAX_DESCRIPTION_LENGTH - 32 {
+            description.push_str(&format!("*...and {} more*", items.len() - i));
+            break;
+        }
+
+        description.push_str(&line);
+    }
+
+    description
+}
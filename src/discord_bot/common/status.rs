@@ -0,0 +1,143 @@
+//! A reaction on each item's message showing whether it is pending, bought or removed, which stays
+//! visible when the embed is collapsed, e.g. on mobile.
+
+use log::error;
+use serenity::{
+    all::{ChannelId, MessageId, ReactionType},
+    prelude::Context,
+};
+
+use crate::discord_bot::database::shopping::ShoppingListItemModel;
+
+/// where an item is in its life on the list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemStatus {
+    /// on the list, waiting to be bought
+    Pending,
+    /// bought, whether with its own button or in bulk
+    Bought,
+    /// taken off the list without being bought
+    Removed,
+}
+
+impl ItemStatus {
+    /// the emoji standing for the status, as reacted and in plain text messages
+    pub fn symbol(self) -> &'static str {
+        match self {
+            ItemStatus::Pending => "⏳",
+            ItemStatus::Bought => "✅",
+            ItemStatus::Removed => "❌",
+        }
+    }
+
+    fn emoji(self) -> ReactionType {
+        ReactionType::Unicode(self.symbol().to_string())
+    }
+}
+
+/// the reaction taken off an item's message as it moves to `status`, if any, and the one put on.
+/// Items only ever move on from pending, so the bot's pending reaction is the only one taken off
+fn reaction_changes(status: ItemStatus) -> (Option<ReactionType>, ReactionType) {
+    let removed = (status != ItemStatus::Pending).then(|| ItemStatus::Pending.emoji());
+    (removed, status.emoji())
+}
+
+/// react to an item's message with its status. Failures are only logged, as the status is already
+/// saved and shown on the embed
+pub async fn set_status_reaction(
+    ctx: &Context,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    status: ItemStatus,
+) {
+    let (removed, added) = reaction_changes(status);
+    if let Some(removed) = removed {
+        if let Err(e) = channel_id
+            .delete_reaction(ctx, message_id, None, removed)
+            .await
+        {
+            error!("error removing status reaction from {}: {}", message_id, e);
+        }
+    }
+
+    if let Err(e) = channel_id.create_reaction(ctx, message_id, added).await {
+        error!("error adding status reaction to {}: {}", message_id, e);
+    }
+}
+
+/// the posted message of each item, skipping items never posted or whose message has expired
+fn posted_messages(
+    items: &[ShoppingListItemModel],
+) -> impl Iterator<Item = (ChannelId, MessageId)> + '_ {
+    items
+        .iter()
+        .filter(|item| item.message_expired_at.is_none())
+        .filter_map(|item| {
+            item.channel_id.map(|channel_id| {
+                (
+                    ChannelId::new(channel_id as u64),
+                    MessageId::new(item.message_id as u64),
+                )
+            })
+        })
+}
+
+/// react to the posted message of each item with its new status
+pub async fn set_status_reactions(
+    ctx: &Context,
+    items: &[ShoppingListItemModel],
+    status: ItemStatus,
+) {
+    for (channel_id, message_id) in posted_messages(items) {
+        set_status_reaction(ctx, channel_id, message_id, status).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discord_bot::database::shopping::test_item;
+
+    fn item(message_id: i64, channel_id: Option<i64>, expired: bool) -> ShoppingListItemModel {
+        let item = test_item(message_id as i32, "milk");
+        ShoppingListItemModel {
+            channel_id,
+            message_expired_at: expired.then_some(item.created_at),
+            ..item
+        }
+    }
+
+    fn unicode(emoji: &str) -> ReactionType {
+        ReactionType::Unicode(emoji.to_string())
+    }
+
+    #[test]
+    fn each_status_swaps_the_pending_reaction_for_its_own() {
+        assert_eq!(reaction_changes(ItemStatus::Pending), (None, unicode("⏳")));
+        assert_eq!(
+            reaction_changes(ItemStatus::Bought),
+            (Some(unicode("⏳")), unicode("✅"))
+        );
+        assert_eq!(
+            reaction_changes(ItemStatus::Removed),
+            (Some(unicode("⏳")), unicode("❌"))
+        );
+    }
+
+    #[test]
+    fn only_posted_messages_are_reacted_to() {
+        let items = [
+            item(10, Some(2), false),
+            item(11, None, false),
+            item(12, Some(2), true),
+            item(13, Some(4), false),
+        ];
+        assert_eq!(
+            posted_messages(&items).collect::<Vec<_>>(),
+            vec![
+                (ChannelId::new(2), MessageId::new(10)),
+                (ChannelId::new(4), MessageId::new(13)),
+            ]
+        );
+    }
+}
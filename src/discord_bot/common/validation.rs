@@ -0,0 +1,148 @@
+//! Collecting every problem with a list of items before rejecting it, so that a long list can be
+//! fixed in one go rather than one mistake at a time
+
+use serenity::builder::{CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage};
+
+use super::embed::EmbedColor;
+
+/// the longest embed description discord allows, less room for the note of problems left out
+const MAX_DESCRIPTION_LENGTH: usize = 4096 - 64;
+
+/// a problem with one entry of a list, or with the list as a whole
+#[derive(Debug, Clone)]
+struct Problem {
+    /// the position of the entry in the list, counting from 1
+    number: Option<usize>,
+    /// the entry as it was given
+    entry: String,
+    problem: String,
+}
+
+/// every problem found while checking a list of items
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    problems: Vec<Problem>,
+}
+
+impl ValidationReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// record a problem with the entry at `number` in the list, counting from 1
+    pub fn add(&mut self, number: usize, entry: &str, problem: impl Into<String>) {
+        self.problems.push(Problem {
+            number: Some(number),
+            entry: entry.to_string(),
+            problem: problem.into(),
+        });
+    }
+
+    /// record a problem with the list as a whole, such as it being too long
+    pub fn add_general(&mut self, problem: impl Into<String>) {
+        self.problems.push(Problem {
+            number: None,
+            entry: String::new(),
+            problem: problem.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// an embed listing each problem, with the number and text of the entry it was found in
+    fn embed(&self, title: &str) -> CreateEmbed {
+        let mut description = String::new();
+        for (i, problem) in self.problems.iter().enumerate() {
+            let line = match problem.number {
+                Some(number) => format!(
+                    "**{}.** `{}`: {}\n",
+                    number,
+                    problem.entry.replace('`', "'"),
+                    problem.problem
+                ),
+                None => format!("{}\n", problem.problem),
+            };
+
+            if description.len() + line.len() > MAX_DESCRIPTION_LENGTH {
+                description.push_str(&format!(
+                    "...and {} more problem(s)",
+                    self.problems.len() - i
+                ));
+                break;
+            }
+            description.push_str(&line);
+        }
+
+        CreateEmbed::new()
+            .title(format!("{} ({} problem(s))", title, self.problems.len()))
+            .description(description)
+            .color(EmbedColor::Red as u32)
+    }
+
+    /// the embed as an ephemeral response, for a command to fail with
+    pub fn response(&self, title: &str) -> CreateInteractionResponse {
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(self.embed(title))
+                .ephemeral(true),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the title and description of the embed a report is shown in
+    fn shown(report: &ValidationReport) -> (String, String) {
+        let embed = serde_json::to_value(report.embed("Not saved")).unwrap();
+        (
+            embed["title"].as_str().unwrap().to_string(),
+            embed["description"].as_str().unwrap().to_string(),
+        )
+    }
+
+    #[test]
+    fn every_problem_is_listed_with_its_entry() {
+        // a list where only the second and fourth entries are invalid
+        let list = ["milk", "30 eggs", "2 bread", "`bad` bread", "rice"];
+        let mut report = ValidationReport::new();
+        for (i, entry) in list.iter().enumerate() {
+            if entry.starts_with("30") {
+                report.add(i + 1, entry, "the quantity must be from 1 to 25");
+            }
+            if entry.contains('`') {
+                report.add(i + 1, entry, "item names can't contain backticks");
+            }
+        }
+        assert!(!report.is_empty());
+        report.add_general("a template can hold at most 25 items, this one has 26");
+
+        let (title, description) = shown(&report);
+        assert_eq!(title, "Not saved (3 problem(s))");
+        assert_eq!(
+            description,
+            "**2.** `30 eggs`: the quantity must be from 1 to 25\n\
+             **4.** `'bad' bread`: item names can't contain backticks\n\
+             a template can hold at most 25 items, this one has 26\n"
+        );
+    }
+
+    #[test]
+    fn a_long_report_notes_the_problems_left_out() {
+        let mut report = ValidationReport::new();
+        assert!(report.is_empty());
+        let entry = "x".repeat(100);
+        for number in 1..=100 {
+            report.add(number, &entry, "too long");
+        }
+
+        let (title, description) = shown(&report);
+        assert_eq!(title, "Not saved (100 problem(s))");
+        assert!(description.len() <= MAX_DESCRIPTION_LENGTH + 64);
+        assert!(description.starts_with("**1.** "));
+        assert!(description.ends_with("more problem(s)"));
+    }
+}
@@ -0,0 +1,110 @@
+//! A per-guild list of words which may not be used in free text, such as item names and notes.
+
+use std::{borrow::Cow, collections::HashSet};
+
+/// a compiled set of filtered words, matched against whole words regardless of case
+#[derive(Debug, Default)]
+pub struct WordFilter {
+    words: HashSet<String>,
+}
+
+/// split text into runs of word and non-word characters, so that it can be put back together
+fn tokens(text: &str) -> impl Iterator<Item = (bool, &str)> {
+    let mut start = 0;
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let is_word = first.is_alphanumeric();
+        let end = rest
+            .char_indices()
+            .find(|(_, c)| c.is_alphanumeric() != is_word)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+
+        let token = &text[start..start + end];
+        start += end;
+        rest = &rest[end..];
+        Some((is_word, token))
+    })
+}
+
+impl WordFilter {
+    pub fn new(words: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            words: words.into_iter().map(|w| w.to_lowercase()).collect(),
+        }
+    }
+
+    /// normalise a word before it is stored, returns None if it could never match anything
+    pub fn normalise(word: &str) -> Option<String> {
+        let word = word.trim().to_lowercase();
+        match !word.is_empty() && word.chars().all(char::is_alphanumeric) {
+            true => Some(word),
+            false => None,
+        }
+    }
+
+    fn is_filtered(&self, word: &str) -> bool {
+        self.words.contains(&word.to_lowercase())
+    }
+
+    /// the first filtered word found in the text
+    pub fn find<'t>(&self, text: &'t str) -> Option<&'t str> {
+        if self.words.is_empty() {
+            return None;
+        }
+        tokens(text)
+            .find(|(is_word, token)| *is_word && self.is_filtered(token))
+            .map(|(_, token)| token)
+    }
+
+    /// replace every filtered word in the text with asterisks
+    pub fn mask<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        if self.find(text).is_none() {
+            return Cow::Borrowed(text);
+        }
+
+        let mut masked = String::with_capacity(text.len());
+        for (is_word, token) in tokens(text) {
+            if is_word && self.is_filtered(token) {
+                masked.extend(token.chars().map(|_| '*'));
+            } else {
+                masked.push_str(token);
+            }
+        }
+        Cow::Owned(masked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter() -> WordFilter {
+        WordFilter::new(["Darn".to_string(), "heck".to_string()])
+    }
+
+    #[test]
+    fn only_whole_words_are_found_regardless_of_case() {
+        assert_eq!(filter().find("oh HECK, the milk"), Some("HECK"));
+        assert_eq!(filter().find("darned socks from checkout"), None);
+        assert_eq!(WordFilter::default().find("heck"), None);
+    }
+
+    #[test]
+    fn filtered_words_are_masked_keeping_everything_else() {
+        assert_eq!(filter().mask("darn-it, heck!"), "****-it, ****!");
+        assert_eq!(filter().mask("ünïcode heck"), "ünïcode ****");
+        assert!(matches!(
+            filter().mask("oat milk"),
+            Cow::Borrowed("oat milk")
+        ));
+    }
+
+    #[test]
+    fn words_which_can_never_match_are_not_stored() {
+        assert_eq!(WordFilter::normalise(" Heck "), Some("heck".to_string()));
+        assert_eq!(WordFilter::normalise("two words"), None);
+        assert_eq!(WordFilter::normalise("  "), None);
+    }
+}
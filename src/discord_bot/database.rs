@@ -1,16 +1,44 @@
+#[cfg_attr(not(any(feature = "maps", feature = "shopping")), allow(dead_code))]
 pub type DatabaseResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
+/// which autocomplete suggestions of /shop an entry belongs to
+#[cfg(feature = "shopping")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestionKind {
+    Item,
+    Store,
+}
+
+#[cfg(feature = "shopping")]
+impl SuggestionKind {
+    /// the name of the kind, as stored in the database and given to commands
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuggestionKind::Item => "item",
+            SuggestionKind::Store => "store",
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
 pub mod shopping {
+    use std::collections::{BTreeSet, HashMap};
+
+    use crate::discord_bot::common::quantity::MAX_QUANTITY;
     use crate::state::AppState;
-    use chrono::Local;
+    use chrono::{Datelike, Local, NaiveDateTime, TimeZone, Timelike, Weekday};
+    use chrono_tz::Tz;
+    use sea_orm::sea_query::Expr;
     use sea_orm::ActiveValue;
     use sea_orm::ColumnTrait;
     use sea_orm::EntityTrait;
+    use sea_orm::FromQueryResult;
     use sea_orm::IntoActiveModel;
     use sea_orm::ModelTrait;
     use sea_orm::QueryFilter;
     use sea_orm::QueryOrder;
     use sea_orm::QuerySelect;
+    use sea_orm::TransactionTrait;
     use serenity::async_trait;
 
     use super::DatabaseResult;
@@ -25,12 +53,122 @@ pub mod shopping {
 
     use sea_orm::ActiveModelTrait;
 
+    /// how far back to look when working out how often an item is usually bought
+    const STAPLE_HISTORY_DAYS: i64 = 180;
+    /// the number of times an item must have been added before it is considered a staple
+    const STAPLE_MIN_OCCURRENCES: usize = 3;
+    /// how much longer than usual an item must have gone without being added to be overdue
+    const STAPLE_OVERDUE_FACTOR: f64 = 1.25;
+    /// the most items loaded when finding staples, the most recent are kept so a very busy
+    /// guild loses only the start of its history
+    const STAPLE_MAX_ITEMS: u64 = 5000;
+    /// how far back to look when finding which items are added together
+    const INSIGHTS_HISTORY_DAYS: i64 = 90;
+    /// the most items loaded when finding which items are added together, keeping the query cheap
+    const INSIGHTS_MAX_ITEMS: u64 = 5000;
+    /// the longest gap between items added by one user for them to count as the same trip
+    const INSIGHTS_TRIP_GAP_MINUTES: i64 = 30;
+    /// the fewest items needed before there is enough history to say anything useful
+    const INSIGHTS_MIN_ITEMS: usize = 20;
+    /// the number of item pairs reported
+    const INSIGHTS_TOP_PAIRS: usize = 5;
+    /// the most items a trip may have for its pairs to be counted. The pairs grow with the square
+    /// of the trip, and a trip this large is a bulk import rather than a habit
+    const INSIGHTS_MAX_TRIP_ITEMS: usize = 50;
+    /// the most of a user's items searched for earlier adds of an item, keeping the query cheap
+    const QUANTITY_HISTORY_ITEMS: u64 = 500;
+
+    /// patterns in how a guild uses the shopping list
+    #[derive(Debug, Clone)]
+    pub struct Insights {
+        /// the number of items the insights were drawn from
+        pub items: usize,
+        /// the pairs of items most often added on the same trip, with the number of trips
+        pub pairs: Vec<(String, String, usize)>,
+        /// the day of the week the most items are added on, with the number of items
+        pub busiest_day: (Weekday, usize),
+        /// the hour of the day the most items are added in, with the number of items
+        pub busiest_hour: (u32, usize),
+    }
+
+    /// an item which is usually added regularly, but hasn't been added for longer than usual
+    #[derive(Debug, Clone)]
+    pub struct OverdueStaple {
+        /// the most recent time the item was added
+        pub latest: ShoppingListItemModel,
+        /// the usual number of days between the item being added
+        pub cadence_days: f64,
+        /// the number of days since the item was last added
+        pub days_since: f64,
+    }
+
+    /// how far through the current list a guild is, where the list is everything added since the
+    /// oldest item still waiting to be bought
+    #[derive(Debug, Clone, Default)]
+    pub struct ListProgress {
+        pub bought: usize,
+        pub total: usize,
+        /// the progress of each store on the list, with items from any store last
+        pub stores: Vec<StoreProgress>,
+    }
+
+    /// how many of the items on the current list from one store have been bought
+    #[derive(Debug, Clone)]
+    pub struct StoreProgress {
+        pub store: Option<String>,
+        pub bought: usize,
+        pub total: usize,
+    }
+
+    /// the number of items on the current list with one store which are, or aren't, bought,
+    /// counted by the database so that a long list isn't loaded to be counted
+    #[derive(Debug, Clone, FromQueryResult)]
+    struct StoreCount {
+        store: Option<String>,
+        bought: bool,
+        count: i64,
+    }
+
+    /// the number of items one person has bought, counted by the database
+    #[derive(Debug, Clone, FromQueryResult)]
+    struct ShopperCount {
+        bought_by: i64,
+        count: i64,
+    }
+
+    /// how the note of each item is changed by /shop-annotate
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum NoteUpdate {
+        /// add the note after any the item already has
+        Append,
+        /// replace any note the item already has
+        Replace,
+    }
+
+    impl NoteUpdate {
+        /// the note an item is left with once `note` is applied to it. Appending a note the item
+        /// already ends with, such as by running the same command twice, doesn't repeat it
+        pub fn apply(&self, existing: Option<&str>, note: &str) -> String {
+            match (self, existing.map(str::trim).filter(|n| !n.is_empty())) {
+                (NoteUpdate::Append, Some(existing)) if existing.ends_with(note) => {
+                    existing.to_string()
+                }
+                (NoteUpdate::Append, Some(existing)) => format!("{} {}", existing, note),
+                _ => note.to_string(),
+            }
+        }
+    }
+
     pub struct NewShoppingListItem<'a> {
         pub item: &'a str,
         pub store: Option<&'a str>,
         pub notes: Option<&'a str>,
         pub quantity: i64,
         pub personal: bool,
+        pub recipe_group: Option<&'a str>,
+        pub bought_for: Option<&'a str>,
+        /// a link to the item's page in an online store
+        pub product_url: Option<&'a str>,
     }
 
     #[async_trait]
@@ -60,13 +198,22 @@ pub mod shopping {
             message_id: u64,
         ) -> DatabaseResult<Option<ShoppingListItemModel>>;
 
+        /// `bought_by` is who bought the item, None if it's being removed rather than bought
         async fn set_shopping_list_item_bought(
             &self,
             user: u64,
             message_id: u64,
             setting: bool,
+            bought_by: Option<u64>,
         ) -> DatabaseResult<()>;
 
+        /// returns false if the user has no item posted in the message
+        async fn delete_shopping_list_item(
+            &self,
+            user: u64,
+            message_id: u64,
+        ) -> DatabaseResult<bool>;
+
         async fn get_recent_shopping_list_items_by_user(
             &self,
             user: u64,
@@ -78,9 +225,186 @@ pub mod shopping {
             count: u64,
         ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
 
+        /// the items most recently added in a guild, bought or not, or outside of any guild if
+        /// `guild` is none
+        async fn get_recent_guild_items(
+            &self,
+            guild: Option<u64>,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn get_recent_item_quantities(
+            &self,
+            guild: u64,
+            user: u64,
+            item: &str,
+            count: usize,
+        ) -> DatabaseResult<Vec<i64>>;
+
         async fn get_unbought_shopping_list_items(
             &self,
         ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn get_pending_items(&self, guild: u64)
+            -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn get_pending_items_by_user(
+            &self,
+            guild: u64,
+            user: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn get_items_by_recipe_group(
+            &self,
+            guild: u64,
+            recipe_group: &str,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn get_pending_items_by_store(
+            &self,
+            guild: u64,
+            store: &str,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        /// `bought_by` is who bought the items, None if they're being removed rather than bought
+        async fn set_shopping_list_items_bought(
+            &self,
+            ids: &[i32],
+            setting: bool,
+            bought_by: Option<u64>,
+        ) -> DatabaseResult<()>;
+
+        /// change the store of an item which is still pending, returning whether it was changed
+        async fn update_item_store(
+            &self,
+            user: u64,
+            message_id: u64,
+            store: Option<&str>,
+        ) -> DatabaseResult<bool>;
+
+        async fn clear_store_on_items(
+            &self,
+            user: u64,
+            guild: u64,
+            store: &str,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn move_pending_items_store(
+            &self,
+            user: u64,
+            guild: u64,
+            from: &str,
+            to: &str,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn add_item_quantity(
+            &self,
+            user: u64,
+            id: i32,
+            quantity: i64,
+        ) -> DatabaseResult<Option<ShoppingListItemModel>>;
+
+        async fn set_item_quantity(
+            &self,
+            user: u64,
+            id: i32,
+            quantity: i64,
+        ) -> DatabaseResult<Option<ShoppingListItemModel>>;
+
+        async fn step_item_quantity(
+            &self,
+            user: u64,
+            id: i32,
+            step: i64,
+        ) -> DatabaseResult<Option<ShoppingListItemModel>>;
+
+        async fn split_item(
+            &self,
+            user: u64,
+            id: i32,
+            quantity: i64,
+            store: Option<&str>,
+            message_id: u64,
+            channel_id: u64,
+        ) -> DatabaseResult<Option<(ShoppingListItemModel, ShoppingListItemModel)>>;
+
+        async fn update_item_notes(
+            &self,
+            user: u64,
+            id: i32,
+            notes: Option<&str>,
+        ) -> DatabaseResult<Option<ShoppingListItemModel>>;
+
+        async fn get_top_item(&self, guild: u64) -> DatabaseResult<Option<ShoppingListItemModel>>;
+
+        async fn get_oldest_pending(
+            &self,
+            guild: u64,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn get_overdue_staples(
+            &self,
+            user: Option<u64>,
+            guild: u64,
+        ) -> DatabaseResult<Vec<OverdueStaple>>;
+
+        async fn get_recent_pending_items(
+            &self,
+            guild: u64,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn find_items(
+            &self,
+            guild: u64,
+            store: &str,
+            older_than: NaiveDateTime,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn get_insights(&self, guild: u64, timezone: Tz) -> DatabaseResult<Option<Insights>>;
+
+        async fn get_progress(&self, guild: u64) -> DatabaseResult<ListProgress>;
+
+        async fn get_shopper_leaderboard(
+            &self,
+            guild: u64,
+            since: NaiveDateTime,
+            personal: bool,
+        ) -> DatabaseResult<Vec<(u64, usize)>>;
+
+        async fn get_items_to_clear(
+            &self,
+            guild: u64,
+            bought_before: NaiveDateTime,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn set_shopping_list_items_cleared(&self, ids: &[i32]) -> DatabaseResult<()>;
+
+        async fn get_items_to_expire(
+            &self,
+            guild: u64,
+            created_before: NaiveDateTime,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn set_item_messages_expired(&self, ids: &[i32]) -> DatabaseResult<()>;
+
+        async fn delete_shopping_list_items(&self, ids: &[i32]) -> DatabaseResult<()>;
+
+        async fn get_digest_items(&self, guild: u64) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        /// returns false if the item isn't pending or can't move any further
+        async fn reorder_item(&self, guild: u64, id: i32, offset: i64) -> DatabaseResult<bool>;
+
+        async fn bulk_update_notes(
+            &self,
+            user: u64,
+            ids: &[i32],
+            note: &str,
+            update: NoteUpdate,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
     }
 
     #[async_trait]
@@ -162,6 +486,18 @@ pub mod shopping {
                 personal: ActiveValue::Set(item.personal),
                 store: ActiveValue::Set(item.store.map(|s| s.to_string())),
                 notes: ActiveValue::Set(item.notes.map(|n| n.to_string())),
+                guild_id: ActiveValue::Set(guild_id.map(|g| g as i64)),
+                channel_id: ActiveValue::Set(Some(channel_id as i64)),
+                recipe_group: ActiveValue::Set(item.recipe_group.map(normalise_recipe_group)),
+                modified_by: ActiveValue::Set(None),
+                modified_at: ActiveValue::Set(None),
+                bought_at: ActiveValue::Set(None),
+                cleared_at: ActiveValue::Set(None),
+                order_index: ActiveValue::Set(None),
+                message_expired_at: ActiveValue::Set(None),
+                bought_for: ActiveValue::Set(item.bought_for.map(|f| f.to_string())),
+                product_url: ActiveValue::Set(item.product_url.map(|u| u.to_string())),
+                bought_by: ActiveValue::Set(None),
             };
             item.insert(&*self.database).await?;
 
@@ -200,6 +536,7 @@ pub mod shopping {
             user: u64,
             message_id: u64,
             setting: bool,
+            bought_by: Option<u64>,
         ) -> DatabaseResult<()> {
             let shopping_list_item = ShoppingListItemEntity::find()
                 .filter(
@@ -213,12 +550,35 @@ pub mod shopping {
             if let Some(shopping_list_item) = shopping_list_item {
                 let mut shopping_list_item = shopping_list_item.into_active_model();
                 shopping_list_item.bought = ActiveValue::Set(setting);
+                shopping_list_item.bought_at =
+                    ActiveValue::Set(setting.then(|| Local::now().naive_local()));
+                shopping_list_item.bought_by =
+                    ActiveValue::Set(bought_by.filter(|_| setting).map(|u| u as i64));
                 shopping_list_item.update(&*self.database).await?;
             }
 
             Ok(())
         }
 
+        /// delete an item outright rather than marking it bought, for guilds which don't keep
+        /// removed items around
+        async fn delete_shopping_list_item(
+            &self,
+            user: u64,
+            message_id: u64,
+        ) -> DatabaseResult<bool> {
+            let result = ShoppingListItemEntity::delete_many()
+                .filter(
+                    <ShoppingListItemEntity as EntityTrait>::Column::MessageId
+                        .eq(message_id as i64),
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+
         async fn get_recent_shopping_list_items_by_user(
             &self,
             user: u64,
@@ -247,6 +607,51 @@ pub mod shopping {
             Ok(shopping_list)
         }
 
+        async fn get_recent_guild_items(
+            &self,
+            guild: Option<u64>,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let column = <ShoppingListItemEntity as EntityTrait>::Column::GuildId;
+            let items = ShoppingListItemEntity::find()
+                .filter(match guild {
+                    Some(guild) => column.eq(guild as i64),
+                    None => column.is_null(),
+                })
+                .order_by_desc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .limit(count)
+                .all(&*self.database)
+                .await?;
+
+            Ok(items)
+        }
+
+        /// the quantities a user most recently added an item with in a guild, most recent first.
+        /// Names are matched regardless of case, as with staples
+        async fn get_recent_item_quantities(
+            &self,
+            guild: u64,
+            user: u64,
+            item: &str,
+            count: usize,
+        ) -> DatabaseResult<Vec<i64>> {
+            let history: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .order_by_desc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .limit(QUANTITY_HISTORY_ITEMS)
+                .all(&*self.database)
+                .await?;
+
+            let item = item.trim().to_lowercase();
+            Ok(history
+                .into_iter()
+                .filter(|model| model.item.trim().to_lowercase() == item)
+                .map(|model| model.quantity)
+                .take(count)
+                .collect())
+        }
+
         async fn get_unbought_shopping_list_items(
             &self,
         ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
@@ -258,5 +663,3616 @@ pub mod shopping {
 
             Ok(shopping_list)
         }
+
+        async fn get_pending_items(
+            &self,
+            guild: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let shopping_list: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(shopping_list)
+        }
+
+        async fn get_pending_items_by_user(
+            &self,
+            guild: u64,
+            user: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let shopping_list: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(shopping_list)
+        }
+
+        async fn get_items_by_recipe_group(
+            &self,
+            guild: u64,
+            recipe_group: &str,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let shopping_list: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(
+                    <ShoppingListItemEntity as EntityTrait>::Column::RecipeGroup
+                        .eq(normalise_recipe_group(recipe_group)),
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(shopping_list)
+        }
+
+        async fn get_pending_items_by_store(
+            &self,
+            guild: u64,
+            store: &str,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let shopping_list: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Store.eq(store))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(shopping_list)
+        }
+
+        async fn set_shopping_list_items_bought(
+            &self,
+            ids: &[i32],
+            setting: bool,
+            bought_by: Option<u64>,
+        ) -> DatabaseResult<()> {
+            ShoppingListItemEntity::update_many()
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::Bought,
+                    Expr::value(setting),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::BoughtAt,
+                    Expr::value(setting.then(|| Local::now().naive_local())),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::BoughtBy,
+                    Expr::value(bought_by.filter(|_| setting).map(|u| u as i64)),
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.is_in(ids.to_vec()))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+
+        async fn update_item_store(
+            &self,
+            user: u64,
+            message_id: u64,
+            store: Option<&str>,
+        ) -> DatabaseResult<bool> {
+            // checked in the update itself, so an item bought while its store menu was open is
+            // left alone
+            let result = ShoppingListItemEntity::update_many()
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::Store,
+                    Expr::value(store.map(|s| s.to_string())),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::ModifiedBy,
+                    Expr::value(Some(user as i64)),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::ModifiedAt,
+                    Expr::value(Some(Local::now().naive_local())),
+                )
+                .filter(
+                    <ShoppingListItemEntity as EntityTrait>::Column::MessageId
+                        .eq(message_id as i64),
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+
+        /// add to the quantity of an item which is still pending, returning the updated item
+        async fn add_item_quantity(
+            &self,
+            user: u64,
+            id: i32,
+            quantity: i64,
+        ) -> DatabaseResult<Option<ShoppingListItemModel>> {
+            let shopping_list_item = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.eq(id))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .one(&*self.database)
+                .await?;
+
+            let shopping_list_item = match shopping_list_item {
+                Some(item) => item,
+                None => return Ok(None),
+            };
+
+            let new_quantity = shopping_list_item
+                .quantity
+                .checked_add(quantity)
+                .ok_or("the quantity is too large")?;
+            let mut shopping_list_item = shopping_list_item.into_active_model();
+            shopping_list_item.quantity = ActiveValue::Set(new_quantity);
+            shopping_list_item.modified_by = ActiveValue::Set(Some(user as i64));
+            shopping_list_item.modified_at = ActiveValue::Set(Some(Local::now().naive_local()));
+            let updated = shopping_list_item.update(&*self.database).await?;
+
+            Ok(Some(updated))
+        }
+
+        /// replace the quantity of an item which is still pending, returning the updated item
+        async fn set_item_quantity(
+            &self,
+            user: u64,
+            id: i32,
+            quantity: i64,
+        ) -> DatabaseResult<Option<ShoppingListItemModel>> {
+            let shopping_list_item = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.eq(id))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .one(&*self.database)
+                .await?;
+
+            let shopping_list_item = match shopping_list_item {
+                Some(item) => item,
+                None => return Ok(None),
+            };
+
+            let mut shopping_list_item = shopping_list_item.into_active_model();
+            shopping_list_item.quantity = ActiveValue::Set(quantity);
+            shopping_list_item.modified_by = ActiveValue::Set(Some(user as i64));
+            shopping_list_item.modified_at = ActiveValue::Set(Some(Local::now().naive_local()));
+            let updated = shopping_list_item.update(&*self.database).await?;
+
+            Ok(Some(updated))
+        }
+
+        /// change the quantity of an item which is still pending by `step`, returning the updated
+        /// item. The quantity is never taken below 1, an item at 1 is returned unchanged
+        async fn step_item_quantity(
+            &self,
+            user: u64,
+            id: i32,
+            step: i64,
+        ) -> DatabaseResult<Option<ShoppingListItemModel>> {
+            // the quantity is changed in the database rather than read and written back, so two
+            // presses at once both count, and neither can take it past the bounds
+            let mut update = ShoppingListItemEntity::update_many()
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::Quantity,
+                    Expr::col(<ShoppingListItemEntity as EntityTrait>::Column::Quantity).add(step),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::ModifiedBy,
+                    Expr::value(Some(user as i64)),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::ModifiedAt,
+                    Expr::value(Some(Local::now().naive_local())),
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.eq(id))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false));
+            if step < 0 {
+                update = update
+                    .filter(<ShoppingListItemEntity as EntityTrait>::Column::Quantity.gt(-step));
+            } else if step > 0 {
+                update = update.filter(
+                    <ShoppingListItemEntity as EntityTrait>::Column::Quantity
+                        .lte(MAX_QUANTITY - step),
+                );
+            }
+            update.exec(&*self.database).await?;
+
+            // reloaded whether or not it changed, an item at 1 or the most allowed is returned as
+            // it is
+            let shopping_list_item = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.eq(id))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .one(&*self.database)
+                .await?;
+
+            Ok(shopping_list_item)
+        }
+
+        /// take `quantity` off an item which is still pending and add it as an item of its own
+        /// from `store`, posted in `message_id`, returning the updated item and the new one. Both
+        /// are written in one transaction, so the quantity split off is never lost. Nothing is
+        /// changed if the item has no more than `quantity` of it, as the split would leave nothing
+        /// behind
+        async fn split_item(
+            &self,
+            user: u64,
+            id: i32,
+            quantity: i64,
+            store: Option<&str>,
+            message_id: u64,
+            channel_id: u64,
+        ) -> DatabaseResult<Option<(ShoppingListItemModel, ShoppingListItemModel)>> {
+            let txn = self.database.begin().await?;
+
+            let shopping_list_item = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.eq(id))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Quantity.gt(quantity))
+                .one(&txn)
+                .await?;
+
+            let shopping_list_item = match shopping_list_item {
+                Some(item) => item,
+                None => return Ok(None),
+            };
+
+            // only the quantity and store of the split differ from the original
+            let now = Local::now().naive_local();
+            let split = ShoppingListItemActiveModel {
+                id: ActiveValue::NotSet,
+                list_id: ActiveValue::Set(shopping_list_item.list_id),
+                message_id: ActiveValue::Set(message_id as i64),
+                user_id: ActiveValue::Set(user as i64),
+
+                created_at: ActiveValue::Set(now),
+                bought: ActiveValue::Set(false),
+
+                item: ActiveValue::Set(shopping_list_item.item.clone()),
+                quantity: ActiveValue::Set(quantity),
+                personal: ActiveValue::Set(shopping_list_item.personal),
+                store: ActiveValue::Set(store.map(|s| s.to_string())),
+                notes: ActiveValue::Set(shopping_list_item.notes.clone()),
+                guild_id: ActiveValue::Set(shopping_list_item.guild_id),
+                channel_id: ActiveValue::Set(Some(channel_id as i64)),
+                recipe_group: ActiveValue::Set(shopping_list_item.recipe_group.clone()),
+                modified_by: ActiveValue::Set(None),
+                modified_at: ActiveValue::Set(None),
+                bought_at: ActiveValue::Set(None),
+                cleared_at: ActiveValue::Set(None),
+                order_index: ActiveValue::Set(None),
+                message_expired_at: ActiveValue::Set(None),
+                bought_for: ActiveValue::Set(shopping_list_item.bought_for.clone()),
+                product_url: ActiveValue::Set(shopping_list_item.product_url.clone()),
+                bought_by: ActiveValue::Set(None),
+            }
+            .insert(&txn)
+            .await?;
+
+            let new_quantity = shopping_list_item.quantity - quantity;
+            let mut shopping_list_item = shopping_list_item.into_active_model();
+            shopping_list_item.quantity = ActiveValue::Set(new_quantity);
+            shopping_list_item.modified_by = ActiveValue::Set(Some(user as i64));
+            shopping_list_item.modified_at = ActiveValue::Set(Some(now));
+            let updated = shopping_list_item.update(&txn).await?;
+
+            txn.commit().await?;
+            Ok(Some((updated, split)))
+        }
+
+        /// replace the note of an item which is still pending, returning the updated item. Nothing
+        /// is changed if the item has since been bought
+        async fn update_item_notes(
+            &self,
+            user: u64,
+            id: i32,
+            notes: Option<&str>,
+        ) -> DatabaseResult<Option<ShoppingListItemModel>> {
+            let shopping_list_item = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.eq(id))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .one(&*self.database)
+                .await?;
+
+            let shopping_list_item = match shopping_list_item {
+                Some(item) => item,
+                None => return Ok(None),
+            };
+
+            let mut shopping_list_item = shopping_list_item.into_active_model();
+            shopping_list_item.notes = ActiveValue::Set(notes.map(|n| n.to_string()));
+            shopping_list_item.modified_by = ActiveValue::Set(Some(user as i64));
+            shopping_list_item.modified_at = ActiveValue::Set(Some(Local::now().naive_local()));
+            let updated = shopping_list_item.update(&*self.database).await?;
+
+            Ok(Some(updated))
+        }
+
+        async fn clear_store_on_items(
+            &self,
+            user: u64,
+            guild: u64,
+            store: &str,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let mut items = self.get_pending_items_by_store(guild, store).await?;
+
+            if items.is_empty() {
+                return Ok(items);
+            }
+
+            let modified_at = Local::now().naive_local();
+            let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+            ShoppingListItemEntity::update_many()
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::Store,
+                    Expr::value(Option::<String>::None),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::ModifiedBy,
+                    Expr::value(Some(user as i64)),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::ModifiedAt,
+                    Expr::value(Some(modified_at)),
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.is_in(ids))
+                .exec(&*self.database)
+                .await?;
+
+            // return the items as they now are, so that their messages can be re-rendered
+            for item in items.iter_mut() {
+                item.store = None;
+                item.modified_by = Some(user as i64);
+                item.modified_at = Some(modified_at);
+            }
+            Ok(items)
+        }
+
+        /// move the items still pending from one store to another, leaving bought items as they
+        /// were bought. Returns the moved items as they now are
+        async fn move_pending_items_store(
+            &self,
+            user: u64,
+            guild: u64,
+            from: &str,
+            to: &str,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let mut items = self.get_pending_items_by_store(guild, from).await?;
+
+            if items.is_empty() {
+                return Ok(items);
+            }
+
+            let modified_at = Local::now().naive_local();
+            let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+            ShoppingListItemEntity::update_many()
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::Store,
+                    Expr::value(Some(to.to_string())),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::ModifiedBy,
+                    Expr::value(Some(user as i64)),
+                )
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::ModifiedAt,
+                    Expr::value(Some(modified_at)),
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.is_in(ids))
+                .exec(&*self.database)
+                .await?;
+
+            for item in items.iter_mut() {
+                item.store = Some(to.to_string());
+                item.modified_by = Some(user as i64);
+                item.modified_at = Some(modified_at);
+            }
+            Ok(items)
+        }
+
+        /// the most urgent shared item still pending, currently the one which has waited longest
+        async fn get_top_item(&self, guild: u64) -> DatabaseResult<Option<ShoppingListItemModel>> {
+            let item = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Personal.eq(false))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .one(&*self.database)
+                .await?;
+
+            Ok(item)
+        }
+
+        /// the non-personal items which have been waiting longest to be bought, oldest first
+        async fn get_oldest_pending(
+            &self,
+            guild: u64,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let items = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Personal.eq(false))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .limit(count)
+                .all(&*self.database)
+                .await?;
+
+            Ok(items)
+        }
+
+        /// the most recently added items which are still pending in a guild
+        async fn get_recent_pending_items(
+            &self,
+            guild: u64,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let items = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .order_by_desc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .limit(count)
+                .all(&*self.database)
+                .await?;
+
+            Ok(items)
+        }
+
+        /// the pending items from a store in a guild which were added before `older_than`, oldest
+        /// first
+        async fn find_items(
+            &self,
+            guild: u64,
+            store: &str,
+            older_than: NaiveDateTime,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let items = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Store.eq(store))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt.lt(older_than))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(items)
+        }
+
+        /// patterns in the recent history of a guild, or none if there isn't enough history yet.
+        /// Personal items and items for someone else are left out, as with staples
+        async fn get_insights(&self, guild: u64, timezone: Tz) -> DatabaseResult<Option<Insights>> {
+            let since = Local::now().naive_local() - chrono::Duration::days(INSIGHTS_HISTORY_DAYS);
+
+            let history: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Personal.eq(false))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::BoughtFor.is_null())
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt.gte(since))
+                .order_by_desc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .limit(INSIGHTS_MAX_ITEMS)
+                .all(&*self.database)
+                .await?;
+
+            Ok(insights(history, timezone))
+        }
+
+        /// how many items on the current list of a guild have been bought, leaving out items
+        /// picked up for someone outside the household. The list is empty if nothing is waiting
+        /// to be bought, even if items were bought recently
+        async fn get_progress(&self, guild: u64) -> DatabaseResult<ListProgress> {
+            let oldest = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::BoughtFor.is_null())
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .one(&*self.database)
+                .await?;
+            let since = match oldest {
+                Some(oldest) => oldest.created_at,
+                None => return Ok(ListProgress::default()),
+            };
+
+            // one row per store and bought state, however many items are on the list
+            let counts: Vec<StoreCount> = ShoppingListItemEntity::find()
+                .select_only()
+                .column(<ShoppingListItemEntity as EntityTrait>::Column::Store)
+                .column(<ShoppingListItemEntity as EntityTrait>::Column::Bought)
+                .column_as(
+                    Expr::col(<ShoppingListItemEntity as EntityTrait>::Column::Id).count(),
+                    "count",
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::BoughtFor.is_null())
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt.gte(since))
+                .group_by(<ShoppingListItemEntity as EntityTrait>::Column::Store)
+                .group_by(<ShoppingListItemEntity as EntityTrait>::Column::Bought)
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::Store)
+                .into_model::<StoreCount>()
+                .all(&*self.database)
+                .await?;
+
+            Ok(list_progress(&counts))
+        }
+
+        /// how many items each person in a guild has bought since the given time, most first.
+        /// Personal items are only counted if `personal` is set, and removed items never are
+        async fn get_shopper_leaderboard(
+            &self,
+            guild: u64,
+            since: NaiveDateTime,
+            personal: bool,
+        ) -> DatabaseResult<Vec<(u64, usize)>> {
+            let mut query = ShoppingListItemEntity::find()
+                .select_only()
+                .column(<ShoppingListItemEntity as EntityTrait>::Column::BoughtBy)
+                .column_as(
+                    Expr::col(<ShoppingListItemEntity as EntityTrait>::Column::Id).count(),
+                    "count",
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(true))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::BoughtBy.is_not_null())
+                // items picked up for someone outside the household aren't part of its shopping
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::BoughtFor.is_null())
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::BoughtAt.gte(since));
+            if !personal {
+                query = query
+                    .filter(<ShoppingListItemEntity as EntityTrait>::Column::Personal.eq(false));
+            }
+
+            let counts: Vec<ShopperCount> = query
+                .group_by(<ShoppingListItemEntity as EntityTrait>::Column::BoughtBy)
+                .into_model::<ShopperCount>()
+                .all(&*self.database)
+                .await?;
+
+            let mut leaderboard: Vec<(u64, usize)> = counts
+                .into_iter()
+                .map(|count| (count.bought_by as u64, count.count as usize))
+                .collect();
+            leaderboard.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            Ok(leaderboard)
+        }
+
+        /// up to `count` items of a guild which were bought before the given time and are still in
+        /// the channel, oldest first
+        async fn get_items_to_clear(
+            &self,
+            guild: u64,
+            bought_before: NaiveDateTime,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let items: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(true))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::ClearedAt.is_null())
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::BoughtAt.lt(bought_before))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::BoughtAt)
+                .limit(count)
+                .all(&*self.database)
+                .await?;
+
+            Ok(items)
+        }
+
+        /// mark items as cleared from the channel, keeping them for history
+        async fn set_shopping_list_items_cleared(&self, ids: &[i32]) -> DatabaseResult<()> {
+            ShoppingListItemEntity::update_many()
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::ClearedAt,
+                    Expr::value(Some(Local::now().naive_local())),
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.is_in(ids.to_vec()))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+
+        /// the oldest pending items in a guild added before `created_before` whose message is
+        /// still in the channel
+        async fn get_items_to_expire(
+            &self,
+            guild: u64,
+            created_before: NaiveDateTime,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let items: Vec<ShoppingListItemModel> = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::MessageExpiredAt.is_null())
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::ChannelId.is_not_null())
+                .filter(
+                    <ShoppingListItemEntity as EntityTrait>::Column::CreatedAt.lt(created_before),
+                )
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .limit(count)
+                .all(&*self.database)
+                .await?;
+
+            Ok(items)
+        }
+
+        /// record that the messages of items have been deleted, the items stay on the list and
+        /// are acted on through /shopping-list instead
+        async fn set_item_messages_expired(&self, ids: &[i32]) -> DatabaseResult<()> {
+            ShoppingListItemEntity::update_many()
+                .col_expr(
+                    <ShoppingListItemEntity as EntityTrait>::Column::MessageExpiredAt,
+                    Expr::value(Some(Local::now().naive_local())),
+                )
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.is_in(ids.to_vec()))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+
+        async fn delete_shopping_list_items(&self, ids: &[i32]) -> DatabaseResult<()> {
+            ShoppingListItemEntity::delete_many()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.is_in(ids.to_vec()))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+
+        /// the pending items of a guild in the order they are shown in its digest
+        async fn get_digest_items(&self, guild: u64) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let mut items = self.get_pending_items(guild).await?;
+            digest_order(&mut items);
+            Ok(items)
+        }
+
+        /// move a pending item `offset` places through the digest, negative offsets moving it up
+        async fn reorder_item(&self, guild: u64, id: i32, offset: i64) -> DatabaseResult<bool> {
+            let mut items = self.get_digest_items(guild).await?;
+
+            let from = match items.iter().position(|item| item.id == id) {
+                Some(from) => from,
+                None => return Ok(false),
+            };
+            let to = (from as i64 + offset).clamp(0, items.len() as i64 - 1) as usize;
+            if from == to {
+                return Ok(false);
+            }
+
+            let item = items.remove(from);
+            items.insert(to, item);
+
+            // every pending item is numbered, so that items added later fall in after them
+            for (index, item) in items.into_iter().enumerate() {
+                if item.order_index == Some(index as i32) {
+                    continue;
+                }
+                let mut item = item.into_active_model();
+                item.order_index = ActiveValue::Set(Some(index as i32));
+                item.update(&*self.database).await?;
+            }
+
+            Ok(true)
+        }
+
+        /// change the note of each item which is still pending, returning the items as they now
+        /// are. Items bought since they were chosen are left alone
+        async fn bulk_update_notes(
+            &self,
+            user: u64,
+            ids: &[i32],
+            note: &str,
+            update: NoteUpdate,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let items = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Id.is_in(ids.to_vec()))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(false))
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .all(&*self.database)
+                .await?;
+
+            let modified_at = Local::now().naive_local();
+            let mut updated = Vec::with_capacity(items.len());
+            for item in items.into_iter() {
+                let notes = update.apply(item.notes.as_deref(), note);
+                let mut item = item.into_active_model();
+                item.notes = ActiveValue::Set(Some(notes));
+                item.modified_by = ActiveValue::Set(Some(user as i64));
+                item.modified_at = ActiveValue::Set(Some(modified_at));
+                updated.push(item.update(&*self.database).await?);
+            }
+
+            Ok(updated)
+        }
+
+        /// find staples which are overdue, either for one user or the whole household
+        async fn get_overdue_staples(
+            &self,
+            user: Option<u64>,
+            guild: u64,
+        ) -> DatabaseResult<Vec<OverdueStaple>> {
+            let now = Local::now().naive_local();
+
+            // items picked up for someone outside the household aren't part of its shopping
+            let mut query = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::BoughtFor.is_null())
+                .filter(
+                    <ShoppingListItemEntity as EntityTrait>::Column::CreatedAt
+                        .gte(now - chrono::Duration::days(STAPLE_HISTORY_DAYS)),
+                );
+            query = match user {
+                Some(user) => query.filter(
+                    <ShoppingListItemEntity as EntityTrait>::Column::UserId.eq(user as i64),
+                ),
+                // other people's personal items aren't part of the household's shopping
+                None => query
+                    .filter(<ShoppingListItemEntity as EntityTrait>::Column::Personal.eq(false)),
+            };
+            let mut history: Vec<ShoppingListItemModel> = query
+                .order_by_desc(<ShoppingListItemEntity as EntityTrait>::Column::CreatedAt)
+                .limit(STAPLE_MAX_ITEMS)
+                .all(&*self.database)
+                .await?;
+            history.reverse();
+
+            Ok(overdue_staples(history, now))
+        }
+    }
+
+    /// group a history of items by name, and find those which have gone unusually long without
+    /// being added, ordered by how overdue they are
+    fn overdue_staples(
+        history: Vec<ShoppingListItemModel>,
+        now: chrono::NaiveDateTime,
+    ) -> Vec<OverdueStaple> {
+        let mut by_item: HashMap<String, Vec<ShoppingListItemModel>> = HashMap::new();
+        for item in history {
+            by_item
+                .entry(item.item.trim().to_lowercase())
+                .or_default()
+                .push(item);
+        }
+
+        let days_between = |from: chrono::NaiveDateTime, to: chrono::NaiveDateTime| {
+            (to - from).num_minutes() as f64 / (24.0 * 60.0)
+        };
+
+        let mut staples: Vec<OverdueStaple> = by_item
+            .into_values()
+            .filter(|occurrences| occurrences.len() >= STAPLE_MIN_OCCURRENCES)
+            // an item which is already on the list doesn't need suggesting
+            .filter(|occurrences| occurrences.iter().all(|item| item.bought))
+            .filter_map(|occurrences| {
+                let mut intervals: Vec<f64> = occurrences
+                    .windows(2)
+                    .map(|w| days_between(w[0].created_at, w[1].created_at))
+                    .collect();
+                intervals.sort_by(|a, b| a.total_cmp(b));
+                let cadence_days = intervals[intervals.len() / 2];
+
+                let latest = occurrences.into_iter().last()?;
+                let days_since = days_between(latest.created_at, now);
+
+                // items added several times a day are duplicates rather than a cadence
+                (cadence_days >= 1.0 && days_since > cadence_days * STAPLE_OVERDUE_FACTOR).then(
+                    || OverdueStaple {
+                        latest,
+                        cadence_days,
+                        days_since,
+                    },
+                )
+            })
+            .collect();
+
+        staples.sort_by(|a, b| {
+            (b.days_since / b.cadence_days).total_cmp(&(a.days_since / a.cadence_days))
+        });
+        staples
+    }
+
+    /// total the counts of a list overall and by store. Stores are matched regardless of case,
+    /// and named as they are first written in alphabetical order
+    fn list_progress(counts: &[StoreCount]) -> ListProgress {
+        let mut progress = ListProgress::default();
+        let mut anywhere: Option<StoreProgress> = None;
+
+        for count in counts {
+            let items = count.count.max(0) as usize;
+            let bought = match count.bought {
+                true => items,
+                false => 0,
+            };
+            progress.total += items;
+            progress.bought += bought;
+
+            let store = count
+                .store
+                .as_deref()
+                .map(str::trim)
+                .filter(|store| !store.is_empty());
+            let entry = match store {
+                Some(store) => match progress.stores.iter().position(|s| {
+                    s.store
+                        .as_deref()
+                        .map_or(false, |s| s.eq_ignore_ascii_case(store))
+                }) {
+                    Some(i) => &mut progress.stores[i],
+                    None => {
+                        progress.stores.push(StoreProgress {
+                            store: Some(store.to_string()),
+                            bought: 0,
+                            total: 0,
+                        });
+                        progress.stores.last_mut().unwrap()
+                    }
+                },
+                None => anywhere.get_or_insert(StoreProgress {
+                    store: None,
+                    bought: 0,
+                    total: 0,
+                }),
+            };
+            entry.total += items;
+            entry.bought += bought;
+        }
+
+        progress.stores.extend(anywhere);
+        progress
+    }
+
+    /// split a history of items into trips, runs of items added by one user in quick succession,
+    /// and count the pairs of items which share a trip and when items are added
+    fn insights(mut history: Vec<ShoppingListItemModel>, timezone: Tz) -> Option<Insights> {
+        if history.len() < INSIGHTS_MIN_ITEMS {
+            return None;
+        }
+        history.sort_by_key(|item| (item.user_id, item.created_at));
+
+        let mut trips: Vec<BTreeSet<String>> = Vec::new();
+        let mut last: Option<&ShoppingListItemModel> = None;
+        for item in history.iter() {
+            let same_trip = last.map_or(false, |last| {
+                last.user_id == item.user_id
+                    && item.created_at - last.created_at
+                        <= chrono::Duration::minutes(INSIGHTS_TRIP_GAP_MINUTES)
+            });
+            if !same_trip {
+                trips.push(BTreeSet::new());
+            }
+            if let Some(trip) = trips.last_mut() {
+                trip.insert(item.item.trim().to_lowercase());
+            }
+            last = Some(item);
+        }
+
+        let mut pair_counts: HashMap<(String, String), usize> = HashMap::new();
+        for trip in trips
+            .iter()
+            .filter(|trip| trip.len() <= INSIGHTS_MAX_TRIP_ITEMS)
+        {
+            let names: Vec<&String> = trip.iter().collect();
+            for (i, first) in names.iter().enumerate() {
+                for second in names[i + 1..].iter() {
+                    *pair_counts
+                        .entry(((*first).clone(), (*second).clone()))
+                        .or_default() += 1;
+                }
+            }
+        }
+
+        // a pair seen on a single trip is chance rather than a habit
+        let mut pairs: Vec<(String, String, usize)> = pair_counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|((first, second), count)| (first, second, count))
+            .collect();
+        pairs.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| (&a.0, &a.1).cmp(&(&b.0, &b.1))));
+        pairs.truncate(INSIGHTS_TOP_PAIRS);
+
+        let mut days: HashMap<Weekday, usize> = HashMap::new();
+        let mut hours: HashMap<u32, usize> = HashMap::new();
+        for item in history.iter() {
+            if let Some(added) = Local.from_local_datetime(&item.created_at).earliest() {
+                let added = added.with_timezone(&timezone);
+                *days.entry(added.weekday()).or_default() += 1;
+                *hours.entry(added.hour()).or_default() += 1;
+            }
+        }
+
+        // ties go to the earliest day or hour, so the answer doesn't change between runs
+        let busiest_day = days.into_iter().max_by(|a, b| {
+            a.1.cmp(&b.1)
+                .then_with(|| b.0.num_days_from_monday().cmp(&a.0.num_days_from_monday()))
+        })?;
+        let busiest_hour = hours
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))?;
+
+        Some(Insights {
+            items: history.len(),
+            pairs,
+            busiest_day,
+            busiest_hour,
+        })
+    }
+
+    /// sort items as the digest shows them, in the order they were moved into and then the order
+    /// they were added in
+    fn digest_order(items: &mut [ShoppingListItemModel]) {
+        items.sort_by_key(|item| {
+            (
+                item.order_index.is_none(),
+                item.order_index,
+                item.created_at,
+            )
+        });
+    }
+
+    /// recipe groups are matched case-insensitively, so are stored in a normalised form
+    fn normalise_recipe_group(recipe_group: &str) -> String {
+        recipe_group.trim().to_lowercase()
+    }
+
+    /// an item for tests to start from, pending on list 1 and posted in message `id` of channel 2
+    /// in guild 1 by user 3, at midday on the first of june 2023. Tests override what they need
+    /// with struct update syntax
+    #[cfg(test)]
+    pub fn test_item(id: i32, name: &str) -> ShoppingListItemModel {
+        ShoppingListItemModel {
+            id,
+            list_id: 1,
+            message_id: id as i64,
+            user_id: 3,
+            created_at: chrono::NaiveDate::from_ymd_opt(2023, 6, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            bought: false,
+            item: name.to_string(),
+            quantity: 1,
+            personal: false,
+            store: None,
+            notes: None,
+            guild_id: Some(1),
+            channel_id: Some(2),
+            recipe_group: None,
+            modified_by: None,
+            modified_at: None,
+            bought_at: None,
+            cleared_at: None,
+            order_index: None,
+            message_expired_at: None,
+            bought_for: None,
+            product_url: None,
+            bought_by: None,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use sea_orm::ConnectionTrait;
+
+        use super::*;
+        use crate::state::test_database_urls;
+
+        const GUILD: u64 = 1;
+        const CHANNEL: u64 = 2;
+        const USER: u64 = 3;
+
+        fn new_item(item: &str) -> NewShoppingListItem<'_> {
+            NewShoppingListItem {
+                item,
+                store: None,
+                notes: None,
+                quantity: 1,
+                personal: false,
+                recipe_group: None,
+                bought_for: None,
+                product_url: None,
+            }
+        }
+
+        /// an item added on `day` of the history, where day 0 is the start of june
+        fn added(item: &str, day: i64, bought: bool) -> ShoppingListItemModel {
+            ShoppingListItemModel {
+                user_id: USER as i64,
+                created_at: day_of_history(day),
+                bought,
+                guild_id: Some(GUILD as i64),
+                channel_id: Some(CHANNEL as i64),
+                ..test_item(0, item)
+            }
+        }
+
+        fn day_of_history(day: i64) -> NaiveDateTime {
+            let start = chrono::NaiveDate::from_ymd_opt(2023, 6, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap();
+            start + chrono::Duration::days(day)
+        }
+
+        fn names(items: &[ShoppingListItemModel]) -> Vec<&str> {
+            let mut names: Vec<&str> = items.iter().map(|item| item.item.as_str()).collect();
+            names.sort_unstable();
+            names
+        }
+
+        #[tokio::test]
+        async fn items_are_added_bought_and_deleted() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                state
+                    .add_shopping_list_item(USER, 10, CHANNEL, Some(GUILD), new_item("milk"))
+                    .await
+                    .unwrap();
+                state
+                    .add_shopping_list_item(
+                        USER,
+                        11,
+                        CHANNEL,
+                        Some(GUILD),
+                        NewShoppingListItem {
+                            store: Some("market"),
+                            ..new_item("eggs")
+                        },
+                    )
+                    .await
+                    .unwrap();
+
+                let (_, items) = state.get_shopping_list().await.unwrap().unwrap();
+                assert_eq!(names(&items), ["eggs", "milk"], "{:?}", backend);
+                let pending = state.get_pending_items_by_store(GUILD, "market").await;
+                assert_eq!(names(&pending.unwrap()), ["eggs"], "{:?}", backend);
+
+                state
+                    .set_shopping_list_item_bought(USER, 10, true, Some(USER))
+                    .await
+                    .unwrap();
+                let milk = state
+                    .get_shopping_list_item_by_message_id(10)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert!(milk.bought, "{:?}", backend);
+                assert!(milk.bought_at.is_some(), "{:?}", backend);
+                assert_eq!(milk.bought_by, Some(USER as i64), "{:?}", backend);
+                let pending = state.get_pending_items(GUILD).await.unwrap();
+                assert_eq!(names(&pending), ["eggs"], "{:?}", backend);
+
+                assert!(state.delete_shopping_list_item(USER, 11).await.unwrap());
+                assert!(!state.delete_shopping_list_item(USER, 11).await.unwrap());
+                let pending = state.get_pending_items(GUILD).await.unwrap();
+                assert!(pending.is_empty(), "{:?}", backend);
+            }
+        }
+
+        #[tokio::test]
+        async fn every_change_records_who_made_it() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                state
+                    .add_shopping_list_item(USER, 10, CHANNEL, Some(GUILD), new_item("milk"))
+                    .await
+                    .unwrap();
+                let id = state.get_pending_items(GUILD).await.unwrap()[0].id;
+                let milk = state.get_shopping_list_item_by_id(id as u64).await.unwrap();
+                assert_eq!(milk.unwrap().modified_by, None, "{:?}", backend);
+
+                // each change is made by a different user, so a change which forgets to record
+                // itself leaves the previous user behind
+                let changed_by = |change: &str, user: i64, item: Option<ShoppingListItemModel>| {
+                    let item = item.unwrap_or_else(|| panic!("{} on {:?}", change, backend));
+                    assert_eq!(item.modified_by, Some(user), "{} on {:?}", change, backend);
+                    assert!(item.modified_at.is_some(), "{} on {:?}", change, backend);
+                };
+
+                assert!(state
+                    .update_item_store(101, 10, Some("market"))
+                    .await
+                    .unwrap());
+                let item = state.get_shopping_list_item_by_id(id as u64).await.unwrap();
+                changed_by("update_item_store", 101, item);
+                let item = state.add_item_quantity(102, id, 2).await.unwrap();
+                changed_by("add_item_quantity", 102, item);
+                let item = state.set_item_quantity(103, id, 5).await.unwrap();
+                changed_by("set_item_quantity", 103, item);
+                let item = state.step_item_quantity(104, id, -1).await.unwrap();
+                changed_by("step_item_quantity", 104, item);
+                let split = state
+                    .split_item(105, id, 1, None, 11, CHANNEL)
+                    .await
+                    .unwrap();
+                changed_by("split_item", 105, split.map(|(item, _)| item));
+                let item = state
+                    .update_item_notes(106, id, Some("blue top"))
+                    .await
+                    .unwrap();
+                changed_by("update_item_notes", 106, item);
+                let items = state
+                    .bulk_update_notes(107, &[id], "2l", NoteUpdate::Append)
+                    .await
+                    .unwrap();
+                changed_by("bulk_update_notes", 107, items.into_iter().next());
+
+                // changes to every item from a store return the items as they now are
+                let moved = state
+                    .move_pending_items_store(108, GUILD, "market", "bakery")
+                    .await
+                    .unwrap();
+                changed_by("move_pending_items_store", 108, moved.into_iter().next());
+                let item = state.get_shopping_list_item_by_id(id as u64).await.unwrap();
+                changed_by("move_pending_items_store", 108, item);
+                let cleared = state
+                    .clear_store_on_items(109, GUILD, "bakery")
+                    .await
+                    .unwrap();
+                changed_by("clear_store_on_items", 109, cleared.into_iter().next());
+                let item = state.get_shopping_list_item_by_id(id as u64).await.unwrap();
+                changed_by("clear_store_on_items", 109, item);
+            }
+        }
+
+        #[tokio::test]
+        async fn moving_a_store_leaves_history_alone() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                let at_market = |item| NewShoppingListItem {
+                    store: Some("market"),
+                    ..new_item(item)
+                };
+                for (message_id, item) in [(10, "milk"), (11, "bread"), (12, "eggs")] {
+                    state
+                        .add_shopping_list_item(
+                            USER,
+                            message_id,
+                            CHANNEL,
+                            Some(GUILD),
+                            at_market(item),
+                        )
+                        .await
+                        .unwrap();
+                }
+                state
+                    .add_shopping_list_item(USER, 13, CHANNEL, Some(GUILD + 1), at_market("fish"))
+                    .await
+                    .unwrap();
+
+                // milk was bought on an earlier trip, and bread has since been cleared
+                state
+                    .set_shopping_list_item_bought(USER, 10, true, Some(USER))
+                    .await
+                    .unwrap();
+                state
+                    .set_shopping_list_item_bought(USER, 11, true, Some(USER))
+                    .await
+                    .unwrap();
+                let bread = state
+                    .get_shopping_list_item_by_message_id(11)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                state
+                    .set_shopping_list_items_cleared(&[bread.id])
+                    .await
+                    .unwrap();
+                let history = [
+                    state
+                        .get_shopping_list_item_by_message_id(10)
+                        .await
+                        .unwrap()
+                        .unwrap(),
+                    state
+                        .get_shopping_list_item_by_message_id(11)
+                        .await
+                        .unwrap()
+                        .unwrap(),
+                    state
+                        .get_shopping_list_item_by_message_id(13)
+                        .await
+                        .unwrap()
+                        .unwrap(),
+                ];
+
+                let moved = state
+                    .move_pending_items_store(USER + 1, GUILD, "market", "bakery")
+                    .await
+                    .unwrap();
+                assert_eq!(names(&moved), ["eggs"], "{:?}", backend);
+                assert_eq!(moved[0].store.as_deref(), Some("bakery"), "{:?}", backend);
+
+                for item in history {
+                    let now = state
+                        .get_shopping_list_item_by_id(item.id as u64)
+                        .await
+                        .unwrap();
+                    assert_eq!(now, Some(item), "{:?}", backend);
+                }
+            }
+        }
+
+        #[tokio::test]
+        async fn quantities_are_only_changed_while_pending() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                state
+                    .add_shopping_list_item(USER, 10, CHANNEL, Some(GUILD), new_item("milk"))
+                    .await
+                    .unwrap();
+                let id = state.get_pending_items(GUILD).await.unwrap()[0].id;
+
+                let item = state.add_item_quantity(USER, id, 2).await.unwrap();
+                assert_eq!(item.unwrap().quantity, 3, "{:?}", backend);
+                let item = state.set_item_quantity(USER, id, 5).await.unwrap();
+                assert_eq!(item.unwrap().quantity, 5, "{:?}", backend);
+                let (item, _) = state
+                    .split_item(USER, id, 2, None, 11, CHANNEL)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(item.quantity, 3, "{:?}", backend);
+                assert_eq!(item.modified_by, Some(USER as i64), "{:?}", backend);
+
+                // adding to a quantity which can't grow any larger is refused, not wrapped around
+                state.set_item_quantity(USER, id, i64::MAX).await.unwrap();
+                let added = state.add_item_quantity(USER, id, 2).await;
+                assert!(added.is_err(), "{:?}", backend);
+                let item = state.get_shopping_list_item_by_id(id as u64).await.unwrap();
+                assert_eq!(item.unwrap().quantity, i64::MAX, "{:?}", backend);
+
+                state
+                    .set_shopping_list_items_bought(&[id], true, Some(USER))
+                    .await
+                    .unwrap();
+                let item = state.set_item_quantity(USER, id, 1).await.unwrap();
+                assert!(item.is_none(), "{:?}", backend);
+            }
+        }
+
+        #[tokio::test]
+        async fn splitting_an_item_adds_the_split_to_another_store() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+                state
+                    .add_shopping_list_item(
+                        USER,
+                        10,
+                        CHANNEL,
+                        Some(GUILD),
+                        NewShoppingListItem {
+                            store: Some("market"),
+                            quantity: 4,
+                            notes: Some("free range"),
+                            ..new_item("eggs")
+                        },
+                    )
+                    .await
+                    .unwrap();
+                let id = state.get_pending_items(GUILD).await.unwrap()[0].id;
+
+                // splitting off everything would leave nothing behind
+                let split = state
+                    .split_item(USER, id, 4, Some("bakery"), 11, CHANNEL)
+                    .await
+                    .unwrap();
+                assert!(split.is_none(), "{:?}", backend);
+
+                let (original, split) = state
+                    .split_item(USER, id, 2, Some("bakery"), 11, CHANNEL)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(original.quantity, 2, "{:?}", backend);
+                assert_eq!(split.quantity, 2, "{:?}", backend);
+                assert_eq!(split.message_id, 11, "{:?}", backend);
+                assert_eq!(split.notes.as_deref(), Some("free range"), "{:?}", backend);
+
+                let market = state.get_pending_items_by_store(GUILD, "market").await;
+                let market = market.unwrap();
+                assert_eq!(names(&market), ["eggs"], "{:?}", backend);
+                assert_eq!(market[0].quantity, 2, "{:?}", backend);
+                let bakery = state.get_pending_items_by_store(GUILD, "bakery").await;
+                let bakery = bakery.unwrap();
+                assert_eq!(names(&bakery), ["eggs"], "{:?}", backend);
+                assert_eq!(bakery[0].quantity, 2, "{:?}", backend);
+
+                // an item bought since the modal was opened isn't split
+                state
+                    .set_shopping_list_items_bought(&[id], true, Some(USER))
+                    .await
+                    .unwrap();
+                let split = state
+                    .split_item(USER, id, 1, Some("bakery"), 12, CHANNEL)
+                    .await
+                    .unwrap();
+                assert!(split.is_none(), "{:?}", backend);
+                let pending = state.get_pending_items(GUILD).await.unwrap();
+                assert_eq!(pending.len(), 1, "{:?}", backend);
+            }
+        }
+
+        #[tokio::test]
+        async fn quantities_are_stepped_without_leaving_their_bounds() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                state
+                    .add_shopping_list_item(USER, 10, CHANNEL, Some(GUILD), new_item("milk"))
+                    .await
+                    .unwrap();
+                let id = state.get_pending_items(GUILD).await.unwrap()[0].id;
+
+                let item = state.step_item_quantity(USER, id, 1).await.unwrap();
+                assert_eq!(item.unwrap().quantity, 2, "{:?}", backend);
+                let item = state.step_item_quantity(USER, id, -1).await.unwrap();
+                assert_eq!(item.unwrap().quantity, 1, "{:?}", backend);
+                let item = state.step_item_quantity(USER, id, -1).await.unwrap();
+                assert_eq!(item.unwrap().quantity, 1, "{:?}", backend);
+
+                // each of several presses at once counts
+                let presses = (0..4).map(|_| state.step_item_quantity(USER, id, 1));
+                for item in serenity::futures::future::join_all(presses).await {
+                    item.unwrap();
+                }
+                let item = state.get_shopping_list_item_by_id(id as u64).await.unwrap();
+                assert_eq!(item.unwrap().quantity, 5, "{:?}", backend);
+
+                // nor above the most allowed
+                state
+                    .set_item_quantity(USER, id, MAX_QUANTITY - 1)
+                    .await
+                    .unwrap();
+                let item = state.step_item_quantity(USER, id, 1).await.unwrap();
+                assert_eq!(item.unwrap().quantity, MAX_QUANTITY, "{:?}", backend);
+                let item = state.step_item_quantity(USER, id, 1).await.unwrap();
+                assert_eq!(item.unwrap().quantity, MAX_QUANTITY, "{:?}", backend);
+                let item = state.step_item_quantity(USER, id, -1).await.unwrap();
+                assert_eq!(item.unwrap().quantity, MAX_QUANTITY - 1, "{:?}", backend);
+                state.set_item_quantity(USER, id, 5).await.unwrap();
+
+                state
+                    .set_shopping_list_items_bought(&[id], true, Some(USER))
+                    .await
+                    .unwrap();
+                let item = state.step_item_quantity(USER, id, 1).await.unwrap();
+                assert!(item.is_none(), "{:?}", backend);
+                let item = state.get_shopping_list_item_by_id(id as u64).await.unwrap();
+                assert_eq!(item.unwrap().quantity, 5, "{:?}", backend);
+            }
+        }
+
+        #[tokio::test]
+        async fn stores_are_only_changed_while_pending() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+                state
+                    .add_shopping_list_item(USER, 10, CHANNEL, Some(GUILD), new_item("milk"))
+                    .await
+                    .unwrap();
+                let store = |item: Option<ShoppingListItemModel>| item.unwrap().store;
+
+                let changed = state.update_item_store(USER, 10, Some("market")).await;
+                assert!(changed.unwrap(), "{:?}", backend);
+                let milk = state.get_shopping_list_item_by_message_id(10).await;
+                assert_eq!(
+                    store(milk.unwrap()).as_deref(),
+                    Some("market"),
+                    "{:?}",
+                    backend
+                );
+
+                let changed = state.update_item_store(USER, 10, None).await;
+                assert!(changed.unwrap(), "{:?}", backend);
+                let milk = state.get_shopping_list_item_by_message_id(10).await;
+                assert_eq!(store(milk.unwrap()), None, "{:?}", backend);
+
+                // the store menu was left open while the item was bought
+                state
+                    .set_shopping_list_item_bought(USER, 10, true, Some(USER))
+                    .await
+                    .unwrap();
+                let changed = state.update_item_store(USER, 10, Some("market")).await;
+                assert!(!changed.unwrap(), "{:?}", backend);
+                let milk = state.get_shopping_list_item_by_message_id(10).await;
+                assert_eq!(store(milk.unwrap()), None, "{:?}", backend);
+
+                let changed = state.update_item_store(USER, 99, Some("market")).await;
+                assert!(!changed.unwrap(), "{:?}", backend);
+            }
+        }
+
+        #[tokio::test]
+        async fn changing_the_store_moves_the_item_between_groups() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+                state
+                    .add_shopping_list_item(
+                        USER,
+                        10,
+                        CHANNEL,
+                        Some(GUILD),
+                        NewShoppingListItem {
+                            store: Some("market"),
+                            ..new_item("milk")
+                        },
+                    )
+                    .await
+                    .unwrap();
+                state
+                    .add_shopping_list_item(
+                        USER,
+                        11,
+                        CHANNEL,
+                        Some(GUILD + 1),
+                        NewShoppingListItem {
+                            store: Some("elsewhere"),
+                            ..new_item("eggs")
+                        },
+                    )
+                    .await
+                    .unwrap();
+
+                state
+                    .update_item_store(USER, 10, Some("bakery"))
+                    .await
+                    .unwrap();
+                let market = state.get_pending_items_by_store(GUILD, "market").await;
+                assert!(market.unwrap().is_empty(), "{:?}", backend);
+                let bakery = state.get_pending_items_by_store(GUILD, "bakery").await;
+                assert_eq!(names(&bakery.unwrap()), ["milk"], "{:?}", backend);
+
+                // the store menu only offers the stores of the guild's own items
+                let recent = state.get_recent_guild_items(Some(GUILD), 50).await;
+                assert_eq!(names(&recent.unwrap()), ["milk"], "{:?}", backend);
+                let recent = state.get_recent_guild_items(None, 50).await;
+                assert!(recent.unwrap().is_empty(), "{:?}", backend);
+            }
+        }
+
+        #[tokio::test]
+        async fn clearing_a_recipe_leaves_other_items_pending() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+                let items = [
+                    (GUILD, "spaghetti", Some("Pasta Night")),
+                    (GUILD, "mince", Some("pasta night ")),
+                    (GUILD, "tortillas", Some("tacos")),
+                    (GUILD, "milk", None),
+                    (GUILD + 1, "penne", Some("pasta night")),
+                ];
+                for (message_id, (guild, item, recipe_group)) in (10..).zip(items) {
+                    state
+                        .add_shopping_list_item(
+                            USER,
+                            message_id,
+                            CHANNEL,
+                            Some(guild),
+                            NewShoppingListItem {
+                                recipe_group,
+                                ..new_item(item)
+                            },
+                        )
+                        .await
+                        .unwrap();
+                }
+
+                // groups are matched regardless of case and spacing, within the guild
+                let pasta = state
+                    .get_items_by_recipe_group(GUILD, " PASTA night")
+                    .await
+                    .unwrap();
+                assert_eq!(names(&pasta), ["mince", "spaghetti"], "{:?}", backend);
+
+                let ids: Vec<i32> = pasta.iter().map(|item| item.id).collect();
+                state
+                    .set_shopping_list_items_bought(&ids, true, None)
+                    .await
+                    .unwrap();
+
+                let pasta = state.get_items_by_recipe_group(GUILD, "pasta night").await;
+                assert!(pasta.unwrap().is_empty(), "{:?}", backend);
+                let tacos = state.get_items_by_recipe_group(GUILD, "tacos").await;
+                assert_eq!(names(&tacos.unwrap()), ["tortillas"], "{:?}", backend);
+                let pending = state.get_pending_items(GUILD).await.unwrap();
+                assert_eq!(names(&pending), ["milk", "tortillas"], "{:?}", backend);
+                let elsewhere = state
+                    .get_items_by_recipe_group(GUILD + 1, "pasta night")
+                    .await;
+                assert_eq!(names(&elsewhere.unwrap()), ["penne"], "{:?}", backend);
+            }
+        }
+
+        #[tokio::test]
+        async fn large_histories_are_counted_and_bounded() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+                let now = Local::now().naive_local();
+
+                state
+                    .add_shopping_list_item(USER, 10, CHANNEL, Some(GUILD), new_item("bread"))
+                    .await
+                    .unwrap();
+                let list_id = state.get_shopping_list().await.unwrap().unwrap().0.id;
+                let row = |item: &str, created_at: NaiveDateTime, bought: bool| {
+                    let mut item = ShoppingListItemModel {
+                        list_id,
+                        created_at,
+                        ..added(item, 0, bought)
+                    }
+                    .into_active_model();
+                    item.id = ActiveValue::NotSet;
+                    item
+                };
+
+                // a staple which would be overdue, but is older than the most recent window
+                let mut rows: Vec<ShoppingListItemActiveModel> = [170, 160, 150]
+                    .iter()
+                    .map(|days| row("flour", now - chrono::Duration::days(*days), true))
+                    .collect();
+                // more items than are loaded to find staples, every third still pending, from
+                // the market and anywhere in turn
+                let start = now - chrono::Duration::days(140);
+                rows.extend(
+                    (0..=STAPLE_MAX_ITEMS as i64).map(|i| ShoppingListItemActiveModel {
+                        store: ActiveValue::Set((i % 2 == 0).then(|| "market".to_string())),
+                        ..row(
+                            &format!("item {}", i),
+                            start + chrono::Duration::minutes(i),
+                            i % 3 != 0,
+                        )
+                    }),
+                );
+                // a staple recent enough to be loaded, which is overdue
+                rows.extend(
+                    [60, 50, 40]
+                        .iter()
+                        .map(|days| row("coffee", now - chrono::Duration::days(*days), true)),
+                );
+                for chunk in rows.chunks(500) {
+                    ShoppingListItemEntity::insert_many(chunk.to_vec())
+                        .exec(&*state.database)
+                        .await
+                        .unwrap();
+                }
+
+                // the list starts at the oldest pending item, so the flour isn't on it
+                let progress = state.get_progress(GUILD).await.unwrap();
+                assert_eq!(progress.total, 5005, "{:?}", backend);
+                assert_eq!(progress.bought, 3337, "{:?}", backend);
+                let stores: Vec<(Option<&str>, usize, usize)> = progress
+                    .stores
+                    .iter()
+                    .map(|store| (store.store.as_deref(), store.bought, store.total))
+                    .collect();
+                assert_eq!(
+                    stores,
+                    [(Some("market"), 1667, 2501), (None, 1670, 2504)],
+                    "{:?}",
+                    backend
+                );
+
+                // only the most recent items are loaded, which leaves the flour out
+                let staples = state.get_overdue_staples(None, GUILD).await.unwrap();
+                assert_eq!(overdue_names(&staples), ["coffee"], "{:?}", backend);
+            }
+        }
+
+        fn overdue_names(staples: &[OverdueStaple]) -> Vec<&str> {
+            staples
+                .iter()
+                .map(|staple| staple.latest.item.as_str())
+                .collect()
+        }
+
+        #[test]
+        fn staples_need_three_adds() {
+            let history = vec![added("milk", 0, true), added("milk", 7, true)];
+            assert!(overdue_staples(history, day_of_history(30)).is_empty());
+
+            let history = vec![
+                added("milk", 0, true),
+                added("milk", 7, true),
+                added("Milk ", 14, true),
+            ];
+            let staples = overdue_staples(history, day_of_history(30));
+            assert_eq!(overdue_names(&staples), ["Milk "]);
+            assert_eq!(staples[0].cadence_days, 7.0);
+            assert_eq!(staples[0].days_since, 16.0);
+        }
+
+        #[test]
+        fn cadence_is_the_median_gap() {
+            // gaps of 30, 7, 7 and 1 days, the one long gap and the quick re-add are ignored
+            let history = [0, 30, 37, 44, 45]
+                .into_iter()
+                .map(|day| added("eggs", day, true))
+                .collect();
+            let staples = overdue_staples(history, day_of_history(55));
+            assert_eq!(overdue_names(&staples), ["eggs"]);
+            assert_eq!(staples[0].cadence_days, 7.0);
+        }
+
+        #[test]
+        fn staples_are_only_overdue_well_past_their_cadence() {
+            let history = [0, 10, 20]
+                .into_iter()
+                .map(|day| added("bread", day, true))
+                .collect::<Vec<_>>();
+            assert!(overdue_staples(history.clone(), day_of_history(32)).is_empty());
+            assert_eq!(overdue_staples(history, day_of_history(33)).len(), 1);
+        }
+
+        #[test]
+        fn pending_and_repeated_items_are_not_staples() {
+            let history = vec![
+                added("milk", 0, true),
+                added("milk", 7, true),
+                added("milk", 14, false),
+                // added three times in a day, which is a duplicate rather than a habit
+                added("eggs", 0, true),
+                added("eggs", 0, true),
+                added("eggs", 0, true),
+            ];
+            assert!(overdue_staples(history, day_of_history(60)).is_empty());
+        }
+
+        #[test]
+        fn the_most_overdue_staple_is_first() {
+            let mut history = Vec::new();
+            for day in [0, 7, 14] {
+                history.push(added("milk", day, true));
+            }
+            for day in [0, 2, 4] {
+                history.push(added("bread", day, true));
+            }
+            history.sort_by_key(|item| item.created_at);
+
+            let staples = overdue_staples(history, day_of_history(28));
+            assert_eq!(overdue_names(&staples), ["bread", "milk"]);
+        }
+
+        #[tokio::test]
+        async fn the_leaderboard_only_counts_the_households_shopping() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+                let since = Local::now().naive_local() - chrono::Duration::days(7);
+
+                let items = [
+                    (10, new_item("milk")),
+                    (11, new_item("bread")),
+                    (
+                        12,
+                        NewShoppingListItem {
+                            bought_for: Some("the neighbours"),
+                            ..new_item("eggs")
+                        },
+                    ),
+                    (
+                        13,
+                        NewShoppingListItem {
+                            personal: true,
+                            ..new_item("shampoo")
+                        },
+                    ),
+                ];
+                for (message_id, item) in items {
+                    state
+                        .add_shopping_list_item(USER, message_id, CHANNEL, Some(GUILD), item)
+                        .await
+                        .unwrap();
+                }
+                for (message_id, bought_by) in [(10, USER), (11, 4), (12, 4), (13, 4)] {
+                    state
+                        .set_shopping_list_item_bought(USER, message_id, true, Some(bought_by))
+                        .await
+                        .unwrap();
+                }
+
+                let leaderboard = state.get_shopper_leaderboard(GUILD, since, false).await;
+                assert_eq!(leaderboard.unwrap(), [(USER, 1), (4, 1)], "{:?}", backend);
+                let leaderboard = state.get_shopper_leaderboard(GUILD, since, true).await;
+                assert_eq!(leaderboard.unwrap(), [(4, 2), (USER, 1)], "{:?}", backend);
+            }
+        }
+
+        #[tokio::test]
+        async fn items_for_someone_else_are_left_out_of_progress_and_insights() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+                let for_neighbours = |item| NewShoppingListItem {
+                    bought_for: Some("the neighbours"),
+                    ..new_item(item)
+                };
+
+                // nothing the household needs is waiting, so there is no progress to show
+                state
+                    .add_shopping_list_item(USER, 10, CHANNEL, Some(GUILD), for_neighbours("eggs"))
+                    .await
+                    .unwrap();
+                let progress = state.get_progress(GUILD).await.unwrap();
+                assert_eq!(progress.total, 0, "{:?}", backend);
+
+                state
+                    .add_shopping_list_item(USER, 11, CHANNEL, Some(GUILD), new_item("milk"))
+                    .await
+                    .unwrap();
+                state
+                    .add_shopping_list_item(USER, 12, CHANNEL, Some(GUILD), new_item("bread"))
+                    .await
+                    .unwrap();
+                state
+                    .set_shopping_list_item_bought(USER, 12, true, Some(USER))
+                    .await
+                    .unwrap();
+                let progress = state.get_progress(GUILD).await.unwrap();
+                assert_eq!((progress.bought, progress.total), (1, 2), "{:?}", backend);
+
+                // only the household's own items count towards the history insights need
+                for message_id in 13..(13 + INSIGHTS_MIN_ITEMS as u64 - 3) {
+                    state
+                        .add_shopping_list_item(
+                            USER,
+                            message_id,
+                            CHANNEL,
+                            Some(GUILD),
+                            new_item("rice"),
+                        )
+                        .await
+                        .unwrap();
+                }
+                let insights = state.get_insights(GUILD, Tz::UTC).await.unwrap();
+                assert!(insights.is_none(), "{:?}", backend);
+
+                state
+                    .add_shopping_list_item(USER, 100, CHANNEL, Some(GUILD), new_item("rice"))
+                    .await
+                    .unwrap();
+                let insights = state.get_insights(GUILD, Tz::UTC).await.unwrap();
+                assert_eq!(insights.unwrap().items, INSIGHTS_MIN_ITEMS, "{:?}", backend);
+            }
+        }
+
+        /// the id and name of each item in the guild's digest, in the order it shows them
+        async fn digest(state: &AppState) -> Vec<(i32, String)> {
+            let items = state.get_digest_items(GUILD).await.unwrap();
+            items.into_iter().map(|item| (item.id, item.item)).collect()
+        }
+
+        fn digest_names(items: &[(i32, String)]) -> Vec<&str> {
+            items.iter().map(|(_, item)| item.as_str()).collect()
+        }
+
+        fn digest_id(items: &[(i32, String)], name: &str) -> i32 {
+            items.iter().find(|(_, item)| item == name).unwrap().0
+        }
+
+        #[tokio::test]
+        async fn reordered_items_keep_their_place_in_the_digest() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                for (message_id, item) in [(10, "milk"), (11, "bread"), (12, "eggs"), (13, "fish")]
+                {
+                    state
+                        .add_shopping_list_item(
+                            USER,
+                            message_id,
+                            CHANNEL,
+                            Some(GUILD),
+                            new_item(item),
+                        )
+                        .await
+                        .unwrap();
+                }
+                let items = digest(&state).await;
+                assert_eq!(
+                    digest_names(&items),
+                    ["milk", "bread", "eggs", "fish"],
+                    "{:?}",
+                    backend
+                );
+
+                let eggs = digest_id(&items, "eggs");
+                assert!(
+                    state.reorder_item(GUILD, eggs, -2).await.unwrap(),
+                    "{:?}",
+                    backend
+                );
+                let items = digest(&state).await;
+                assert_eq!(
+                    digest_names(&items),
+                    ["eggs", "milk", "bread", "fish"],
+                    "{:?}",
+                    backend
+                );
+
+                // items added later fall in after the reordered ones
+                state
+                    .add_shopping_list_item(USER, 14, CHANNEL, Some(GUILD), new_item("rice"))
+                    .await
+                    .unwrap();
+                let items = digest(&state).await;
+                let expected = ["eggs", "milk", "bread", "fish", "rice"];
+                assert_eq!(digest_names(&items), expected, "{:?}", backend);
+
+                // moving past the end stops at the end, and the first item can't move up
+                let milk = digest_id(&items, "milk");
+                assert!(
+                    state.reorder_item(GUILD, milk, 10).await.unwrap(),
+                    "{:?}",
+                    backend
+                );
+                assert!(
+                    !state.reorder_item(GUILD, eggs, -1).await.unwrap(),
+                    "{:?}",
+                    backend
+                );
+                let items = digest(&state).await;
+                let expected = ["eggs", "bread", "fish", "rice", "milk"];
+                assert_eq!(digest_names(&items), expected, "{:?}", backend);
+
+                // bought items aren't in the digest, so can't be moved
+                let bread = digest_id(&items, "bread");
+                state
+                    .set_shopping_list_item_bought(USER, 11, true, Some(USER))
+                    .await
+                    .unwrap();
+                assert!(
+                    !state.reorder_item(GUILD, bread, 1).await.unwrap(),
+                    "{:?}",
+                    backend
+                );
+                let items = digest(&state).await;
+                assert_eq!(
+                    digest_names(&items),
+                    ["eggs", "fish", "rice", "milk"],
+                    "{:?}",
+                    backend
+                );
+            }
+        }
+
+        /// mark an item bought some hours ago
+        async fn bought_hours_ago(state: &AppState, message_id: u64, hours: i64) {
+            let item = state
+                .get_shopping_list_item_by_message_id(message_id)
+                .await
+                .unwrap()
+                .unwrap();
+            let mut item = item.into_active_model();
+            item.bought = ActiveValue::Set(true);
+            item.bought_at = ActiveValue::Set(Some(
+                Local::now().naive_local() - chrono::Duration::hours(hours),
+            ));
+            item.update(&*state.database).await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn only_items_bought_long_enough_ago_are_cleared() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                for (message_id, item) in [(10, "milk"), (11, "bread"), (12, "eggs"), (13, "fish")]
+                {
+                    state
+                        .add_shopping_list_item(
+                            USER,
+                            message_id,
+                            CHANNEL,
+                            Some(GUILD),
+                            new_item(item),
+                        )
+                        .await
+                        .unwrap();
+                }
+                bought_hours_ago(&state, 10, 30).await;
+                bought_hours_ago(&state, 11, 2).await;
+                bought_hours_ago(&state, 12, 48).await;
+
+                // a day after they were bought, oldest first, leaving the pending item alone
+                let bought_before = Local::now().naive_local() - chrono::Duration::hours(24);
+                let to_clear = |guild, count| state.get_items_to_clear(guild, bought_before, count);
+                let items = to_clear(GUILD, 10).await.unwrap();
+                let cleared: Vec<&str> = items.iter().map(|item| item.item.as_str()).collect();
+                assert_eq!(cleared, ["eggs", "milk"], "{:?}", backend);
+                assert_eq!(
+                    names(&to_clear(GUILD, 1).await.unwrap()),
+                    ["eggs"],
+                    "{:?}",
+                    backend
+                );
+                assert!(to_clear(9, 10).await.unwrap().is_empty(), "{:?}", backend);
+
+                // items already cleared aren't cleared again
+                state
+                    .set_shopping_list_items_cleared(&[items[0].id])
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    names(&to_clear(GUILD, 10).await.unwrap()),
+                    ["milk"],
+                    "{:?}",
+                    backend
+                );
+            }
+        }
+
+        #[test]
+        fn notes_are_appended_once_or_replaced() {
+            assert_eq!(NoteUpdate::Append.apply(None, "ripe"), "ripe");
+            assert_eq!(
+                NoteUpdate::Append.apply(Some("large"), "ripe"),
+                "large ripe"
+            );
+            assert_eq!(
+                NoteUpdate::Append.apply(Some("large ripe"), "ripe"),
+                "large ripe"
+            );
+            assert_eq!(NoteUpdate::Replace.apply(Some("large"), "ripe"), "ripe");
+            assert_eq!(NoteUpdate::Replace.apply(None, "ripe"), "ripe");
+        }
+
+        #[tokio::test]
+        async fn notes_are_only_updated_on_pending_items() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                for (message_id, item) in [(10, "bananas"), (11, "apples"), (12, "pears")] {
+                    state
+                        .add_shopping_list_item(
+                            USER,
+                            message_id,
+                            CHANNEL,
+                            Some(GUILD),
+                            NewShoppingListItem {
+                                notes: Some("large"),
+                                ..new_item(item)
+                            },
+                        )
+                        .await
+                        .unwrap();
+                }
+                state
+                    .set_shopping_list_item_bought(USER, 12, true, Some(USER))
+                    .await
+                    .unwrap();
+                let ids = state
+                    .get_pending_items(GUILD)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|item| item.id)
+                    .collect::<Vec<_>>();
+                let pears = state
+                    .get_shopping_list_item_by_message_id(12)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let mut all = ids.clone();
+                all.push(pears.id);
+
+                // appending twice doesn't repeat the note
+                for _ in 0..2 {
+                    let updated = state
+                        .bulk_update_notes(USER, &all, "ripe", NoteUpdate::Append)
+                        .await
+                        .unwrap();
+                    assert_eq!(names(&updated), ["apples", "bananas"], "{:?}", backend);
+                    for item in updated {
+                        assert_eq!(item.notes.as_deref(), Some("large ripe"), "{:?}", backend);
+                        assert_eq!(item.modified_by, Some(USER as i64), "{:?}", backend);
+                    }
+                }
+
+                let updated = state
+                    .bulk_update_notes(USER, &ids[..1], "green", NoteUpdate::Replace)
+                    .await
+                    .unwrap();
+                assert_eq!(updated.len(), 1, "{:?}", backend);
+                assert_eq!(updated[0].notes.as_deref(), Some("green"), "{:?}", backend);
+
+                let pears = state
+                    .get_shopping_list_item_by_message_id(12)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(pears.notes.as_deref(), Some("large"), "{:?}", backend);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "maps")]
+pub mod distance {
+    use crate::state::AppState;
+    use chrono::Local;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::IntoActiveModel;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use sea_orm::QuerySelect;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::distance_result::ActiveModel as DistanceResultActiveModel;
+    pub use entity::distance_result::Entity as DistanceResultEntity;
+    pub use entity::distance_result::Model as DistanceResultModel;
+
+    pub use entity::distance_address::ActiveModel as DistanceAddressActiveModel;
+    pub use entity::distance_address::Entity as DistanceAddressEntity;
+
+    /// the most results that will be loaded when summarising the history of a route
+    const MAX_HISTORY: u64 = 1000;
+
+    pub struct NewDistanceResult<'a> {
+        pub destination: &'a str,
+        /// the distance travelled, in meters
+        pub distance: u32,
+        /// the time taken to travel, in seconds
+        pub duration: u32,
+    }
+
+    #[async_trait]
+    pub trait SerenityDistanceDatabase {
+        async fn add_distance_results(
+            &self,
+            origin: &str,
+            results: Vec<NewDistanceResult<'_>>,
+        ) -> DatabaseResult<()>;
+
+        async fn get_distance_history(
+            &self,
+            origin: &str,
+            destination: &str,
+        ) -> DatabaseResult<Vec<DistanceResultModel>>;
+
+        /// the address a user last looked up travel times from, if they have
+        async fn get_last_address(&self, user: u64) -> DatabaseResult<Option<String>>;
+
+        /// remember the address a user last looked up travel times from, for /distance-again
+        async fn set_last_address(&self, user: u64, address: &str) -> DatabaseResult<()>;
+    }
+
+    #[async_trait]
+    impl SerenityDistanceDatabase for AppState {
+        async fn add_distance_results(
+            &self,
+            origin: &str,
+            results: Vec<NewDistanceResult<'_>>,
+        ) -> DatabaseResult<()> {
+            if results.is_empty() {
+                return Ok(());
+            }
+
+            let created_at = Local::now().naive_local();
+            let models = results.into_iter().map(|result| DistanceResultActiveModel {
+                id: ActiveValue::NotSet,
+                origin: ActiveValue::Set(normalise_origin(origin)),
+                destination: ActiveValue::Set(result.destination.to_string()),
+                distance: ActiveValue::Set(result.distance as i64),
+                duration: ActiveValue::Set(result.duration as i64),
+                created_at: ActiveValue::Set(created_at),
+            });
+            DistanceResultEntity::insert_many(models)
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+
+        async fn get_distance_history(
+            &self,
+            origin: &str,
+            destination: &str,
+        ) -> DatabaseResult<Vec<DistanceResultModel>> {
+            let history: Vec<DistanceResultModel> = DistanceResultEntity::find()
+                .filter(
+                    <DistanceResultEntity as EntityTrait>::Column::Origin
+                        .eq(normalise_origin(origin)),
+                )
+                .filter(<DistanceResultEntity as EntityTrait>::Column::Destination.eq(destination))
+                .order_by_desc(<DistanceResultEntity as EntityTrait>::Column::CreatedAt)
+                .limit(MAX_HISTORY)
+                .all(&*self.database)
+                .await?;
+
+            Ok(history)
+        }
+
+        async fn get_last_address(&self, user: u64) -> DatabaseResult<Option<String>> {
+            let last = DistanceAddressEntity::find()
+                .filter(<DistanceAddressEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .one(&*self.database)
+                .await?;
+
+            Ok(last.map(|l| l.address))
+        }
+
+        async fn set_last_address(&self, user: u64, address: &str) -> DatabaseResult<()> {
+            let existing = DistanceAddressEntity::find()
+                .filter(<DistanceAddressEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .one(&*self.database)
+                .await?;
+
+            let mut last = match existing {
+                Some(last) => last.into_active_model(),
+                None => DistanceAddressActiveModel {
+                    id: ActiveValue::NotSet,
+                    user_id: ActiveValue::Set(user as i64),
+                    ..Default::default()
+                },
+            };
+            last.address = ActiveValue::Set(address.to_string());
+            last.updated_at = ActiveValue::Set(Local::now().naive_local());
+            last.save(&*self.database).await?;
+
+            Ok(())
+        }
+    }
+
+    /// origins are typed freely by users, so are stored in a normalised form to match repeat lookups
+    fn normalise_origin(origin: &str) -> String {
+        origin
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use sea_orm::ConnectionTrait;
+
+        use super::*;
+        use crate::state::test_database_urls;
+
+        #[tokio::test]
+        async fn the_last_address_is_remembered_per_user() {
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                assert_eq!(
+                    state.get_last_address(1).await.unwrap(),
+                    None,
+                    "{:?}",
+                    backend
+                );
+
+                // /distance saves the address, and /distance-again reads it back
+                state
+                    .set_last_address(1, "5 Te Apunga Place")
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    state.get_last_address(1).await.unwrap().as_deref(),
+                    Some("5 Te Apunga Place"),
+                    "{:?}",
+                    backend
+                );
+
+                // a later /distance replaces it, without touching anyone else's
+                state.set_last_address(1, "Queen Street").await.unwrap();
+                state.set_last_address(2, "Dominion Road").await.unwrap();
+                assert_eq!(
+                    state.get_last_address(1).await.unwrap().as_deref(),
+                    Some("Queen Street"),
+                    "{:?}",
+                    backend
+                );
+                assert_eq!(
+                    state.get_last_address(2).await.unwrap().as_deref(),
+                    Some("Dominion Road"),
+                    "{:?}",
+                    backend
+                );
+                assert_eq!(
+                    DistanceAddressEntity::find()
+                        .all(&*state.database)
+                        .await
+                        .unwrap()
+                        .len(),
+                    2,
+                    "one row is kept per user on {:?}",
+                    backend
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod recurring {
+    use crate::state::AppState;
+    use chrono::{Local, NaiveDateTime};
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::IntoActiveModel;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::recurring_item::ActiveModel as RecurringItemActiveModel;
+    pub use entity::recurring_item::Entity as RecurringItemEntity;
+    pub use entity::recurring_item::Model as RecurringItemModel;
+
+    pub struct NewRecurringItem<'a> {
+        pub item: &'a str,
+        pub store: Option<&'a str>,
+        pub notes: Option<&'a str>,
+        pub quantity: i64,
+        pub personal: bool,
+        pub schedule_spec: &'a str,
+        /// the first time the item is due, in utc
+        pub next_run: NaiveDateTime,
+    }
+
+    #[async_trait]
+    pub trait SerenityRecurringDatabase {
+        async fn add_recurring_item(
+            &self,
+
+            user: u64,
+            channel_id: u64,
+            guild_id: u64,
+
+            item: NewRecurringItem<'_>,
+        ) -> DatabaseResult<()>;
+
+        async fn get_recurring_items(&self, guild: u64) -> DatabaseResult<Vec<RecurringItemModel>>;
+
+        async fn get_due_recurring_items(
+            &self,
+            guild: u64,
+            now: NaiveDateTime,
+        ) -> DatabaseResult<Vec<RecurringItemModel>>;
+
+        async fn set_recurring_next_run(
+            &self,
+            id: i32,
+            next_run: NaiveDateTime,
+        ) -> DatabaseResult<()>;
+
+        async fn set_recurring_active(&self, id: i32, active: bool) -> DatabaseResult<()>;
+
+        /// replace the details and schedule of a recurring item, resuming it if it was paused
+        async fn update_recurring_item(
+            &self,
+            id: i32,
+            item: NewRecurringItem<'_>,
+        ) -> DatabaseResult<()>;
+
+        async fn remove_recurring_item(&self, id: i32) -> DatabaseResult<()>;
+    }
+
+    #[async_trait]
+    impl SerenityRecurringDatabase for AppState {
+        async fn add_recurring_item(
+            &self,
+
+            user: u64,
+            channel_id: u64,
+            guild_id: u64,
+
+            item: NewRecurringItem<'_>,
+        ) -> DatabaseResult<()> {
+            let recurring_item = RecurringItemActiveModel {
+                id: ActiveValue::NotSet,
+                guild_id: ActiveValue::Set(guild_id as i64),
+                channel_id: ActiveValue::Set(channel_id as i64),
+                user_id: ActiveValue::Set(user as i64),
+                item: ActiveValue::Set(item.item.to_string()),
+                quantity: ActiveValue::Set(item.quantity),
+                personal: ActiveValue::Set(item.personal),
+                store: ActiveValue::Set(item.store.map(|s| s.to_string())),
+                notes: ActiveValue::Set(item.notes.map(|s| s.to_string())),
+                schedule_spec: ActiveValue::Set(item.schedule_spec.to_string()),
+                next_run: ActiveValue::Set(item.next_run),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+                active: ActiveValue::Set(true),
+            };
+            recurring_item.insert(&*self.database).await?;
+
+            Ok(())
+        }
+
+        async fn get_recurring_items(&self, guild: u64) -> DatabaseResult<Vec<RecurringItemModel>> {
+            let items: Vec<RecurringItemModel> = RecurringItemEntity::find()
+                .filter(<RecurringItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .order_by_asc(<RecurringItemEntity as EntityTrait>::Column::NextRun)
+                .all(&*self.database)
+                .await?;
+
+            Ok(items)
+        }
+
+        async fn get_due_recurring_items(
+            &self,
+            guild: u64,
+            now: NaiveDateTime,
+        ) -> DatabaseResult<Vec<RecurringItemModel>> {
+            let items: Vec<RecurringItemModel> = RecurringItemEntity::find()
+                .filter(<RecurringItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<RecurringItemEntity as EntityTrait>::Column::NextRun.lte(now))
+                .filter(<RecurringItemEntity as EntityTrait>::Column::Active.eq(true))
+                .order_by_asc(<RecurringItemEntity as EntityTrait>::Column::NextRun)
+                .all(&*self.database)
+                .await?;
+
+            Ok(items)
+        }
+
+        async fn set_recurring_next_run(
+            &self,
+            id: i32,
+            next_run: NaiveDateTime,
+        ) -> DatabaseResult<()> {
+            let recurring_item = RecurringItemEntity::find_by_id(id)
+                .one(&*self.database)
+                .await?;
+
+            if let Some(recurring_item) = recurring_item {
+                let mut recurring_item = recurring_item.into_active_model();
+                recurring_item.next_run = ActiveValue::Set(next_run);
+                recurring_item.update(&*self.database).await?;
+            }
+
+            Ok(())
+        }
+
+        /// paused items are skipped by the scheduler until they are made active again
+        async fn set_recurring_active(&self, id: i32, active: bool) -> DatabaseResult<()> {
+            let recurring_item = RecurringItemEntity::find_by_id(id)
+                .one(&*self.database)
+                .await?;
+
+            if let Some(recurring_item) = recurring_item {
+                let mut recurring_item = recurring_item.into_active_model();
+                recurring_item.active = ActiveValue::Set(active);
+                recurring_item.update(&*self.database).await?;
+            }
+
+            Ok(())
+        }
+
+        async fn update_recurring_item(
+            &self,
+            id: i32,
+            item: NewRecurringItem<'_>,
+        ) -> DatabaseResult<()> {
+            let recurring_item = RecurringItemEntity::find_by_id(id)
+                .one(&*self.database)
+                .await?;
+
+            if let Some(recurring_item) = recurring_item {
+                let mut recurring_item = recurring_item.into_active_model();
+                recurring_item.item = ActiveValue::Set(item.item.to_string());
+                recurring_item.quantity = ActiveValue::Set(item.quantity);
+                recurring_item.personal = ActiveValue::Set(item.personal);
+                recurring_item.store = ActiveValue::Set(item.store.map(|s| s.to_string()));
+                recurring_item.notes = ActiveValue::Set(item.notes.map(|s| s.to_string()));
+                recurring_item.schedule_spec = ActiveValue::Set(item.schedule_spec.to_string());
+                recurring_item.next_run = ActiveValue::Set(item.next_run);
+                recurring_item.active = ActiveValue::Set(true);
+                recurring_item.update(&*self.database).await?;
+            }
+
+            Ok(())
+        }
+
+        async fn remove_recurring_item(&self, id: i32) -> DatabaseResult<()> {
+            RecurringItemEntity::delete_by_id(id)
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use chrono::{Duration, NaiveDate};
+        use sea_orm::ConnectionTrait;
+
+        use super::*;
+        use crate::state::test_database_urls;
+
+        const GUILD: u64 = 1;
+
+        fn weekly(item: &str, next_run: NaiveDateTime) -> NewRecurringItem<'_> {
+            NewRecurringItem {
+                item,
+                store: None,
+                notes: None,
+                quantity: 1,
+                personal: false,
+                schedule_spec: "weekly",
+                next_run,
+            }
+        }
+
+        fn due_names(items: Vec<RecurringItemModel>) -> Vec<String> {
+            items.into_iter().map(|item| item.item).collect()
+        }
+
+        #[tokio::test]
+        async fn paused_items_are_not_due() {
+            let now = NaiveDate::from_ymd_opt(2023, 6, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap();
+
+            for url in test_database_urls() {
+                let state = AppState::for_tests(&url).await;
+                let backend = state.database.get_database_backend();
+
+                for item in ["milk", "bread"] {
+                    state
+                        .add_recurring_item(3, 2, GUILD, weekly(item, now - Duration::hours(1)))
+                        .await
+                        .unwrap();
+                }
+                let items = state.get_recurring_items(GUILD).await.unwrap();
+                let milk = items.iter().find(|item| item.item == "milk").unwrap().id;
+
+                state.set_recurring_active(milk, false).await.unwrap();
+                assert_eq!(
+                    due_names(state.get_due_recurring_items(GUILD, now).await.unwrap()),
+                    vec!["bread"],
+                    "{:?}",
+                    backend
+                );
+
+                // editing a paused item resumes it
+                state
+                    .update_recurring_item(milk, weekly("milk", now - Duration::hours(2)))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    due_names(state.get_due_recurring_items(GUILD, now).await.unwrap()),
+                    vec!["milk", "bread"],
+                    "{:?}",
+                    backend
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod pins {
+    use crate::state::AppState;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::IntoActiveModel;
+    use sea_orm::QueryFilter;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::pinned_item::ActiveModel as PinnedItemActiveModel;
+    pub use entity::pinned_item::Entity as PinnedItemEntity;
+    pub use entity::pinned_item::Model as PinnedItemModel;
+
+    #[async_trait]
+    pub trait SerenityPinDatabase {
+        async fn get_pin(&self, guild: u64) -> DatabaseResult<Option<PinnedItemModel>>;
+
+        async fn set_pin(
+            &self,
+            guild: u64,
+            channel_id: u64,
+            message_id: Option<u64>,
+            item_id: Option<i32>,
+        ) -> DatabaseResult<()>;
+    }
+
+    #[async_trait]
+    impl SerenityPinDatabase for AppState {
+        async fn get_pin(&self, guild: u64) -> DatabaseResult<Option<PinnedItemModel>> {
+            let pin = PinnedItemEntity::find()
+                .filter(<PinnedItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .one(&*self.database)
+                .await?;
+
+            Ok(pin)
+        }
+
+        async fn set_pin(
+            &self,
+            guild: u64,
+            channel_id: u64,
+            message_id: Option<u64>,
+            item_id: Option<i32>,
+        ) -> DatabaseResult<()> {
+            let mut pin = match self.get_pin(guild).await? {
+                Some(pin) => pin.into_active_model(),
+                None => PinnedItemActiveModel {
+                    id: ActiveValue::NotSet,
+                    guild_id: ActiveValue::Set(guild as i64),
+                    ..Default::default()
+                },
+            };
+            pin.channel_id = ActiveValue::Set(channel_id as i64);
+            pin.message_id = ActiveValue::Set(message_id.map(|m| m as i64));
+            pin.item_id = ActiveValue::Set(item_id);
+            pin.save(&*self.database).await?;
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod private_threads {
+    use crate::state::AppState;
+    use chrono::Local;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::QueryFilter;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::private_thread::ActiveModel as PrivateThreadActiveModel;
+    pub use entity::private_thread::Entity as PrivateThreadEntity;
+    pub use entity::private_thread::Model as PrivateThreadModel;
+
+    #[async_trait]
+    pub trait SerenityPrivateThreadDatabase {
+        /// the private thread a user's personal items from a channel are posted in, if they have
+        /// one
+        async fn get_user_private_thread(
+            &self,
+            guild: u64,
+            channel: u64,
+            user: u64,
+        ) -> DatabaseResult<Option<u64>>;
+
+        async fn set_user_private_thread(
+            &self,
+            guild: u64,
+            channel: u64,
+            user: u64,
+            thread: u64,
+        ) -> DatabaseResult<()>;
+
+        /// forget a user's private thread, e.g. once it has been deleted. Returns false if they
+        /// didn't have one
+        async fn remove_user_private_thread(
+            &self,
+            guild: u64,
+            channel: u64,
+            user: u64,
+        ) -> DatabaseResult<bool>;
+    }
+
+    #[async_trait]
+    impl SerenityPrivateThreadDatabase for AppState {
+        async fn get_user_private_thread(
+            &self,
+            guild: u64,
+            channel: u64,
+            user: u64,
+        ) -> DatabaseResult<Option<u64>> {
+            let thread = PrivateThreadEntity::find()
+                .filter(<PrivateThreadEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<PrivateThreadEntity as EntityTrait>::Column::ChannelId.eq(channel as i64))
+                .filter(<PrivateThreadEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .one(&*self.database)
+                .await?;
+
+            Ok(thread.map(|t| t.thread_id as u64))
+        }
+
+        async fn set_user_private_thread(
+            &self,
+            guild: u64,
+            channel: u64,
+            user: u64,
+            thread: u64,
+        ) -> DatabaseResult<()> {
+            self.remove_user_private_thread(guild, channel, user)
+                .await?;
+
+            let thread = PrivateThreadActiveModel {
+                id: ActiveValue::NotSet,
+                guild_id: ActiveValue::Set(guild as i64),
+                channel_id: ActiveValue::Set(channel as i64),
+                user_id: ActiveValue::Set(user as i64),
+                thread_id: ActiveValue::Set(thread as i64),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+            };
+            thread.insert(&*self.database).await?;
+
+            Ok(())
+        }
+
+        async fn remove_user_private_thread(
+            &self,
+            guild: u64,
+            channel: u64,
+            user: u64,
+        ) -> DatabaseResult<bool> {
+            let result = PrivateThreadEntity::delete_many()
+                .filter(<PrivateThreadEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<PrivateThreadEntity as EntityTrait>::Column::ChannelId.eq(channel as i64))
+                .filter(<PrivateThreadEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod digest {
+    use crate::state::AppState;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::IntoActiveModel;
+    use sea_orm::QueryFilter;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::digest_message::ActiveModel as DigestMessageActiveModel;
+    pub use entity::digest_message::Entity as DigestMessageEntity;
+    pub use entity::digest_message::Model as DigestMessageModel;
+
+    #[async_trait]
+    pub trait SerenityDigestDatabase {
+        async fn get_digest(&self, guild: u64) -> DatabaseResult<Option<DigestMessageModel>>;
+
+        async fn set_digest(
+            &self,
+            guild: u64,
+            channel_id: u64,
+            message_id: u64,
+        ) -> DatabaseResult<()>;
+    }
+
+    #[async_trait]
+    impl SerenityDigestDatabase for AppState {
+        async fn get_digest(&self, guild: u64) -> DatabaseResult<Option<DigestMessageModel>> {
+            let digest = DigestMessageEntity::find()
+                .filter(<DigestMessageEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .one(&*self.database)
+                .await?;
+
+            Ok(digest)
+        }
+
+        async fn set_digest(
+            &self,
+            guild: u64,
+            channel_id: u64,
+            message_id: u64,
+        ) -> DatabaseResult<()> {
+            let mut digest = match self.get_digest(guild).await? {
+                Some(digest) => digest.into_active_model(),
+                None => DigestMessageActiveModel {
+                    id: ActiveValue::NotSet,
+                    guild_id: ActiveValue::Set(guild as i64),
+                    ..Default::default()
+                },
+            };
+            digest.channel_id = ActiveValue::Set(channel_id as i64);
+            digest.message_id = ActiveValue::Set(message_id as i64);
+            digest.save(&*self.database).await?;
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod word_filter {
+    use crate::state::AppState;
+    use chrono::Local;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::filtered_word::ActiveModel as FilteredWordActiveModel;
+    pub use entity::filtered_word::Entity as FilteredWordEntity;
+    pub use entity::filtered_word::Model as FilteredWordModel;
+
+    #[async_trait]
+    pub trait SerenityWordFilterDatabase {
+        async fn get_filtered_words(&self, guild: u64) -> DatabaseResult<Vec<String>>;
+
+        /// returns false if the word was already filtered
+        async fn add_filtered_word(&self, guild: u64, word: &str) -> DatabaseResult<bool>;
+
+        /// returns false if the word was not filtered
+        async fn remove_filtered_word(&self, guild: u64, word: &str) -> DatabaseResult<bool>;
+    }
+
+    #[async_trait]
+    impl SerenityWordFilterDatabase for AppState {
+        async fn get_filtered_words(&self, guild: u64) -> DatabaseResult<Vec<String>> {
+            let words: Vec<FilteredWordModel> = FilteredWordEntity::find()
+                .filter(<FilteredWordEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .order_by_asc(<FilteredWordEntity as EntityTrait>::Column::Word)
+                .all(&*self.database)
+                .await?;
+
+            Ok(words.into_iter().map(|w| w.word).collect())
+        }
+
+        async fn add_filtered_word(&self, guild: u64, word: &str) -> DatabaseResult<bool> {
+            let existing = FilteredWordEntity::find()
+                .filter(<FilteredWordEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<FilteredWordEntity as EntityTrait>::Column::Word.eq(word))
+                .one(&*self.database)
+                .await?;
+            if existing.is_some() {
+                return Ok(false);
+            }
+
+            let word = FilteredWordActiveModel {
+                id: ActiveValue::NotSet,
+                guild_id: ActiveValue::Set(guild as i64),
+                word: ActiveValue::Set(word.to_string()),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+            };
+            word.insert(&*self.database).await?;
+
+            Ok(true)
+        }
+
+        async fn remove_filtered_word(&self, guild: u64, word: &str) -> DatabaseResult<bool> {
+            let result = FilteredWordEntity::delete_many()
+                .filter(<FilteredWordEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<FilteredWordEntity as EntityTrait>::Column::Word.eq(word))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod item_alias {
+    use crate::state::AppState;
+    use chrono::Local;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::item_alias::ActiveModel as ItemAliasActiveModel;
+    pub use entity::item_alias::Entity as ItemAliasEntity;
+    pub use entity::item_alias::Model as ItemAliasModel;
+
+    #[async_trait]
+    pub trait SerenityItemAliasDatabase {
+        async fn get_item_aliases(&self, guild: u64) -> DatabaseResult<Vec<ItemAliasModel>>;
+
+        /// the item an alias expands to, if the alias is set in the guild
+        async fn get_item_alias(&self, guild: u64, alias: &str) -> DatabaseResult<Option<String>>;
+
+        /// returns false if the alias was already set
+        async fn add_item_alias(&self, guild: u64, alias: &str, item: &str)
+            -> DatabaseResult<bool>;
+
+        /// returns false if the alias was not set
+        async fn remove_item_alias(&self, guild: u64, alias: &str) -> DatabaseResult<bool>;
+    }
+
+    #[async_trait]
+    impl SerenityItemAliasDatabase for AppState {
+        async fn get_item_aliases(&self, guild: u64) -> DatabaseResult<Vec<ItemAliasModel>> {
+            Ok(ItemAliasEntity::find()
+                .filter(<ItemAliasEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .order_by_asc(<ItemAliasEntity as EntityTrait>::Column::Alias)
+                .all(&*self.database)
+                .await?)
+        }
+
+        async fn get_item_alias(&self, guild: u64, alias: &str) -> DatabaseResult<Option<String>> {
+            let alias = ItemAliasEntity::find()
+                .filter(<ItemAliasEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ItemAliasEntity as EntityTrait>::Column::Alias.eq(alias))
+                .one(&*self.database)
+                .await?;
+
+            Ok(alias.map(|a| a.item))
+        }
+
+        async fn add_item_alias(
+            &self,
+            guild: u64,
+            alias: &str,
+            item: &str,
+        ) -> DatabaseResult<bool> {
+            if self.get_item_alias(guild, alias).await?.is_some() {
+                return Ok(false);
+            }
+
+            let alias = ItemAliasActiveModel {
+                id: ActiveValue::NotSet,
+                guild_id: ActiveValue::Set(guild as i64),
+                alias: ActiveValue::Set(alias.to_string()),
+                item: ActiveValue::Set(item.to_string()),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+            };
+            alias.insert(&*self.database).await?;
+
+            Ok(true)
+        }
+
+        async fn remove_item_alias(&self, guild: u64, alias: &str) -> DatabaseResult<bool> {
+            let result = ItemAliasEntity::delete_many()
+                .filter(<ItemAliasEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ItemAliasEntity as EntityTrait>::Column::Alias.eq(alias))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod seed {
+    use crate::state::AppState;
+    use chrono::Local;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use serenity::async_trait;
+
+    use super::{DatabaseResult, SuggestionKind};
+
+    pub use entity::seed_entry::ActiveModel as SeedEntryActiveModel;
+    pub use entity::seed_entry::Entity as SeedEntryEntity;
+    pub use entity::seed_entry::Model as SeedEntryModel;
+
+    #[async_trait]
+    pub trait SerenitySeedDatabase {
+        /// the seed entries of a kind set in the guild, alphabetically
+        async fn get_seeds(&self, guild: u64, kind: SuggestionKind) -> DatabaseResult<Vec<String>>;
+
+        /// returns false if the entry was already set
+        async fn add_seed(
+            &self,
+            guild: u64,
+            kind: SuggestionKind,
+            value: &str,
+        ) -> DatabaseResult<bool>;
+
+        /// returns false if the entry was not set
+        async fn remove_seed(
+            &self,
+            guild: u64,
+            kind: SuggestionKind,
+            value: &str,
+        ) -> DatabaseResult<bool>;
+    }
+
+    #[async_trait]
+    impl SerenitySeedDatabase for AppState {
+        async fn get_seeds(&self, guild: u64, kind: SuggestionKind) -> DatabaseResult<Vec<String>> {
+            let seeds: Vec<SeedEntryModel> = SeedEntryEntity::find()
+                .filter(<SeedEntryEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<SeedEntryEntity as EntityTrait>::Column::Kind.eq(kind.as_str()))
+                .order_by_asc(<SeedEntryEntity as EntityTrait>::Column::Value)
+                .all(&*self.database)
+                .await?;
+
+            Ok(seeds.into_iter().map(|s| s.value).collect())
+        }
+
+        async fn add_seed(
+            &self,
+            guild: u64,
+            kind: SuggestionKind,
+            value: &str,
+        ) -> DatabaseResult<bool> {
+            let existing = SeedEntryEntity::find()
+                .filter(<SeedEntryEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<SeedEntryEntity as EntityTrait>::Column::Kind.eq(kind.as_str()))
+                .filter(<SeedEntryEntity as EntityTrait>::Column::Value.eq(value))
+                .one(&*self.database)
+                .await?;
+            if existing.is_some() {
+                return Ok(false);
+            }
+
+            let seed = SeedEntryActiveModel {
+                id: ActiveValue::NotSet,
+                guild_id: ActiveValue::Set(guild as i64),
+                kind: ActiveValue::Set(kind.as_str().to_string()),
+                value: ActiveValue::Set(value.to_string()),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+            };
+            seed.insert(&*self.database).await?;
+
+            Ok(true)
+        }
+
+        async fn remove_seed(
+            &self,
+            guild: u64,
+            kind: SuggestionKind,
+            value: &str,
+        ) -> DatabaseResult<bool> {
+            let result = SeedEntryEntity::delete_many()
+                .filter(<SeedEntryEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<SeedEntryEntity as EntityTrait>::Column::Kind.eq(kind.as_str()))
+                .filter(<SeedEntryEntity as EntityTrait>::Column::Value.eq(value))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod mru {
+    use crate::state::AppState;
+    use chrono::Local;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::IntoActiveModel;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use sea_orm::QuerySelect;
+    use serenity::async_trait;
+
+    use super::{DatabaseResult, SuggestionKind};
+
+    pub use entity::autocomplete_mru::ActiveModel as AutocompleteMruActiveModel;
+    pub use entity::autocomplete_mru::Entity as AutocompleteMruEntity;
+    pub use entity::autocomplete_mru::Model as AutocompleteMruModel;
+
+    /// the most entries of each kind kept for a user, as many as discord will show as suggestions
+    const MRU_CAP: u64 = 25;
+
+    #[async_trait]
+    pub trait SerenityMruDatabase {
+        /// move an item or store to the front of a user's most recently used list, dropping the
+        /// least recently used entries beyond the cap
+        async fn record_mru(
+            &self,
+            user: u64,
+            kind: SuggestionKind,
+            value: &str,
+        ) -> DatabaseResult<()>;
+
+        /// up to `count` of a user's most recently used items or stores, most recent first
+        async fn get_mru(
+            &self,
+            user: u64,
+            kind: SuggestionKind,
+            count: u64,
+        ) -> DatabaseResult<Vec<String>>;
+    }
+
+    #[async_trait]
+    impl SerenityMruDatabase for AppState {
+        async fn record_mru(
+            &self,
+            user: u64,
+            kind: SuggestionKind,
+            value: &str,
+        ) -> DatabaseResult<()> {
+            let now = Local::now().naive_local();
+            let existing = AutocompleteMruEntity::find()
+                .filter(<AutocompleteMruEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .filter(<AutocompleteMruEntity as EntityTrait>::Column::Kind.eq(kind.as_str()))
+                .filter(<AutocompleteMruEntity as EntityTrait>::Column::Value.eq(value))
+                .one(&*self.database)
+                .await?;
+
+            match existing {
+                Some(existing) => {
+                    let mut existing = existing.into_active_model();
+                    existing.used_at = ActiveValue::Set(now);
+                    existing.update(&*self.database).await?;
+                }
+                None => {
+                    let entry = AutocompleteMruActiveModel {
+                        id: ActiveValue::NotSet,
+                        user_id: ActiveValue::Set(user as i64),
+                        kind: ActiveValue::Set(kind.as_str().to_string()),
+                        value: ActiveValue::Set(value.to_string()),
+                        used_at: ActiveValue::Set(now),
+                    };
+                    entry.insert(&*self.database).await?;
+                }
+            }
+
+            // the list is capped on every write, so at most one entry is ever over the cap
+            let entries: Vec<AutocompleteMruModel> = AutocompleteMruEntity::find()
+                .filter(<AutocompleteMruEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .filter(<AutocompleteMruEntity as EntityTrait>::Column::Kind.eq(kind.as_str()))
+                .order_by_desc(<AutocompleteMruEntity as EntityTrait>::Column::UsedAt)
+                .all(&*self.database)
+                .await?;
+            let stale: Vec<i32> = entries
+                .into_iter()
+                .skip(MRU_CAP as usize)
+                .map(|entry| entry.id)
+                .collect();
+            if !stale.is_empty() {
+                AutocompleteMruEntity::delete_many()
+                    .filter(<AutocompleteMruEntity as EntityTrait>::Column::Id.is_in(stale))
+                    .exec(&*self.database)
+                    .await?;
+            }
+
+            Ok(())
+        }
+
+        async fn get_mru(
+            &self,
+            user: u64,
+            kind: SuggestionKind,
+            count: u64,
+        ) -> DatabaseResult<Vec<String>> {
+            let entries: Vec<AutocompleteMruModel> = AutocompleteMruEntity::find()
+                .filter(<AutocompleteMruEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .filter(<AutocompleteMruEntity as EntityTrait>::Column::Kind.eq(kind.as_str()))
+                .order_by_desc(<AutocompleteMruEntity as EntityTrait>::Column::UsedAt)
+                .limit(count.min(MRU_CAP))
+                .all(&*self.database)
+                .await?;
+
+            Ok(entries.into_iter().map(|entry| entry.value).collect())
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod shop_template {
+    use crate::state::AppState;
+    use chrono::Local;
+    use sea_orm::sea_query::Expr;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::shop_template::ActiveModel as ShopTemplateActiveModel;
+    pub use entity::shop_template::Entity as ShopTemplateEntity;
+    pub use entity::shop_template::Model as ShopTemplateModel;
+
+    #[async_trait]
+    pub trait SerenityShopTemplateDatabase {
+        async fn get_shop_templates(&self, guild: u64) -> DatabaseResult<Vec<ShopTemplateModel>>;
+
+        async fn get_shop_template(
+            &self,
+            guild: u64,
+            name: &str,
+        ) -> DatabaseResult<Option<ShopTemplateModel>>;
+
+        /// returns false if a template with the name already exists
+        async fn add_shop_template(
+            &self,
+            user: u64,
+            guild: u64,
+            name: &str,
+            items: &str,
+        ) -> DatabaseResult<bool>;
+
+        /// returns false if there is no template with the name
+        async fn update_shop_template(
+            &self,
+            guild: u64,
+            name: &str,
+            items: &str,
+        ) -> DatabaseResult<bool>;
+
+        /// returns false if there is no template with the name
+        async fn remove_shop_template(&self, guild: u64, name: &str) -> DatabaseResult<bool>;
+    }
+
+    #[async_trait]
+    impl SerenityShopTemplateDatabase for AppState {
+        async fn get_shop_templates(&self, guild: u64) -> DatabaseResult<Vec<ShopTemplateModel>> {
+            Ok(ShopTemplateEntity::find()
+                .filter(<ShopTemplateEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .order_by_asc(<ShopTemplateEntity as EntityTrait>::Column::Name)
+                .all(&*self.database)
+                .await?)
+        }
+
+        async fn get_shop_template(
+            &self,
+            guild: u64,
+            name: &str,
+        ) -> DatabaseResult<Option<ShopTemplateModel>> {
+            Ok(ShopTemplateEntity::find()
+                .filter(<ShopTemplateEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShopTemplateEntity as EntityTrait>::Column::Name.eq(name))
+                .one(&*self.database)
+                .await?)
+        }
+
+        async fn add_shop_template(
+            &self,
+            user: u64,
+            guild: u64,
+            name: &str,
+            items: &str,
+        ) -> DatabaseResult<bool> {
+            if self.get_shop_template(guild, name).await?.is_some() {
+                return Ok(false);
+            }
+
+            let template = ShopTemplateActiveModel {
+                id: ActiveValue::NotSet,
+                guild_id: ActiveValue::Set(guild as i64),
+                name: ActiveValue::Set(name.to_string()),
+                items: ActiveValue::Set(items.to_string()),
+                created_by: ActiveValue::Set(user as i64),
+                created_at: ActiveValue::Set(Local::now().naive_local()),
+            };
+            template.insert(&*self.database).await?;
+
+            Ok(true)
+        }
+
+        async fn update_shop_template(
+            &self,
+            guild: u64,
+            name: &str,
+            items: &str,
+        ) -> DatabaseResult<bool> {
+            let result = ShopTemplateEntity::update_many()
+                .col_expr(
+                    <ShopTemplateEntity as EntityTrait>::Column::Items,
+                    Expr::value(items),
+                )
+                .filter(<ShopTemplateEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShopTemplateEntity as EntityTrait>::Column::Name.eq(name))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+
+        async fn remove_shop_template(&self, guild: u64, name: &str) -> DatabaseResult<bool> {
+            let result = ShopTemplateEntity::delete_many()
+                .filter(<ShopTemplateEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShopTemplateEntity as EntityTrait>::Column::Name.eq(name))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(result.rows_affected > 0)
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod visibility {
+    use crate::state::AppState;
+    use chrono::Local;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::IntoActiveModel;
+    use sea_orm::QueryFilter;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::shop_visibility::ActiveModel as ShopVisibilityActiveModel;
+    pub use entity::shop_visibility::Entity as ShopVisibilityEntity;
+    pub use entity::shop_visibility::Model as ShopVisibilityModel;
+
+    #[async_trait]
+    pub trait SerenityVisibilityDatabase {
+        /// whether a user has chosen for their /shop responses to be seen only by them, or none if
+        /// they use the guild's default
+        async fn get_shop_ephemeral(&self, guild: u64, user: u64) -> DatabaseResult<Option<bool>>;
+
+        /// choose whether a user's /shop responses are seen only by them, or none to go back to
+        /// the guild's default
+        async fn set_shop_ephemeral(
+            &self,
+            guild: u64,
+            user: u64,
+            ephemeral: Option<bool>,
+        ) -> DatabaseResult<()>;
+    }
+
+    #[async_trait]
+    impl SerenityVisibilityDatabase for AppState {
+        async fn get_shop_ephemeral(&self, guild: u64, user: u64) -> DatabaseResult<Option<bool>> {
+            let setting = ShopVisibilityEntity::find()
+                .filter(<ShopVisibilityEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShopVisibilityEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .one(&*self.database)
+                .await?;
+
+            Ok(setting.map(|s| s.ephemeral))
+        }
+
+        async fn set_shop_ephemeral(
+            &self,
+            guild: u64,
+            user: u64,
+            ephemeral: Option<bool>,
+        ) -> DatabaseResult<()> {
+            let ephemeral = match ephemeral {
+                Some(ephemeral) => ephemeral,
+                None => {
+                    ShopVisibilityEntity::delete_many()
+                        .filter(
+                            <ShopVisibilityEntity as EntityTrait>::Column::GuildId.eq(guild as i64),
+                        )
+                        .filter(
+                            <ShopVisibilityEntity as EntityTrait>::Column::UserId.eq(user as i64),
+                        )
+                        .exec(&*self.database)
+                        .await?;
+                    return Ok(());
+                }
+            };
+
+            let existing = ShopVisibilityEntity::find()
+                .filter(<ShopVisibilityEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShopVisibilityEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .one(&*self.database)
+                .await?;
+
+            let mut setting = match existing {
+                Some(setting) => setting.into_active_model(),
+                None => ShopVisibilityActiveModel {
+                    id: ActiveValue::NotSet,
+                    guild_id: ActiveValue::Set(guild as i64),
+                    user_id: ActiveValue::Set(user as i64),
+                    ..Default::default()
+                },
+            };
+            setting.ephemeral = ActiveValue::Set(ephemeral);
+            setting.updated_at = ActiveValue::Set(Local::now().naive_local());
+            setting.save(&*self.database).await?;
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod sheets {
+    use crate::state::AppState;
+    use chrono::{Local, NaiveDateTime};
+    use sea_orm::sea_query::{Condition, Expr};
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::IntoActiveModel;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use sea_orm::QuerySelect;
+    use serenity::async_trait;
+
+    use super::shopping::{ShoppingListItemEntity, ShoppingListItemModel};
+    use super::DatabaseResult;
+
+    pub use entity::sheet_connection::ActiveModel as SheetConnectionActiveModel;
+    pub use entity::sheet_connection::Entity as SheetConnectionEntity;
+    pub use entity::sheet_connection::Model as SheetConnectionModel;
+
+    #[async_trait]
+    pub trait SerenitySheetDatabase {
+        async fn get_sheet_connection(
+            &self,
+            guild: u64,
+        ) -> DatabaseResult<Option<SheetConnectionModel>>;
+
+        async fn set_sheet_connection(
+            &self,
+            guild: u64,
+            channel: u64,
+            sheet_id: &str,
+            refresh_token: &str,
+        ) -> DatabaseResult<()>;
+
+        async fn get_items_to_sync(
+            &self,
+            guild: u64,
+            synced_until: NaiveDateTime,
+            synced_id: i32,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>>;
+
+        async fn set_sheet_synced(
+            &self,
+            guild: u64,
+            synced_until: NaiveDateTime,
+            synced_id: i32,
+        ) -> DatabaseResult<()>;
+
+        async fn set_sheet_failed(&self, guild: u64) -> DatabaseResult<()>;
+    }
+
+    #[async_trait]
+    impl SerenitySheetDatabase for AppState {
+        async fn get_sheet_connection(
+            &self,
+            guild: u64,
+        ) -> DatabaseResult<Option<SheetConnectionModel>> {
+            let connection = SheetConnectionEntity::find()
+                .filter(<SheetConnectionEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .one(&*self.database)
+                .await?;
+
+            Ok(connection)
+        }
+
+        /// connect a guild to a sheet, replacing any sheet it was connected to. Only items bought
+        /// from now on are synced, and a connection which had failed is tried again
+        async fn set_sheet_connection(
+            &self,
+            guild: u64,
+            channel: u64,
+            sheet_id: &str,
+            refresh_token: &str,
+        ) -> DatabaseResult<()> {
+            let existing = self.get_sheet_connection(guild).await?;
+            let now = Local::now().naive_local();
+
+            let mut connection = match existing {
+                Some(connection) => connection.into_active_model(),
+                None => SheetConnectionActiveModel {
+                    id: ActiveValue::NotSet,
+                    guild_id: ActiveValue::Set(guild as i64),
+                    ..Default::default()
+                },
+            };
+            connection.channel_id = ActiveValue::Set(channel as i64);
+            connection.sheet_id = ActiveValue::Set(sheet_id.to_string());
+            connection.refresh_token = ActiveValue::Set(refresh_token.to_string());
+            connection.synced_until = ActiveValue::Set(now);
+            connection.synced_id = ActiveValue::Set(0);
+            connection.failed_at = ActiveValue::Set(None);
+            connection.created_at = ActiveValue::Set(now);
+            connection.save(&*self.database).await?;
+
+            Ok(())
+        }
+
+        /// up to `count` items of a guild bought after the last item synced, in the order they
+        /// were bought. Items bought at the same time are ordered by id, so a sync which stops
+        /// part way through them carries on from the right one. Removed items aren't synced
+        async fn get_items_to_sync(
+            &self,
+            guild: u64,
+            synced_until: NaiveDateTime,
+            synced_id: i32,
+            count: u64,
+        ) -> DatabaseResult<Vec<ShoppingListItemModel>> {
+            let items = ShoppingListItemEntity::find()
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::Bought.eq(true))
+                .filter(<ShoppingListItemEntity as EntityTrait>::Column::BoughtBy.is_not_null())
+                .filter(
+                    Condition::any()
+                        .add(
+                            <ShoppingListItemEntity as EntityTrait>::Column::BoughtAt
+                                .gt(synced_until),
+                        )
+                        .add(
+                            Condition::all()
+                                .add(
+                                    <ShoppingListItemEntity as EntityTrait>::Column::BoughtAt
+                                        .eq(synced_until),
+                                )
+                                .add(
+                                    <ShoppingListItemEntity as EntityTrait>::Column::Id
+                                        .gt(synced_id),
+                                ),
+                        ),
+                )
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::BoughtAt)
+                .order_by_asc(<ShoppingListItemEntity as EntityTrait>::Column::Id)
+                .limit(count)
+                .all(&*self.database)
+                .await?;
+
+            Ok(items)
+        }
+
+        /// record the last item added to a guild's sheet
+        async fn set_sheet_synced(
+            &self,
+            guild: u64,
+            synced_until: NaiveDateTime,
+            synced_id: i32,
+        ) -> DatabaseResult<()> {
+            SheetConnectionEntity::update_many()
+                .col_expr(
+                    <SheetConnectionEntity as EntityTrait>::Column::SyncedUntil,
+                    Expr::value(synced_until),
+                )
+                .col_expr(
+                    <SheetConnectionEntity as EntityTrait>::Column::SyncedId,
+                    Expr::value(synced_id),
+                )
+                .filter(<SheetConnectionEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+
+        /// stop syncing a guild's sheet until it is connected again, after google refused its
+        /// credentials
+        async fn set_sheet_failed(&self, guild: u64) -> DatabaseResult<()> {
+            SheetConnectionEntity::update_many()
+                .col_expr(
+                    <SheetConnectionEntity as EntityTrait>::Column::FailedAt,
+                    Expr::value(Some(Local::now().naive_local())),
+                )
+                .filter(<SheetConnectionEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "shopping")]
+pub mod reminders {
+    use crate::state::AppState;
+    use chrono::NaiveDateTime;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue;
+    use sea_orm::ColumnTrait;
+    use sea_orm::EntityTrait;
+    use sea_orm::IntoActiveModel;
+    use sea_orm::QueryFilter;
+    use sea_orm::QueryOrder;
+    use serenity::async_trait;
+
+    use super::DatabaseResult;
+
+    pub use entity::item_reminder::ActiveModel as ItemReminderActiveModel;
+    pub use entity::item_reminder::Entity as ItemReminderEntity;
+    pub use entity::item_reminder::Model as ItemReminderModel;
+
+    #[async_trait]
+    pub trait SerenityReminderDatabase {
+        async fn set_item_reminder(
+            &self,
+            guild: u64,
+            item_id: i32,
+            user: u64,
+            remind_at: NaiveDateTime,
+        ) -> DatabaseResult<()>;
+
+        async fn get_due_reminders(
+            &self,
+            guild: u64,
+            now: NaiveDateTime,
+        ) -> DatabaseResult<Vec<ItemReminderModel>>;
+
+        async fn remove_item_reminder(&self, id: i32) -> DatabaseResult<()>;
+    }
+
+    #[async_trait]
+    impl SerenityReminderDatabase for AppState {
+        /// remind a user about an item at a utc time, replacing any reminder they already had set
+        /// for it
+        async fn set_item_reminder(
+            &self,
+            guild: u64,
+            item_id: i32,
+            user: u64,
+            remind_at: NaiveDateTime,
+        ) -> DatabaseResult<()> {
+            let existing = ItemReminderEntity::find()
+                .filter(<ItemReminderEntity as EntityTrait>::Column::ItemId.eq(item_id))
+                .filter(<ItemReminderEntity as EntityTrait>::Column::UserId.eq(user as i64))
+                .one(&*self.database)
+                .await?;
+
+            let mut reminder = match existing {
+                Some(reminder) => reminder.into_active_model(),
+                None => ItemReminderActiveModel {
+                    id: ActiveValue::NotSet,
+                    guild_id: ActiveValue::Set(guild as i64),
+                    item_id: ActiveValue::Set(item_id),
+                    user_id: ActiveValue::Set(user as i64),
+                    ..Default::default()
+                },
+            };
+            reminder.remind_at = ActiveValue::Set(remind_at);
+            reminder.save(&*self.database).await?;
+
+            Ok(())
+        }
+
+        async fn get_due_reminders(
+            &self,
+            guild: u64,
+            now: NaiveDateTime,
+        ) -> DatabaseResult<Vec<ItemReminderModel>> {
+            let reminders = ItemReminderEntity::find()
+                .filter(<ItemReminderEntity as EntityTrait>::Column::GuildId.eq(guild as i64))
+                .filter(<ItemReminderEntity as EntityTrait>::Column::RemindAt.lte(now))
+                .order_by_asc(<ItemReminderEntity as EntityTrait>::Column::RemindAt)
+                .all(&*self.database)
+                .await?;
+
+            Ok(reminders)
+        }
+
+        async fn remove_item_reminder(&self, id: i32) -> DatabaseResult<()> {
+            ItemReminderEntity::delete_by_id(id)
+                .exec(&*self.database)
+                .await?;
+
+            Ok(())
+        }
     }
 }
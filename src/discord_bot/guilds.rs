@@ -1,6 +1,8 @@
 //! A handler for a guild, each guild will have one handler instance to manage it
 
 use std::{
+    fmt::Display,
+    future::Future,
     sync::{atomic::Ordering, Arc},
     time::Duration,
 };
@@ -11,6 +13,7 @@ use serenity::{
     builder::{CreateAutocompleteResponse, CreateInteractionResponse},
     client::Context,
     futures::{stream::FuturesUnordered, StreamExt},
+    http::HttpError,
     model::{id::GuildId, prelude::Message},
 };
 use tokio::{
@@ -18,17 +21,166 @@ use tokio::{
     sync::mpsc::{UnboundedReceiver, UnboundedSender},
     sync::RwLock,
     task::JoinHandle,
+    time::MissedTickBehavior,
 };
 
 use super::manager::{DiscordEvent, InternalSender};
+#[cfg(feature = "shopping")]
+use super::scheduler::run_scheduled_tasks;
 use crate::{
     discord_bot::{
-        commands::{application_command, autocomplete, command, interaction as handle_interaction},
+        commands::{
+            application_command, application_command_names, autocomplete, command,
+            interaction as handle_interaction, is_already_acknowledged, modal, CommandResponse,
+        },
         messages::non_command_message,
+        utils::AutocompleteDebouncer,
     },
     state::AppState,
 };
 
+/// how often each guild checks for scheduled work, such as recurring items which have come due
+pub(super) const SCHEDULER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// the first delay before retrying a failed command registration, doubled after each failure
+const REGISTER_INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+
+/// the longest delay between attempts to register commands
+const REGISTER_MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// the most attempts made to register commands with a guild before giving up on it
+const REGISTER_MAX_ATTEMPTS: u32 = 8;
+
+/// whether a response with the given status is worth trying again, as discord was rate limiting
+/// or overloaded rather than rejecting the request itself
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// whether a failed request is worth trying again. Serenity already waits out the rate limits it
+/// knows about, so a 429 only gets here if discord sent one unexpectedly
+fn is_retryable(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(http) => {
+            let http: &HttpError = http;
+            match http {
+                HttpError::UnsuccessfulRequest(r) => is_retryable_status(r.status_code.as_u16()),
+                // no response was received at all, e.g. the connection dropped
+                HttpError::Request(_) => true,
+                _ => false,
+            }
+        }
+        serenity::Error::Io(_) => true,
+        _ => false,
+    }
+}
+
+/// run `request` until it succeeds, fails in a way that trying again won't fix, or has been tried
+/// `max_attempts` times. The wait between attempts starts at `backoff` and doubles each time
+async fn retry_with_backoff<T, E, F, Fut>(
+    what: &str,
+    max_attempts: u32,
+    mut backoff: Duration,
+    is_retryable: impl Fn(&E) -> bool,
+    mut request: F,
+) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match request().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < max_attempts && is_retryable(&e) => {
+                warn!(
+                    "failed to {} (attempt {}/{}), retrying in {:?}: {}",
+                    what, attempt, max_attempts, backoff, e
+                );
+            }
+            Err(e) => return Err(e),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(REGISTER_MAX_BACKOFF);
+        attempt += 1;
+    }
+}
+
+/// register every command with a guild, returning how many discord confirmed.
+/// all commands are sent as a single bulk overwrite, so a failed attempt never leaves a partial
+/// set behind. Attempts which failed for a reason which may pass, such as discord being
+/// overloaded, are retried with an exponential backoff
+async fn register_commands(guild: GuildId, context: &Context) -> Result<usize, serenity::Error> {
+    let registered = retry_with_backoff(
+        &format!("register commands for guild {}", guild),
+        REGISTER_MAX_ATTEMPTS,
+        REGISTER_INITIAL_BACKOFF,
+        is_retryable,
+        || guild.set_commands(context, application_command()),
+    )
+    .await?;
+
+    let expected = application_command_names();
+    let missing: Vec<&str> = expected
+        .iter()
+        .filter(|name| !registered.iter().any(|command| &command.name == *name))
+        .map(|name| name.as_str())
+        .collect();
+
+    if missing.is_empty() {
+        info!(
+            "registered {}/{} commands for guild {}",
+            expected.len(),
+            expected.len(),
+            guild
+        );
+    } else {
+        error!(
+            "registered {}/{} commands for guild {}, missing: {}",
+            expected.len() - missing.len(),
+            expected.len(),
+            guild,
+            missing.join(", ")
+        );
+    }
+
+    Ok(expected.len() - missing.len())
+}
+
+/// send the result of a handler to the user who triggered the interaction, logging failures. If
+/// the handler had already answered the interaction before failing, the failure is sent as a
+/// followup instead, so an interaction is never answered twice. See `CommandResponse::NoResponse`
+/// for handlers which answer the interaction themselves.
+macro_rules! respond {
+    ($interaction:expr, $context:expr, $result:expr) => {{
+        let response: CommandResponse = match $result {
+            Ok(response) => response,
+            Err(response) => {
+                response.write_to_log();
+                response
+            }
+        };
+        trace!("Sending response: {:?}", response);
+
+        let followup = response.generate_followup();
+        if let Some(resp) = response.generate_response() {
+            match $interaction.create_response(&$context, resp).await {
+                Ok(()) => {}
+                Err(e) if is_already_acknowledged(&e) => match followup {
+                    Some(followup) => {
+                        if let Err(e) = $interaction.create_followup(&$context, followup).await {
+                            error!("Unable to send followup: {:?}", e);
+                        }
+                    }
+                    None => error!("Unable to send response, the interaction was already answered"),
+                },
+                Err(e) => error!("Unable to send response: {:?}", e),
+            }
+        }
+    }};
+}
+
 /// handle an interaction generated by slash command.
 /// matches over the type of interaction and then handles it appropriately, generating a response that can be sent to the user
 async fn handle_slash_command(interaction: Interaction, context: Context, app_state: AppState) {
@@ -36,35 +188,39 @@ async fn handle_slash_command(interaction: Interaction, context: Context, app_st
         Interaction::Command(raw_command) => {
             trace!("Received application command: {:?}", raw_command);
             let res = command(&raw_command, &app_state, &context).await;
-
-            match res {
-                Ok(response) => {
-                    trace!("Sending response: {:?}", response);
-
-                    if let Some(resp) = response.generate_response() {
-                        if let Err(e) = raw_command.create_response(&context, resp).await {
-                            error!("Unable to send response: {:?}", e);
-                        }
-                    }
-                }
-                Err(response) => {
-                    response.write_to_log();
-
-                    if let Some(resp) = response.generate_response() {
-                        if let Err(e) = raw_command.create_response(&context, resp).await {
-                            error!("Unable to send response: {:?}", e);
-                        }
-                    }
-                }
-            }
+            respond!(raw_command, context, res);
         }
         Interaction::Component(component) => {
             trace!("Received component interaction: {:?}", component);
-            if let Err(e) = handle_interaction(&component, &app_state, &context).await {
-                error!("Unable to handle component interaction: {:?}", e);
-            }
+            let res = handle_interaction(&component, &app_state, &context).await;
+            respond!(component, context, res);
         }
         Interaction::Autocomplete(interaction) => {
+            // skip querying for requests which have been superseded by a newer keystroke
+            let debouncer = context
+                .data
+                .read()
+                .await
+                .get::<AutocompleteDebouncer>()
+                .cloned();
+            if let (Some(debouncer), Some(focused)) = (debouncer, interaction.data.autocomplete()) {
+                let field = format!("{}/{}", interaction.data.name, focused.name);
+                if !debouncer.settle(interaction.user.id.into(), field).await {
+                    if let Err(e) = interaction
+                        .create_response(
+                            &context,
+                            CreateInteractionResponse::Autocomplete(
+                                CreateAutocompleteResponse::default(),
+                            ),
+                        )
+                        .await
+                    {
+                        error!("Unable to send autocomplete response: {:?}", e);
+                    }
+                    return;
+                }
+            }
+
             let res = autocomplete(&interaction, &app_state, &context).await;
             let resp = match res {
                 Ok(r) => r,
@@ -81,7 +237,9 @@ async fn handle_slash_command(interaction: Interaction, context: Context, app_st
             }
         }
         Interaction::Modal(submit) => {
-            error!("Received modal submit: {:?}", submit);
+            trace!("Received modal submit: {:?}", submit);
+            let res = modal(&submit, &app_state, &context).await;
+            respond!(submit, context, res);
         }
         // ping commands should not get here
         _ => unreachable!(),
@@ -179,14 +337,27 @@ impl GuildHandler {
 
             self.handle = Some(tokio::task::spawn(async move {
                 // register all commands
-                while let Err(e) = guild.set_commands(&context, application_command()).await {
-                    error!("failed to register commands for guild {}: {}", guild, e);
-                    tokio::time::sleep(Duration::from_secs(10)).await;
-                }
+                let commands_expected = application_command_names().len() as u64;
+                app_state
+                    .commands_expected
+                    .fetch_add(commands_expected, Ordering::Relaxed);
+                let commands_registered = match register_commands(guild, &context).await {
+                    Ok(registered) => registered as u64,
+                    Err(e) => {
+                        error!("gave up registering commands for guild {}: {}", guild, e);
+                        0
+                    }
+                };
+                app_state
+                    .commands_registered
+                    .fetch_add(commands_registered, Ordering::Relaxed);
 
                 let mut internal_rx = internal_rx.write().await;
                 let mut task_handles = FuturesUnordered::new();
 
+                let mut scheduler = tokio::time::interval(SCHEDULER_INTERVAL);
+                scheduler.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
                 loop {
                     select! {
                         Some(message) = internal_rx.recv() => {
@@ -220,6 +391,18 @@ impl GuildHandler {
                                 }
                             }
                         },
+                        _ = scheduler.tick() => {
+                            // all scheduled work is for the shopping list
+                            #[cfg(feature = "shopping")]
+                            {
+                                let guild_id: u64 = guild.0.into();
+                                let t_ctx = context.clone();
+                                let t_app_state = app_state.clone();
+                                task_handles.push(tokio::task::spawn(async move {
+                                    run_scheduled_tasks(guild_id, &t_ctx, &t_app_state).await;
+                                }))
+                            }
+                        },
                         // drain task handles as they complete
                         _ = task_handles.next(), if !task_handles.is_empty() => {},
                         else => break,
@@ -235,6 +418,12 @@ impl GuildHandler {
                 println!("No longer monitoring server with id {:?}", guild);
 
                 app_state.num_connected.fetch_sub(1, Ordering::Relaxed);
+                app_state
+                    .commands_expected
+                    .fetch_sub(commands_expected, Ordering::Relaxed);
+                app_state
+                    .commands_registered
+                    .fetch_sub(commands_registered, Ordering::Relaxed);
             }))
         } else {
             eprintln!("Already monitoring guild");
@@ -253,3 +442,183 @@ impl std::fmt::Debug for GuildHandler {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use serenity::{builder::CreateInteractionResponseFollowup, http::ErrorResponse};
+
+    use super::*;
+
+    /// retry a request which gives each of `responses` in turn, returning its result and how many
+    /// attempts were made
+    async fn retry_responses(responses: Vec<Result<usize, u16>>) -> (Result<usize, u16>, usize) {
+        let mut responses = responses.into_iter();
+        let mut attempts = 0;
+        let result = retry_with_backoff(
+            "register commands",
+            REGISTER_MAX_ATTEMPTS,
+            Duration::ZERO,
+            |status: &u16| is_retryable_status(*status),
+            || {
+                attempts += 1;
+                let response = responses.next().expect("tried more often than expected");
+                async move { response }
+            },
+        )
+        .await;
+        (result, attempts)
+    }
+
+    #[test]
+    fn only_rate_limits_and_server_errors_are_retryable() {
+        assert!(is_retryable_status(429));
+        assert!(is_retryable_status(500));
+        assert!(is_retryable_status(503));
+        assert!(!is_retryable_status(400));
+        assert!(!is_retryable_status(403));
+        assert!(!is_retryable_status(404));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_registration_is_retried_until_it_succeeds() {
+        let (result, attempts) = retry_responses(vec![Err(429), Err(503), Ok(5)]).await;
+        assert_eq!(result, Ok(5));
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn rejected_registration_is_not_retried() {
+        let (result, attempts) = retry_responses(vec![Err(403)]).await;
+        assert_eq!(result, Err(403));
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn registration_gives_up_after_the_last_attempt() {
+        let responses = vec![Err(429); REGISTER_MAX_ATTEMPTS as usize];
+        let (result, attempts) = retry_responses(responses).await;
+        assert_eq!(result, Err(429));
+        assert_eq!(attempts, REGISTER_MAX_ATTEMPTS as usize);
+    }
+
+    /// how an interaction was answered
+    #[derive(Debug, PartialEq, Eq)]
+    enum Answer {
+        /// a response was sent, and accepted unless the interaction had already been answered
+        Response {
+            accepted: bool,
+        },
+        Followup,
+    }
+
+    /// an interaction which records how it is answered, and which may have already been answered
+    /// by its handler
+    #[derive(Default)]
+    struct MockInteraction {
+        acknowledged: bool,
+        answers: Mutex<Vec<Answer>>,
+    }
+
+    impl MockInteraction {
+        async fn create_response(
+            &self,
+            _: &(),
+            _: CreateInteractionResponse,
+        ) -> Result<(), serenity::Error> {
+            let accepted = !self.acknowledged;
+            self.answers
+                .lock()
+                .unwrap()
+                .push(Answer::Response { accepted });
+            match accepted {
+                true => Ok(()),
+                false => Err(already_acknowledged().await),
+            }
+        }
+
+        async fn create_followup(
+            &self,
+            _: &(),
+            _: CreateInteractionResponseFollowup,
+        ) -> Result<(), serenity::Error> {
+            self.answers.lock().unwrap().push(Answer::Followup);
+            Ok(())
+        }
+    }
+
+    /// the error discord gives when an interaction which has been answered is answered again
+    async fn already_acknowledged() -> serenity::Error {
+        let response = http::Response::builder()
+            .status(400)
+            .body(r#"{"code": 40060, "message": "Interaction has already been acknowledged."}"#)
+            .unwrap();
+        let response = ErrorResponse::from_response(response.into(), reqwest::Method::POST).await;
+        HttpError::UnsuccessfulRequest(response).into()
+    }
+
+    /// answer a mock interaction with the result of its handler, returning how it was answered
+    async fn answers(
+        acknowledged: bool,
+        result: Result<CommandResponse, CommandResponse>,
+    ) -> Vec<Answer> {
+        let interaction = MockInteraction {
+            acknowledged,
+            ..Default::default()
+        };
+        let context = ();
+        respond!(interaction, context, result);
+        interaction.answers.into_inner().unwrap()
+    }
+
+    #[tokio::test]
+    async fn no_response_is_never_answered() {
+        assert!(answers(false, Ok(CommandResponse::NoResponse))
+            .await
+            .is_empty());
+        assert!(answers(true, Err(CommandResponse::NoResponse))
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn responses_are_sent_once() {
+        let success = Ok(CommandResponse::BasicSuccess("added".to_string()));
+        assert_eq!(
+            answers(false, success).await,
+            [Answer::Response { accepted: true }]
+        );
+
+        let failure = Err(CommandResponse::BasicFailure("not found".to_string()));
+        assert_eq!(
+            answers(false, failure).await,
+            [Answer::Response { accepted: true }]
+        );
+    }
+
+    #[tokio::test]
+    async fn an_answered_interaction_gets_a_followup_instead() {
+        let failure = Err(CommandResponse::InternalFailure("database".to_string()));
+        assert_eq!(
+            answers(true, failure).await,
+            [Answer::Response { accepted: false }, Answer::Followup]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_complex_success_has_no_followup_to_fall_back_on() {
+        let success = Ok(CommandResponse::ComplexSuccess(
+            CreateInteractionResponse::Acknowledge,
+        ));
+        assert_eq!(
+            answers(true, success).await,
+            [Answer::Response { accepted: false }]
+        );
+    }
+
+    #[tokio::test]
+    async fn already_acknowledged_errors_are_recognised() {
+        assert!(is_already_acknowledged(&already_acknowledged().await));
+    }
+}
@@ -1,7 +1,7 @@
 //! The global manager for the bot, which manages all guilds as individual tasks
 //! and coordinates events between them.
 
-use std::{collections::HashMap, ops::DerefMut, time::Duration};
+use std::{collections::HashMap, ops::DerefMut, sync::Arc, time::Duration};
 
 use log::{error, warn};
 use serenity::{
@@ -16,7 +16,15 @@ use tokio::{
     sync::mpsc::{unbounded_channel, UnboundedSender},
 };
 
-use super::{guilds::GuildHandler, handler::Handler};
+use super::{guilds::GuildHandler, handler::Handler, utils::AutocompleteDebouncer};
+#[cfg(feature = "shopping")]
+use super::{
+    scheduler::SchedulerRegistry,
+    utils::{
+        AutocompleteCache, PendingAddCache, PendingAnnotationCache, PendingImportCache,
+        ReaddCooldowns, WordFilterCache,
+    },
+};
 
 /// An event that may occur between the various discord services
 #[derive(Debug)]
@@ -136,6 +144,17 @@ impl<T: Send + Sync + 'static + Clone + TypeMapKey<Value = T>> DiscordBot<T> {
         {
             let mut data = client.data.write().await;
             data.insert::<InternalSender>(InternalSender(i_tx));
+            data.insert::<AutocompleteDebouncer>(Arc::new(AutocompleteDebouncer::default()));
+            #[cfg(feature = "shopping")]
+            {
+                data.insert::<WordFilterCache>(Arc::new(WordFilterCache::default()));
+                data.insert::<PendingAddCache>(Arc::new(PendingAddCache::default()));
+                data.insert::<PendingImportCache>(Arc::new(PendingImportCache::default()));
+                data.insert::<PendingAnnotationCache>(Arc::new(PendingAnnotationCache::default()));
+                data.insert::<ReaddCooldowns>(Arc::new(ReaddCooldowns::default()));
+                data.insert::<AutocompleteCache>(Arc::new(AutocompleteCache::default()));
+                data.insert::<SchedulerRegistry>(Arc::new(SchedulerRegistry::default()));
+            }
             // data.insert::<BotDiscordId>(BotDiscordId::new(client.user_id.0));
             data.insert::<T>(self.app_state.clone());
         }
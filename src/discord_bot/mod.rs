@@ -9,6 +9,13 @@ mod guilds;
 mod handler;
 mod manager;
 mod messages;
+#[cfg(feature = "shopping")]
+mod scheduler;
+#[cfg(feature = "shopping")]
+mod share;
 mod utils;
 
+pub use commands::validate_application_commands;
 pub use manager::{DiscordBot, DiscordBotBuilder};
+#[cfg(feature = "shopping")]
+pub use share::{share_routes, ShareLinks};
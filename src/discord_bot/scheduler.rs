@@ -0,0 +1,1252 @@
+//! Background work which is run periodically for each guild
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Local, Utc};
+use log::{error, info, warn};
+use serenity::{
+    all::ChannelId,
+    prelude::{Context, TypeMapKey},
+};
+
+use crate::{
+    discord_bot::{
+        commands::{classify_discord_error, post_recurring_item, DiscordErrorKind},
+        common::{
+            bulk::throttled_delete,
+            digest::refresh_digest,
+            pin::{refresh_pin, PinOutcome},
+            reminder::send_reminder,
+            schedule::Schedule,
+        },
+        database::{
+            recurring::SerenityRecurringDatabase,
+            reminders::SerenityReminderDatabase,
+            sheets::SerenitySheetDatabase,
+            shopping::{SerenityShoppingDatabase, ShoppingListItemModel},
+        },
+    },
+    notify::NotifyEvent,
+    sheets::{SheetError, SheetRow, SheetSink},
+    state::{AppState, Flatmate, ListMode, RemoveMode, CONFIG},
+};
+
+use super::guilds::SCHEDULER_INTERVAL;
+
+/// the most bought items cleared on each tick, so that a long backlog doesn't hold up the
+/// scheduler while its messages are deleted
+const AUTO_CLEAR_BATCH: u64 = 50;
+
+/// the most item messages expired on each tick, for the same reason
+const EXPIRE_BATCH: u64 = 50;
+
+/// the most bought items copied to a sheet on each tick, the rest follow on later ticks
+const SHEET_BATCH: u64 = 100;
+
+/// a piece of background work which is run for each guild on every scheduler tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScheduledTask {
+    RecurringItems,
+    PinnedItem,
+    Digest,
+    AutoClear,
+    Reminders,
+    ExpireMessages,
+    SheetSync,
+}
+
+impl ScheduledTask {
+    /// every task, in the order they are run on each tick
+    pub const ALL: [ScheduledTask; 7] = [
+        ScheduledTask::RecurringItems,
+        ScheduledTask::PinnedItem,
+        ScheduledTask::Digest,
+        ScheduledTask::AutoClear,
+        ScheduledTask::Reminders,
+        ScheduledTask::ExpireMessages,
+        ScheduledTask::SheetSync,
+    ];
+
+    /// the name of the task, as shown to and typed by users
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScheduledTask::RecurringItems => "recurring-items",
+            ScheduledTask::PinnedItem => "pinned-item",
+            ScheduledTask::Digest => "digest",
+            ScheduledTask::AutoClear => "auto-clear",
+            ScheduledTask::Reminders => "reminders",
+            ScheduledTask::ExpireMessages => "expire-messages",
+            ScheduledTask::SheetSync => "sheet-sync",
+        }
+    }
+
+    /// the task with the given name, if there is one
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|task| task.name() == name)
+    }
+
+    /// run the task once for a guild, failures are logged and notified by the task itself
+    pub async fn run(
+        self,
+        guild_id: u64,
+        ctx: &Context,
+        app_state: &AppState,
+    ) -> Result<(), String> {
+        match self {
+            ScheduledTask::RecurringItems => {
+                run_due_recurring_items(guild_id, ctx, app_state).await
+            }
+            ScheduledTask::PinnedItem => refresh_pinned_item(guild_id, ctx, app_state).await,
+            ScheduledTask::Digest => refresh_digest_message(guild_id, ctx, app_state).await,
+            ScheduledTask::AutoClear => clear_bought_items(guild_id, ctx, app_state).await,
+            ScheduledTask::Reminders => send_due_reminders(guild_id, ctx, app_state).await,
+            ScheduledTask::ExpireMessages => expire_item_messages(guild_id, ctx, app_state).await,
+            ScheduledTask::SheetSync => sync_sheet(guild_id, ctx, app_state).await,
+        }
+    }
+}
+
+/// the outcome of the last run of a task in a guild
+#[derive(Debug, Clone)]
+pub struct TaskRun {
+    pub at: DateTime<Utc>,
+    pub result: Result<(), String>,
+}
+
+/// Which tasks are paused and how each last went, for every guild. The scheduler records into
+/// this on each tick, and the /scheduler commands read and change it.
+#[derive(Default)]
+pub struct SchedulerRegistry {
+    paused: Mutex<HashSet<(u64, ScheduledTask)>>,
+    last_runs: Mutex<HashMap<(u64, ScheduledTask), TaskRun>>,
+    next_ticks: Mutex<HashMap<u64, DateTime<Utc>>>,
+    /// the tasks being run for each guild, by a tick or by /scheduler-run
+    running: Mutex<HashSet<(u64, ScheduledTask)>>,
+}
+
+/// A task being run for a guild, which can't be started again until this is dropped.
+struct RunningTask<'a> {
+    registry: &'a SchedulerRegistry,
+    key: (u64, ScheduledTask),
+}
+
+impl Drop for RunningTask<'_> {
+    fn drop(&mut self) {
+        self.registry.running.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl SchedulerRegistry {
+    pub fn is_paused(&self, guild_id: u64, task: ScheduledTask) -> bool {
+        self.paused.lock().unwrap().contains(&(guild_id, task))
+    }
+
+    /// pause or resume a task in a guild, returning false if it was already in that state
+    pub fn set_paused(&self, guild_id: u64, task: ScheduledTask, paused: bool) -> bool {
+        let mut set = self.paused.lock().unwrap();
+        match paused {
+            true => set.insert((guild_id, task)),
+            false => set.remove(&(guild_id, task)),
+        }
+    }
+
+    pub fn last_run(&self, guild_id: u64, task: ScheduledTask) -> Option<TaskRun> {
+        self.last_runs
+            .lock()
+            .unwrap()
+            .get(&(guild_id, task))
+            .cloned()
+    }
+
+    /// when the scheduler will next tick for a guild, if it has ticked at all yet
+    pub fn next_tick(&self, guild_id: u64) -> Option<DateTime<Utc>> {
+        self.next_ticks.lock().unwrap().get(&guild_id).copied()
+    }
+
+    /// the tasks to run for a guild on a tick, in the order they are run. Nothing is run during
+    /// maintenance, anything which comes due in the meantime is still due on the first tick
+    /// after it ends
+    fn due_tasks(&self, guild_id: u64, in_maintenance: bool) -> Vec<ScheduledTask> {
+        if in_maintenance {
+            return vec![];
+        }
+        ScheduledTask::ALL
+            .into_iter()
+            .filter(|task| !self.is_paused(guild_id, *task))
+            .collect()
+    }
+
+    /// mark a task as running for a guild, or none if it is already running
+    fn start(&self, guild_id: u64, task: ScheduledTask) -> Option<RunningTask<'_>> {
+        let key = (guild_id, task);
+        match self.running.lock().unwrap().insert(key) {
+            true => Some(RunningTask {
+                registry: self,
+                key,
+            }),
+            false => None,
+        }
+    }
+
+    /// run a task for a guild with `run` and record how it went, or none if it is already
+    /// running. Two runs at once would both read the same due items, so could post a recurring
+    /// item or copy a bought item to a sheet twice
+    async fn run_exclusive<F, Fut>(
+        &self,
+        guild_id: u64,
+        task: ScheduledTask,
+        run: F,
+    ) -> Option<Result<(), String>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(), String>>,
+    {
+        let _running = self.start(guild_id, task)?;
+        let result = run().await;
+        self.record(guild_id, task, result.clone());
+        Some(result)
+    }
+
+    fn record(&self, guild_id: u64, task: ScheduledTask, result: Result<(), String>) {
+        self.last_runs.lock().unwrap().insert(
+            (guild_id, task),
+            TaskRun {
+                at: Utc::now(),
+                result,
+            },
+        );
+    }
+}
+
+impl TypeMapKey for SchedulerRegistry {
+    type Value = Arc<SchedulerRegistry>;
+}
+
+/// run a single task for a guild straight away, recording the outcome as though it had been run
+/// by the scheduler. Paused tasks are still run, so that they can be tried before resuming them.
+/// Returns none without running it if the task is already running for the guild
+pub async fn run_task_now(
+    task: ScheduledTask,
+    guild_id: u64,
+    ctx: &Context,
+    app_state: &AppState,
+) -> Option<Result<(), String>> {
+    let registry = match ctx.data.read().await.get::<SchedulerRegistry>() {
+        Some(registry) => registry.clone(),
+        None => {
+            error!("scheduler registry missing from context");
+            return Some(Err("the scheduler isn't running".to_string()));
+        }
+    };
+
+    registry
+        .run_exclusive(guild_id, task, || task.run(guild_id, ctx, app_state))
+        .await
+}
+
+/// run every task which isn't paused for a guild, called on each scheduler tick. A task still
+/// running from an earlier tick or /scheduler-run is skipped, and catches up on the next tick
+pub async fn run_scheduled_tasks(guild_id: u64, ctx: &Context, app_state: &AppState) {
+    let registry = match ctx.data.read().await.get::<SchedulerRegistry>() {
+        Some(registry) => registry.clone(),
+        None => {
+            error!("scheduler registry missing from context");
+            return;
+        }
+    };
+
+    if let Ok(interval) = chrono::Duration::from_std(SCHEDULER_INTERVAL) {
+        registry
+            .next_ticks
+            .lock()
+            .unwrap()
+            .insert(guild_id, Utc::now() + interval);
+    }
+
+    for task in registry.due_tasks(guild_id, app_state.in_maintenance()) {
+        let run = registry
+            .run_exclusive(guild_id, task, || task.run(guild_id, ctx, app_state))
+            .await;
+        if run.is_none() {
+            warn!(
+                "{} is still running for {}, skipping it this tick",
+                task.name(),
+                guild_id
+            );
+        }
+    }
+}
+
+/// post every recurring item in the guild which has come due, and schedule its next run
+async fn run_due_recurring_items(
+    guild_id: u64,
+    ctx: &Context,
+    app_state: &AppState,
+) -> Result<(), String> {
+    let now = Utc::now();
+    let config = CONFIG.guild(Some(guild_id));
+    // due items stay due, so they are all posted on the first tick after quiet hours end
+    if config.defers_at(now) {
+        return Ok(());
+    }
+    let quiet = config.is_quiet_at(now);
+
+    let items = match app_state
+        .get_due_recurring_items(guild_id, now.naive_utc())
+        .await
+    {
+        Ok(items) => items,
+        Err(e) => {
+            error!("error loading due recurring items for {}: {}", guild_id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Database,
+                    &format!("unable to load due recurring items: {}", e),
+                )
+                .await;
+            return Err(format!("unable to load due recurring items: {}", e));
+        }
+    };
+
+    let mut failed = 0;
+
+    let timezone = config.timezone;
+    for item in items {
+        let schedule = match Schedule::parse(&item.schedule_spec) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                error!("recurring item {} has an invalid schedule: {}", item.id, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        // the next run is stored before posting, so a failure to post is not retried every tick
+        if let Err(e) = app_state
+            .set_recurring_next_run(item.id, schedule.next_run(now, timezone))
+            .await
+        {
+            error!("error scheduling recurring item {}: {}", item.id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Database,
+                    &format!("unable to schedule recurring item {}: {}", item.id, e),
+                )
+                .await;
+            failed += 1;
+            continue;
+        }
+
+        match post_recurring_item(ctx, app_state, &item, quiet).await {
+            Ok(()) => info!("posted recurring item {} in {}", item.id, guild_id),
+            Err(e) => {
+                error!("error posting recurring item {}: {}", item.id, e);
+                app_state
+                    .notifications
+                    .notify(
+                        NotifyEvent::Scheduler,
+                        &format!("unable to post recurring item `{}`: {}", item.item, e),
+                    )
+                    .await;
+                failed += 1;
+            }
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        n => Err(format!("{} recurring item(s) failed, see the logs", n)),
+    }
+}
+
+/// keep the pinned top item of the guild up to date as items are added and bought
+async fn refresh_pinned_item(
+    guild_id: u64,
+    ctx: &Context,
+    app_state: &AppState,
+) -> Result<(), String> {
+    match refresh_pin(guild_id, ctx, app_state, false).await {
+        Ok(PinOutcome::TooManyPins) => {
+            warn!("unable to pin top item in {}, too many pins", guild_id);
+            Err("the channel already has as many pins as discord allows".to_string())
+        }
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("error refreshing pinned item for {}: {}", guild_id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Scheduler,
+                    &format!("unable to refresh the pinned item: {}", e),
+                )
+                .await;
+            Err(e.to_string())
+        }
+    }
+}
+
+/// catch the digest of the guild up with changes made outside of it, such as recurring items
+async fn refresh_digest_message(
+    guild_id: u64,
+    ctx: &Context,
+    app_state: &AppState,
+) -> Result<(), String> {
+    if CONFIG.guild(Some(guild_id)).list_mode != ListMode::Digest {
+        return Ok(());
+    }
+
+    if let Err(e) = refresh_digest(guild_id, None, ctx, app_state, false).await {
+        error!("error refreshing digest for {}: {}", guild_id, e);
+        app_state
+            .notifications
+            .notify(
+                NotifyEvent::Scheduler,
+                &format!("unable to refresh the digest: {}", e),
+            )
+            .await;
+        return Err(e.to_string());
+    }
+
+    Ok(())
+}
+
+/// clear items bought long enough ago from the channel, for guilds which have turned it on. Their
+/// messages are deleted, and the items themselves deleted too or kept for history depending on
+/// the guild's remove mode
+async fn clear_bought_items(
+    guild_id: u64,
+    ctx: &Context,
+    app_state: &AppState,
+) -> Result<(), String> {
+    let config = CONFIG.guild(Some(guild_id));
+    let delay = match config
+        .auto_clear_delay()
+        .and_then(|delay| chrono::Duration::from_std(delay).ok())
+    {
+        Some(delay) => delay,
+        None => return Ok(()),
+    };
+
+    let bought_before = Local::now().naive_local() - delay;
+    let items = match app_state
+        .get_items_to_clear(guild_id, bought_before, AUTO_CLEAR_BATCH)
+        .await
+    {
+        Ok(items) => items,
+        Err(e) => {
+            error!(
+                "error loading bought items to clear for {}: {}",
+                guild_id, e
+            );
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Database,
+                    &format!("unable to load bought items to clear: {}", e),
+                )
+                .await;
+            return Err(format!("unable to load bought items to clear: {}", e));
+        }
+    };
+
+    // items in a digest don't have a message of their own to delete
+    let cleared = match config.list_mode {
+        ListMode::Classic => throttled_delete(ctx, &items).await,
+        ListMode::Digest => items.iter().map(|item| item.id).collect(),
+    };
+
+    if !cleared.is_empty() {
+        let stored = match config.remove_mode {
+            RemoveMode::Strike => app_state.set_shopping_list_items_cleared(&cleared).await,
+            RemoveMode::Delete => app_state.delete_shopping_list_items(&cleared).await,
+        };
+        // the next tick tries again, finding the messages already deleted
+        if let Err(e) = stored {
+            error!("error clearing bought items for {}: {}", guild_id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Database,
+                    &format!("unable to clear bought items: {}", e),
+                )
+                .await;
+            return Err(format!("unable to clear bought items: {}", e));
+        }
+        info!("cleared {} bought item(s) in {}", cleared.len(), guild_id);
+    }
+
+    match items.len() - cleared.len() {
+        0 => Ok(()),
+        n => Err(format!(
+            "{} bought item message(s) could not be deleted, see the logs",
+            n
+        )),
+    }
+}
+
+/// delete the messages of items added long enough ago from the channel, for guilds which have
+/// turned it on. The items stay on the list, and can still be ticked off through /shopping-list
+async fn expire_item_messages(
+    guild_id: u64,
+    ctx: &Context,
+    app_state: &AppState,
+) -> Result<(), String> {
+    let config = CONFIG.guild(Some(guild_id));
+    // items in a digest don't have a message of their own to expire
+    if config.list_mode != ListMode::Classic {
+        return Ok(());
+    }
+    let delay = match config
+        .item_expiry_delay()
+        .and_then(|delay| chrono::Duration::from_std(delay).ok())
+    {
+        Some(delay) => delay,
+        None => return Ok(()),
+    };
+
+    let created_before = Local::now().naive_local() - delay;
+    let items = match app_state
+        .get_items_to_expire(guild_id, created_before, EXPIRE_BATCH)
+        .await
+    {
+        Ok(items) => items,
+        Err(e) => {
+            error!("error loading items to expire for {}: {}", guild_id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Database,
+                    &format!("unable to load items to expire: {}", e),
+                )
+                .await;
+            return Err(format!("unable to load items to expire: {}", e));
+        }
+    };
+
+    let expired = throttled_delete(ctx, &items).await;
+
+    if !expired.is_empty() {
+        // the next tick tries again, finding the messages already deleted
+        if let Err(e) = app_state.set_item_messages_expired(&expired).await {
+            error!("error expiring item messages for {}: {}", guild_id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Database,
+                    &format!("unable to expire item messages: {}", e),
+                )
+                .await;
+            return Err(format!("unable to expire item messages: {}", e));
+        }
+        info!("expired {} item message(s) in {}", expired.len(), guild_id);
+    }
+
+    match items.len() - expired.len() {
+        0 => Ok(()),
+        n => Err(format!(
+            "{} item message(s) could not be deleted, see the logs",
+            n
+        )),
+    }
+}
+
+/// send every reminder in the guild which has come due. Reminders about items which have been
+/// bought or removed since they were set are dropped without being sent
+async fn send_due_reminders(
+    guild_id: u64,
+    ctx: &Context,
+    app_state: &AppState,
+) -> Result<(), String> {
+    let now = Utc::now();
+    let config = CONFIG.guild(Some(guild_id));
+    // due reminders stay due, so they are all sent on the first tick after quiet hours end
+    if config.defers_at(now) {
+        return Ok(());
+    }
+    let quiet = config.is_quiet_at(now);
+
+    deliver_due_reminders(guild_id, app_state, now, |user_id, item| async move {
+        send_reminder(ctx, guild_id, user_id, &item, quiet).await
+    })
+    .await
+}
+
+/// send each reminder due by `now` with `send`. A reminder is removed once it has been sent, or
+/// once sending it fails in a way trying again won't fix, such as the bot being unable to message
+/// the user. Any other failure leaves it due, so it is tried again on the next tick
+async fn deliver_due_reminders<F, Fut>(
+    guild_id: u64,
+    app_state: &AppState,
+    now: DateTime<Utc>,
+    send: F,
+) -> Result<(), String>
+where
+    F: Fn(u64, ShoppingListItemModel) -> Fut,
+    Fut: Future<Output = Result<(), serenity::Error>>,
+{
+    let reminders = match app_state.get_due_reminders(guild_id, now.naive_utc()).await {
+        Ok(reminders) => reminders,
+        Err(e) => {
+            error!("error loading due reminders for {}: {}", guild_id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Database,
+                    &format!("unable to load due reminders: {}", e),
+                )
+                .await;
+            return Err(format!("unable to load due reminders: {}", e));
+        }
+    };
+
+    let mut failed = 0;
+
+    for reminder in reminders {
+        let item = match app_state
+            .get_shopping_list_item_by_id(reminder.item_id as u64)
+            .await
+        {
+            Ok(item) => item.filter(|item| !item.bought),
+            Err(e) => {
+                error!("error loading item for reminder {}: {}", reminder.id, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        match item {
+            Some(item) => match send(reminder.user_id as u64, item).await {
+                Ok(()) => info!("sent reminder {} in {}", reminder.id, guild_id),
+                Err(e) if classify_discord_error(&e) == DiscordErrorKind::Permission => {
+                    warn!(
+                        "dropped reminder {} in {}, the user can't be reached: {}",
+                        reminder.id, guild_id, e
+                    );
+                }
+                Err(e) => {
+                    error!("error sending reminder {}: {}", reminder.id, e);
+                    failed += 1;
+                    continue;
+                }
+            },
+            None => info!(
+                "cancelled reminder {} in {}, its item is no longer pending",
+                reminder.id, guild_id
+            ),
+        }
+
+        // a reminder which fails to be removed after it was sent is sent again on the next tick
+        if let Err(e) = app_state.remove_item_reminder(reminder.id).await {
+            error!("error removing reminder {}: {}", reminder.id, e);
+            failed += 1;
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        n => Err(format!("{} reminder(s) failed, see the logs", n)),
+    }
+}
+
+/// what became of a guild's sheet after a sync
+#[derive(Debug, PartialEq, Eq)]
+enum SheetSync {
+    /// this many items were added to the sheet, none if nothing was bought since the last sync
+    Added(usize),
+    /// the sheet's credentials were refused, so it isn't synced again until it is reconnected
+    Refused { channel_id: u64, error: String },
+}
+
+/// copy the items bought since the last sync to the guild's sheet, if it has connected one
+async fn sync_sheet(guild_id: u64, ctx: &Context, app_state: &AppState) -> Result<(), String> {
+    let sink = match &app_state.sheets {
+        Some(sink) => sink,
+        None => return Ok(()),
+    };
+
+    match sync_sheet_batch(
+        guild_id,
+        app_state,
+        sink.as_ref(),
+        &CONFIG.flatmates,
+        SHEET_BATCH,
+    )
+    .await?
+    {
+        SheetSync::Added(_) => Ok(()),
+        SheetSync::Refused { channel_id, error } => {
+            // told once, as the sheet isn't synced again until it is reconnected
+            if let Err(e) = ChannelId::new(channel_id)
+                .say(
+                    ctx,
+                    "Google refused access to the shopping sheet, so bought items are no longer \
+                     being copied to it. Run /shop-sheet-connect again to carry on",
+                )
+                .await
+            {
+                error!("error announcing sheet failure in {}: {}", guild_id, e);
+            }
+            Err(format!("the sheet's credentials were refused: {}", error))
+        }
+    }
+}
+
+/// send up to `count` of the items bought since the last sync to the guild's sheet. Items are sent
+/// oldest first and the last one sent is recorded, so each item is added to the sheet once even if
+/// a sync fails part way through the guild's items
+async fn sync_sheet_batch(
+    guild_id: u64,
+    app_state: &AppState,
+    sink: &dyn SheetSink,
+    flatmates: &[Flatmate],
+    count: u64,
+) -> Result<SheetSync, String> {
+    let connection = match app_state.get_sheet_connection(guild_id).await {
+        // a sheet whose credentials were refused isn't tried again until it is reconnected
+        Ok(Some(connection)) if connection.failed_at.is_none() => connection,
+        Ok(_) => return Ok(SheetSync::Added(0)),
+        Err(e) => {
+            error!("error loading sheet connection for {}: {}", guild_id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Database,
+                    &format!("unable to load sheet connection: {}", e),
+                )
+                .await;
+            return Err(format!("unable to load sheet connection: {}", e));
+        }
+    };
+
+    let items = match app_state
+        .get_items_to_sync(
+            guild_id,
+            connection.synced_until,
+            connection.synced_id,
+            count,
+        )
+        .await
+    {
+        Ok(items) => items,
+        Err(e) => {
+            error!("error loading bought items to sync for {}: {}", guild_id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Database,
+                    &format!("unable to load bought items to sync: {}", e),
+                )
+                .await;
+            return Err(format!("unable to load bought items to sync: {}", e));
+        }
+    };
+
+    let last = match items.last() {
+        Some(item) => (item.bought_at.unwrap_or(connection.synced_until), item.id),
+        None => return Ok(SheetSync::Added(0)),
+    };
+
+    let rows = items
+        .iter()
+        .map(|item| {
+            let buyer = item.bought_by.unwrap_or_default() as u64;
+            SheetRow {
+                bought_at: item
+                    .bought_at
+                    .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default(),
+                item: item.item.clone(),
+                quantity: item.quantity,
+                store: item.store.clone().unwrap_or_default(),
+                buyer: flatmates
+                    .iter()
+                    .find(|flatmate| flatmate.discord_id == buyer)
+                    .map(|flatmate| flatmate.display_name.clone())
+                    .unwrap_or_else(|| buyer.to_string()),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    match sink
+        .append_rows(&connection.sheet_id, &connection.refresh_token, &rows)
+        .await
+    {
+        Ok(()) => {}
+        Err(SheetError::Auth(e)) => {
+            warn!("sheet credentials refused for {}: {}", guild_id, e);
+            if let Err(e) = app_state.set_sheet_failed(guild_id).await {
+                error!("error pausing sheet sync for {}: {}", guild_id, e);
+                return Err(format!("unable to pause sheet sync: {}", e));
+            }
+            return Ok(SheetSync::Refused {
+                channel_id: connection.channel_id as u64,
+                error: e,
+            });
+        }
+        Err(e) => {
+            // nothing was recorded as synced, so the same items are sent on the next tick
+            error!("error syncing sheet for {}: {}", guild_id, e);
+            app_state
+                .notifications
+                .notify(
+                    NotifyEvent::Scheduler,
+                    &format!("unable to sync the shopping sheet: {}", e),
+                )
+                .await;
+            return Err(e.to_string());
+        }
+    }
+
+    if let Err(e) = app_state.set_sheet_synced(guild_id, last.0, last.1).await {
+        error!("error recording sheet sync for {}: {}", guild_id, e);
+        app_state
+            .notifications
+            .notify(
+                NotifyEvent::Database,
+                &format!("unable to record sheet sync: {}", e),
+            )
+            .await;
+        return Err(format!("unable to record sheet sync: {}", e));
+    }
+    info!(
+        "synced {} bought item(s) to the sheet of {}",
+        rows.len(),
+        guild_id
+    );
+
+    Ok(SheetSync::Added(rows.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::ConnectionTrait;
+    use serenity::{
+        async_trait,
+        http::{ErrorResponse, HttpError},
+    };
+
+    use super::*;
+    use crate::{
+        discord_bot::{common::digest::digest_components, database::shopping::NewShoppingListItem},
+        state::test_database_urls,
+    };
+
+    const GUILD: u64 = 1;
+    const CHANNEL: u64 = 2;
+    const USER: u64 = 3;
+
+    /// a sheet which remembers the rows appended to it, and can be made to fail the next append
+    #[derive(Default)]
+    struct MockSheet {
+        rows: Mutex<Vec<SheetRow>>,
+        appends: Mutex<usize>,
+        fail_next: Mutex<Option<SheetError>>,
+    }
+
+    impl MockSheet {
+        fn items(&self) -> Vec<String> {
+            let rows = self.rows.lock().unwrap();
+            rows.iter().map(|row| row.item.clone()).collect()
+        }
+    }
+
+    #[async_trait]
+    impl SheetSink for MockSheet {
+        async fn append_rows(&self, _: &str, _: &str, rows: &[SheetRow]) -> Result<(), SheetError> {
+            *self.appends.lock().unwrap() += 1;
+            if let Some(e) = self.fail_next.lock().unwrap().take() {
+                return Err(e);
+            }
+            self.rows.lock().unwrap().extend(rows.iter().cloned());
+            Ok(())
+        }
+    }
+
+    /// add an item to the guild's list, returning its id
+    async fn add_item(state: &AppState, message_id: u64, item: &str) -> i32 {
+        state
+            .add_shopping_list_item(
+                USER,
+                message_id,
+                CHANNEL,
+                Some(GUILD),
+                NewShoppingListItem {
+                    item,
+                    store: None,
+                    notes: None,
+                    quantity: 1,
+                    personal: false,
+                    recipe_group: None,
+                    bought_for: None,
+                    product_url: None,
+                },
+            )
+            .await
+            .unwrap();
+        state
+            .get_shopping_list_item_by_message_id(message_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .id
+    }
+
+    /// add the items to the guild's list and buy them all at once, so they share a bought time
+    async fn buy_together(state: &AppState, first_message: u64, items: &[&str]) {
+        let mut ids = Vec::new();
+        for (message_id, &item) in (first_message..).zip(items) {
+            ids.push(add_item(state, message_id, item).await);
+        }
+        state
+            .set_shopping_list_items_bought(&ids, true, Some(USER))
+            .await
+            .unwrap();
+    }
+
+    /// an error discord gives with the given status code
+    async fn discord_error(status: u16) -> serenity::Error {
+        let response = http::Response::builder()
+            .status(status)
+            .body(r#"{"code": 0, "message": "request failed"}"#)
+            .unwrap();
+        let response = ErrorResponse::from_response(response.into(), reqwest::Method::POST).await;
+        HttpError::UnsuccessfulRequest(response).into()
+    }
+
+    /// remind the user about an item, a minute ago or a day from now
+    async fn remind(state: &AppState, item_id: i32, due: bool) {
+        let offset = match due {
+            true => chrono::Duration::minutes(-1),
+            false => chrono::Duration::days(1),
+        };
+        let remind_at = (Utc::now() + offset).naive_utc();
+        state
+            .set_item_reminder(GUILD, item_id, USER, remind_at)
+            .await
+            .unwrap();
+    }
+
+    /// deliver the guild's due reminders, each send failing with the next of `failures` until
+    /// they run out. Returns the result and the items which were sent, or tried to be
+    async fn deliver(
+        state: &AppState,
+        failures: Vec<serenity::Error>,
+    ) -> (Result<(), String>, Vec<String>) {
+        let failures = Mutex::new(failures.into_iter());
+        let sent = Mutex::new(Vec::new());
+        let delivered = deliver_due_reminders(GUILD, state, Utc::now(), |user_id, item| {
+            assert_eq!(user_id, USER);
+            sent.lock().unwrap().push(item.item);
+            let result = match failures.lock().unwrap().next() {
+                Some(e) => Err(e),
+                None => Ok(()),
+            };
+            async move { result }
+        })
+        .await;
+        (delivered, sent.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn reminders_are_sent_once_for_pending_items_only() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+
+            let milk = add_item(&state, 10, "milk").await;
+            remind(&state, milk, true).await;
+            let eggs = add_item(&state, 11, "eggs").await;
+            remind(&state, eggs, false).await;
+            buy_together(&state, 12, &["bread"]).await;
+            let bread = state
+                .get_shopping_list_item_by_message_id(12)
+                .await
+                .unwrap()
+                .unwrap();
+            remind(&state, bread.id, true).await;
+
+            // the bought item's reminder is dropped unsent, and the one not yet due is kept
+            let (delivered, sent) = deliver(&state, vec![]).await;
+            assert_eq!(delivered, Ok(()), "{:?}", backend);
+            assert_eq!(sent, ["milk"], "{:?}", backend);
+            let due = state.get_due_reminders(GUILD, Utc::now().naive_utc()).await;
+            assert!(due.unwrap().is_empty(), "{:?}", backend);
+            let later = Utc::now() + chrono::Duration::days(2);
+            let due = state.get_due_reminders(GUILD, later.naive_utc()).await;
+            assert_eq!(due.unwrap().len(), 1, "{:?}", backend);
+
+            let (_, sent) = deliver(&state, vec![]).await;
+            assert!(sent.is_empty(), "{:?}", backend);
+        }
+    }
+
+    #[tokio::test]
+    async fn reminders_are_kept_until_they_can_be_sent() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+
+            // discord being unavailable is retried on the next tick
+            let milk = add_item(&state, 10, "milk").await;
+            remind(&state, milk, true).await;
+            let (delivered, sent) = deliver(&state, vec![discord_error(500).await]).await;
+            assert!(delivered.is_err(), "{:?}", backend);
+            assert_eq!(sent, ["milk"], "{:?}", backend);
+            let (delivered, sent) = deliver(&state, vec![]).await;
+            assert_eq!(delivered, Ok(()), "{:?}", backend);
+            assert_eq!(sent, ["milk"], "{:?}", backend);
+
+            // a user the bot can't message isn't tried again
+            let bread = add_item(&state, 11, "bread").await;
+            remind(&state, bread, true).await;
+            let (delivered, sent) = deliver(&state, vec![discord_error(403).await]).await;
+            assert_eq!(delivered, Ok(()), "{:?}", backend);
+            assert_eq!(sent, ["bread"], "{:?}", backend);
+
+            let (_, sent) = deliver(&state, vec![]).await;
+            assert!(sent.is_empty(), "{:?}", backend);
+        }
+    }
+
+    #[test]
+    fn no_tasks_are_run_during_maintenance() {
+        let registry = SchedulerRegistry::default();
+        assert!(registry.due_tasks(1, true).is_empty());
+        assert_eq!(registry.due_tasks(1, false), ScheduledTask::ALL);
+    }
+
+    #[test]
+    fn paused_tasks_are_skipped_in_their_guild_only() {
+        let registry = SchedulerRegistry::default();
+        registry.set_paused(1, ScheduledTask::Digest, true);
+
+        let due = registry.due_tasks(1, false);
+        assert_eq!(due.len(), ScheduledTask::ALL.len() - 1);
+        assert!(!due.contains(&ScheduledTask::Digest));
+        assert_eq!(registry.due_tasks(2, false), ScheduledTask::ALL);
+
+        registry.set_paused(1, ScheduledTask::Digest, false);
+        assert_eq!(registry.due_tasks(1, false), ScheduledTask::ALL);
+    }
+
+    #[test]
+    fn task_names_round_trip() {
+        for task in ScheduledTask::ALL {
+            assert_eq!(ScheduledTask::from_name(task.name()), Some(task));
+        }
+        assert_eq!(ScheduledTask::from_name("unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn only_items_bought_since_connecting_are_synced() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            let sheet = MockSheet::default();
+
+            // nothing is sent until a sheet is connected
+            buy_together(&state, 10, &["flour"]).await;
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], SHEET_BATCH).await;
+            assert_eq!(synced, Ok(SheetSync::Added(0)), "{:?}", backend);
+            assert_eq!(*sheet.appends.lock().unwrap(), 0, "{:?}", backend);
+
+            state
+                .set_sheet_connection(GUILD, CHANNEL, "sheet", "token")
+                .await
+                .unwrap();
+            buy_together(&state, 20, &["milk"]).await;
+            let flatmates = [Flatmate {
+                discord_id: USER,
+                name: "sam".to_string(),
+                display_name: "Sam".to_string(),
+                home_address: None,
+            }];
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &flatmates, SHEET_BATCH).await;
+            assert_eq!(synced, Ok(SheetSync::Added(1)), "{:?}", backend);
+            assert_eq!(sheet.items(), ["milk"], "{:?}", backend);
+            assert_eq!(sheet.rows.lock().unwrap()[0].buyer, "Sam", "{:?}", backend);
+        }
+    }
+
+    #[tokio::test]
+    async fn items_bought_together_are_synced_once_across_batches() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            let sheet = MockSheet::default();
+            state
+                .set_sheet_connection(GUILD, CHANNEL, "sheet", "token")
+                .await
+                .unwrap();
+            buy_together(&state, 10, &["eggs", "milk", "bread"]).await;
+
+            // the batch ends between items with the same bought time, the next carries on by id
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], 2).await;
+            assert_eq!(synced, Ok(SheetSync::Added(2)), "{:?}", backend);
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], 2).await;
+            assert_eq!(synced, Ok(SheetSync::Added(1)), "{:?}", backend);
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], 2).await;
+            assert_eq!(synced, Ok(SheetSync::Added(0)), "{:?}", backend);
+
+            buy_together(&state, 20, &["butter"]).await;
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], 2).await;
+            assert_eq!(synced, Ok(SheetSync::Added(1)), "{:?}", backend);
+
+            assert_eq!(
+                sheet.items(),
+                ["eggs", "milk", "bread", "butter"],
+                "{:?}",
+                backend
+            );
+            assert_eq!(sheet.rows.lock().unwrap()[0].buyer, USER.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_syncs_are_sent_again_without_duplicates() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            let sheet = MockSheet::default();
+            state
+                .set_sheet_connection(GUILD, CHANNEL, "sheet", "token")
+                .await
+                .unwrap();
+            buy_together(&state, 10, &["eggs", "milk"]).await;
+
+            *sheet.fail_next.lock().unwrap() = Some(SheetError::Other("timed out".to_string()));
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], SHEET_BATCH).await;
+            assert_eq!(synced, Err("timed out".to_string()), "{:?}", backend);
+            assert!(sheet.items().is_empty(), "{:?}", backend);
+
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], SHEET_BATCH).await;
+            assert_eq!(synced, Ok(SheetSync::Added(2)), "{:?}", backend);
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], SHEET_BATCH).await;
+            assert_eq!(synced, Ok(SheetSync::Added(0)), "{:?}", backend);
+            assert_eq!(sheet.items(), ["eggs", "milk"], "{:?}", backend);
+        }
+    }
+
+    #[tokio::test]
+    async fn refused_sheets_are_not_synced_until_reconnected() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            let sheet = MockSheet::default();
+            state
+                .set_sheet_connection(GUILD, CHANNEL, "sheet", "token")
+                .await
+                .unwrap();
+            buy_together(&state, 10, &["eggs"]).await;
+
+            *sheet.fail_next.lock().unwrap() = Some(SheetError::Auth("invalid_grant".to_string()));
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], SHEET_BATCH).await;
+            assert_eq!(
+                synced,
+                Ok(SheetSync::Refused {
+                    channel_id: CHANNEL,
+                    error: "invalid_grant".to_string()
+                }),
+                "{:?}",
+                backend
+            );
+
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], SHEET_BATCH).await;
+            assert_eq!(synced, Ok(SheetSync::Added(0)), "{:?}", backend);
+            assert_eq!(*sheet.appends.lock().unwrap(), 1, "{:?}", backend);
+
+            // reconnecting starts from then, the item bought before isn't sent
+            state
+                .set_sheet_connection(GUILD, CHANNEL, "sheet", "token")
+                .await
+                .unwrap();
+            buy_together(&state, 20, &["milk"]).await;
+            let synced = sync_sheet_batch(GUILD, &state, &sheet, &[], SHEET_BATCH).await;
+            assert_eq!(synced, Ok(SheetSync::Added(1)), "{:?}", backend);
+            assert_eq!(sheet.items(), ["milk"], "{:?}", backend);
+        }
+    }
+
+    #[tokio::test]
+    async fn an_item_whose_message_expired_stays_on_the_list() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+
+            let id = add_item(&state, 10, "milk").await;
+            let created_before = Local::now().naive_local() + chrono::Duration::minutes(1);
+            let items = state
+                .get_items_to_expire(GUILD, created_before, EXPIRE_BATCH)
+                .await
+                .unwrap();
+            assert_eq!(items.len(), 1, "{:?}", backend);
+
+            state.set_item_messages_expired(&[id]).await.unwrap();
+            let items = state
+                .get_items_to_expire(GUILD, created_before, EXPIRE_BATCH)
+                .await
+                .unwrap();
+            assert!(items.is_empty(), "expired only once on {:?}", backend);
+
+            // /shopping-list still shows it, and offers it to be ticked off
+            let listed = state.get_digest_items(GUILD).await.unwrap();
+            assert_eq!(listed.len(), 1, "{:?}", backend);
+            assert!(listed[0].message_expired_at.is_some(), "{:?}", backend);
+            let menu = serde_json::to_value(digest_components(&listed).remove(0)).unwrap();
+            let option = &menu["components"][0]["options"][0]["value"];
+            assert_eq!(option, &id.to_string(), "{:?}", backend);
+
+            state
+                .set_shopping_list_items_bought(&[id], true, Some(USER))
+                .await
+                .unwrap();
+            let bought = state.get_shopping_list_item_by_id(id as u64).await.unwrap();
+            assert!(bought.unwrap().bought, "{:?}", backend);
+            let listed = state.get_digest_items(GUILD).await.unwrap();
+            assert!(listed.is_empty(), "{:?}", backend);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_task_already_running_isnt_started_again() {
+        for url in test_database_urls() {
+            let state = AppState::for_tests(&url).await;
+            let backend = state.database.get_database_backend();
+            let sheet = MockSheet::default();
+            state
+                .set_sheet_connection(GUILD, CHANNEL, "sheet", "token")
+                .await
+                .unwrap();
+            buy_together(&state, 10, &["eggs", "milk"]).await;
+
+            // a tick and /scheduler-run started together
+            let registry = SchedulerRegistry::default();
+            let (state, sheet) = (&state, &sheet);
+            let sync = || async move {
+                sync_sheet_batch(GUILD, state, sheet, &[], SHEET_BATCH)
+                    .await
+                    .map(|_| ())
+            };
+            let (first, second) = tokio::join!(
+                registry.run_exclusive(GUILD, ScheduledTask::SheetSync, sync),
+                registry.run_exclusive(GUILD, ScheduledTask::SheetSync, sync),
+            );
+            assert_eq!(first, Some(Ok(())), "{:?}", backend);
+            assert_eq!(second, None, "{:?}", backend);
+            assert_eq!(sheet.items(), ["eggs", "milk"], "{:?}", backend);
+
+            // once it has finished it can be run again, finding nothing left to sync
+            let again = registry
+                .run_exclusive(GUILD, ScheduledTask::SheetSync, sync)
+                .await;
+            assert_eq!(again, Some(Ok(())), "{:?}", backend);
+            assert_eq!(*sheet.appends.lock().unwrap(), 1, "{:?}", backend);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Read-only links to a guild's shopping list, for people who aren't on discord. Each link holds a
+//! random token which maps to a guild until it expires, and is served by the healthcheck server.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use log::error;
+use rand::{distributions::Alphanumeric, Rng};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+use crate::state::{AppState, MAX_SHARE_EXPIRY_HOURS};
+
+use super::{
+    common::shopping::describe_item,
+    database::shopping::{SerenityShoppingDatabase, ShoppingListItemModel},
+};
+
+/// the length of a share token, long enough that it can't be guessed
+const TOKEN_LENGTH: usize = 32;
+
+#[derive(Debug)]
+struct ShareLink {
+    guild_id: u64,
+    expires: Instant,
+}
+
+/// The share links which haven't expired yet. Links are only held in memory, so they stop working
+/// when the bot restarts.
+#[derive(Debug, Default)]
+pub struct ShareLinks {
+    links: Mutex<HashMap<String, ShareLink>>,
+}
+
+impl ShareLinks {
+    /// create a link to the list of a guild, returning its token. The link works for `ttl`, up to
+    /// [`MAX_SHARE_EXPIRY_HOURS`]
+    pub fn create(&self, guild_id: u64, ttl: Duration) -> String {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        let now = Instant::now();
+        let ttl = ttl.min(Duration::from_secs(MAX_SHARE_EXPIRY_HOURS * 60 * 60));
+        let mut links = self.links.lock().unwrap();
+        links.retain(|_, link| link.expires > now);
+        links.insert(
+            token.clone(),
+            ShareLink {
+                guild_id,
+                expires: now + ttl,
+            },
+        );
+
+        token
+    }
+
+    /// the guild a token links to, if it exists and hasn't expired
+    pub fn guild(&self, token: &str) -> Option<u64> {
+        self.links
+            .lock()
+            .unwrap()
+            .get(token)
+            .filter(|link| link.expires > Instant::now())
+            .map(|link| link.guild_id)
+    }
+}
+
+/// escape text for use in html, item names and notes are free text typed by users
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// the pending items a link shows, personal items are left off as the link may be passed around
+async fn shared_items(
+    token: &str,
+    state: &AppState,
+) -> Result<Vec<ShoppingListItemModel>, Box<dyn Reply>> {
+    let guild_id = match state.share_links.guild(token) {
+        Some(guild_id) => guild_id,
+        None => {
+            return Err(Box::new(warp::reply::with_status(
+                "this link has expired, ask for a new one with /shop-share",
+                StatusCode::NOT_FOUND,
+            )))
+        }
+    };
+
+    match state.get_pending_items(guild_id).await {
+        Ok(items) => Ok(items.into_iter().filter(|i| !i.personal).collect()),
+        Err(e) => {
+            error!("error loading shared list for {}: {}", guild_id, e);
+            Err(Box::new(warp::reply::with_status(
+                "unable to load the shopping list",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )))
+        }
+    }
+}
+
+async fn share_html(token: String, state: AppState) -> Result<Box<dyn Reply>, Infallible> {
+    let items = match shared_items(&token, &state).await {
+        Ok(items) => items,
+        Err(reply) => return Ok(reply),
+    };
+
+    let list = match items.is_empty() {
+        true => "<p>Nothing is waiting to be bought</p>".to_string(),
+        false => format!(
+            "<ul>{}</ul>",
+            items
+                .iter()
+                .map(|item| {
+                    let mut line = escape_html(&describe_item(item));
+                    if let Some(notes) = item.notes.as_ref() {
+                        line.push_str(&format!(" <em>({})</em>", escape_html(notes)));
+                    }
+                    format!("<li>{}</li>", line)
+                })
+                .collect::<String>()
+        ),
+    };
+
+    Ok(Box::new(warp::reply::html(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>Shopping list</title></head>\
+         <body><h1>Shopping list</h1>{}</body></html>",
+        list
+    ))))
+}
+
+async fn share_json(token: String, state: AppState) -> Result<Box<dyn Reply>, Infallible> {
+    let items = match shared_items(&token, &state).await {
+        Ok(items) => items,
+        Err(reply) => return Ok(reply),
+    };
+
+    let items: Vec<serde_json::Value> = items
+        .iter()
+        .map(|item| {
+            serde_json::json!({
+                "item": item.item,
+                "quantity": item.quantity,
+                "store": item.store,
+                "notes": item.notes,
+            })
+        })
+        .collect();
+
+    Ok(Box::new(warp::reply::json(
+        &serde_json::json!({ "items": items }),
+    )))
+}
+
+/// `/share/<token>` shows the list as a web page, and `/share/<token>/json` as json
+pub fn share_routes(
+    state: AppState,
+) -> impl Filter<Extract = (Box<dyn Reply>,), Error = Rejection> + Clone {
+    let with_state = warp::any().map(move || state.clone());
+
+    let html = warp::path!("share" / String)
+        .and(warp::get())
+        .and(with_state.clone())
+        .and_then(share_html);
+    let json = warp::path!("share" / String / "json")
+        .and(warp::get())
+        .and(with_state)
+        .and_then(share_json);
+
+    html.or(json).unify()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_only_work_until_they_expire() {
+        let links = ShareLinks::default();
+        let token = links.create(1, Duration::from_secs(60));
+        assert_eq!(token.len(), TOKEN_LENGTH);
+        assert_eq!(links.guild(&token), Some(1));
+        assert_eq!(links.guild("not-a-token"), None);
+
+        let expired = links.create(2, Duration::ZERO);
+        assert_eq!(links.guild(&expired), None);
+
+        // a lifetime too long to add to the clock is cut down rather than panicking
+        let forever = links.create(3, Duration::MAX);
+        assert_eq!(links.guild(&forever), Some(3));
+        assert_ne!(token, forever);
+    }
+
+    #[test]
+    fn user_text_is_escaped() {
+        assert_eq!(
+            escape_html(r#"<script>alert("milk & 'eggs'")</script>"#),
+            "&lt;script&gt;alert(&quot;milk &amp; &#39;eggs&#39;&quot;)&lt;/script&gt;"
+        );
+        assert_eq!(escape_html("&amp;"), "&amp;amp;");
+        assert_eq!(escape_html("2l milk"), "2l milk");
+    }
+}
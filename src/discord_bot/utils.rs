@@ -1,5 +1,32 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+#[cfg(feature = "shopping")]
+use std::time::Instant;
+
+use log::debug;
 use serenity::prelude::TypeMapKey;
 
+#[cfg(feature = "shopping")]
+use super::{
+    common::word_filter::WordFilter,
+    database::shopping::{NoteUpdate, ShoppingListItemModel},
+};
+
+/// how long an autocomplete request waits for a newer request on the same field before answering
+const AUTOCOMPLETE_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// how long an answer is waited for by a [TimedCache], matching how long discord lets an
+/// interaction be answered
+#[cfg(feature = "shopping")]
+const PENDING_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
 /// represents the unique identifier that represents the user-id of this discord bot
 pub struct BotDiscordId(u64);
 
@@ -18,3 +45,318 @@ impl BotDiscordId {
 impl TypeMapKey for BotDiscordId {
     type Value = BotDiscordId;
 }
+
+/// Discord sends an autocomplete request for every keystroke, this tracks the most recent
+/// request for each (user, field) so that stale requests can be skipped rather than queried.
+#[derive(Default)]
+pub struct AutocompleteDebouncer {
+    /// the generation of the most recent request for each (user, field)
+    latest: Mutex<HashMap<(u64, String), u64>>,
+    /// a counter used to hand out a unique generation to each request
+    generation: AtomicU64,
+    /// the number of requests which were answered
+    answered: AtomicU64,
+    /// the number of requests which were superseded before being answered
+    skipped: AtomicU64,
+}
+
+impl AutocompleteDebouncer {
+    /// wait out the debounce window for this request, returns true if this is still
+    /// the most recent request for the field and should be answered
+    pub async fn settle(&self, user: u64, field: String) -> bool {
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed);
+        let key = (user, field);
+
+        self.latest.lock().unwrap().insert(key.clone(), generation);
+
+        tokio::time::sleep(AUTOCOMPLETE_DEBOUNCE).await;
+
+        let is_latest = {
+            let mut latest = self.latest.lock().unwrap();
+            let is_latest = latest.get(&key) == Some(&generation);
+            if is_latest {
+                latest.remove(&key);
+            }
+            is_latest
+        };
+
+        if is_latest {
+            self.answered.fetch_add(1, Ordering::Relaxed);
+        } else {
+            let skipped = self.skipped.fetch_add(1, Ordering::Relaxed) + 1;
+            debug!(
+                "skipped stale autocomplete request ({} skipped, {} answered)",
+                skipped,
+                self.answered.load(Ordering::Relaxed)
+            );
+        }
+
+        is_latest
+    }
+
+    /// the number of requests answered and skipped since the bot started
+    pub fn counts(&self) -> (u64, u64) {
+        (
+            self.answered.load(Ordering::Relaxed),
+            self.skipped.load(Ordering::Relaxed),
+        )
+    }
+}
+
+impl TypeMapKey for AutocompleteDebouncer {
+    type Value = Arc<AutocompleteDebouncer>;
+}
+
+/// The compiled word filter of each guild, loaded from the database on first use and dropped
+/// whenever the guild's word list is edited.
+#[cfg(feature = "shopping")]
+#[derive(Default)]
+pub struct WordFilterCache {
+    filters: Mutex<HashMap<u64, Arc<WordFilter>>>,
+}
+
+#[cfg(feature = "shopping")]
+impl WordFilterCache {
+    pub fn get(&self, guild: u64) -> Option<Arc<WordFilter>> {
+        self.filters.lock().unwrap().get(&guild).cloned()
+    }
+
+    pub fn insert(&self, guild: u64, filter: Arc<WordFilter>) {
+        self.filters.lock().unwrap().insert(guild, filter);
+    }
+
+    pub fn invalidate(&self, guild: u64) {
+        self.filters.lock().unwrap().remove(&guild);
+    }
+}
+
+#[cfg(feature = "shopping")]
+impl TypeMapKey for WordFilterCache {
+    type Value = Arc<WordFilterCache>;
+}
+
+/// Answers a user is yet to give, such as whether an add is a duplicate, keyed by the id of the
+/// interaction which asked for them. Anything left unanswered longer than its timeout is dropped.
+#[cfg(feature = "shopping")]
+pub struct TimedCache<T> {
+    values: Mutex<HashMap<u64, (Instant, T)>>,
+    timeout: Duration,
+}
+
+#[cfg(feature = "shopping")]
+impl<T> Default for TimedCache<T> {
+    fn default() -> Self {
+        Self::with_timeout(PENDING_TIMEOUT)
+    }
+}
+
+#[cfg(feature = "shopping")]
+impl<T> TimedCache<T> {
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            values: Mutex::new(HashMap::new()),
+            timeout,
+        }
+    }
+
+    /// hold a value until it is answered, dropping any which have gone unanswered too long
+    pub fn insert(&self, key: u64, value: T) {
+        let mut values = self.values.lock().unwrap();
+        values.retain(|_, (at, _)| at.elapsed() < self.timeout);
+        values.insert(key, (Instant::now(), value));
+    }
+
+    /// remove and return a value, so that it can only be answered once. A value which has gone
+    /// unanswered too long is dropped rather than returned
+    pub fn take(&self, key: u64) -> Option<T> {
+        self.values
+            .lock()
+            .unwrap()
+            .remove(&key)
+            .filter(|(at, _)| at.elapsed() < self.timeout)
+            .map(|(_, value)| value)
+    }
+}
+
+#[cfg(feature = "shopping")]
+impl<T: Send + Sync + 'static> TypeMapKey for TimedCache<T> {
+    type Value = Arc<TimedCache<T>>;
+}
+
+/// an item a user asked to add, held while they decide whether it is a duplicate of an item
+/// already on the list
+#[cfg(feature = "shopping")]
+#[derive(Debug, Clone)]
+pub struct PendingAdd {
+    pub item: String,
+    pub personal: bool,
+    pub quantity: i64,
+    pub store: Option<String>,
+    pub notes: Option<String>,
+    pub recipe: Option<String>,
+    pub bought_for: Option<String>,
+    pub product_url: Option<String>,
+}
+
+/// adds waiting on an answer to a near-duplicate prompt
+#[cfg(feature = "shopping")]
+pub type PendingAddCache = TimedCache<PendingAdd>;
+
+/// items read from a photo, held while the user who sent it checks the preview
+#[cfg(feature = "shopping")]
+#[derive(Debug, Clone)]
+pub struct PendingImport {
+    pub user_id: u64,
+    pub items: Vec<(i64, String)>,
+}
+
+/// imports waiting to be confirmed
+#[cfg(feature = "shopping")]
+pub type PendingImportCache = TimedCache<PendingImport>;
+
+/// a note waiting to be put on the items matched by /shop-annotate
+#[cfg(feature = "shopping")]
+#[derive(Debug, Clone)]
+pub struct PendingAnnotation {
+    pub user_id: u64,
+    pub ids: Vec<i32>,
+    pub note: String,
+    pub update: NoteUpdate,
+}
+
+/// annotations waiting to be confirmed
+#[cfg(feature = "shopping")]
+pub type PendingAnnotationCache = TimedCache<PendingAnnotation>;
+
+/// The item messages whose Re-add button was pressed recently, keyed by message id with the time
+/// the button may be used again, so that repeated presses don't flood the list with copies.
+#[cfg(feature = "shopping")]
+#[derive(Default)]
+pub struct ReaddCooldowns {
+    readds: Mutex<HashMap<u64, Instant>>,
+}
+
+#[cfg(feature = "shopping")]
+impl ReaddCooldowns {
+    /// record a re-add of the item posted in a message, returns false without recording it if the
+    /// message is still cooling down from an earlier re-add
+    pub fn try_readd(&self, message_id: u64, cooldown: Duration) -> bool {
+        let now = Instant::now();
+        let mut readds = self.readds.lock().unwrap();
+        readds.retain(|_, until| *until > now);
+        if readds.contains_key(&message_id) {
+            return false;
+        }
+        readds.insert(message_id, now + cooldown);
+        true
+    }
+
+    /// forget a re-add which failed, so that the message can be re-added again straight away
+    pub fn release(&self, message_id: u64) {
+        self.readds.lock().unwrap().remove(&message_id);
+    }
+}
+
+#[cfg(feature = "shopping")]
+impl TypeMapKey for ReaddCooldowns {
+    type Value = Arc<ReaddCooldowns>;
+}
+
+/// how long the last good autocomplete candidates of a user are served while the database is
+/// failing, after which suggestions fall back to the static lists alone
+#[cfg(feature = "shopping")]
+const AUTOCOMPLETE_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The last items loaded successfully for /shop autocomplete for each user in each guild, so
+/// suggestions keep working through a database outage. They're kept per user as they include the
+/// user's own items, personal ones among them, which mustn't be suggested to anyone else.
+#[cfg(feature = "shopping")]
+#[derive(Default)]
+pub struct AutocompleteCache {
+    items: Mutex<HashMap<(Option<u64>, u64), (Instant, Vec<ShoppingListItemModel>)>>,
+}
+
+#[cfg(feature = "shopping")]
+impl AutocompleteCache {
+    /// remember the candidates loaded for a user in a guild
+    pub fn insert(&self, guild: Option<u64>, user: u64, items: Vec<ShoppingListItemModel>) {
+        let mut cached = self.items.lock().unwrap();
+        cached.retain(|_, (at, _)| at.elapsed() < AUTOCOMPLETE_CACHE_TTL);
+        cached.insert((guild, user), (Instant::now(), items));
+    }
+
+    /// the candidates last loaded for a user in a guild, unless they have expired
+    pub fn get(&self, guild: Option<u64>, user: u64) -> Option<Vec<ShoppingListItemModel>> {
+        self.items
+            .lock()
+            .unwrap()
+            .get(&(guild, user))
+            .filter(|(at, _)| at.elapsed() < AUTOCOMPLETE_CACHE_TTL)
+            .map(|(_, items)| items.clone())
+    }
+}
+
+#[cfg(feature = "shopping")]
+impl TypeMapKey for AutocompleteCache {
+    type Value = Arc<AutocompleteCache>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn only_the_latest_autocomplete_request_is_answered() {
+        let debouncer = AutocompleteDebouncer::default();
+        let (first, second, other) = tokio::join!(
+            debouncer.settle(1, "shop/item".to_string()),
+            async {
+                // typed before the first request's window is up, so it supersedes it
+                tokio::time::sleep(AUTOCOMPLETE_DEBOUNCE / 3).await;
+                debouncer.settle(1, "shop/item".to_string()).await
+            },
+            debouncer.settle(1, "shop/store".to_string()),
+        );
+
+        assert!(!first);
+        assert!(second);
+        assert!(other, "a request for another field isn't superseded");
+        assert_eq!(debouncer.counts(), (2, 1));
+    }
+
+    #[cfg(feature = "shopping")]
+    #[test]
+    fn timed_values_are_only_taken_once_and_before_they_expire() {
+        let cache = TimedCache::default();
+        cache.insert(1, "milk");
+        assert_eq!(cache.take(1), Some("milk"));
+        assert_eq!(cache.take(1), None);
+
+        let cache = TimedCache::with_timeout(Duration::ZERO);
+        cache.insert(1, "milk");
+        assert_eq!(cache.take(1), None);
+    }
+
+    #[cfg(feature = "shopping")]
+    #[test]
+    fn a_quick_second_readd_is_blocked() {
+        let cooldowns = ReaddCooldowns::default();
+        let cooldown = Duration::from_secs(10);
+        assert!(cooldowns.try_readd(1, cooldown));
+        assert!(!cooldowns.try_readd(1, cooldown));
+        assert!(
+            cooldowns.try_readd(2, cooldown),
+            "another item isn't blocked"
+        );
+
+        // a re-add which failed doesn't hold up the next one
+        cooldowns.release(1);
+        assert!(cooldowns.try_readd(1, cooldown));
+
+        assert!(cooldowns.try_readd(3, Duration::ZERO));
+        assert!(
+            cooldowns.try_readd(3, Duration::ZERO),
+            "the cooldown is over"
+        );
+    }
+}
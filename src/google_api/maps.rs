@@ -1,5 +1,9 @@
-use std::time::Instant;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
+use chrono::{Timelike, Utc};
 use log::{debug, error};
 use reqwest::Client;
 use serde::Deserialize;
@@ -9,11 +13,20 @@ use crate::state::Destination;
 
 const API_URL: &str = "https://maps.googleapis.com/maps/api/distancematrix/json";
 
+/// how long a travel time is reused for lookups departing in the same hour of the day. Traffic
+/// differs through the day, so a time is only reused within the hour it was looked up for
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// the pause before trying a request to google maps again
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
 pub type GoogleMapApiResponse = Result<GoogleMapsData, GoogleMapError>;
 
 #[derive(Debug)]
 pub enum GoogleMapError {
     NetworkError,
+    /// google maps didn't respond in time, even after trying again
+    Timeout,
     APILimitReached,
     InvalidAddress,
     // Unknown(String),
@@ -25,6 +38,7 @@ impl std::fmt::Display for GoogleMapError {
             Self::APILimitReached => write!(f, "API limit reached"),
             Self::InvalidAddress => write!(f, "Invalid address"),
             Self::NetworkError => write!(f, "Network error"),
+            Self::Timeout => write!(f, "Google Maps didn't respond in time"),
             // Self::Unknown(s) => write!(f, "Unknown error: {}", s),
         }
     }
@@ -47,18 +61,81 @@ pub struct GoogleMapsRow {
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct GoogleMapsElement {
+    /// missing when the lookup for this destination failed, see `status`
+    #[serde(default)]
     pub distance: GoogleMapsDistance,
+    #[serde(default)]
     pub duration: GoogleMapsDuration,
+    /// the travel time given the traffic expected when leaving, only given for driving routes
+    #[serde(default)]
+    pub duration_in_traffic: Option<GoogleMapsDuration>,
     pub status: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+impl GoogleMapsElement {
+    /// whether a route was found to this destination
+    pub fn is_ok(&self) -> bool {
+        self.status == "OK"
+    }
+
+    /// the travel time to this destination, allowing for traffic when google gives a time for it
+    pub fn travel_time(&self) -> &GoogleMapsDuration {
+        self.duration_in_traffic.as_ref().unwrap_or(&self.duration)
+    }
+}
+
+/// a route looked up from an origin to one destination, kept so that repeat lookups in the same
+/// hour of the day don't need another request
+#[derive(Debug, Clone)]
+struct CachedRoute {
+    fetched: Instant,
+    /// the origin as google resolved it
+    origin_address: String,
+    /// the destination as google resolved it
+    destination_address: String,
+    element: GoogleMapsElement,
+}
+
+/// the error to give up with after a request failed on its `attempt`th retry, or none if it should
+/// be tried again. A lookup only reads, so is safe to try again if google was too slow or
+/// unreachable, but only `retries` times
+fn give_up_after(
+    timed_out: bool,
+    connect_failed: bool,
+    attempt: u32,
+    retries: u32,
+) -> Option<GoogleMapError> {
+    match (timed_out || connect_failed) && attempt < retries {
+        true => None,
+        false if timed_out => Some(GoogleMapError::Timeout),
+        false => Some(GoogleMapError::NetworkError),
+    }
+}
+
+/// the origin as typed, the destination address and the hour of the day (in utc) of departure
+type CacheKey = (String, String, u32);
+
+/// the key a route is cached under, origins are typed freely so are matched regardless of case
+/// and spacing
+fn cache_key(origin: &str, destination: &Destination, hour: u32) -> CacheKey {
+    (
+        origin
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .to_lowercase(),
+        destination.address.clone(),
+        hour,
+    )
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct GoogleMapsDistance {
     pub text: String,
     pub value: u32,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct GoogleMapsDuration {
     pub text: String,
     pub value: u32,
@@ -74,11 +151,19 @@ struct GoogleMapsRequest {
 #[derive(Debug)]
 pub struct MapsApiBuilder {
     key: Option<String>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    retries: u32,
 }
 
 impl MapsApiBuilder {
     pub fn new() -> Self {
-        Self { key: None }
+        Self {
+            key: None,
+            connect_timeout: None,
+            timeout: None,
+            retries: 0,
+        }
     }
 
     pub fn key(mut self, key: String) -> Self {
@@ -86,15 +171,40 @@ impl MapsApiBuilder {
         self
     }
 
+    /// how long to wait to connect, and for a whole response, before giving up on a request
+    pub fn timeouts(mut self, connect: Duration, response: Duration) -> Self {
+        self.connect_timeout = Some(connect);
+        self.timeout = Some(response);
+        self
+    }
+
+    /// how many times a request which timed out or couldn't connect is tried again
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
     pub fn build(self) -> GoogleMapsApi {
         let (tx, rx) = tokio::sync::mpsc::channel(100);
 
+        let mut client = Client::builder();
+        if let Some(connect_timeout) = self.connect_timeout {
+            client = client.connect_timeout(connect_timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+
         GoogleMapsApi {
-            client: Client::new(),
+            client: client
+                .build()
+                .expect("failed to build the google maps http client"),
+            retries: self.retries,
             key: self.key.unwrap(),
             internal_receiver: rx,
             internal_sender: tx,
             timeout: None,
+            cache: HashMap::new(),
         }
     }
 }
@@ -102,10 +212,13 @@ impl MapsApiBuilder {
 #[derive(Debug)]
 pub struct GoogleMapsApi {
     client: Client,
+    /// how many times a request which timed out or couldn't connect is tried again
+    retries: u32,
     key: String,
     internal_receiver: Receiver<GoogleMapsRequest>,
     internal_sender: Sender<GoogleMapsRequest>,
     timeout: Option<Instant>,
+    cache: HashMap<CacheKey, CachedRoute>,
 }
 
 impl GoogleMapsApi {
@@ -113,12 +226,86 @@ impl GoogleMapsApi {
         MapsApiBuilder::new()
     }
 
+    /// the routes to every destination from the cache, if they were all looked up within the hour
+    /// of the day `hour` and hadn't expired by `now`
+    fn cached(
+        &self,
+        origin: &str,
+        destinations: &[Destination],
+        hour: u32,
+        now: Instant,
+    ) -> Option<GoogleMapsData> {
+        let routes = destinations
+            .iter()
+            .map(|destination| {
+                self.cache
+                    .get(&cache_key(origin, destination, hour))
+                    .filter(|route| now.duration_since(route.fetched) < CACHE_TTL)
+            })
+            .collect::<Option<Vec<&CachedRoute>>>()?;
+
+        Some(GoogleMapsData {
+            destination_addresses: routes
+                .iter()
+                .map(|route| route.destination_address.clone())
+                .collect(),
+            origin_addresses: vec![routes.first()?.origin_address.clone()],
+            rows: vec![GoogleMapsRow {
+                elements: routes.iter().map(|route| route.element.clone()).collect(),
+            }],
+            status: "OK".to_string(),
+        })
+    }
+
+    /// remember the routes found by a lookup at `now`, dropping any which have expired. Failed
+    /// routes aren't kept, so they are looked up again next time
+    fn store(
+        &mut self,
+        origin: &str,
+        destinations: &[Destination],
+        hour: u32,
+        now: Instant,
+        data: &GoogleMapsData,
+    ) {
+        self.cache
+            .retain(|_, route| now.duration_since(route.fetched) < CACHE_TTL);
+
+        let (origin_address, row) = match (data.origin_addresses.first(), data.rows.first()) {
+            (Some(origin_address), Some(row)) => (origin_address, row),
+            _ => return,
+        };
+        for (i, (destination, element)) in destinations.iter().zip(row.elements.iter()).enumerate()
+        {
+            if !element.is_ok() {
+                continue;
+            }
+            self.cache.insert(
+                cache_key(origin, destination, hour),
+                CachedRoute {
+                    fetched: now,
+                    origin_address: origin_address.clone(),
+                    destination_address: data
+                        .destination_addresses
+                        .get(i)
+                        .cloned()
+                        .unwrap_or_else(|| destination.address.clone()),
+                    element: element.clone(),
+                },
+            );
+        }
+    }
+
     async fn get_distance(
         &mut self,
         origin: &str,
         destinations: Vec<Destination>,
     ) -> GoogleMapApiResponse {
-        //TODO create a check to see if this request has previously been processed
+        // travel times are reused within the hour of the day they were looked up for
+        let hour = Utc::now().hour();
+        if let Some(data) = self.cached(origin, &destinations, hour, Instant::now()) {
+            debug!("using cached travel times from {}", origin);
+            return Ok(data);
+        }
 
         if let Some(timeout) = self.timeout {
             if timeout > Instant::now() {
@@ -130,6 +317,8 @@ impl GoogleMapsApi {
         let url: String = {
             let mut url = String::from(API_URL);
             url.push_str("?units=metric");
+            // leaving now gives travel times which allow for traffic, where google has them
+            url.push_str("&departure_time=now");
             url.push_str("&key=");
             url.push_str(&self.key);
             url.push_str("&origins=");
@@ -137,20 +326,35 @@ impl GoogleMapsApi {
             url.push_str("&destinations=");
             url.push_str(
                 &destinations
-                    .into_iter()
-                    .map(|x| x.address)
+                    .iter()
+                    .map(|x| x.address.clone())
                     .collect::<Vec<String>>()
                     .join("|"),
             );
             url
         };
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|_| GoogleMapError::NetworkError)?;
+        let mut attempt = 0;
+        let response = loop {
+            let e = match self.client.get(&url).send().await {
+                Ok(response) => break response,
+                Err(e) => e,
+            };
+            match give_up_after(e.is_timeout(), e.is_connect(), attempt, self.retries) {
+                None => {
+                    attempt += 1;
+                    debug!(
+                        "Google Maps request failed, trying again ({} of {}): {}",
+                        attempt, self.retries, e
+                    );
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+                Some(error) => {
+                    error!("Google Maps request failed: {}", e);
+                    return Err(error);
+                }
+            }
+        };
 
         // validate status
         if !response.status().is_success() {
@@ -161,20 +365,23 @@ impl GoogleMapsApi {
                 return Err(GoogleMapError::APILimitReached);
             }
 
-            error!(
-                "Google Maps API returned error: {}\nbody:{}",
-                response.status(),
-                response.text().await.unwrap()
-            );
+            // the timeout covers reading the body too, so it may not arrive
+            let status = response.status();
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|e| format!("(unreadable: {})", e));
+            error!("Google Maps API returned error: {}\nbody:{}", status, body);
 
             return Err(GoogleMapError::NetworkError);
         }
 
-        let data: GoogleMapsData = response
-            .json()
-            .await
-            .map_err(|_| GoogleMapError::InvalidAddress)?;
+        let data: GoogleMapsData = response.json().await.map_err(|e| match e.is_timeout() {
+            true => GoogleMapError::Timeout,
+            false => GoogleMapError::InvalidAddress,
+        })?;
 
+        self.store(origin, &destinations, hour, Instant::now(), &data);
         Ok(data)
     }
 
@@ -184,7 +391,8 @@ impl GoogleMapsApi {
             let response = self
                 .get_distance(&request.origin, request.destinations)
                 .await;
-            request.sender.send(response).unwrap();
+            // the command waiting on it may have given up, which mustn't stop later lookups
+            let _ = request.sender.send(response);
         }
     }
 
@@ -217,3 +425,158 @@ impl GoogleMapsApiHandle {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+impl GoogleMapsApiHandle {
+    /// a handle whose requests go nowhere, for tests which never look anything up
+    pub fn disconnected() -> Self {
+        let (internal_sender, _) = tokio::sync::mpsc::channel(1);
+        Self { internal_sender }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn destination(address: &str) -> Destination {
+        Destination {
+            label: address.to_string(),
+            address: address.to_string(),
+        }
+    }
+
+    fn element(status: &str, seconds: u32) -> GoogleMapsElement {
+        GoogleMapsElement {
+            distance: GoogleMapsDistance::default(),
+            duration: GoogleMapsDuration {
+                text: format!("{} secs", seconds),
+                value: seconds,
+            },
+            duration_in_traffic: None,
+            status: status.to_string(),
+        }
+    }
+
+    fn lookup(elements: Vec<GoogleMapsElement>) -> GoogleMapsData {
+        GoogleMapsData {
+            destination_addresses: vec!["Work, Auckland".to_string(), "Gym, Auckland".to_string()],
+            origin_addresses: vec!["1 Queen Street, Auckland".to_string()],
+            rows: vec![GoogleMapsRow { elements }],
+            status: "OK".to_string(),
+        }
+    }
+
+    fn maps() -> GoogleMapsApi {
+        GoogleMapsApi::builder().key("key".to_string()).build()
+    }
+
+    #[test]
+    fn lookups_in_the_same_hour_are_cached() {
+        let mut maps = maps();
+        let destinations = [destination("work"), destination("gym")];
+        let now = Instant::now();
+        maps.store(
+            "1 Queen St",
+            &destinations,
+            8,
+            now,
+            &lookup(vec![element("OK", 600), element("OK", 900)]),
+        );
+
+        // the origin is matched regardless of case and spacing
+        let later = now + Duration::from_secs(30 * 60);
+        let data = maps
+            .cached(" 1  queen st", &destinations, 8, later)
+            .expect("the same hour is cached");
+        assert_eq!(data.origin_addresses, ["1 Queen Street, Auckland"]);
+        assert_eq!(
+            data.destination_addresses,
+            ["Work, Auckland", "Gym, Auckland"]
+        );
+        assert_eq!(data.rows[0].elements[1].duration.value, 900);
+
+        // a subset of the destinations is served from the cache too
+        assert!(maps
+            .cached("1 Queen St", &destinations[1..], 8, later)
+            .is_some());
+    }
+
+    #[test]
+    fn other_hours_and_old_lookups_miss() {
+        let mut maps = maps();
+        let destinations = [destination("work"), destination("gym")];
+        let now = Instant::now();
+        maps.store(
+            "1 Queen St",
+            &destinations,
+            8,
+            now,
+            &lookup(vec![element("OK", 600), element("OK", 900)]),
+        );
+
+        assert!(maps.cached("1 Queen St", &destinations, 9, now).is_none());
+        assert!(maps.cached("2 Queen St", &destinations, 8, now).is_none());
+
+        let expired = now + CACHE_TTL;
+        assert!(maps
+            .cached("1 Queen St", &destinations, 8, expired)
+            .is_none());
+        // storing anything later drops the expired routes
+        maps.store("2 Queen St", &[], 8, expired, &lookup(vec![]));
+        assert!(maps.cache.is_empty());
+    }
+
+    #[test]
+    fn failed_routes_are_not_cached() {
+        let mut maps = maps();
+        let destinations = [destination("work"), destination("gym")];
+        let now = Instant::now();
+        maps.store(
+            "1 Queen St",
+            &destinations,
+            8,
+            now,
+            &lookup(vec![element("OK", 600), element("ZERO_RESULTS", 0)]),
+        );
+
+        assert!(maps.cached("1 Queen St", &destinations, 8, now).is_none());
+        assert!(maps
+            .cached("1 Queen St", &destinations[..1], 8, now)
+            .is_some());
+    }
+
+    #[test]
+    fn timeouts_and_connection_failures_are_retried() {
+        assert!(give_up_after(true, false, 0, 2).is_none());
+        assert!(give_up_after(false, true, 1, 2).is_none());
+    }
+
+    #[test]
+    fn running_out_of_retries_gives_a_clear_error() {
+        assert!(matches!(
+            give_up_after(true, false, 2, 2),
+            Some(GoogleMapError::Timeout)
+        ));
+        assert!(matches!(
+            give_up_after(true, false, 0, 0),
+            Some(GoogleMapError::Timeout)
+        ));
+        assert!(matches!(
+            give_up_after(false, true, 2, 2),
+            Some(GoogleMapError::NetworkError)
+        ));
+        assert_eq!(
+            GoogleMapError::Timeout.to_string(),
+            "Google Maps didn't respond in time"
+        );
+    }
+
+    #[test]
+    fn other_failures_are_not_retried() {
+        assert!(matches!(
+            give_up_after(false, false, 0, 2),
+            Some(GoogleMapError::NetworkError)
+        ));
+    }
+}
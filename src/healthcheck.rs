@@ -38,6 +38,8 @@ impl Healthcheck {
 
         let start_time = self.state.start_time;
         let num_connected = self.state.num_connected.clone();
+        let commands_registered = self.state.commands_registered.clone();
+        let commands_expected = self.state.commands_expected.clone();
 
         let healthcheck = warp::path!("healthcheck").and(warp::get()).map(move || {
             // return if uptime less than 1 minute
@@ -52,10 +54,25 @@ impl Healthcheck {
                 );
             }
 
+            if commands_registered.load(Ordering::Relaxed)
+                < commands_expected.load(Ordering::Relaxed)
+            {
+                return warp::reply::with_status(
+                    "NOT OK - COMMANDS NOT REGISTERED",
+                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                );
+            }
+
             warp::reply::with_status("OK", warp::http::StatusCode::OK)
         });
 
-        let server = warp::serve(healthcheck);
+        // the read-only shopping list links are served alongside the healthcheck
+        #[cfg(feature = "shopping")]
+        let routes = healthcheck.or(crate::discord_bot::share_routes(self.state.clone()));
+        #[cfg(not(feature = "shopping"))]
+        let routes = healthcheck;
+
+        let server = warp::serve(routes);
 
         server.bind(([0, 0, 0, 0], 3000)).await;
     }
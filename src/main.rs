@@ -1,43 +1,99 @@
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+compile_error!("at least one database backend feature must be enabled: postgres or sqlite");
+
 mod discord_bot;
+#[cfg(feature = "maps")]
 mod google_api;
 
 mod healthcheck;
 
 mod logging;
+mod notify;
+#[cfg(feature = "shopping")]
+mod ocr;
+mod self_test;
+#[cfg(feature = "shopping")]
+mod sheets;
 mod state;
 
 use log::{error, info};
 use std::process::exit;
 
+#[cfg(feature = "maps")]
+use crate::google_api::maps::GoogleMapsApi;
 use crate::{
-    discord_bot::DiscordBot, google_api::maps::GoogleMapsApi, logging::configure_logger,
-    state::AppState,
+    discord_bot::DiscordBot,
+    logging::configure_logger,
+    notify::NotifyEvent,
+    self_test::self_test,
+    state::{rollback_last_migration, AppState, CONFIG},
 };
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     configure_logger()?;
 
-    let database_url = format!(
-        "postgres://{}:{}@{}:{}/{}",
-        std::env::var("POSTGRES_USER").expect("POSTGRES_USER must be set"),
-        std::env::var("POSTGRES_PASS").expect("POSTGRES_PASSWORD must be set"),
-        std::env::var("POSTGRES_HOST").expect("POSTGRES_HOST must be set"),
-        std::env::var("POSTGRES_PORT").expect("POSTGRES_PORT must be set"),
-        std::env::var("POSTGRES_DB").expect("POSTGRES_DB must be set")
-    );
+    // DATABASE_URL allows any supported backend, e.g. `sqlite://tom_bot.db?mode=rwc`
+    let database_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => format!(
+            "postgres://{}:{}@{}:{}/{}",
+            std::env::var("POSTGRES_USER").expect("POSTGRES_USER must be set"),
+            std::env::var("POSTGRES_PASS").expect("POSTGRES_PASSWORD must be set"),
+            std::env::var("POSTGRES_HOST").expect("POSTGRES_HOST must be set"),
+            std::env::var("POSTGRES_PORT").expect("POSTGRES_PORT must be set"),
+            std::env::var("POSTGRES_DB").expect("POSTGRES_DB must be set")
+        ),
+    };
+
+    // `tom_bot --rollback-migration` undoes the latest migration and exits, run it with the newer
+    // build before going back to an older one
+    if std::env::args().any(|arg| arg == "--rollback-migration") {
+        rollback_last_migration(database_url).await?;
+        return Ok(());
+    }
+
     let discord_token = std::env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN must be set");
-    let google_maps_token =
-        std::env::var("GOOGLE_MAPS_TOKEN").expect("GOOGLE_MAPS_TOKEN must be set");
-
-    info!("spawning google maps handler");
-    let mut google_maps_api_handler = GoogleMapsApi::builder().key(google_maps_token).build();
-    let google_maps_api_handle = google_maps_api_handler.handle();
-    let google_maps_thread_handle = tokio::spawn(async move {
-        google_maps_api_handler.run().await;
-    });
 
-    let state = AppState::new(database_url, google_maps_api_handle).await?;
+    #[cfg(feature = "maps")]
+    let (state, google_maps_thread_handle) = {
+        let google_maps_token =
+            std::env::var("GOOGLE_MAPS_TOKEN").expect("GOOGLE_MAPS_TOKEN must be set");
+
+        info!("spawning google maps handler");
+        let mut google_maps_api_handler = GoogleMapsApi::builder()
+            .key(google_maps_token)
+            .timeouts(CONFIG.maps.connect_timeout(), CONFIG.maps.timeout())
+            .retries(CONFIG.maps.retries)
+            .build();
+        let google_maps_api_handle = google_maps_api_handler.handle();
+        let google_maps_thread_handle = tokio::spawn(async move {
+            google_maps_api_handler.run().await;
+        });
+
+        let state = AppState::new(database_url, google_maps_api_handle).await?;
+        (state, google_maps_thread_handle)
+    };
+
+    // without maps there is no handler to watch, so its branch of the shutdown loop never fires
+    #[cfg(not(feature = "maps"))]
+    let (state, google_maps_thread_handle) = (
+        AppState::new(database_url).await?,
+        std::future::pending::<()>(),
+    );
+
+    info!("running startup self-test");
+    let report = self_test(&state).await;
+    if report.critical_failures() > 0 {
+        state
+            .notifications
+            .notify(NotifyEvent::Startup, &report.failure_summary())
+            .await;
+    }
+    if CONFIG.strict_self_test && report.critical_failures() > 0 {
+        error!("refusing to start, critical self-test checks failed");
+        exit(1);
+    }
 
     info!("spawning discord handler");
     let discord_state = state.clone();
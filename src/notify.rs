@@ -0,0 +1,342 @@
+//! Alerts sent outside of discord when something goes wrong in the background, so that failures
+//! which no user would see are still noticed by whoever runs the bot.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Display,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lettre::{
+    transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
+    Tokio1Executor,
+};
+use log::{error, info};
+use serde::Deserialize;
+use serenity::async_trait;
+
+/// the shortest gap between two identical alerts, so a persistent failure in a task which runs
+/// every minute doesn't flood the sinks
+const NOTIFY_COOLDOWN: Duration = Duration::from_secs(15 * 60);
+
+/// the longest a notifier may take to send an alert, so a sink which has stopped responding
+/// doesn't hold up the task which hit the failure
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// the kinds of event which can be sent to a notifier
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyEvent {
+    /// the database could not be reached or queried
+    Database,
+    /// a scheduled task, such as posting a recurring item, failed
+    Scheduler,
+    /// a critical startup self-test failed
+    Startup,
+}
+
+impl Display for NotifyEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyEvent::Database => write!(f, "database"),
+            NotifyEvent::Scheduler => write!(f, "scheduler"),
+            NotifyEvent::Startup => write!(f, "startup"),
+        }
+    }
+}
+
+/// a destination for alerts, as configured in config.toml
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum NotifierConfig {
+    /// post to a discord webhook, which works even when the bot itself can't connect
+    Webhook {
+        url: String,
+        events: Vec<NotifyEvent>,
+    },
+    /// send an email through an smtp relay
+    Email {
+        smtp_host: String,
+        username: String,
+        password: String,
+        from: String,
+        to: String,
+        events: Vec<NotifyEvent>,
+    },
+}
+
+/// somewhere an alert can be sent
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(
+        &self,
+        event: NotifyEvent,
+        message: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+/// sends alerts to a discord webhook
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(NOTIFY_TIMEOUT)
+                .build()
+                .expect("failed to build the webhook http client"),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(
+        &self,
+        event: NotifyEvent,
+        message: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.client
+            .post(&self.url)
+            .json(&serde_json::json!({
+                "content": format!("**tom-bot {} alert**\n{}", event, message),
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// sends alerts by email
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: String,
+    to: String,
+}
+
+impl EmailNotifier {
+    pub fn new(
+        smtp_host: &str,
+        username: String,
+        password: String,
+        from: String,
+        to: String,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(smtp_host)?
+            .credentials(Credentials::new(username, password))
+            .timeout(Some(NOTIFY_TIMEOUT))
+            .build();
+        Ok(Self {
+            transport,
+            from,
+            to,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn notify(
+        &self,
+        event: NotifyEvent,
+        message: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let email = Message::builder()
+            .from(self.from.parse()?)
+            .to(self.to.parse()?)
+            .subject(format!("tom-bot {} alert", event))
+            .body(message.to_string())?;
+        self.transport.send(email).await?;
+        Ok(())
+    }
+}
+
+/// every configured notifier, along with the events each one is sent. With none configured,
+/// sending an alert does nothing.
+#[derive(Default)]
+pub struct Notifications {
+    sinks: Vec<(Vec<NotifyEvent>, Box<dyn Notifier>)>,
+    /// when each alert was last sent, keyed by its event and message so that one failure being
+    /// repeated doesn't hide a different one
+    last_sent: Mutex<HashMap<(NotifyEvent, String), Instant>>,
+}
+
+impl Notifications {
+    /// build the notifiers described in the config, any which are invalid are logged and skipped
+    pub fn from_config(configs: &[NotifierConfig]) -> Self {
+        let mut notifications = Self::default();
+        for config in configs {
+            match config {
+                NotifierConfig::Webhook { url, events } => {
+                    notifications.add(events.clone(), Box::new(WebhookNotifier::new(url.clone())))
+                }
+                NotifierConfig::Email {
+                    smtp_host,
+                    username,
+                    password,
+                    from,
+                    to,
+                    events,
+                } => match EmailNotifier::new(
+                    smtp_host,
+                    username.clone(),
+                    password.clone(),
+                    from.clone(),
+                    to.clone(),
+                ) {
+                    Ok(notifier) => notifications.add(events.clone(), Box::new(notifier)),
+                    Err(e) => error!("unable to create email notifier for {}: {}", smtp_host, e),
+                },
+            }
+        }
+
+        info!("{} notifier(s) configured", notifications.sinks.len());
+        notifications
+    }
+
+    /// send alerts for the given events to a notifier
+    pub fn add(&mut self, events: Vec<NotifyEvent>, notifier: Box<dyn Notifier>) {
+        self.sinks.push((events, notifier));
+    }
+
+    /// send an alert to every notifier interested in the event. Failures to send are only
+    /// logged, as there is nowhere else to report them.
+    pub async fn notify(&self, event: NotifyEvent, message: &str) {
+        {
+            let mut last_sent = self.last_sent.lock().unwrap();
+            last_sent.retain(|_, sent| sent.elapsed() < NOTIFY_COOLDOWN);
+            if last_sent.contains_key(&(event, message.to_string())) {
+                return;
+            }
+            last_sent.insert((event, message.to_string()), Instant::now());
+        }
+
+        for (events, notifier) in self.sinks.iter() {
+            if !events.contains(&event) {
+                continue;
+            }
+            if let Err(e) = notifier.notify(event, message).await {
+                error!("unable to send {} notification: {}", event, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// a notifier which records every alert it is sent, or fails to send any
+    #[derive(Default)]
+    struct MockNotifier {
+        sent: Arc<Mutex<Vec<(NotifyEvent, String)>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Notifier for MockNotifier {
+        async fn notify(
+            &self,
+            event: NotifyEvent,
+            message: &str,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            if self.fail {
+                return Err("the sink is down".into());
+            }
+            self.sent.lock().unwrap().push((event, message.to_string()));
+            Ok(())
+        }
+    }
+
+    /// add a mock notifier for the given events, returning what it is sent
+    fn add_mock(
+        notifications: &mut Notifications,
+        events: Vec<NotifyEvent>,
+    ) -> Arc<Mutex<Vec<(NotifyEvent, String)>>> {
+        let notifier = MockNotifier::default();
+        let sent = notifier.sent.clone();
+        notifications.add(events, Box::new(notifier));
+        sent
+    }
+
+    #[tokio::test]
+    async fn alerts_only_go_to_notifiers_for_their_event() {
+        let mut notifications = Notifications::default();
+        let database = add_mock(&mut notifications, vec![NotifyEvent::Database]);
+        let both = add_mock(
+            &mut notifications,
+            vec![NotifyEvent::Database, NotifyEvent::Scheduler],
+        );
+
+        notifications
+            .notify(NotifyEvent::Scheduler, "recurring item failed")
+            .await;
+        notifications
+            .notify(NotifyEvent::Database, "connection refused")
+            .await;
+
+        assert_eq!(
+            *database.lock().unwrap(),
+            [(NotifyEvent::Database, "connection refused".to_string())]
+        );
+        assert_eq!(
+            *both.lock().unwrap(),
+            [
+                (NotifyEvent::Scheduler, "recurring item failed".to_string()),
+                (NotifyEvent::Database, "connection refused".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn repeated_alerts_are_held_back_but_different_ones_are_not() {
+        let mut notifications = Notifications::default();
+        let sent = add_mock(&mut notifications, vec![NotifyEvent::Scheduler]);
+
+        notifications
+            .notify(NotifyEvent::Scheduler, "recurring item failed")
+            .await;
+        notifications
+            .notify(NotifyEvent::Scheduler, "recurring item failed")
+            .await;
+        notifications
+            .notify(NotifyEvent::Scheduler, "reminder failed")
+            .await;
+
+        assert_eq!(
+            *sent.lock().unwrap(),
+            [
+                (NotifyEvent::Scheduler, "recurring item failed".to_string()),
+                (NotifyEvent::Scheduler, "reminder failed".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_failing_notifier_doesnt_stop_the_others() {
+        let mut notifications = Notifications::default();
+        notifications.add(
+            vec![NotifyEvent::Startup],
+            Box::new(MockNotifier {
+                fail: true,
+                ..Default::default()
+            }),
+        );
+        let sent = add_mock(&mut notifications, vec![NotifyEvent::Startup]);
+
+        notifications
+            .notify(NotifyEvent::Startup, "self-test failed")
+            .await;
+
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+}
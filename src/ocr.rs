@@ -0,0 +1,128 @@
+//! Reading the text of a photo, for importing a handwritten or printed list. Photos are only sent
+//! to a third party once the host has configured a provider, without one importing is off.
+
+use std::{error::Error, sync::Arc};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use serenity::async_trait;
+
+const GOOGLE_VISION_URL: &str = "https://vision.googleapis.com/v1/images:annotate";
+
+/// the service used to read photos, as configured in config.toml
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum OcrConfig {
+    /// google cloud vision, using an api key with the vision api enabled
+    Google { api_key: String },
+    /// read every photo as the same text, for trying out imports without a cloud account
+    Stub { text: String },
+}
+
+/// something which can read the text in a photo
+#[async_trait]
+pub trait OcrProvider: Send + Sync {
+    /// the text in the image, with each line of the photo on its own line
+    async fn read_text(&self, image: &[u8]) -> Result<String, Box<dyn Error + Send + Sync>>;
+}
+
+/// reads photos with google cloud vision's document text detection, which handles handwriting
+pub struct GoogleOcrProvider {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+impl GoogleOcrProvider {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct VisionResponse {
+    #[serde(default)]
+    responses: Vec<VisionImageResponse>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VisionImageResponse {
+    full_text_annotation: Option<VisionText>,
+    error: Option<VisionError>,
+}
+
+#[derive(Deserialize)]
+struct VisionText {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct VisionError {
+    message: String,
+}
+
+#[async_trait]
+impl OcrProvider for GoogleOcrProvider {
+    async fn read_text(&self, image: &[u8]) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let response: VisionResponse = self
+            .client
+            .post(GOOGLE_VISION_URL)
+            // sent as a header rather than in the url, which reqwest includes in its errors
+            .header("x-goog-api-key", &self.api_key)
+            .json(&serde_json::json!({
+                "requests": [{
+                    "image": { "content": STANDARD.encode(image) },
+                    "features": [{ "type": "DOCUMENT_TEXT_DETECTION" }],
+                }],
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let image = response
+            .responses
+            .into_iter()
+            .next()
+            .ok_or("no response for the image")?;
+        if let Some(error) = image.error {
+            return Err(error.message.into());
+        }
+
+        // a photo without any text has no annotation at all
+        Ok(image
+            .full_text_annotation
+            .map(|annotation| annotation.text)
+            .unwrap_or_default())
+    }
+}
+
+/// reads every photo as the configured text, without sending it anywhere
+pub struct StubOcrProvider {
+    text: String,
+}
+
+impl StubOcrProvider {
+    pub fn new(text: String) -> Self {
+        Self { text }
+    }
+}
+
+#[async_trait]
+impl OcrProvider for StubOcrProvider {
+    async fn read_text(&self, _: &[u8]) -> Result<String, Box<dyn Error + Send + Sync>> {
+        Ok(self.text.clone())
+    }
+}
+
+/// build the provider described in the config, if there is one
+pub fn provider(config: Option<&OcrConfig>) -> Option<Arc<dyn OcrProvider>> {
+    match config? {
+        OcrConfig::Google { api_key } => Some(Arc::new(GoogleOcrProvider::new(api_key.clone()))),
+        OcrConfig::Stub { text } => Some(Arc::new(StubOcrProvider::new(text.clone()))),
+    }
+}
@@ -0,0 +1,154 @@
+//! Checks run once at startup, so that misconfiguration is caught on boot rather than by the
+//! first user to run a command.
+
+#[cfg(feature = "maps")]
+use std::time::Duration;
+
+use log::{error, info, warn};
+use migration::{Migrator, MigratorTrait};
+
+#[cfg(feature = "maps")]
+use crate::state::CONFIG;
+use crate::{discord_bot::validate_application_commands, state::AppState};
+
+/// how long to wait for the test call to the maps api
+#[cfg(feature = "maps")]
+const MAPS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// the outcome of a single startup check
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    /// whether a failure of this check should prevent the bot from starting in strict mode
+    pub critical: bool,
+    pub outcome: Result<String, String>,
+}
+
+/// the outcome of every startup check
+#[derive(Debug)]
+pub struct SelfTestReport {
+    pub checks: Vec<Check>,
+}
+
+impl SelfTestReport {
+    /// the number of critical checks which failed
+    pub fn critical_failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|c| c.critical && c.outcome.is_err())
+            .count()
+    }
+
+    /// a line for each critical check which failed, for sending to a notifier
+    pub fn failure_summary(&self) -> String {
+        self.checks
+            .iter()
+            .filter(|c| c.critical)
+            .filter_map(|c| {
+                c.outcome
+                    .as_ref()
+                    .err()
+                    .map(|e| format!("{}: {}", c.name, e))
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// log a pass/fail line for every check, followed by a summary
+    pub fn log(&self) {
+        for check in self.checks.iter() {
+            match (&check.outcome, check.critical) {
+                (Ok(msg), _) => info!("self-test PASS {}: {}", check.name, msg),
+                (Err(msg), true) => error!("self-test FAIL {}: {}", check.name, msg),
+                (Err(msg), false) => warn!("self-test FAIL {}: {}", check.name, msg),
+            }
+        }
+
+        let passed = self.checks.iter().filter(|c| c.outcome.is_ok()).count();
+        info!(
+            "self-test complete: {}/{} checks passed, {} critical failure(s)",
+            passed,
+            self.checks.len(),
+            self.critical_failures()
+        );
+    }
+}
+
+/// check that the database is reachable and every migration has been applied
+async fn check_database(state: &AppState) -> Result<String, String> {
+    let pending = Migrator::get_pending_migrations(&*state.database)
+        .await
+        .map_err(|e| format!("unable to query migrations: {}", e))?;
+
+    if pending.is_empty() {
+        Ok("reachable and fully migrated".to_string())
+    } else {
+        Err(format!(
+            "{} migration(s) have not been applied",
+            pending.len()
+        ))
+    }
+}
+
+/// make a single cheap request to the maps api, to check that the key is valid
+#[cfg(feature = "maps")]
+async fn check_maps(state: &AppState) -> Result<String, String> {
+    let destination = match CONFIG.destinations.first() {
+        Some(d) => d,
+        None => return Err("no destinations are configured".to_string()),
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    state
+        .maps_api()
+        .add_to_queue(
+            destination.address.clone(),
+            std::slice::from_ref(destination),
+            tx,
+        )
+        .await;
+
+    let data = match tokio::time::timeout(MAPS_TIMEOUT, rx).await {
+        Ok(Ok(Ok(data))) => data,
+        Ok(Ok(Err(e))) => return Err(e.to_string()),
+        Ok(Err(e)) => return Err(format!("maps handler closed: {}", e)),
+        Err(_) => return Err("timed out waiting for a response".to_string()),
+    };
+
+    // an invalid key is reported in the body of an otherwise successful response
+    if data.status == "OK" {
+        Ok("api key accepted".to_string())
+    } else {
+        Err(format!("api returned status {}", data.status))
+    }
+}
+
+/// check the command definitions which will be registered with each guild
+fn check_commands() -> Result<String, String> {
+    validate_application_commands().map(|count| format!("{} commands are valid", count))
+}
+
+/// run every startup check, logging a summary of the results
+pub async fn self_test(state: &AppState) -> SelfTestReport {
+    let mut checks = vec![Check {
+        name: "database",
+        critical: true,
+        outcome: check_database(state).await,
+    }];
+    #[cfg(feature = "maps")]
+    checks.push(Check {
+        name: "google maps",
+        critical: false,
+        outcome: check_maps(state).await,
+    });
+    checks.push(Check {
+        name: "commands",
+        critical: true,
+        outcome: check_commands(),
+    });
+
+    let report = SelfTestReport { checks };
+
+    report.log();
+    report
+}
@@ -0,0 +1,199 @@
+//! Copying bought items to a spreadsheet, so a flat can keep its own record of what was bought and
+//! by whom. Items are only sent to a third party once the host has configured a sink, and a guild
+//! has connected a sheet with `/shop-sheet-connect`.
+
+use std::{fmt::Display, sync::Arc};
+
+use log::info;
+use serde::Deserialize;
+use serenity::async_trait;
+
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_SHEETS_URL: &str = "https://sheets.googleapis.com/v4/spreadsheets";
+
+/// the service bought items are copied to, as configured in config.toml
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SheetConfig {
+    /// google sheets, using an oauth client with the sheets api enabled. Each guild authorises
+    /// the client for its own sheet, and connects it with the refresh token it was given
+    Google {
+        client_id: String,
+        client_secret: String,
+    },
+    /// log the rows rather than sending them anywhere, for trying out syncing without a cloud
+    /// account
+    Stub,
+}
+
+/// a bought item, as a row of the sheet
+#[derive(Debug, Clone)]
+pub struct SheetRow {
+    pub bought_at: String,
+    pub item: String,
+    pub quantity: i64,
+    pub store: String,
+    pub buyer: String,
+}
+
+#[derive(Debug)]
+pub enum SheetError {
+    /// the sheet's credentials were refused, they won't work until the sheet is connected again
+    Auth(String),
+    /// anything else, which may work the next time the sheet is synced
+    Other(String),
+}
+
+impl Display for SheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SheetError::Auth(e) => write!(f, "credentials refused: {}", e),
+            SheetError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for SheetError {
+    fn from(e: reqwest::Error) -> Self {
+        SheetError::Other(e.to_string())
+    }
+}
+
+/// somewhere bought items can be copied to
+#[async_trait]
+pub trait SheetSink: Send + Sync {
+    /// add the rows to the end of the sheet, in order
+    async fn append_rows(
+        &self,
+        sheet_id: &str,
+        refresh_token: &str,
+        rows: &[SheetRow],
+    ) -> Result<(), SheetError>;
+}
+
+/// appends rows to a google sheet, authorised with a guild's refresh token
+pub struct GoogleSheetSink {
+    client: reqwest::Client,
+    client_id: String,
+    client_secret: String,
+}
+
+impl GoogleSheetSink {
+    pub fn new(client_id: String, client_secret: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            client_id,
+            client_secret,
+        }
+    }
+
+    /// swap a refresh token for an access token, which lasts about an hour
+    async fn access_token(&self, refresh_token: &str) -> Result<String, SheetError> {
+        let response = self
+            .client
+            .post(GOOGLE_TOKEN_URL)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("refresh_token", refresh_token),
+                ("grant_type", "refresh_token"),
+            ])
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body: TokenResponse = response.json().await?;
+        match (body.access_token, body.error) {
+            (Some(token), _) if status.is_success() => Ok(token),
+            // a revoked or expired refresh token, or a client which no longer exists
+            (_, Some(error)) if error == "invalid_grant" || error == "invalid_client" => {
+                Err(SheetError::Auth(error))
+            }
+            (_, error) => Err(SheetError::Other(format!(
+                "{} from google: {}",
+                status,
+                error.unwrap_or_default()
+            ))),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+}
+
+#[async_trait]
+impl SheetSink for GoogleSheetSink {
+    async fn append_rows(
+        &self,
+        sheet_id: &str,
+        refresh_token: &str,
+        rows: &[SheetRow],
+    ) -> Result<(), SheetError> {
+        let token = self.access_token(refresh_token).await?;
+
+        let values = rows
+            .iter()
+            .map(|row| {
+                serde_json::json!([row.bought_at, row.item, row.quantity, row.store, row.buyer])
+            })
+            .collect::<Vec<_>>();
+
+        let response = self
+            .client
+            .post(format!(
+                "{}/{}/values/A1:append",
+                GOOGLE_SHEETS_URL, sheet_id
+            ))
+            .query(&[
+                ("valueInputOption", "USER_ENTERED"),
+                ("insertDataOption", "INSERT_ROWS"),
+            ])
+            .bearer_auth(token)
+            .json(&serde_json::json!({ "values": values }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        match status.as_u16() {
+            _ if status.is_success() => Ok(()),
+            // the account which authorised the sheet can no longer edit it, or it was deleted
+            401 | 403 | 404 => Err(SheetError::Auth(format!("{} from google sheets", status))),
+            _ => Err(SheetError::Other(format!("{} from google sheets", status))),
+        }
+    }
+}
+
+/// logs rows rather than sending them anywhere
+pub struct StubSheetSink;
+
+#[async_trait]
+impl SheetSink for StubSheetSink {
+    async fn append_rows(
+        &self,
+        sheet_id: &str,
+        _: &str,
+        rows: &[SheetRow],
+    ) -> Result<(), SheetError> {
+        for row in rows {
+            info!("sheet {}: {:?}", sheet_id, row);
+        }
+        Ok(())
+    }
+}
+
+/// build the sink described in the config, if there is one
+pub fn sink(config: Option<&SheetConfig>) -> Option<Arc<dyn SheetSink>> {
+    match config? {
+        SheetConfig::Google {
+            client_id,
+            client_secret,
+        } => Some(Arc::new(GoogleSheetSink::new(
+            client_id.clone(),
+            client_secret.clone(),
+        ))),
+        SheetConfig::Stub => Some(Arc::new(StubSheetSink)),
+    }
+}